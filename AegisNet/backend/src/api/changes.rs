@@ -0,0 +1,61 @@
+use axum::{
+    Json,
+    extract::{State, Query},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use chrono::DateTime;
+use sea_orm::*;
+use serde::Deserialize;
+use crate::entities::change_event;
+
+#[derive(Deserialize)]
+pub struct ListChangesParams {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+    pub target: Option<String>,
+    pub kind: Option<String>,     // e.g. "port_opened"
+    pub severity: Option<String>, // e.g. "HIGH"
+    pub ip: Option<String>,
+    pub since: Option<String>,    // RFC 3339
+}
+
+pub async fn list_changes(
+    State(db): State<DatabaseConnection>,
+    Query(params): Query<ListChangesParams>,
+) -> impl IntoResponse {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+
+    let mut query = change_event::Entity::find();
+    if let Some(target) = params.target {
+        query = query.filter(change_event::Column::Target.eq(target));
+    }
+    if let Some(kind) = params.kind {
+        query = query.filter(change_event::Column::Kind.eq(kind));
+    }
+    if let Some(severity) = params.severity {
+        query = query.filter(change_event::Column::Severity.eq(severity.to_uppercase()));
+    }
+    if let Some(ip) = params.ip {
+        query = query.filter(change_event::Column::Ip.eq(ip));
+    }
+    if let Some(since) = params.since {
+        match DateTime::parse_from_rfc3339(&since) {
+            Ok(t) => query = query.filter(change_event::Column::DetectedAt.gte(t.naive_utc())),
+            Err(_) => return (StatusCode::BAD_REQUEST, format!("Invalid 'since' timestamp '{}'", since)).into_response(),
+        }
+    }
+
+    let paginator = query
+        .order_by_desc(change_event::Column::Id)
+        .paginate(&db, limit);
+
+    match paginator.fetch_page(page - 1).await {
+        Ok(events) => Json(events).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to fetch change events: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch change events").into_response()
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use axum::{
+    Json,
+    extract::{State, Query},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sea_orm::*;
+use serde::Deserialize;
+use crate::entities::dhcp_client;
+
+#[derive(Deserialize)]
+pub struct ListDhcpParams {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+    pub os_family: Option<String>, // e.g. "Windows"
+}
+
+pub async fn list_dhcp_clients(
+    State(db): State<DatabaseConnection>,
+    Query(params): Query<ListDhcpParams>,
+) -> impl IntoResponse {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(100).clamp(1, 500);
+
+    let mut query = dhcp_client::Entity::find();
+    if let Some(family) = params.os_family {
+        query = query.filter(dhcp_client::Column::OsFamily.eq(family));
+    }
+
+    let paginator = query
+        .order_by_desc(dhcp_client::Column::LastSeen)
+        .paginate(&db, limit);
+
+    match paginator.fetch_page(page - 1).await {
+        Ok(clients) => Json(clients).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to fetch DHCP clients: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch DHCP clients").into_response()
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use axum::{
+    Json,
+    extract::{State, Query, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::entities::{host, service, finding};
+use crate::scanner::fingerprint::snmp::{Interface, Neighbor};
+use crate::scanner::identity::{Attribute, IdentityWeights, Provenance};
+use crate::services::inventory;
+
+#[derive(Deserialize)]
+pub struct ListHostsParams {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+impl ListHostsParams {
+    /// 1-based, page 0 is page 1.
+    fn page(&self) -> u64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    fn page_size(&self) -> u64 {
+        self.limit.unwrap_or(100).clamp(1, 500)
+    }
+}
+
+#[derive(Serialize)]
+pub struct HostSummary {
+    pub id: i32,
+    pub ip: String,
+    pub ipv6: Vec<String>,
+    pub mac: String,
+    pub hostname: String,
+    pub vendor: String,
+    pub os_family: String,
+    pub os_confidence: i32,
+    pub device_type: String,
+    pub open_ports: Vec<u16>,
+    pub risk_score: i32,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+#[derive(Serialize)]
+pub struct HostDetail {
+    #[serde(flatten)]
+    pub host: HostSummary,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub friendly_name: Option<String>,
+    pub workgroup: Option<String>,
+    pub logged_in_user: Option<String>,
+    pub dhcp_first_seen: Option<String>,
+    pub identity: BTreeMap<Attribute, Provenance>, // which source each name/model came from
+    pub sys_object_id: Option<String>,
+    pub interfaces: Vec<Interface>,
+    pub neighbors: Vec<Neighbor>,
+    pub services: Vec<service::Model>,
+    pub findings: Vec<finding::Model>,
+}
+
+impl From<host::Model> for HostSummary {
+    fn from(h: host::Model) -> Self {
+        Self {
+            id: h.id,
+            open_ports: serde_json::from_str(&h.open_ports).unwrap_or_default(),
+            ipv6: serde_json::from_str(&h.ipv6).unwrap_or_default(),
+            ip: h.ip,
+            mac: h.mac,
+            hostname: h.hostname,
+            vendor: h.vendor,
+            os_family: h.os_family,
+            os_confidence: h.os_confidence,
+            device_type: h.device_type,
+            risk_score: h.risk_score,
+            first_seen: h.first_seen.to_string(),
+            last_seen: h.last_seen.to_string(),
+        }
+    }
+}
+
+pub async fn list_hosts(
+    State(db): State<DatabaseConnection>,
+    Query(params): Query<ListHostsParams>,
+) -> impl IntoResponse {
+    let page = params.page();
+    let limit = params.page_size();
+
+    let paginator = host::Entity::find()
+        .order_by_desc(host::Column::LastSeen)
+        .paginate(&db, limit);
+
+    match paginator.fetch_page(page - 1).await {
+        Ok(hosts) => {
+            let res: Vec<HostSummary> = hosts.into_iter().map(HostSummary::from).collect();
+            Json(res).into_response()
+        },
+        Err(e) => {
+            tracing::error!("Failed to fetch hosts: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch hosts").into_response()
+        }
+    }
+}
+
+pub async fn get_host(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    let host = match host::Entity::find_by_id(id).one(&db).await {
+        Ok(Some(h)) => h,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Host not found").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to fetch host {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch host").into_response();
+        }
+    };
+
+    let services = service::Entity::find()
+        .filter(service::Column::HostId.eq(id))
+        .order_by_asc(service::Column::Port)
+        .all(&db)
+        .await;
+
+    let findings = finding::Entity::find()
+        .filter(finding::Column::HostId.eq(id))
+        .order_by_asc(finding::Column::Port)
+        .all(&db)
+        .await;
+
+    let identity = inventory::load_identity(&db, id).await;
+
+    match (services, findings, identity) {
+        (Ok(services), Ok(findings), Ok(identity)) => Json(HostDetail {
+            manufacturer: host.manufacturer.clone(),
+            model: host.model.clone(),
+            friendly_name: host.friendly_name.clone(),
+            workgroup: host.workgroup.clone(),
+            logged_in_user: host.logged_in_user.clone(),
+            dhcp_first_seen: host.dhcp_first_seen.map(|t| t.to_string()),
+            sys_object_id: host.sys_object_id.clone(),
+            interfaces: serde_json::from_str(&host.interfaces).unwrap_or_default(),
+            neighbors: serde_json::from_str(&host.neighbors).unwrap_or_default(),
+            host: host.into(),
+            identity: identity.provenance(IdentityWeights::global()),
+            services,
+            findings,
+        }).into_response(),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            tracing::error!("Failed to fetch details for host {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch host").into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_size_clamped() {
+        let params = |page, limit| ListHostsParams { page, limit };
+        assert_eq!(params(None, Some(0)).page_size(), 1);
+        assert_eq!(params(None, Some(10000)).page_size(), 500);
+        assert_eq!(params(None, Some(50)).page_size(), 50);
+        assert_eq!(params(None, None).page_size(), 100);
+        assert_eq!(params(Some(0), None).page(), 1);
+        assert_eq!(params(Some(3), None).page(), 3);
+    }
+}
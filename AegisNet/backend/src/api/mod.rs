@@ -2,3 +2,9 @@ pub mod ingest;
 pub mod scan;
 pub mod stats;
 pub mod traffic;
+pub mod hosts;
+pub mod changes;
+pub mod profiles;
+pub mod schedules;
+pub mod dhcp;
+pub mod snmp;
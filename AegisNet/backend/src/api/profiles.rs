@@ -0,0 +1,147 @@
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sea_orm::*;
+use serde::Deserialize;
+use std::time::Duration;
+use crate::entities::scan_profile;
+use crate::scanner::ports::{self, PortSpec};
+use crate::scanner::profile::{ScanProfile, DiscoveryMethod};
+use crate::services::profiles;
+
+// Anything longer blocks a scan job for no gain, larger values overflow the i32 columns
+const MAX_LISTEN_SECS: u64 = 300;
+const MAX_DURATION_MS: u64 = 300_000;
+const MAX_CONCURRENCY: usize = 4096;
+const MAX_PROBES_PER_SEC: u32 = 100_000;
+
+#[derive(Deserialize)]
+pub struct SaveProfileRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub methods: Option<Vec<DiscoveryMethod>>,
+    pub mdns_secs: Option<u64>,
+    pub llmnr_secs: Option<u64>,
+    pub ssdp_secs: Option<u64>,
+    pub arp_settle_ms: Option<u64>,
+    pub ports: Option<String>,
+    pub port_concurrency: Option<usize>,
+    pub host_concurrency: Option<usize>,
+    pub connect_timeout_ms: Option<u64>,
+    pub max_probes_per_sec: Option<u32>,
+    pub intrusive: Option<bool>,
+    pub snmp_communities: Option<Vec<String>>,
+}
+
+impl SaveProfileRequest {
+    fn validate(&self) -> Result<(), String> {
+        let listen = [("mdns_secs", self.mdns_secs), ("llmnr_secs", self.llmnr_secs), ("ssdp_secs", self.ssdp_secs)];
+        if let Some((field, _)) = listen.iter().find(|(_, v)| v.is_some_and(|v| v > MAX_LISTEN_SECS)) {
+            return Err(format!("{} must be at most {}", field, MAX_LISTEN_SECS));
+        }
+        let millis = [("arp_settle_ms", self.arp_settle_ms), ("connect_timeout_ms", self.connect_timeout_ms)];
+        if let Some((field, _)) = millis.iter().find(|(_, v)| v.is_some_and(|v| v > MAX_DURATION_MS)) {
+            return Err(format!("{} must be at most {}", field, MAX_DURATION_MS));
+        }
+        let concurrency = [("port_concurrency", self.port_concurrency), ("host_concurrency", self.host_concurrency)];
+        if let Some((field, _)) = concurrency.iter().find(|(_, v)| v.is_some_and(|v| v > MAX_CONCURRENCY)) {
+            return Err(format!("{} must be at most {}", field, MAX_CONCURRENCY));
+        }
+        if self.max_probes_per_sec.is_some_and(|v| v > MAX_PROBES_PER_SEC) {
+            return Err(format!("max_probes_per_sec must be at most {}", MAX_PROBES_PER_SEC));
+        }
+        Ok(())
+    }
+}
+
+pub async fn list_profiles(
+    State(db): State<DatabaseConnection>,
+) -> impl IntoResponse {
+    match scan_profile::Entity::find().order_by_asc(scan_profile::Column::Name).all(&db).await {
+        Ok(profiles) => Json(profiles).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to fetch scan profiles: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch scan profiles").into_response()
+        }
+    }
+}
+
+pub async fn save_profile(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<SaveProfileRequest>,
+) -> impl IntoResponse {
+    if payload.name.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "Profile name is required").into_response();
+    }
+    if let Err(e) = payload.validate() {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+
+    // Unset fields fall back to the standard profile
+    let base = ScanProfile::default();
+    let ports = match payload.ports.as_deref().map(PortSpec::parse).transpose() {
+        Ok(p) => p.unwrap_or(base.options.ports.clone()),
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let mut profile = ScanProfile {
+        name: payload.name.trim().to_string(),
+        description: payload.description.unwrap_or_default(),
+        methods: payload.methods.unwrap_or(base.methods.clone()),
+        mdns_duration: payload.mdns_secs.map(Duration::from_secs).unwrap_or(base.mdns_duration),
+        llmnr_duration: payload.llmnr_secs.map(Duration::from_secs).unwrap_or(base.llmnr_duration),
+        ssdp_duration: payload.ssdp_secs.map(Duration::from_secs).unwrap_or(base.ssdp_duration),
+        arp_settle: payload.arp_settle_ms.map(Duration::from_millis).unwrap_or(base.arp_settle),
+        max_probes_per_sec: payload.max_probes_per_sec.unwrap_or(base.max_probes_per_sec),
+        intrusive: payload.intrusive.unwrap_or(base.intrusive),
+        options: base.options.clone(),
+    };
+    profile.options.ports = ports;
+    if let Some(n) = payload.port_concurrency { profile.options.port_concurrency = n.max(1); }
+    if let Some(n) = payload.host_concurrency { profile.options.host_concurrency = n.max(1); }
+    if let Some(ms) = payload.connect_timeout_ms { profile.options.connect_timeout = Duration::from_millis(ms.max(1)); }
+    if let Some(communities) = payload.snmp_communities {
+        profile.options.snmp_communities = communities.into_iter().filter(|c| !c.is_empty()).collect();
+    }
+
+    // Connects past the open file limit fail, and their ports would read as closed
+    let budget = ports::socket_budget();
+    if profile.options.sockets_in_flight() > budget {
+        if payload.port_concurrency.is_some() || payload.host_concurrency.is_some() {
+            let msg = format!("port_concurrency x host_concurrency must not exceed {} (open file limit)", budget);
+            return (StatusCode::BAD_REQUEST, msg).into_response();
+        }
+        profile.options.fit_socket_budget(budget);
+    }
+
+    match profiles::save(&db, &profile).await {
+        Ok(Some(model)) => (StatusCode::CREATED, Json(model)).into_response(),
+        Ok(None) => (StatusCode::CONFLICT, "Built-in profiles cannot be modified").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to save scan profile: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save scan profile").into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(json: &str) -> SaveProfileRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_validate_limits() {
+        assert!(request(r#"{"name":"lab","mdns_secs":300,"port_concurrency":4096,"connect_timeout_ms":800}"#).validate().is_ok());
+        assert_eq!(request(r#"{"name":"lab","connect_timeout_ms":3000000000}"#).validate().unwrap_err(),
+                   "connect_timeout_ms must be at most 300000");
+        assert!(request(r#"{"name":"lab","llmnr_secs":86400}"#).validate().is_err());
+        assert!(request(r#"{"name":"lab","host_concurrency":5000}"#).validate().is_err());
+        assert!(request(r#"{"name":"lab","max_probes_per_sec":4294967295}"#).validate().is_err());
+    }
+}
@@ -1,46 +1,107 @@
 use axum::{
     Json,
+    extract::{State, Path},
     response::IntoResponse,
+    http::StatusCode,
 };
+use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
-use crate::services::scanner::Scanner;
+use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct ScanRequest {
     pub target: String,
-    pub start_port: u16,
-    pub end_port: u16,
+    pub exclude: Option<String>,
+    pub profile: Option<String>, // defaults to "standard"
+    pub ports: Option<String>, // e.g. "top100", "1-1024,3306,8080"
+    pub start_port: Option<u16>,
+    pub end_port: Option<u16>,
 }
 
-use crate::scanner::{self, Host};
-use crate::scanner::core::ScannerCore;
 use crate::services::discovery;
+use crate::services::jobs::{ScanJobRegistry, JobStatus};
+use crate::scanner::target::ScanTarget;
+use crate::scanner::ports::PortSpec;
+use crate::scanner::profile::ScanProfile;
+use crate::services::profiles;
 
 #[derive(Serialize)]
 pub struct ScanResponse {
+    pub job_id: Uuid,
     pub target: String,
-    pub status: String,
-    pub hosts: Vec<Host>,
+    pub status: JobStatus,
 }
 
 pub async fn start_scan(
+    State(db): State<DatabaseConnection>,
+    State(jobs): State<Arc<ScanJobRegistry>>,
     Json(payload): Json<ScanRequest>,
 ) -> impl IntoResponse {
-    let target = if payload.target == "auto" {
+    let spec = if payload.target == "auto" {
         discovery::NetworkDiscovery::detect_local_subnet()
     } else {
         payload.target.clone()
     };
 
-    println!("Starting Next-Gen Scan on: {}", target);
+    let target = match ScanTarget::parse(&spec)
+        .and_then(|t| t.with_exclusions(payload.exclude.as_deref().unwrap_or("")))
+    {
+        Ok(t) => t,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let profile_name = payload.profile.as_deref().unwrap_or(ScanProfile::DEFAULT);
+    let mut profile = match profiles::load(&db, profile_name).await {
+        Ok(Some(p)) => p,
+        Ok(None) => return (StatusCode::BAD_REQUEST, format!("Unknown scan profile '{}'", profile_name)).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to load scan profile: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load scan profile").into_response();
+        }
+    };
+
+    // Explicit port spec wins over the legacy start/end range, both override the profile
+    let ports = match (&payload.ports, payload.start_port, payload.end_port) {
+        (Some(spec), _, _) => PortSpec::parse(spec).map(Some),
+        (None, Some(start), Some(end)) if start > 0 && end > 0 => Ok(Some(PortSpec::range(start, end))),
+        _ => Ok(None),
+    };
+    match ports {
+        Ok(Some(p)) => profile.options.ports = p,
+        Ok(None) => {}
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+
+    tracing::info!("Starting Next-Gen Scan on: {} (profile: {}, {} ports)", target, profile.name, profile.options.ports.len());
 
-    // Call the new Engine
-    let hosts = ScannerCore::scan_network(&target).await;
+    // Run the Engine in the background, the client polls /api/v1/scan/:job_id
+    let job = jobs.start(db, target, profile);
 
-    Json(ScanResponse { 
-        target, 
-        status: "Completed".into(),
-        hosts 
-    })
+    (StatusCode::ACCEPTED, Json(ScanResponse {
+        job_id: job.id,
+        target: job.target.to_string(),
+        status: job.status(),
+    })).into_response()
+}
+
+pub async fn get_scan(
+    State(jobs): State<Arc<ScanJobRegistry>>,
+    Path(job_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match jobs.get(&job_id) {
+        Some(job) => Json(job.view(true)).into_response(),
+        None => (StatusCode::NOT_FOUND, "Scan job not found").into_response(),
+    }
+}
+
+pub async fn cancel_scan(
+    State(jobs): State<Arc<ScanJobRegistry>>,
+    Path(job_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match jobs.cancel(&job_id) {
+        Some(true) => StatusCode::NO_CONTENT.into_response(),
+        Some(false) => (StatusCode::CONFLICT, "Scan job already finished").into_response(),
+        None => (StatusCode::NOT_FOUND, "Scan job not found").into_response(),
+    }
 }
@@ -0,0 +1,188 @@
+use axum::{
+    Json,
+    extract::{State, Query, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use chrono::Utc;
+use sea_orm::*;
+use serde::Deserialize;
+use crate::entities::{schedule, schedule_run};
+use crate::scanner::profile::ScanProfile;
+use crate::services::{profiles, scheduler};
+
+#[derive(Deserialize)]
+pub struct ScheduleRequest {
+    pub name: String,
+    pub target: String,
+    pub exclude: Option<String>,
+    pub profile: Option<String>, // defaults to "standard"
+    pub cron: String,            // e.g. "0 2 * * *" or "@hourly"
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ListRunsParams {
+    pub limit: Option<u64>,
+}
+
+/// Checks everything a run would need, so a bad schedule fails on save
+/// rather than silently at 2 AM.
+async fn validate(db: &DatabaseConnection, req: &ScheduleRequest) -> Result<(), (StatusCode, String)> {
+    if req.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Schedule name is required".into()));
+    }
+    scheduler::parse_cron(&req.cron).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if req.target != "auto" {
+        scheduler::resolve_target(&req.target, req.exclude.as_deref()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    let profile = req.profile.as_deref().unwrap_or(ScanProfile::DEFAULT);
+    match profiles::load(db, profile).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err((StatusCode::BAD_REQUEST, format!("Unknown scan profile '{}'", profile))),
+        Err(e) => {
+            tracing::error!("Failed to load scan profile: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to load scan profile".into()))
+        }
+    }
+}
+
+fn apply(model: &mut schedule::ActiveModel, req: ScheduleRequest) {
+    let enabled = req.enabled.unwrap_or(true);
+    model.next_run_at = Set(if enabled { scheduler::next_run(&req.cron) } else { None });
+    model.name = Set(req.name.trim().to_string());
+    model.target = Set(req.target);
+    model.exclude = Set(req.exclude.filter(|e| !e.trim().is_empty()));
+    model.profile = Set(req.profile.unwrap_or_else(|| ScanProfile::DEFAULT.into()));
+    model.cron = Set(req.cron.trim().to_string());
+    model.enabled = Set(enabled);
+}
+
+pub async fn list_schedules(
+    State(db): State<DatabaseConnection>,
+) -> impl IntoResponse {
+    match schedule::Entity::find().order_by_asc(schedule::Column::Id).all(&db).await {
+        Ok(schedules) => Json(schedules).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to fetch schedules: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch schedules").into_response()
+        }
+    }
+}
+
+pub async fn get_schedule(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    match schedule::Entity::find_by_id(id).one(&db).await {
+        Ok(Some(s)) => Json(s).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Schedule not found").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to fetch schedule {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch schedule").into_response()
+        }
+    }
+}
+
+pub async fn create_schedule(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<ScheduleRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = validate(&db, &payload).await {
+        return e.into_response();
+    }
+
+    let mut model = schedule::ActiveModel {
+        created_at: Set(Utc::now().naive_utc()),
+        last_run_at: Set(None),
+        ..Default::default()
+    };
+    apply(&mut model, payload);
+
+    match model.insert(&db).await {
+        Ok(s) => (StatusCode::CREATED, Json(s)).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to create schedule: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create schedule").into_response()
+        }
+    }
+}
+
+pub async fn update_schedule(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+    Json(payload): Json<ScheduleRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = validate(&db, &payload).await {
+        return e.into_response();
+    }
+
+    let existing = match schedule::Entity::find_by_id(id).one(&db).await {
+        Ok(Some(s)) => s,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Schedule not found").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to fetch schedule {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch schedule").into_response();
+        }
+    };
+
+    let mut model: schedule::ActiveModel = existing.into();
+    apply(&mut model, payload);
+
+    match model.update(&db).await {
+        Ok(s) => Json(s).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to update schedule {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update schedule").into_response()
+        }
+    }
+}
+
+pub async fn delete_schedule(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    let deleted = schedule::Entity::delete_by_id(id).exec(&db).await;
+    match deleted {
+        Ok(res) if res.rows_affected == 0 => (StatusCode::NOT_FOUND, "Schedule not found").into_response(),
+        Ok(_) => {
+            // Run history goes with the schedule
+            if let Err(e) = schedule_run::Entity::delete_many()
+                .filter(schedule_run::Column::ScheduleId.eq(id))
+                .exec(&db)
+                .await
+            {
+                tracing::warn!("Failed to delete run history of schedule {}: {}", id, e);
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to delete schedule {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete schedule").into_response()
+        }
+    }
+}
+
+pub async fn list_runs(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+    Query(params): Query<ListRunsParams>,
+) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(20).clamp(1, 500);
+
+    let runs = schedule_run::Entity::find()
+        .filter(schedule_run::Column::ScheduleId.eq(id))
+        .order_by_desc(schedule_run::Column::StartedAt)
+        .limit(limit)
+        .all(&db)
+        .await;
+
+    match runs {
+        Ok(runs) => Json(runs).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to fetch runs of schedule {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch schedule runs").into_response()
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use axum::{
+    Json,
+    extract::{State, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use crate::entities::snmp_credential;
+use crate::scanner::fingerprint::snmp::usm::{AuthProtocol, PrivProtocol, UsmUser};
+use crate::scanner::target::ScanTarget;
+use crate::services::credentials;
+
+// RFC 3414 11.2: agents reject shorter passwords
+const MIN_PASSWORD_LEN: usize = 8;
+
+#[derive(Deserialize)]
+pub struct CreateCredentialRequest {
+    pub target: String,                 // e.g. "10.0.0.0/8"
+    pub username: String,
+    pub auth_protocol: Option<String>,  // sha (default), sha256
+    pub auth_password: String,
+    pub priv_protocol: Option<String>,  // aes
+    pub priv_password: Option<String>,  // set for authPriv
+}
+
+/// A stored credential as the API shows it: never the keys, they are as good as the passwords.
+#[derive(Serialize)]
+pub struct CredentialSummary {
+    pub id: i32,
+    pub target: String,
+    pub username: String,
+    pub auth_protocol: String,
+    pub priv_protocol: Option<String>,
+    pub created_at: String,
+}
+
+impl From<snmp_credential::Model> for CredentialSummary {
+    fn from(c: snmp_credential::Model) -> Self {
+        Self {
+            id: c.id,
+            target: c.target,
+            username: c.username,
+            auth_protocol: c.auth_protocol,
+            priv_protocol: c.priv_protocol,
+            created_at: c.created_at.to_string(),
+        }
+    }
+}
+
+pub async fn list_credentials(
+    State(db): State<DatabaseConnection>,
+) -> impl IntoResponse {
+    match snmp_credential::Entity::find().order_by_asc(snmp_credential::Column::Id).all(&db).await {
+        Ok(credentials) => {
+            let summaries: Vec<CredentialSummary> = credentials.into_iter().map(CredentialSummary::from).collect();
+            Json(summaries).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch SNMP credentials: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch SNMP credentials").into_response()
+        }
+    }
+}
+
+pub async fn create_credential(
+    State(db): State<DatabaseConnection>,
+    Json(payload): Json<CreateCredentialRequest>,
+) -> impl IntoResponse {
+    let target = match ScanTarget::parse_scope(payload.target.trim()) {
+        Ok(t) => t,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    if payload.username.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "Username is required").into_response();
+    }
+    let Some(auth) = AuthProtocol::parse(payload.auth_protocol.as_deref().unwrap_or("sha")) else {
+        return (StatusCode::BAD_REQUEST, "Unknown auth protocol, expected sha or sha256").into_response();
+    };
+    let privacy = match (payload.priv_protocol.as_deref(), payload.priv_password.as_deref()) {
+        (_, None) => None,
+        (protocol, Some(password)) => match PrivProtocol::parse(protocol.unwrap_or("aes")) {
+            Some(p) => Some((p, password)),
+            None => return (StatusCode::BAD_REQUEST, "Unknown privacy protocol, expected aes").into_response(),
+        },
+    };
+    let mut passwords = std::iter::once(payload.auth_password.as_str()).chain(privacy.map(|(_, p)| p));
+    if passwords.any(|p| p.chars().count() < MIN_PASSWORD_LEN) {
+        return (StatusCode::BAD_REQUEST, "Passwords must be at least 8 characters").into_response();
+    }
+
+    // Stretching the passwords hashes a megabyte each
+    let username = payload.username.trim().to_string();
+    let auth_password = payload.auth_password;
+    let privacy = privacy.map(|(p, password)| (p, password.to_string()));
+    let user = tokio::task::spawn_blocking(move || {
+        UsmUser::from_passwords(&username, auth, &auth_password, privacy.as_ref().map(|(p, password)| (*p, password.as_str())))
+    }).await;
+    let Ok(user) = user else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to derive SNMP keys").into_response();
+    };
+
+    match credentials::save(&db, &target.to_string(), &user).await {
+        Ok(model) => (StatusCode::CREATED, Json(CredentialSummary::from(model))).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to save SNMP credential: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save SNMP credential").into_response()
+        }
+    }
+}
+
+pub async fn delete_credential(
+    State(db): State<DatabaseConnection>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    match snmp_credential::Entity::delete_by_id(id).exec(&db).await {
+        Ok(res) if res.rows_affected == 0 => (StatusCode::NOT_FOUND, "Credential not found").into_response(),
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            tracing::error!("Failed to delete SNMP credential {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete SNMP credential").into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_omits_keys() {
+        let model = snmp_credential::Model {
+            id: 1,
+            target: "10.0.0.0/8".into(),
+            username: "monitor".into(),
+            auth_protocol: "sha".into(),
+            auth_key: "9fb5cc0381497b3793528939ff788d5d79145211".into(),
+            priv_protocol: Some("aes".into()),
+            priv_key: Some("9fb5cc0381497b3793528939ff788d5d79145211".into()),
+            created_at: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+        };
+        let json = serde_json::to_value(CredentialSummary::from(model.clone())).unwrap();
+        assert_eq!(json["username"], "monitor");
+        assert!(json.get("auth_key").is_none() && json.get("priv_key").is_none());
+        assert!(!json.to_string().contains(&model.auth_key));
+    }
+}
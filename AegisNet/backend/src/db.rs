@@ -1,4 +1,4 @@
-use sea_orm::{Database, DatabaseConnection, ConnectionTrait};
+use sea_orm::{Database, DatabaseConnection, ConnectionTrait, Statement};
 use std::env;
 
 pub async fn connect() -> Result<DatabaseConnection, sea_orm::DbErr> {
@@ -15,9 +15,17 @@ pub async fn connect() -> Result<DatabaseConnection, sea_orm::DbErr> {
     Ok(db)
 }
 
+/// A fresh in-memory database with the full schema.
+#[cfg(test)]
+pub async fn memory() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.expect("in-memory SQLite");
+    create_schema(&db).await.expect("schema");
+    db
+}
+
 async fn create_schema(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
     use sea_orm::{schema::Schema, DbBackend};
-    use crate::entities::{user, log};
+    use crate::entities::{user, log, host, host_attribute, service, finding, scan_profile, schedule, schedule_run, scan_snapshot, change_event, dhcp_client, snmp_credential};
 
     let builder = db.get_database_backend();
     let schema = Schema::new(builder);
@@ -27,7 +35,74 @@ async fn create_schema(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
 
     let stmt_log = schema.create_table_from_entity(log::Entity).if_not_exists().to_owned();
     db.execute(builder.build(&stmt_log)).await?;
+
+    // Asset Inventory
+    let stmt_host = schema.create_table_from_entity(host::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_host)).await?;
+    ensure_column(db, "hosts", "os_confidence", "INTEGER NOT NULL DEFAULT 0").await?;
+    ensure_column(db, "hosts", "workgroup", "TEXT").await?;
+    ensure_column(db, "hosts", "logged_in_user", "TEXT").await?;
+    ensure_column(db, "hosts", "dhcp_first_seen", "TEXT").await?;
+    ensure_column(db, "hosts", "ipv6", "TEXT NOT NULL DEFAULT '[]'").await?;
+    ensure_column(db, "hosts", "sys_object_id", "TEXT").await?;
+    ensure_column(db, "hosts", "interfaces", "TEXT NOT NULL DEFAULT '[]'").await?;
+    ensure_column(db, "hosts", "neighbors", "TEXT NOT NULL DEFAULT '[]'").await?;
+
+    let stmt_attribute = schema.create_table_from_entity(host_attribute::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_attribute)).await?;
+
+    let stmt_service = schema.create_table_from_entity(service::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_service)).await?;
+    ensure_column(db, "services", "cpe", "TEXT NOT NULL DEFAULT '[]'").await?;
+    ensure_column(db, "services", "tls", "TEXT").await?;
+
+    let stmt_finding = schema.create_table_from_entity(finding::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_finding)).await?;
+
+    // Scan Profiles
+    let stmt_profile = schema.create_table_from_entity(scan_profile::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_profile)).await?;
+    ensure_column(db, "scan_profiles", "snmp_communities", "TEXT NOT NULL DEFAULT '[\"public\",\"private\"]'").await?;
+    crate::services::profiles::seed_builtin(db).await?;
+
+    // Scheduled Scans
+    let stmt_schedule = schema.create_table_from_entity(schedule::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_schedule)).await?;
+
+    let stmt_run = schema.create_table_from_entity(schedule_run::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_run)).await?;
+
+    // Scan History & Change Events
+    let stmt_snapshot = schema.create_table_from_entity(scan_snapshot::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_snapshot)).await?;
+
+    let stmt_change = schema.create_table_from_entity(change_event::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_change)).await?;
+
+    // Passive DHCP Fingerprints
+    let stmt_dhcp = schema.create_table_from_entity(dhcp_client::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_dhcp)).await?;
+
+    // SNMPv3 users
+    let stmt_credential = schema.create_table_from_entity(snmp_credential::Entity).if_not_exists().to_owned();
+    db.execute(builder.build(&stmt_credential)).await?;
     
-    tracing::info!("Schema initialized (Users & Logs tables)");
+    tracing::info!("Schema initialized (Users, Logs, Inventory, Profile, Schedule, Change, DHCP & Credential tables)");
+    Ok(())
+}
+
+/// `create_table_from_entity` leaves existing tables alone, so columns added
+/// to an entity later have to be patched into databases created before.
+async fn ensure_column(db: &DatabaseConnection, table: &str, column: &str, definition: &str) -> Result<(), sea_orm::DbErr> {
+    let backend = db.get_database_backend();
+    let columns = db
+        .query_all(Statement::from_string(backend, format!("PRAGMA table_info({})", table)))
+        .await?;
+    let exists = columns.iter().any(|row| row.try_get::<String>("", "name").map(|n| n == column).unwrap_or(false));
+
+    if !exists {
+        db.execute(Statement::from_string(backend, format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))).await?;
+        tracing::info!("Added column {}.{}", table, column);
+    }
     Ok(())
 }
@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "change_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(index)]
+    pub snapshot_id: i32,             // snapshot that surfaced the change
+    pub target: String,
+    pub kind: String,                 // host_appeared, port_opened, mac_changed, ...
+    pub severity: String,             // INFO, LOW, MEDIUM, HIGH
+    pub ip: String,
+    pub mac: String,
+    pub port: Option<i32>,
+    pub previous: Option<String>,
+    pub current: Option<String>,
+    pub message: String,
+    pub detected_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
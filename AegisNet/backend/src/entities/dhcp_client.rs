@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "dhcp_clients")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(index)]
+    pub mac: String,
+    pub ip: Option<String>,
+    pub hostname: Option<String>,
+    pub vendor_class: Option<String>, // option 60
+    pub param_list: String,           // option 55, "1,3,6,15,..."
+    pub message_type: String,         // DISCOVER, REQUEST, INFORM
+    pub os_family: String,
+    pub device_type: String,
+    pub label: String,                // fingerprint label, e.g. "Windows 10 / 11"
+    pub confidence: i32,
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
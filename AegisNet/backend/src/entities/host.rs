@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "hosts")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(index)]
+    pub ip: String,
+    pub ipv6: String,             // JSON array of IPv6 addresses linked to the host
+    #[sea_orm(index)]
+    pub mac: String,              // "00:00:00:00:00:00" when unknown (routed hosts)
+    pub hostname: String,
+    pub vendor: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub friendly_name: Option<String>,
+    pub os_family: String,
+    #[sea_orm(default_value = 0)]
+    pub os_confidence: i32,
+    pub device_type: String,
+    pub workgroup: Option<String>,       // NetBIOS workgroup / domain
+    pub logged_in_user: Option<String>,
+    pub open_ports: String,       // JSON array of ports from the latest scan
+    pub risk_score: i32,
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+    pub dhcp_first_seen: Option<DateTime>, // first DHCP request seen from this MAC
+    pub sys_object_id: Option<String>, // SNMP sysObjectID
+    pub interfaces: String,       // JSON array of SNMP interfaces
+    pub neighbors: String,        // JSON array of LLDP/CDP neighbors
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
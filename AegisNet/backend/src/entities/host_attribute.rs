@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "host_attributes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(index)]
+    pub host_id: i32,
+    pub attribute: String,    // hostname, manufacturer, model, friendly_name
+    pub value: String,
+    pub source: String,       // netbios, mdns, nbns, ssdp, dhcp, smb, snmp, lldp
+    pub confidence: i32,
+    pub seen_count: i32,
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
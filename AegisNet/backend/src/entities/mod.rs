@@ -1,2 +1,13 @@
 pub mod user;
 pub mod log;
+pub mod host;
+pub mod host_attribute;
+pub mod service;
+pub mod finding;
+pub mod scan_profile;
+pub mod schedule;
+pub mod schedule_run;
+pub mod scan_snapshot;
+pub mod change_event;
+pub mod dhcp_client;
+pub mod snmp_credential;
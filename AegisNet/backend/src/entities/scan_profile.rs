@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "scan_profiles")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub name: String,               // e.g. "passive-only", "deep-audit"
+    pub description: String,
+    pub methods: String,            // JSON array of discovery methods, e.g. ["mdns","icmp"]
+    pub mdns_secs: i32,
+    pub llmnr_secs: i32,
+    pub ssdp_secs: i32,
+    pub arp_settle_ms: i32,
+    pub ports: String,              // PortSpec, e.g. "top100", "1-1024,3306"
+    pub port_concurrency: i32,
+    pub host_concurrency: i32,
+    pub connect_timeout_ms: i32,
+    pub max_probes_per_sec: i32,    // 0 = unlimited
+    pub intrusive: bool,            // SMB / SNMP / HTTP fingerprinting allowed
+    pub snmp_communities: String,   // JSON array, e.g. ["public","private"]
+    pub builtin: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "schedules")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    pub target: String,               // ScanTarget spec, e.g. "10.0.0.0/22, !10.0.1.0/28"
+    pub exclude: Option<String>,
+    pub profile: String,              // scan profile name
+    pub cron: String,                 // "0 2 * * *", "@hourly", ...
+    pub enabled: bool,
+    pub created_at: DateTime,
+    pub last_run_at: Option<DateTime>,
+    pub next_run_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
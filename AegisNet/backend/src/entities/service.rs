@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "services")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(index)]
+    pub host_id: i32,
+    pub port: i32,
+    pub protocol: String,     // TCP/UDP
+    pub name: String,
+    #[sea_orm(column_type = "Text")]
+    pub banner: String,
+    pub version: String,
+    pub cpe: String,          // JSON array of CPE names
+    #[sea_orm(column_type = "Text", nullable)]
+    pub tls: Option<String>,  // JSON TlsInfo of a TLS port
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "snmp_credentials")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub target: String,               // ScanTarget spec the user applies to, e.g. "10.0.0.0/8"
+    pub username: String,
+    pub auth_protocol: String,        // sha, sha256
+    #[serde(skip_serializing)]
+    pub auth_key: String,             // hex password key (Ku), never the password
+    pub priv_protocol: Option<String>, // aes, None = authNoPriv
+    #[serde(skip_serializing)]
+    pub priv_key: Option<String>,     // hex password key (Ku)
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, post},
+    routing::{get, post, delete},
     Router,
     Json,
 };
@@ -15,6 +15,7 @@ mod scanner;
 
 use axum::extract::FromRef;
 use scanner::traffic::store::TrafficStore;
+use services::jobs::ScanJobRegistry;
 use std::sync::Arc;
 use sea_orm::DatabaseConnection;
 
@@ -22,6 +23,7 @@ use sea_orm::DatabaseConnection;
 struct AppState {
     db: DatabaseConnection,
     traffic: Arc<TrafficStore>,
+    jobs: Arc<ScanJobRegistry>,
 }
 
 impl FromRef<AppState> for DatabaseConnection {
@@ -36,6 +38,12 @@ impl FromRef<AppState> for Arc<TrafficStore> {
     }
 }
 
+impl FromRef<AppState> for Arc<ScanJobRegistry> {
+    fn from_ref(state: &AppState) -> Self {
+        state.jobs.clone()
+    }
+}
+
 #[tokio::main]
 async fn main() {
     // Initialize tracing
@@ -44,6 +52,9 @@ async fn main() {
     // Load env vars
     dotenvy::dotenv().ok();
 
+    // Raise the open file limit before anything opens sockets
+    tracing::info!("Port scans limited to {} sockets in flight", scanner::ports::socket_budget());
+
     // Initialize OUI Database (Download if needed)
     scanner::fingerprint::oui_live::OuiLive::init().await;
 
@@ -63,8 +74,17 @@ async fn main() {
     let state = AppState {
         db,
         traffic: traffic_analyzer.get_store(),
+        jobs: Arc::new(ScanJobRegistry::new()),
     };
 
+    // Start Scheduled Scans
+    services::scheduler::Scheduler::new(state.db.clone(), state.jobs.clone()).start();
+
+    // Passive DHCP Fingerprinting (opt-in, DHCP_MONITOR=1)
+    if services::dhcp::DhcpMonitor::enabled() {
+        services::dhcp::DhcpMonitor::new(state.db.clone()).start();
+    }
+
     // CORS Layer
     let cors = CorsLayer::permissive();
 
@@ -74,6 +94,17 @@ async fn main() {
         .route("/health", get(health_check))
         .route("/api/v1/logs", post(api::ingest::ingest_log).get(api::ingest::list_logs))
         .route("/api/v1/scan", post(api::scan::start_scan))
+        .route("/api/v1/scan/:job_id", get(api::scan::get_scan).delete(api::scan::cancel_scan))
+        .route("/api/v1/hosts", get(api::hosts::list_hosts))
+        .route("/api/v1/profiles", get(api::profiles::list_profiles).post(api::profiles::save_profile))
+        .route("/api/v1/hosts/:id", get(api::hosts::get_host))
+        .route("/api/v1/schedules", get(api::schedules::list_schedules).post(api::schedules::create_schedule))
+        .route("/api/v1/schedules/:id", get(api::schedules::get_schedule).put(api::schedules::update_schedule).delete(api::schedules::delete_schedule))
+        .route("/api/v1/schedules/:id/runs", get(api::schedules::list_runs))
+        .route("/api/v1/changes", get(api::changes::list_changes))
+        .route("/api/v1/dhcp", get(api::dhcp::list_dhcp_clients))
+        .route("/api/v1/snmp/credentials", get(api::snmp::list_credentials).post(api::snmp::create_credential))
+        .route("/api/v1/snmp/credentials/:id", delete(api::snmp::delete_credential))
         .route("/api/v1/stats", get(api::stats::get_stats))
         .route("/api/v1/traffic", get(api::traffic::get_traffic)) // New Endpoint
         .with_state(state)
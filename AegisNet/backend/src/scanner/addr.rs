@@ -0,0 +1,255 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An Ethernet (EUI-48) address. Displays and serializes as `AA:BB:CC:DD:EE:FF`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MacAddr([u8; 6]);
+
+impl MacAddr {
+    /// What the inventory records for hosts whose MAC we never learned (routed hosts).
+    pub const UNSPECIFIED: MacAddr = MacAddr([0; 6]);
+    pub const BROADCAST: MacAddr = MacAddr([0xff; 6]);
+
+    pub const fn new(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+
+    /// Organizationally unique identifier, the vendor part.
+    pub fn oui(&self) -> [u8; 3] {
+        [self.0[0], self.0[1], self.0[2]]
+    }
+
+    pub fn is_unspecified(&self) -> bool {
+        *self == Self::UNSPECIFIED
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    /// Group bit: the address names a multicast group, not a device.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    /// U/L bit: assigned by software rather than burnt in by the vendor.
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    /// A device address that the OS made up, e.g. iOS/Android/Windows private Wi-Fi addresses.
+    pub fn is_randomized(&self) -> bool {
+        self.is_locally_administered() && !self.is_multicast()
+    }
+
+    /// Builds from the first six bytes, e.g. a link-layer option or a frame header.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        Some(Self(bytes.get(..6)?.try_into().ok()?))
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}", a, b, c, d, e, g)
+    }
+}
+
+/// Accepts `aa:bb:cc:dd:ee:ff`, `aa-bb-cc-dd-ee-ff` and the unpadded
+/// `0:1b:63:a:b:c` that BSD tools print.
+impl FromStr for MacAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let octets: Vec<u8> = s.trim().split([':', '-'])
+            .map(|p| if (1..=2).contains(&p.len()) { u8::from_str_radix(p, 16).ok() } else { None })
+            .collect::<Option<_>>()
+            .ok_or_else(|| format!("Invalid MAC address '{}'", s))?;
+        let octets: [u8; 6] = octets.try_into().map_err(|_| format!("Invalid MAC address '{}'", s))?;
+        Ok(Self(octets))
+    }
+}
+
+impl Serialize for MacAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// An IPv4 or IPv6 prefix, e.g. `192.168.1.0/24` or `2001:db8::/64`.
+/// Host bits are kept as written, `network()` masks them off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, String> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(format!("Invalid prefix length /{} for {}", prefix, addr));
+        }
+        Ok(Self { addr, prefix })
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn network(&self) -> IpAddr {
+        match self.addr {
+            IpAddr::V4(a) => IpAddr::V4(Ipv4Addr::from(u32::from(a) & v4_mask(self.prefix))),
+            IpAddr::V6(a) => IpAddr::V6(Ipv6Addr::from(u128::from(a) & v6_mask(self.prefix))),
+        }
+    }
+
+    /// Last address of the prefix (the IPv4 broadcast address).
+    pub fn last(&self) -> IpAddr {
+        match self.addr {
+            IpAddr::V4(a) => IpAddr::V4(Ipv4Addr::from(u32::from(a) | !v4_mask(self.prefix))),
+            IpAddr::V6(a) => IpAddr::V6(Ipv6Addr::from(u128::from(a) | !v6_mask(self.prefix))),
+        }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = v4_mask(self.prefix);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = v6_mask(self.prefix);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn v4_mask(prefix: u8) -> u32 {
+    if prefix == 0 { 0 } else { u32::MAX << (32 - prefix as u32) }
+}
+
+fn v6_mask(prefix: u8) -> u128 {
+    if prefix == 0 { 0 } else { u128::MAX << (128 - prefix as u32) }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+/// `address/prefix`, a bare address is a host route (/32 or /128).
+impl FromStr for IpNet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((a, p)) => (a, Some(p)),
+            None => (s.trim(), None),
+        };
+        let addr: IpAddr = addr.trim().parse().map_err(|_| format!("Invalid IP address '{}'", addr.trim()))?;
+        let prefix = match prefix {
+            Some(p) => p.trim().parse().map_err(|_| format!("Invalid prefix length in '{}'", s.trim()))?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        Self::new(addr, prefix).map_err(|_| format!("Invalid prefix length in '{}'", s.trim()))
+    }
+}
+
+impl Serialize for IpNet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// False for addresses that never name a single host: multicast, limited broadcast, unspecified.
+/// Subnet broadcast addresses depend on the prefix, `ScanTarget` leaves those out.
+pub fn is_host_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => !(v4.is_multicast() || v4.is_broadcast() || v4.is_unspecified()),
+        IpAddr::V6(v6) => !(v6.is_multicast() || v6.is_unspecified()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mac_formats() {
+        let mac: MacAddr = "a4-83-e7-01-02-0f".parse().unwrap();
+        assert_eq!(mac.to_string(), "A4:83:E7:01:02:0F");
+        assert_eq!("0:1b:63:a:b:c".parse::<MacAddr>().unwrap().to_string(), "00:1B:63:0A:0B:0C");
+        assert_eq!(mac.oui(), [0xa4, 0x83, 0xe7]);
+        assert!("123".parse::<MacAddr>().is_err());
+        assert!("aa:bb:cc:dd:ee".parse::<MacAddr>().is_err());
+        assert!("aa:bb:cc:dd:ee:fff".parse::<MacAddr>().is_err());
+
+        assert_eq!(serde_json::to_string(&mac).unwrap(), "\"A4:83:E7:01:02:0F\"");
+        assert_eq!(serde_json::from_str::<MacAddr>("\"a4:83:e7:01:02:0f\"").unwrap(), mac);
+    }
+
+    #[test]
+    fn test_mac_bits() {
+        let private: MacAddr = "DA:A1:19:12:34:56".parse().unwrap();
+        assert!(private.is_randomized());
+        assert!(!"00:17:F2:00:00:00".parse::<MacAddr>().unwrap().is_locally_administered());
+        // IPv6 multicast MACs have the U/L bit set too, but name a group
+        let group: MacAddr = "33:33:00:00:00:01".parse().unwrap();
+        assert!(group.is_multicast() && !group.is_randomized());
+        assert!(MacAddr::BROADCAST.is_broadcast() && MacAddr::UNSPECIFIED.is_unspecified());
+    }
+
+    #[test]
+    fn test_ipnet() {
+        let net: IpNet = "10.0.1.77/22".parse().unwrap();
+        assert_eq!(net.network(), "10.0.0.0".parse::<IpAddr>().unwrap());
+        assert_eq!(net.last(), "10.0.3.255".parse::<IpAddr>().unwrap());
+        assert!(net.contains(&"10.0.2.0".parse().unwrap()));
+        assert!(!net.contains(&"10.0.4.1".parse().unwrap()));
+        assert!(!net.contains(&"::1".parse().unwrap()));
+
+        let v6: IpNet = "2001:db8:1::/64".parse().unwrap();
+        assert!(v6.contains(&"2001:db8:1::42".parse().unwrap()));
+        assert!(!v6.contains(&"2001:db8:2::42".parse().unwrap()));
+        assert_eq!("192.168.1.5".parse::<IpNet>().unwrap().prefix(), 32);
+        assert_eq!(v6.to_string(), "2001:db8:1::/64");
+
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("2001:db8::/129".parse::<IpNet>().is_err());
+        assert!("10.0.0/8".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn test_host_address() {
+        assert!(is_host_address(&"10.0.1.0".parse().unwrap())); // valid host inside a /16
+        assert!(is_host_address(&"172.16.0.255".parse().unwrap())); // broadcast only on a /24
+        assert!(!is_host_address(&"224.0.0.251".parse().unwrap()));
+        assert!(!is_host_address(&"255.255.255.255".parse().unwrap()));
+        assert!(!is_host_address(&"ff02::1".parse().unwrap()));
+    }
+}
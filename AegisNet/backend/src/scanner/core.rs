@@ -1,188 +1,443 @@
 use crate::scanner::{Host, Service};
-use crate::scanner::discovery::{arp, tcp, icmp, mdns, ssdp, netbios, llmnr, udp};
-use crate::scanner::fingerprint::{oui, os, http, snmp, smb};
+use crate::scanner::addr::{self, MacAddr};
+use crate::scanner::discovery::{icmp, mdns, ssdp, netbios, llmnr, ipv6};
+use crate::scanner::discovery::registry::{DiscoveryRegistry, Evidence, ModuleReport, Observation};
+use crate::scanner::identity::{Attribute, Identity, Source};
+use crate::scanner::fingerprint::{oui, os, stack, http, snmp, smb, tls};
+use crate::scanner::fingerprint::banner::{Detection, ServiceBanner};
 use crate::scanner::vuln::db;
+use crate::scanner::progress::{ScanProgress, ScanPhase};
+use crate::scanner::target::ScanTarget;
+use crate::scanner::ports::PortSpec;
+use crate::scanner::profile::ScanProfile;
+use crate::services::scanner::Scanner;
+use futures::stream::{self, StreamExt};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+// Ports that speak TLS from the first byte; others get probed once a service probe says "ssl"
+const TLS_PORTS: [u16; 15] = [443, 465, 636, 853, 993, 995, 2376, 4443, 5061, 5986, 6443, 8443, 8883, 9443, 10250];
+
+/// Tunables for the enrichment phase.
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    pub ports: PortSpec,
+    pub port_concurrency: usize,  // connects in flight per host
+    pub host_concurrency: usize,  // hosts enriched in parallel
+    pub connect_timeout: Duration, // initial value, adapts to the host's RTT
+    pub snmp_communities: Vec<String>, // tried at once, the first one an agent answers wins
+    pub snmp_credentials: Vec<snmp::usm::Credential>, // v3 users, loaded when a scan starts
+}
+
+impl ScanOptions {
+    /// Every enriched host runs its own port scan, so this many connects can be open at once.
+    pub fn sockets_in_flight(&self) -> usize {
+        self.port_concurrency.saturating_mul(self.host_concurrency)
+    }
+
+    /// Lowers the per-host port concurrency (and the host concurrency if it alone is too
+    /// high) until the whole scan fits into `budget` sockets. Returns whether it had to.
+    pub fn fit_socket_budget(&mut self, budget: usize) -> bool {
+        if self.sockets_in_flight() <= budget {
+            return false;
+        }
+        self.host_concurrency = self.host_concurrency.clamp(1, budget.max(1));
+        self.port_concurrency = (budget / self.host_concurrency).max(1);
+        true
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            ports: PortSpec::default(),
+            port_concurrency: 128,
+            host_concurrency: 8,
+            connect_timeout: Duration::from_millis(500),
+            snmp_communities: snmp::DEFAULT_COMMUNITIES.map(String::from).to_vec(),
+            snmp_credentials: Vec::new(),
+        }
+    }
+}
+
+/// What the discovery phase learned, handed to every enrichment task.
+struct Discovered {
+    arp_table: HashMap<IpAddr, MacAddr>,
+    netbios: HashMap<IpAddr, netbios::NetBiosInfo>,
+    mdns: HashMap<IpAddr, mdns::MdnsInfo>,
+    ssdp: HashMap<IpAddr, ssdp::UpnpDevice>,
+    icmp: HashMap<IpAddr, icmp::IcmpReply>,
+    names: HashMap<IpAddr, llmnr::NameActivity>, // LLMNR / NBNS traffic per sender
+    identities: HashMap<IpAddr, Identity>, // hostnames the modules heard each host claim
+    ipv6: HashMap<MacAddr, Vec<Ipv6Addr>>, // MAC -> IPv6 addresses, preferred first
+    ipv6_macs: HashMap<IpAddr, MacAddr>, // IPv6-only host -> MAC
+    ipv6_routers: HashSet<MacAddr>, // MACs that advertise themselves as IPv6 routers
+    reliable: HashSet<IpAddr>, // IPs confirmed by active/passive means
+    sources: HashMap<IpAddr, BTreeSet<&'static str>>, // modules that saw each address
+    ipv6_sources: HashMap<MacAddr, BTreeSet<&'static str>>, // modules that saw IPv6 neighbors of a MAC
+}
+
+impl Discovered {
+    /// Folds the modules' observations into per-source lookups and returns the in-scope hosts.
+    fn collect(target: &ScanTarget, observations: Vec<Observation>) -> (Self, Vec<IpAddr>) {
+        let mut found = Discovered {
+            arp_table: HashMap::new(),
+            netbios: HashMap::new(),
+            mdns: HashMap::new(),
+            ssdp: HashMap::new(),
+            icmp: HashMap::new(),
+            names: HashMap::new(),
+            identities: HashMap::new(),
+            ipv6: HashMap::new(),
+            ipv6_macs: HashMap::new(),
+            ipv6_routers: HashSet::new(),
+            reliable: HashSet::new(),
+            sources: HashMap::new(),
+            ipv6_sources: HashMap::new(),
+        };
+        let mut unique_ips: Vec<IpAddr> = Vec::new();
+        let mut seen = HashSet::new();
+        let mut ipv6_unlinked = Vec::new();
+
+        for obs in observations {
+            let ip = obs.ip;
+            tracing::debug!("{} saw {} ({:?}) at {}", obs.source, ip, obs.mac, obs.seen_at);
+            found.sources.entry(ip).or_default().insert(obs.source);
+            if let Some((source, confidence)) = name_source(obs.source) {
+                let identity = found.identities.entry(ip).or_default();
+                for name in &obs.names {
+                    identity.offer_at(Attribute::Hostname, source, confidence, Some(name), obs.seen_at.naive_utc());
+                }
+            }
+            match obs.evidence {
+                // The sweep runs before the cache is read, its answers win
+                Evidence::Arp => if let Some(mac) = obs.mac { found.arp_table.entry(ip).or_insert(mac); },
+                Evidence::Icmp(reply) => { found.icmp.insert(ip, reply); }
+                Evidence::NetBios(info) => { found.netbios.insert(ip, info); }
+                Evidence::Mdns(info) => { found.mdns.insert(ip, info); }
+                Evidence::Upnp(device) => { found.ssdp.insert(ip, device); }
+                Evidence::Names(activity) => { found.names.insert(ip, activity); }
+                Evidence::Reachable => {}
+                // IPv6 neighbors are linked to the IPv4 host with the same MAC further down
+                Evidence::Ndp { router } => {
+                    let IpAddr::V6(addr) = ip else { continue };
+                    match obs.mac {
+                        Some(mac) => {
+                            found.ipv6.entry(mac).or_default().push(addr);
+                            found.ipv6_sources.entry(mac).or_default().insert(obs.source);
+                            if router { found.ipv6_routers.insert(mac); }
+                        }
+                        None => ipv6_unlinked.push(vec![addr]),
+                    }
+                    continue;
+                }
+            }
+            if obs.reliable {
+                found.reliable.insert(ip);
+            }
+            // Passive sources (mDNS, SSDP, LLMNR, ARP cache) hear the whole segment, keep only in-scope hosts
+            if target.contains_ip(&ip) && seen.insert(ip) {
+                unique_ips.push(ip);
+            }
+        }
+
+        // IPv6 neighbors without an IPv4 host of the same MAC are IPv6-only hosts,
+        // reported when the target names an IPv6 scope they fall into.
+        let ipv4_macs: HashSet<MacAddr> = unique_ips.iter()
+            .filter_map(|ip| found.arp_table.get(ip).copied().or_else(|| found.netbios.get(ip).and_then(|n| n.mac)))
+            .collect();
+        for (mac, addrs) in found.ipv6.iter_mut() {
+            ipv6::sort_addresses(addrs);
+            if !ipv4_macs.contains(mac) {
+                ipv6_unlinked.push(addrs.clone());
+                if let Some(primary) = addrs.first() { found.ipv6_macs.insert(IpAddr::V6(*primary), *mac); }
+            }
+        }
+        for addrs in ipv6_unlinked {
+            if let Some(primary) = addrs.iter().find(|a| target.contains_v6(a)) {
+                let primary = IpAddr::V6(*primary);
+                found.reliable.insert(primary);
+                if seen.insert(primary) {
+                    unique_ips.push(primary);
+                }
+            }
+        }
+        (found, unique_ips)
+    }
+
+    /// Modules that found the host, directly or through one of its IPv6 neighbors.
+    fn discovered_by(&self, ip: &IpAddr, mac: &MacAddr) -> Vec<String> {
+        let mut modules = self.sources.get(ip).cloned().unwrap_or_default();
+        if !mac.is_unspecified() {
+            modules.extend(self.ipv6_sources.get(mac).into_iter().flatten());
+        }
+        modules.into_iter().map(str::to_string).collect()
+    }
+}
+
+/// Hosts of a scan plus how each discovery module fared.
+pub struct ScanOutcome {
+    pub hosts: Vec<Host>,
+    pub modules: Vec<ModuleReport>,
+}
 
 pub struct ScannerCore;
 
 impl ScannerCore {
-    pub async fn scan_network(cidr: &str) -> Vec<Host> {
+    pub async fn scan_network(target: &ScanTarget, profile: &ScanProfile, progress: &ScanProgress) -> ScanOutcome {
         let mut hosts = Vec::new();
+        progress.set_phase(ScanPhase::Discovery);
         
         // 1. DISCOVERY PHASE
-        // Run ARP and TCP scans in parallel
-        // 1. DISCOVERY PHASE
-        // Combine Passive Listening, Aggressive ICMP, and TCP Probing
-        
-        let (mdns_res, icmp_ips, tcp_ips, netbios_res, llmnr_ips, udp_ips, ssdp_res) = tokio::join!(
-            mdns::MdnsScanner::scan(tokio::time::Duration::from_secs(4)),
-            icmp::IcmpScanner::scan_subnet(cidr),
-            tcp::TcpDiscovery::scan_subnet(cidr),
-            netbios::NetBiosScanner::scan_subnet(cidr),
-            llmnr::LlmnrListener::listen(tokio::time::Duration::from_secs(5)),
-            udp::UdpScanner::scan_subnet(cidr),
-            ssdp::SsdpScanner::scan(tokio::time::Duration::from_secs(4))
-        );
-
-        // Allow some time for ARP table to settle after all that noise
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-
-        // Read ARP Table
-        let arp_table = arp::ArpScanner::scan().await;
-
-        // Process NetBIOS Names
-        let mut netbios_map = std::collections::HashMap::new();
-        for (ip, name) in &netbios_res {
-            netbios_map.insert(ip.clone(), name.clone());
-        }
-
-        // Merge results
-        let mut unique_ips = Vec::new();
-        let mut seen = std::collections::HashSet::new();
-        let mut reliable_hosts = std::collections::HashSet::new(); // IPs confirmed by active/passive means
-
-        // Mark IPs from Active means as "Reliably Alive"
-        for ip in mdns_res.keys().chain(icmp_ips.iter()).chain(udp_ips.iter()).chain(llmnr_ips.iter()).chain(netbios_res.iter().map(|(k,_)| k)).chain(ssdp_res.keys()) {
-            reliable_hosts.insert(ip.clone());
-        }
-
-        for ip in mdns_res.keys()
-            .chain(icmp_ips.iter())
-            .chain(tcp_ips.iter()) 
-            .chain(llmnr_ips.iter())
-            .chain(udp_ips.iter())
-            .chain(ssdp_res.keys())
-            .chain(netbios_map.keys())
-            .chain(arp_table.keys()) {
-            
-            if seen.insert(ip.clone()) {
-                unique_ips.push(ip.clone());
-            }
-        }
+        // Every registered module the profile enables: passive listeners, sweeps, then the neighbour caches
+        let (observations, modules) = DiscoveryRegistry::builtin().run(target, profile).await;
+        let (found, unique_ips) = Discovered::collect(target, observations);
+
+        progress.hosts_discovered.store(unique_ips.len(), Ordering::Relaxed);
 
         // 2. ENRICHMENT PHASE
-        for ip in unique_ips {
-            if let Some(host) = Self::enrich_host(ip, &arp_table, &netbios_map, &mdns_res, &ssdp_res, &reliable_hosts).await {
+        progress.set_phase(ScanPhase::Enrichment);
+        let mut enriched = stream::iter(unique_ips)
+            .map(|ip| Self::enrich_host(ip, profile, &found))
+            .buffer_unordered(profile.options.host_concurrency.max(1));
+
+        while let Some(result) = enriched.next().await {
+            if let Some(host) = result {
                 hosts.push(host);
             }
+            progress.hosts_enriched.fetch_add(1, Ordering::Relaxed);
         }
         
+        Self::learn_from_agents(target, &mut hosts);
+
+        // Sort (enrichment completes out of order)
+        hosts.sort_by_key(|h| h.ip);
+
         // Deduplicate hosts by IP just in case
         hosts.dedup_by(|a, b| a.ip == b.ip);
-        
-        // Sort
-        hosts.sort_by(|a, b| {
-             let a_last = a.ip.split('.').last().unwrap_or("0").parse::<u8>().unwrap_or(0);
-             let b_last = b.ip.split('.').last().unwrap_or("0").parse::<u8>().unwrap_or(0);
-             a_last.cmp(&b_last)
-        });
-
-        hosts
+
+        ScanOutcome { hosts, modules }
     }
 
-    async fn enrich_host(
-        ip: String, 
-        arp_table: &std::collections::HashMap<String, String>, 
-        netbios_map: &std::collections::HashMap<String, String>, 
-        mdns_map: &std::collections::HashMap<String, mdns::MdnsInfo>,
-        ssdp_map: &std::collections::HashMap<String, ssdp::UpnpDevice>,
-        reliable_hosts: &std::collections::HashSet<String>
-    ) -> Option<Host> {
-        // Filter Broadcast / Multicast
-        if ip.ends_with(".255") || ip.ends_with(".0") || ip.starts_with("224.") || ip.starts_with("239.") { 
+    /// What SNMP agents know about their neighbours: ARP table entries give routed hosts
+    /// their MAC, LLDP/CDP tables name devices, and in-scope hosts the sweeps missed are added.
+    fn learn_from_agents(target: &ScanTarget, hosts: &mut Vec<Host>) {
+        let mut macs: HashMap<IpAddr, MacAddr> = HashMap::new();
+        let mut names: HashMap<IpAddr, String> = HashMap::new();
+        for agent in hosts.iter().filter_map(|h| h.snmp.as_ref()) {
+            for entry in &agent.arp_table {
+                macs.entry(entry.ip).or_insert(entry.mac);
+            }
+            for neighbor in &agent.neighbors {
+                let Some(address) = neighbor.address else { continue };
+                if let Some(mac) = neighbor.mac { macs.entry(address).or_insert(mac); }
+                if let Some(name) = &neighbor.name { names.entry(address).or_insert_with(|| name.clone()); }
+            }
+        }
+
+        let known: HashSet<IpAddr> = hosts.iter().map(|h| h.ip).collect();
+        let learned = macs.keys().chain(names.keys())
+            .filter(|ip| !known.contains(ip) && target.contains_ip(ip) && addr::is_host_address(ip))
+            .copied()
+            .collect::<BTreeSet<_>>();
+        for ip in learned {
+            let mac = MacAddr::UNSPECIFIED;
+            hosts.push(Host {
+                ip,
+                ipv6: Vec::new(),
+                mac,
+                hostname: String::new(),
+                vendor: oui::OuiDb::lookup(&mac),
+                manufacturer: None,
+                model: None,
+                friendly_name: None,
+                os_family: "Unknown".into(),
+                os_confidence: 0,
+                device_type: "Network Device".into(),
+                workgroup: None,
+                logged_in_user: None,
+                spoofed_names: Vec::new(),
+                discovered_by: vec!["snmp".into()],
+                identity: Identity::default(),
+                snmp: None,
+                open_ports: Vec::new(),
+                services: Vec::new(),
+                risk_score: 0,
+            });
+        }
+
+        for host in hosts.iter_mut() {
+            if let (true, Some(mac)) = (host.mac.is_unspecified(), macs.get(&host.ip)) {
+                host.mac = *mac;
+                host.vendor = oui::OuiDb::lookup(mac);
+            }
+            host.identity.offer(Attribute::Hostname, Source::Lldp, 75, names.get(&host.ip).map(String::as_str));
+            host.hostname = host.identity.best(Attribute::Hostname)
+                .map(|c| c.value.clone())
+                .unwrap_or_else(|| host.vendor.clone());
+        }
+    }
+
+    async fn enrich_host(ip: IpAddr, profile: &ScanProfile, found: &Discovered) -> Option<Host> {
+        // Filter Broadcast / Multicast (subnet broadcasts never make it out of ScanTarget)
+        if !addr::is_host_address(&ip) {
             return None; 
         }
 
-        // Get MAC
-        let mac = arp_table.get(&ip).cloned().unwrap_or_else(|| "00:00:00:00:00:00".to_string());
-        if mac == "FF:FF:FF:FF:FF:FF" { return None; } // Exclude Broadcast MAC
+        // Get MAC (ARP on the local segment, NetBIOS node status also across routers, NDP for IPv6-only hosts)
+        let nbstat = found.netbios.get(&ip);
+        let mac = found.arp_table.get(&ip).copied()
+            .or_else(|| nbstat.and_then(|n| n.mac))
+            .or_else(|| found.ipv6_macs.get(&ip).copied())
+            .unwrap_or(MacAddr::UNSPECIFIED);
+        if mac.is_broadcast() { return None; } // Exclude Broadcast MAC
         
         // FINGERPRINT: Vendor
         let vendor = oui::OuiDb::lookup(&mac);
         
-        // NetBIOS Name Preference
-        let mut hostname = if let Some(nb_name) = netbios_map.get(&ip) {
-            nb_name.clone()
-        } else {
-            // Try mDNS hostname
-            if let Some(mdns_info) = mdns_map.get(&ip) {
-                mdns_info.hostname.clone().unwrap_or(vendor.clone())
-            } else {
-                vendor.clone() 
-            }
-        };
+        // IDENTITY: every source's take on name, manufacturer and model, the weighted best wins
+        let names = found.names.get(&ip);
+        let mdns_info = found.mdns.get(&ip);
+        let mut identity = found.identities.get(&ip).cloned().unwrap_or_default();
+        if let Some(ssdp_dev) = found.ssdp.get(&ip) {
+            identity.offer(Attribute::Manufacturer, Source::Ssdp, 80, ssdp_dev.manufacturer.as_deref());
+            identity.offer(Attribute::Model, Source::Ssdp, 70, ssdp_dev.model_name.as_deref());
+            identity.offer(Attribute::FriendlyName, Source::Ssdp, 80, ssdp_dev.friendly_name.as_deref());
+        }
+        // TXT model keys are often more precise than UPnP, especially for Apple
+        identity.offer(Attribute::Model, Source::Mdns, 80, mdns_info.and_then(|m| m.model.as_deref()));
 
-        // Gather Rich Details
-        let mut manufacturer = None;
-        let mut model = None;
-        let mut friendly_name = None;
 
-        // SSDP Overrides
-        if let Some(ssdp_dev) = ssdp_map.get(&ip) {
-             if let Some(m) = &ssdp_dev.manufacturer { manufacturer = Some(m.clone()); }
-             if let Some(m) = &ssdp_dev.model_name { model = Some(m.clone()); }
-             if let Some(f) = &ssdp_dev.friendly_name { friendly_name = Some(f.clone()); }
+        // IPv6 addresses: NDP neighbors with the same MAC, plus what mDNS announced
+        let mut ipv6_addrs = found.ipv6.get(&mac).cloned().unwrap_or_default();
+        if let IpAddr::V6(v6) = ip {
+            if !ipv6_addrs.contains(&v6) { ipv6_addrs.push(v6); }
         }
-
-        // mDNS Overrides (often better for Apple)
-        if let Some(mdns_info) = mdns_map.get(&ip) {
-             if let Some(m) = &mdns_info.model { model = Some(m.clone()); }
+        for addr in found.mdns.get(&ip).map(|m| m.ipv6.as_slice()).unwrap_or_default() {
+            if !ipv6_addrs.contains(addr) { ipv6_addrs.push(*addr); }
         }
+        ipv6::sort_addresses(&mut ipv6_addrs);
         
         // SCAN: Ports (Re-Verify)
-        let open_ports = quick_port_scan(&ip).await;
+        let options = &profile.options;
+        let open_ports = if options.ports.is_empty() {
+            Vec::new()
+        } else {
+            Scanner::new(ip, options.ports.clone())
+                .with_concurrency(options.port_concurrency)
+                .with_timeout(options.connect_timeout)
+                .run()
+                .await
+                .open_ports
+        };
         // UPDATE: For iPhones/Firewalled devices, we TRUST ARP if it's there.
         // Even if no ports are open, if ARP says it's there (presumably because we just tickled it), we keep it.
         // We only drop if it's NOT in ARP, NOT in NetBIOS, and NOT reliable.
-        if open_ports.is_empty() && !arp_table.contains_key(&ip) && !netbios_map.contains_key(&ip) && !reliable_hosts.contains(&ip) { return None; }
+        if open_ports.is_empty() && !found.arp_table.contains_key(&ip) && !found.netbios.contains_key(&ip) && !found.reliable.contains(&ip) { return None; }
 
-        // FINGERPRINT: OS
-        let mut os_family = os::OsFingerprint::infer(64, &open_ports);
+        // FINGERPRINT: OS (echo reply TTL/DF plus the SYN-ACK of the first open port)
+        let mut traits = found.icmp.get(&ip)
+            .map(|r| stack::StackTraits::from_ttl(r.ttl, r.df))
+            .unwrap_or_default();
+        if let (Some(&port), IpAddr::V4(addr)) = (open_ports.first(), ip) {
+            let timeout = options.connect_timeout.max(Duration::from_millis(500));
+            if let Some(syn_ack) = stack::probe_syn_ack(addr, port, timeout).await {
+                traits = syn_ack.merge(traits);
+            }
+        }
+        let os_guess = os::OsFingerprint::identify(&traits, &open_ports);
+        let mut os_family = os_guess.family;
+        let mut os_confidence = os_guess.confidence;
         
         // Fallback: If OS unknown but Vendor is clear
         if os_family == "Unknown" {
+             os_confidence = 20;
              if vendor.contains("Apple") { os_family = "iOS/macOS".to_string(); }
              else if vendor.contains("Samsung") || vendor.contains("Huawei") || vendor.contains("Google") { os_family = "Android".to_string(); }
              else if vendor.contains("Microsoft") { os_family = "Windows".to_string(); }
              else if vendor.contains("Synology") || vendor.contains("Qlync") { os_family = "DSM (Linux)".to_string(); }
+             else { os_confidence = 0; }
         }
         
         // CLASSIFY: Device Type
-        let device_type = if vendor.contains("Apple") || vendor.contains("Samsung") { "Mobile/Tablet".to_string() }
+        let mdns_role = found.mdns.get(&ip).and_then(|m| m.device_role.as_deref());
+        let device_type = if nbstat.is_some_and(|n| n.domain_controller) { "Server (Domain Controller)".to_string() }
+                          else if let Some(role) = mdns_role.and_then(device_type_for_role) { role.to_string() }
+                          else if found.ipv6_routers.contains(&mac) { "Router".to_string() }
+                          else if vendor.contains("Apple") || vendor.contains("Samsung") { "Mobile/Tablet".to_string() }
                           else if open_ports.contains(&80) || open_ports.contains(&443) { "Server/Web".to_string() }
-                          else if open_ports.contains(&3389) || netbios_map.contains_key(&ip) { "Workstation (Windows)".to_string() }
+                          else if open_ports.contains(&3389) || found.netbios.contains_key(&ip) { "Workstation (Windows)".to_string() }
                           else { "Network Device".to_string() };
 
         // VULN: CVEs
         let mut services = Vec::new();
-        let mut host_risk = 0;
+        let mut host_risk: u32 = 0;
+
+        // LLMNR/NBNS poisoner (answers a name nobody owns)
+        let spoofed_names: Vec<String> = names.map(|n| n.spoofed.iter().cloned().collect()).unwrap_or_default();
+        if !spoofed_names.is_empty() { host_risk += 50; }
         
+        let mut smb_info = None;
         for port in &open_ports {
             let mut banner = String::from("Unknown");
             let mut service_name = "tcp".to_string();
+            let mut version = String::new();
+            let mut detected = None;
 
             // SMB Fingerprinting
-            if *port == 445 {
-                 if let Some(info) = smb::probe(&ip).await {
-                     banner = format!("SMB: {}", info.native_os);
+            if *port == 445 && profile.intrusive {
+                 if let Some(info) = smb::probe(ip).await {
+                     banner = info.describe();
                      service_name = "smb".into();
+                     version = info.dialects.last().map(|d| d.to_string()).unwrap_or_else(|| "1".into());
+                     if info.smb1 { host_risk += 20; } // EternalBlue & co.
+                     if !info.signing_required { host_risk += 5; } // NTLM relay target
+                     smb_info = Some(info);
                  } else {
-                     banner = crate::scanner::fingerprint::banner::ServiceBanner::grab(&ip, *port).await;
+                     detected = Some(ServiceBanner::grab(ip, *port, profile.intrusive).await);
                  }
-            } else if [80, 443, 8080, 8081, 3000, 5000, 8000].contains(port) {
-                if let Some(info) = http::analyze(&ip, *port).await {
+            } else if [80, 443, 8080, 8081, 3000, 5000, 8000].contains(port) && profile.intrusive {
+                if let Some(info) = http::analyze(ip, *port).await {
                     banner = format!("HTTP {} | Server: {} | Title: {}", info.status, info.server, info.title);
                     service_name = if *port == 443 { "https".into() } else { "http".into() };
+                    // Plain HTTP: the probes still name the server product
+                    if *port != 443 {
+                        let probed = ServiceBanner::grab(ip, *port, profile.intrusive).await;
+                        detected = Some(Detection { banner: banner.clone(), ..probed });
+                    }
                 } else {
-                     // Fallback to basic grab if HTTP fails
-                     banner = crate::scanner::fingerprint::banner::ServiceBanner::grab(&ip, *port).await;
+                     // Fallback to the probes if HTTP fails
+                     detected = Some(ServiceBanner::grab(ip, *port, profile.intrusive).await);
                 }
             } else {
-                 // Standard Banner Grab
-                 banner = crate::scanner::fingerprint::banner::ServiceBanner::grab(&ip, *port).await;
+                 // Service probes
+                 detected = Some(ServiceBanner::grab(ip, *port, profile.intrusive).await);
+            }
+
+            let mut cpe = Vec::new();
+            if let Some(detection) = detected {
+                banner = detection.banner;
+                if let Some(found) = detection.service {
+                    if service_name == "tcp" { service_name = found.service.clone(); }
+                    version = found.describe();
+                    cpe = found.cpe;
+                }
             }
 
-            let vulns = db::CveDb::check(*port, &banner);
+            // TLS: versions, suites and the certificate chain
+            let mut tls_info = None;
+            if profile.intrusive && (TLS_PORTS.contains(port) || service_name == "ssl") {
+                tls_info = tls::probe(ip, *port).await;
+                if let Some(info) = &tls_info {
+                    if service_name == "tcp" { service_name = "ssl".into(); }
+                    if banner == "Unknown" { banner = info.describe(); }
+                }
+            }
+
+            let mut vulns = db::CveDb::check(*port, &banner);
+            if let Some(info) = &tls_info { vulns.extend(info.findings(chrono::Utc::now())); }
             if !vulns.is_empty() { host_risk += 10; }
             
             services.push(Service {
@@ -190,20 +445,25 @@ impl ScannerCore {
                 protocol: "TCP".into(),
                 name: service_name, 
                 banner,
-                version: "".into(),
+                version,
+                cpe,
+                tls: tls_info,
                 cves: vulns.iter().map(|v| format!("{}|{}", v.id, v.url)).collect(), 
             });
         }
 
         // UDP Service: SNMP (Active Probe)
-        if let Some(snmp_info) = snmp::fingerprint(&ip).await {
+        let snmp_info = if profile.intrusive { snmp::fingerprint(ip, &options.snmp_communities, &options.snmp_credentials).await } else { None };
+        if let Some(snmp_info) = &snmp_info {
              host_risk += 5; // SNMP visible is info leak
              services.push(Service {
                 port: 161,
                 protocol: "UDP".into(),
                 name: "snmp".into(),
                 banner: snmp_info.sys_descr.clone(),
-                version: "v1/v2c".into(),
+                version: snmp_info.version.clone(),
+                cpe: vec![],
+                tls: None,
                 cves: vec![],
              });
              
@@ -215,8 +475,73 @@ impl ScannerCore {
              }
         }
 
+        // DNS-SD: every advertised instance with a resolved port
+        if let Some(mdns_info) = found.mdns.get(&ip) {
+            for svc in &mdns_info.services {
+                let Some(port) = svc.port else { continue };
+                if services.iter().any(|s| s.port == port && s.protocol == "mDNS") { continue; }
+                services.push(Service {
+                    port,
+                    protocol: "mDNS".into(),
+                    name: svc.service_type.clone(),
+                    banner: svc.describe(),
+                    version: "".into(),
+                    cpe: vec![],
+                    tls: None,
+                    cves: vec![],
+                });
+            }
+        }
+
+        // UPnP: description endpoint, flagged when it hands out port mappings (IGD)
+        if let Some(ssdp_dev) = found.ssdp.get(&ip) {
+            if let Some(port) = ssdp_dev.http_port() {
+                let igd = ssdp_dev.exposes_port_mapping();
+                let mut banner = ssdp_dev.server.clone().unwrap_or_default();
+                if igd {
+                    host_risk += 10; // any LAN client can punch holes into the firewall
+                    let mapping: Vec<&str> = ssdp_dev.port_mapping_services().map(|s| s.service_type.as_str()).collect();
+                    banner = format!("{} | Port mapping: {}", banner, mapping.join(", "));
+                }
+                services.push(Service {
+                    port,
+                    protocol: "UPnP".into(),
+                    name: if igd { "upnp-igd".into() } else { "upnp".into() },
+                    banner,
+                    version: ssdp_dev.model_number.clone().unwrap_or_default(),
+                    cpe: vec![],
+                    tls: None,
+                    cves: vec![],
+                });
+            }
+        }
+
+        // SMB: the NTLM challenge names the machine, its domain and the Windows build
+        let ntlm = smb_info.as_ref().and_then(|i| i.ntlm.as_ref());
+        identity.offer(Attribute::Hostname, Source::Smb, 90, ntlm.and_then(|n| n.netbios_computer.as_deref()));
+        if let Some(family) = smb_info.as_ref().and_then(|i| i.os_family()) {
+            if os_confidence < 90 {
+                os_family = family.to_string();
+                os_confidence = 90;
+            }
+        }
+        // SNMP: the agent's sysName, the vendor behind its sysObjectID
+        if let Some(snmp_info) = &snmp_info {
+            identity.offer(Attribute::Hostname, Source::Snmp, 80, snmp_info.sys_name.as_deref());
+            identity.offer(Attribute::Manufacturer, Source::Snmp, 60, snmp_info.manufacturer());
+        }
+        let workgroup = nbstat.and_then(|n| n.workgroup.clone())
+            .or_else(|| ntlm.and_then(|n| n.netbios_domain.clone()));
+
+        let best = |attribute| identity.best(attribute).map(|c| c.value.clone());
+        let hostname = best(Attribute::Hostname).unwrap_or_else(|| vendor.clone());
+        let manufacturer = best(Attribute::Manufacturer);
+        let model = best(Attribute::Model);
+        let friendly_name = best(Attribute::FriendlyName);
+
         Some(Host {
             ip,
+            ipv6: ipv6_addrs,
             mac,
             hostname,
             vendor,
@@ -224,25 +549,70 @@ impl ScannerCore {
             model,
             friendly_name,
             os_family,
+            os_confidence,
             device_type,
+            workgroup,
+            logged_in_user: nbstat.and_then(|n| n.user.clone()),
+            spoofed_names,
+            discovered_by: found.discovered_by(&ip, &mac),
+            identity,
+            snmp: snmp_info,
             open_ports,
             services,
-            risk_score: host_risk,
+            risk_score: host_risk.min(100) as u8,
         })
 
     }
 }
 
-async fn quick_port_scan(ip: &str) -> Vec<u16> {
-    use tokio::net::TcpStream;
-    use std::time::Duration;
-    let ports = [21, 22, 23, 80, 443, 445, 3389, 8080];
-    let mut open = Vec::new();
-    for port in ports {
-        let addr = format!("{}:{}", ip, port);
-        if tokio::time::timeout(Duration::from_millis(40), TcpStream::connect(addr)).await.is_ok() {
-            open.push(port);
-        }
+/// Identity source and confidence of the hostnames a discovery module reports.
+fn name_source(module: &str) -> Option<(Source, u8)> {
+    match module {
+        "netbios" => Some((Source::Netbios, 90)), // node status, the machine's own answer
+        "mdns" => Some((Source::Mdns, 85)),
+        "llmnr" => Some((Source::Nbns, 70)), // broadcast registrations
+        _ => None,
+    }
+}
+
+/// Device type for the role advertised over mDNS / DNS-SD.
+fn device_type_for_role(role: &str) -> Option<&'static str> {
+    match role {
+        "printer" => Some("Printer"),
+        "camera" => Some("Camera"),
+        "smart-home" => Some("IoT/Smart Home"),
+        "media" => Some("Media Player"),
+        "mobile" => Some("Mobile/Tablet"),
+        "file-server" => Some("NAS/File Server"),
+        "server" => Some("Server"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_collect_offers_names() {
+        let target = ScanTarget::parse("10.0.0.0/24").unwrap();
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+        let heard = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mut mdns = Observation::new("mdns", ip, Evidence::Reachable).name(Some("printer.local".into()));
+        mdns.seen_at = heard;
+        let observations = vec![
+            mdns,
+            Observation::new("netbios", ip, Evidence::Reachable).name(Some("PRN-LOBBY".into())),
+            Observation::new("tcp", ip, Evidence::Reachable).name(Some("ignored".into())),
+        ];
+
+        let (found, ips) = Discovered::collect(&target, observations);
+        assert_eq!(ips, vec![ip]);
+        let identity = &found.identities[&ip];
+        assert_eq!(identity.candidates().len(), 2);
+        assert_eq!(identity.best(Attribute::Hostname).unwrap().value, "PRN-LOBBY");
+        let mdns = identity.candidates().iter().find(|c| c.source == Source::Mdns).unwrap();
+        assert_eq!((mdns.value.as_str(), mdns.last_seen), ("printer.local", heard.naive_utc()));
     }
-    open
 }
@@ -1,39 +1,443 @@
 use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
 use tokio::process::Command;
+use crate::scanner::addr::{self, MacAddr};
+use crate::scanner::target::ScanTarget;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation, Stage};
+
+const ETH_P_ARP: u16 = 0x0806;
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+// Ethernet header (14) + ARP payload (28), padded to the 60 byte minimum frame
+const FRAME_LEN: usize = 60;
+// Unanswered hosts get a second who-has
+const ROUNDS: usize = 2;
+
+/// One answer to an active who-has.
+#[derive(Clone, Debug)]
+pub struct ArpReply {
+    pub mac: MacAddr,
+    pub latency: Duration,
+}
 
 pub struct ArpScanner;
 
 impl ArpScanner {
-    pub async fn scan() -> HashMap<String, String> {
-        let mut map = HashMap::new();
-        // Windows 'arp -a'
-        let output = match Command::new("arp").arg("-a").output().await {
-            Ok(o) => o,
-            Err(_) => return map,
-        };
-        
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for line in stdout.lines() {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 3 {
-                 let mut ip = String::new();
-                 let mut mac = String::new();
-                 for part in &parts {
-                     if part.contains('.') && part.chars().any(|c| c.is_numeric()) {
-                         let clean = part.replace("(", "").replace(")", "");
-                         if clean.split('.').count() == 4 { ip = clean; }
-                     } else if (part.contains('-') || part.contains(':')) && part.len() == 17 {
-                         mac = part.replace('-', ":").to_uppercase();
-                     }
-                 }
-                 if !ip.is_empty() && !mac.is_empty() {
-                     // Filter multicast/broadcast
-                     if !ip.starts_with("224.") && !ip.starts_with("239.") && !ip.starts_with("255.") && !ip.ends_with(".255") {
-                        map.insert(ip, mac);
-                     }
+    /// Active ARP sweep of the on-link part of `target` over an AF_PACKET socket.
+    /// Fails when raw sockets are unavailable (no CAP_NET_RAW, not Linux), callers
+    /// should fall back to `scan()` and rely on other probes filling the cache.
+    pub async fn sweep(target: &ScanTarget, interval: Duration, settle: Duration) -> io::Result<HashMap<Ipv4Addr, ArpReply>> {
+        #[cfg(target_os = "linux")]
+        {
+            raw::sweep(target, interval, settle).await
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (target, interval, settle);
+            Err(io::Error::new(io::ErrorKind::Unsupported, "ARP sweep needs AF_PACKET sockets"))
+        }
+    }
+
+    /// Reads the OS neighbour cache: `/proc/net/arp` on Linux, `arp -a` elsewhere.
+    pub async fn scan() -> HashMap<Ipv4Addr, MacAddr> {
+        if let Ok(table) = tokio::fs::read_to_string("/proc/net/arp").await {
+            return parse_proc_arp(&table);
+        }
+
+        match Command::new("arp").arg("-a").output().await {
+            Ok(o) => parse_arp_output(&String::from_utf8_lossy(&o.stdout)),
+            Err(_) => HashMap::new(),
+        }
+    }
+}
+
+impl DiscoveryModule for ArpScanner {
+    fn name(&self) -> &'static str {
+        "arp"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::ArpSweep
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            let replies = Self::sweep(ctx.target, ctx.profile.probe_interval(), ctx.profile.arp_settle).await
+                .map_err(|e| format!("ARP sweep unavailable: {}", e))?;
+            Ok(replies.into_iter()
+                .map(|(ip, reply)| {
+                    tracing::debug!("ARP reply from {} ({}) in {:?}", ip, reply.mac, reply.latency);
+                    Observation::new(self.name(), ip.into(), Evidence::Arp).mac(Some(reply.mac))
+                })
+                .collect())
+        })
+    }
+}
+
+/// The OS neighbour cache, read once the probes are done. Covers hosts that
+/// answered some other probe and platforms without an active sweep.
+pub struct ArpCache;
+
+impl DiscoveryModule for ArpCache {
+    fn name(&self) -> &'static str {
+        "arp-cache"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Arp
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Cache
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            if !ctx.succeeded(ArpScanner.name()) {
+                // The cache only knows what the other probes tickled, give it time to settle
+                tokio::time::sleep(ctx.profile.arp_settle).await;
+            }
+            Ok(ArpScanner::scan().await.into_iter()
+                .map(|(ip, mac)| {
+                    Observation::new(self.name(), ip.into(), Evidence::Arp).mac(Some(mac)).unreliable()
+                })
+                .collect())
+        })
+    }
+}
+
+/// Drops entries that do not name a single neighbour: multicast groups, broadcast
+/// addresses (which the cache maps to ff:ff:ff:ff:ff:ff) and incomplete entries.
+fn is_neighbor(ip: Ipv4Addr, mac: &MacAddr) -> bool {
+    addr::is_host_address(&IpAddr::V4(ip)) && !mac.is_multicast() && !mac.is_unspecified()
+}
+
+/// `IP address  HW type  Flags  HW address  Mask  Device`, flags 0x0 marks an incomplete entry.
+fn parse_proc_arp(table: &str) -> HashMap<Ipv4Addr, MacAddr> {
+    let mut map = HashMap::new();
+    for line in table.lines().skip(1) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 4 || parts[2] == "0x0" {
+            continue;
+        }
+        let (Ok(ip), Ok(mac)) = (parts[0].parse(), parts[3].parse()) else { continue };
+        if is_neighbor(ip, &mac) {
+            map.insert(ip, mac);
+        }
+    }
+    map
+}
+
+/// Windows (`192.168.1.1  aa-bb-cc-dd-ee-ff  dynamic`) and BSD/macOS
+/// (`? (192.168.1.1) at aa:bb:cc:dd:ee:ff on en0`) style output.
+fn parse_arp_output(stdout: &str) -> HashMap<Ipv4Addr, MacAddr> {
+    let mut map = HashMap::new();
+    for line in stdout.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 3 {
+             let mut ip = None;
+             let mut mac = None;
+             for part in &parts {
+                 if let Ok(addr) = part.trim_matches(|c| c == '(' || c == ')').parse::<Ipv4Addr>() {
+                     ip = Some(addr);
+                 } else if let Ok(addr) = part.parse::<MacAddr>() {
+                     mac = Some(addr);
                  }
+             }
+             if let (Some(ip), Some(mac)) = (ip, mac) {
+                if is_neighbor(ip, &mac) {
+                    map.insert(ip, mac);
+                }
+             }
+        }
+    }
+    map
+}
+
+/// Broadcast who-has `target_ip`, tell `src_ip`.
+fn build_request(src_mac: [u8; 6], src_ip: Ipv4Addr, target_ip: Ipv4Addr) -> [u8; FRAME_LEN] {
+    let mut frame = [0u8; FRAME_LEN];
+    frame[0..6].copy_from_slice(&[0xff; 6]);
+    frame[6..12].copy_from_slice(&src_mac);
+    frame[12..14].copy_from_slice(&ETH_P_ARP.to_be_bytes());
+
+    let arp = &mut frame[14..42];
+    arp[0..2].copy_from_slice(&1u16.to_be_bytes());      // Ethernet
+    arp[2..4].copy_from_slice(&0x0800u16.to_be_bytes()); // IPv4
+    arp[4] = 6;
+    arp[5] = 4;
+    arp[6..8].copy_from_slice(&ARP_REQUEST.to_be_bytes());
+    arp[8..14].copy_from_slice(&src_mac);
+    arp[14..18].copy_from_slice(&src_ip.octets());
+    // target hardware address stays zero
+    arp[24..28].copy_from_slice(&target_ip.octets());
+    frame
+}
+
+/// Sender IP and MAC of an ARP reply frame.
+fn parse_reply(frame: &[u8]) -> Option<(Ipv4Addr, MacAddr)> {
+    if frame.len() < 42 || frame[12..14] != ETH_P_ARP.to_be_bytes() {
+        return None;
+    }
+    let arp = &frame[14..42];
+    if arp[2..4] != 0x0800u16.to_be_bytes() || arp[4] != 6 || arp[5] != 4 || arp[6..8] != ARP_REPLY.to_be_bytes() {
+        return None;
+    }
+    let mac = MacAddr::from_slice(&arp[8..14])?;
+    let ip = Ipv4Addr::new(arp[14], arp[15], arp[16], arp[17]);
+    Some((ip, mac))
+}
+
+#[cfg(target_os = "linux")]
+mod raw {
+    use super::*;
+    use std::ffi::{CStr, CString};
+    use std::mem;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use crate::scanner::discovery::StopOnDrop;
+
+    struct Interface {
+        name: String,
+        index: i32,
+        mac: [u8; 6],
+        ip: Ipv4Addr,
+        netmask: Ipv4Addr,
+    }
+
+    impl Interface {
+        fn on_link(&self, ip: &Ipv4Addr) -> bool {
+            let mask = u32::from(self.netmask);
+            u32::from(*ip) & mask == u32::from(self.ip) & mask
+        }
+    }
+
+    pub async fn sweep(target: &ScanTarget, interval: Duration, settle: Duration) -> io::Result<HashMap<Ipv4Addr, ArpReply>> {
+        let hosts = target.hosts();
+        let iface = local_interfaces()?
+            .into_iter()
+            .find(|i| hosts.iter().any(|h| i.on_link(h)))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No interface on-link with the target"))?;
+        let hosts: Vec<Ipv4Addr> = hosts.into_iter().filter(|h| iface.on_link(h) && *h != iface.ip).collect();
+
+        let socket = Arc::new(open_socket(iface.index)?);
+        tracing::info!("ARP sweep of {} hosts on {}", hosts.len(), iface.name);
+
+        let sent: Arc<Mutex<HashMap<Ipv4Addr, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+        let replies: Arc<Mutex<HashMap<Ipv4Addr, ArpReply>>> = Arc::new(Mutex::new(HashMap::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let receiver = {
+            let (socket, sent, replies, stop) = (socket.clone(), sent.clone(), replies.clone(), stop.clone());
+            tokio::task::spawn_blocking(move || receive(&socket, &sent, &replies, &stop))
+        };
+        let _guard = StopOnDrop(stop.clone());
+
+        for _ in 0..ROUNDS {
+            for ip in &hosts {
+                if replies.lock().map(|r| r.contains_key(ip)).unwrap_or(false) {
+                    continue;
+                }
+                sent.lock().unwrap().insert(*ip, Instant::now());
+                if let Err(e) = send_request(&socket, &iface, *ip) {
+                    stop.store(true, Ordering::Relaxed);
+                    let _ = receiver.await;
+                    return Err(e);
+                }
+                if !interval.is_zero() {
+                    tokio::time::sleep(interval).await;
+                }
+            }
+            tokio::time::sleep(settle).await;
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = receiver.await;
+
+        let replies = replies.lock().unwrap();
+        Ok(replies.clone())
+    }
+
+    fn receive(
+        socket: &OwnedFd,
+        sent: &Mutex<HashMap<Ipv4Addr, Instant>>,
+        replies: &Mutex<HashMap<Ipv4Addr, ArpReply>>,
+        stop: &AtomicBool,
+    ) {
+        let mut buf = [0u8; 1514];
+        while !stop.load(Ordering::Relaxed) {
+            // SO_RCVTIMEO wakes us up regularly to check `stop`
+            let n = unsafe { libc::recv(socket.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            if n <= 0 {
+                continue;
+            }
+            let Some((ip, mac)) = parse_reply(&buf[..n as usize]) else { continue };
+            // Only answers to our own who-has, not gratuitous ARP or other chatter
+            let Some(started) = sent.lock().ok().and_then(|s| s.get(&ip).copied()) else { continue };
+            if let Ok(mut replies) = replies.lock() {
+                replies.entry(ip).or_insert(ArpReply { mac, latency: started.elapsed() });
+            }
+        }
+    }
+
+    fn open_socket(ifindex: i32) -> io::Result<OwnedFd> {
+        let protocol = ETH_P_ARP.to_be();
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, protocol as i32) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = protocol;
+        addr.sll_ifindex = ifindex;
+        let rc = unsafe {
+            libc::bind(fd, &addr as *const _ as *const libc::sockaddr, mem::size_of::<libc::sockaddr_ll>() as u32)
+        };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let timeout = libc::timeval { tv_sec: 0, tv_usec: 100_000 };
+        unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const _ as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as u32,
+            );
+        }
+        Ok(socket)
+    }
+
+    fn send_request(socket: &OwnedFd, iface: &Interface, ip: Ipv4Addr) -> io::Result<()> {
+        let frame = build_request(iface.mac, iface.ip, ip);
+
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = ETH_P_ARP.to_be();
+        addr.sll_ifindex = iface.index;
+        addr.sll_halen = 6;
+        addr.sll_addr[..6].copy_from_slice(&[0xff; 6]);
+
+        let rc = unsafe {
+            libc::sendto(
+                socket.as_raw_fd(),
+                frame.as_ptr() as *const libc::c_void,
+                frame.len(),
+                0,
+                &addr as *const _ as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as u32,
+            )
+        };
+        if rc < 0 { Err(io::Error::last_os_error()) } else { Ok(()) }
+    }
+
+    /// Up, non-loopback IPv4 interfaces with their MAC.
+    fn local_interfaces() -> io::Result<Vec<Interface>> {
+        let mut ifaddrs: *mut libc::ifaddrs = std::ptr::null_mut();
+        if unsafe { libc::getifaddrs(&mut ifaddrs) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut result = Vec::new();
+        let mut cursor = ifaddrs;
+        while !cursor.is_null() {
+            let entry = unsafe { &*cursor };
+            cursor = entry.ifa_next;
+
+            let flags = entry.ifa_flags as i32;
+            if entry.ifa_addr.is_null() || entry.ifa_netmask.is_null()
+                || flags & libc::IFF_UP == 0 || flags & libc::IFF_LOOPBACK != 0
+                || unsafe { (*entry.ifa_addr).sa_family } as i32 != libc::AF_INET
+            {
+                continue;
             }
+
+            let name = unsafe { CStr::from_ptr(entry.ifa_name) }.to_string_lossy().into_owned();
+            let ip = unsafe { &*(entry.ifa_addr as *const libc::sockaddr_in) }.sin_addr.s_addr;
+            let netmask = unsafe { &*(entry.ifa_netmask as *const libc::sockaddr_in) }.sin_addr.s_addr;
+            let Some(mac) = interface_mac(&name) else { continue };
+            let Ok(cname) = CString::new(name.clone()) else { continue };
+            let index = unsafe { libc::if_nametoindex(cname.as_ptr()) } as i32;
+            if index == 0 {
+                continue;
+            }
+
+            result.push(Interface {
+                name,
+                index,
+                mac,
+                ip: Ipv4Addr::from(u32::from_be(ip)),
+                netmask: Ipv4Addr::from(u32::from_be(netmask)),
+            });
         }
-        map
+
+        unsafe { libc::freeifaddrs(ifaddrs) };
+        Ok(result)
+    }
+
+    fn interface_mac(name: &str) -> Option<[u8; 6]> {
+        let text = std::fs::read_to_string(format!("/sys/class/net/{}/address", name)).ok()?;
+        let bytes: Vec<u8> = text.trim().split(':').filter_map(|b| u8::from_str_radix(b, 16).ok()).collect();
+        let mac: [u8; 6] = bytes.try_into().ok()?;
+        // Tunnels and other L3-only devices have no usable link-layer address
+        if mac == [0; 6] { None } else { Some(mac) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_reply_roundtrip() {
+        let ours = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let frame = build_request(ours, Ipv4Addr::new(192, 168, 1, 10), Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(&frame[0..6], &[0xff; 6]);
+        assert_eq!(&frame[38..42], &[192, 168, 1, 1]);
+        assert!(parse_reply(&frame).is_none()); // a request is not a reply
+
+        // Turn it into the answer from 192.168.1.1
+        let theirs = [0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff];
+        let mut reply = frame;
+        reply[20..22].copy_from_slice(&ARP_REPLY.to_be_bytes());
+        reply[22..28].copy_from_slice(&theirs);
+        reply[28..32].copy_from_slice(&[192, 168, 1, 1]);
+
+        let (ip, mac) = parse_reply(&reply).unwrap();
+        assert_eq!(ip, Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(mac.to_string(), "AA:BB:CC:DD:EE:FF");
+    }
+
+    #[test]
+    fn test_parse_proc_arp() {
+        let table = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         aa:bb:cc:dd:ee:ff     *        eth0
+192.168.1.77     0x1         0x0         00:00:00:00:00:00     *        eth0
+192.168.1.255    0x1         0x2         ff:ff:ff:ff:ff:ff     *        eth0
+";
+        let map = parse_proc_arp(table);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&Ipv4Addr::new(192, 168, 1, 1)), Some(&"AA:BB:CC:DD:EE:FF".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_arp_output() {
+        let windows = "  192.168.1.1           aa-bb-cc-dd-ee-ff     dynamic";
+        let bsd = "? (10.0.0.2) at 0:1b:63:a:b:c on en0 ifscope [ethernet]";
+        let multicast = "? (224.0.0.251) at 1:0:5e:0:0:fb on en0 ifscope permanent [ethernet]";
+        assert_eq!(parse_arp_output(windows).get(&Ipv4Addr::new(192, 168, 1, 1)).map(MacAddr::to_string).as_deref(), Some("AA:BB:CC:DD:EE:FF"));
+        assert_eq!(parse_arp_output(bsd).get(&Ipv4Addr::new(10, 0, 0, 2)).map(MacAddr::to_string).as_deref(), Some("00:1B:63:0A:0B:0C"));
+        assert!(parse_arp_output(multicast).is_empty());
     }
 }
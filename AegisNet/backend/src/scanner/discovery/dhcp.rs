@@ -0,0 +1,283 @@
+use std::net::Ipv4Addr;
+use socket2::{Socket, Domain, Type, Protocol};
+use tokio::sync::mpsc;
+use crate::scanner::addr::MacAddr;
+
+const SERVER_PORT: u16 = 67;
+const BOOTREQUEST: u8 = 1;
+const MAGIC_COOKIE: [u8; 4] = [0x63, 0x82, 0x53, 0x63];
+const OPTIONS_OFFSET: usize = 240;
+
+// Options
+const OPT_PAD: u8 = 0;
+const OPT_HOSTNAME: u8 = 12;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_MESSAGE_TYPE: u8 = 53;
+const OPT_PARAM_LIST: u8 = 55;
+const OPT_VENDOR_CLASS: u8 = 60;
+const OPT_CLIENT_FQDN: u8 = 81;
+const OPT_END: u8 = 255;
+
+// DHCP message types clients send while joining or renewing
+pub const DHCPDISCOVER: u8 = 1;
+pub const DHCPREQUEST: u8 = 3;
+pub const DHCPINFORM: u8 = 8;
+
+/// The identifying parts of a client's DHCP request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DhcpRequest {
+    pub message_type: u8,
+    pub client_mac: MacAddr,
+    pub ip: Option<Ipv4Addr>, // ciaddr when renewing, else the requested address
+    pub hostname: Option<String>, // option 12, or the FQDN of option 81
+    pub vendor_class: Option<String>, // option 60, e.g. "MSFT 5.0", "android-dhcp-13"
+    pub param_list: Vec<u8>, // option 55, in the client's order
+}
+
+impl DhcpRequest {
+    /// Option 55 as written in fingerprint databases: "1,3,6,15,...".
+    pub fn param_key(&self) -> String {
+        self.param_list.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",")
+    }
+
+    pub fn message_name(&self) -> &'static str {
+        match self.message_type {
+            DHCPDISCOVER => "DISCOVER",
+            DHCPREQUEST => "REQUEST",
+            DHCPINFORM => "INFORM",
+            _ => "OTHER",
+        }
+    }
+
+    /// Parses a BOOTREQUEST from a client. Server replies and non-DHCP BOOTP are rejected.
+    pub fn parse(packet: &[u8]) -> Result<Self, String> {
+        if packet.len() < OPTIONS_OFFSET {
+            return Err("Packet too short for DHCP".into());
+        }
+        if packet[0] != BOOTREQUEST {
+            return Err("Not a client request".into());
+        }
+        if packet[236..240] != MAGIC_COOKIE {
+            return Err("Missing DHCP magic cookie".into());
+        }
+
+        // Ethernet hardware address
+        let hlen = (packet[2] as usize).min(16);
+        if packet[1] != 1 || hlen != 6 {
+            return Err(format!("Unsupported hardware type {}", packet[1]));
+        }
+        let client_mac = MacAddr::from_slice(&packet[28..34]).ok_or("Truncated client hardware address")?;
+        let ciaddr = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+
+        let mut request = DhcpRequest {
+            message_type: 0,
+            client_mac,
+            ip: (!ciaddr.is_unspecified()).then_some(ciaddr),
+            hostname: None,
+            vendor_class: None,
+            param_list: Vec::new(),
+        };
+
+        let mut opts = &packet[OPTIONS_OFFSET..];
+        while let Some(&code) = opts.first() {
+            match code {
+                OPT_END => break,
+                OPT_PAD => { opts = &opts[1..]; continue; }
+                _ => {}
+            }
+            let len = *opts.get(1).ok_or("Truncated DHCP option")? as usize;
+            let data = opts.get(2..2 + len).ok_or("Truncated DHCP option")?;
+            match code {
+                OPT_MESSAGE_TYPE if len == 1 => request.message_type = data[0],
+                OPT_HOSTNAME => request.hostname = text(data),
+                OPT_VENDOR_CLASS => request.vendor_class = text(data),
+                OPT_PARAM_LIST => request.param_list = data.to_vec(),
+                OPT_REQUESTED_IP if len == 4 && request.ip.is_none() => {
+                    request.ip = Some(Ipv4Addr::new(data[0], data[1], data[2], data[3]));
+                }
+                // flags, two deprecated rcodes, then the name (ASCII unless the E flag asks for wire format)
+                OPT_CLIENT_FQDN if len > 3 && data[0] & 0x04 == 0 && request.hostname.is_none() => {
+                    request.hostname = text(&data[3..]);
+                }
+                _ => {}
+            }
+            opts = &opts[2 + len..];
+        }
+
+        if request.message_type == 0 {
+            return Err("BOOTP request without DHCP message type".into());
+        }
+        Ok(request)
+    }
+}
+
+fn text(data: &[u8]) -> Option<String> {
+    let s = String::from_utf8_lossy(data).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+    (!s.is_empty()).then_some(s)
+}
+
+pub struct DhcpListener;
+
+impl DhcpListener {
+    /// Captures client requests to the server port until the receiver goes away.
+    /// On Linux a raw UDP socket sees copies of the packets, so a DHCP server on
+    /// this host keeps getting every one of them. Elsewhere the port is only bound
+    /// when no server owns it.
+    pub async fn listen(tx: mpsc::Sender<DhcpRequest>) -> std::io::Result<()> {
+        let mut capture = Capture::open()?;
+        let mut buf = [0u8; 1500];
+        loop {
+            let (len, from) = capture.recv(&mut buf).await?;
+            let Some(payload) = Capture::payload(&buf[..len]) else { continue };
+            match DhcpRequest::parse(payload) {
+                Ok(request) if matches!(request.message_type, DHCPDISCOVER | DHCPREQUEST | DHCPINFORM) => {
+                    if tx.send(request).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::debug!("Ignoring packet to port 67 from {}: {}", from, e),
+            }
+        }
+    }
+}
+
+/// Raw IPPROTO_UDP socket: every inbound UDP datagram with its IP header, nothing taken away.
+#[cfg(target_os = "linux")]
+struct Capture(tokio::io::unix::AsyncFd<Socket>);
+
+#[cfg(target_os = "linux")]
+impl Capture {
+    fn open() -> std::io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::UDP))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self(tokio::io::unix::AsyncFd::new(socket)?))
+    }
+
+    async fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<(usize, Ipv4Addr)> {
+        use std::os::fd::AsRawFd;
+        loop {
+            let mut guard = self.0.readable().await?;
+            let result = guard.try_io(|socket| {
+                let n = unsafe { libc::recv(socket.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+                if n < 0 { Err(std::io::Error::last_os_error()) } else { Ok(n as usize) }
+            });
+            if let Ok(n) = result {
+                let n = n?;
+                let from = if n >= 20 { Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]) } else { Ipv4Addr::UNSPECIFIED };
+                return Ok((n, from));
+            }
+        }
+    }
+
+    fn payload(packet: &[u8]) -> Option<&[u8]> {
+        udp_to_server(packet)
+    }
+}
+
+/// A plain socket on port 67, without SO_REUSEADDR so it never shares the port with a server.
+#[cfg(not(target_os = "linux"))]
+struct Capture(tokio::net::UdpSocket);
+
+#[cfg(not(target_os = "linux"))]
+impl Capture {
+    fn open() -> std::io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_broadcast(true)?;
+        socket.bind(&std::net::SocketAddr::from((Ipv4Addr::UNSPECIFIED, SERVER_PORT)).into())?;
+        socket.set_nonblocking(true)?;
+        Ok(Self(tokio::net::UdpSocket::from_std(socket.into())?))
+    }
+
+    async fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<(usize, Ipv4Addr)> {
+        let (n, addr) = self.0.recv_from(buf).await?;
+        let from = match addr { std::net::SocketAddr::V4(a) => *a.ip(), _ => Ipv4Addr::UNSPECIFIED };
+        Ok((n, from))
+    }
+
+    fn payload(datagram: &[u8]) -> Option<&[u8]> {
+        Some(datagram)
+    }
+}
+
+/// The UDP payload of a raw IPv4 datagram addressed to the DHCP server port.
+#[cfg(target_os = "linux")]
+fn udp_to_server(packet: &[u8]) -> Option<&[u8]> {
+    if packet.len() < 20 || packet[0] >> 4 != 4 || packet[9] != 17 {
+        return None;
+    }
+    let ihl = ((packet[0] & 0x0f) as usize) * 4;
+    let udp = packet.get(ihl..ihl + 8)?;
+    if u16::from_be_bytes([udp[2], udp[3]]) != SERVER_PORT {
+        return None;
+    }
+    packet.get(ihl + 8..)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A DHCPREQUEST as Windows 10 sends it when joining.
+    pub(crate) fn windows_request() -> Vec<u8> {
+        let mut p = vec![0u8; OPTIONS_OFFSET];
+        p[0] = BOOTREQUEST;
+        p[1] = 1; // Ethernet
+        p[2] = 6;
+        p[4..8].copy_from_slice(&[0x3d, 0x1d, 0x9a, 0x01]);
+        p[28..34].copy_from_slice(&[0x00, 0x15, 0x5d, 0x01, 0x02, 0x03]);
+        p[236..240].copy_from_slice(&MAGIC_COOKIE);
+        p.extend_from_slice(&[OPT_MESSAGE_TYPE, 1, DHCPREQUEST]);
+        p.extend_from_slice(&[61, 7, 1, 0x00, 0x15, 0x5d, 0x01, 0x02, 0x03]);
+        p.extend_from_slice(&[OPT_REQUESTED_IP, 4, 192, 168, 1, 23]);
+        p.extend_from_slice(&[OPT_HOSTNAME, 12]);
+        p.extend_from_slice(b"DESKTOP-7K2M");
+        p.extend_from_slice(&[OPT_CLIENT_FQDN, 15, 0, 0, 0]);
+        p.extend_from_slice(b"DESKTOP-7K2M");
+        p.extend_from_slice(&[OPT_VENDOR_CLASS, 8]);
+        p.extend_from_slice(b"MSFT 5.0");
+        p.extend_from_slice(&[OPT_PARAM_LIST, 14, 1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, 252]);
+        p.extend_from_slice(&[OPT_PAD, OPT_END]);
+        p
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let request = DhcpRequest::parse(&windows_request()).unwrap();
+        assert_eq!(request.message_name(), "REQUEST");
+        assert_eq!(request.client_mac.to_string(), "00:15:5D:01:02:03");
+        assert_eq!(request.ip, Some(Ipv4Addr::new(192, 168, 1, 23)));
+        assert_eq!(request.hostname.as_deref(), Some("DESKTOP-7K2M"));
+        assert_eq!(request.vendor_class.as_deref(), Some("MSFT 5.0"));
+        assert_eq!(request.param_key(), "1,3,6,15,31,33,43,44,46,47,119,121,249,252");
+    }
+
+    #[test]
+    fn test_reject_malformed() {
+        let mut reply = windows_request();
+        reply[0] = 2; // BOOTREPLY
+        assert!(DhcpRequest::parse(&reply).is_err());
+
+        let mut truncated = windows_request();
+        truncated.truncate(OPTIONS_OFFSET + 5); // inside the client identifier
+        assert!(DhcpRequest::parse(&truncated).is_err());
+
+        assert!(DhcpRequest::parse(&[1, 1, 6, 0]).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_udp_to_server() {
+        let request = windows_request();
+        let mut packet = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 64, 17, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255];
+        packet.extend_from_slice(&[0, 68, 0, 67, 0, 0, 0, 0]);
+        packet.extend_from_slice(&request);
+        assert_eq!(udp_to_server(&packet), Some(&request[..]));
+
+        // Server replies go to the client port
+        packet[22..24].copy_from_slice(&68u16.to_be_bytes());
+        assert_eq!(udp_to_server(&packet), None);
+        packet[9] = 6; // TCP
+        assert_eq!(udp_to_server(&packet), None);
+    }
+}
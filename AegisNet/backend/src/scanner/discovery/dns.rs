@@ -0,0 +1,319 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_TXT: u16 = 16;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_SRV: u16 = 33;
+pub const CLASS_IN: u16 = 1;
+
+// Top bit of the class: cache-flush in answers, unicast-response in questions (mDNS)
+const CLASS_FLAG: u16 = 0x8000;
+// Guards against pointer loops in malicious packets
+const MAX_POINTER_JUMPS: usize = 32;
+
+/// A decoded DNS / mDNS / LLMNR message.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Message {
+    pub id: u16,
+    pub flags: u16,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+    pub authorities: Vec<Record>,
+    pub additionals: Vec<Record>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Question {
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+    pub unicast: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub data: RData,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ptr(String),
+    Srv { priority: u16, weight: u16, port: u16, target: String },
+    Txt(Vec<String>),
+    Other(Vec<u8>),
+}
+
+impl Message {
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    /// Answers, authorities and additionals, in that order.
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.answers.iter().chain(&self.authorities).chain(&self.additionals)
+    }
+
+    pub fn parse(buf: &[u8]) -> Result<Self, String> {
+        let mut r = Reader { buf, pos: 0 };
+        let id = r.u16()?;
+        let flags = r.u16()?;
+        let counts = [r.u16()?, r.u16()?, r.u16()?, r.u16()?];
+
+        let mut msg = Message { id, flags, ..Default::default() };
+        for _ in 0..counts[0] {
+            let name = r.name()?;
+            let qtype = r.u16()?;
+            let class = r.u16()?;
+            msg.questions.push(Question { name, qtype, qclass: class & !CLASS_FLAG, unicast: class & CLASS_FLAG != 0 });
+        }
+        for (count, section) in counts[1..].iter().zip([&mut msg.answers, &mut msg.authorities, &mut msg.additionals]) {
+            for _ in 0..*count {
+                section.push(r.record()?);
+            }
+        }
+        Ok(msg)
+    }
+}
+
+/// Builds a query with one question per `(name, qtype)`.
+pub fn build_query(id: u16, questions: &[(&str, u16)], unicast: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(512);
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes()); // standard query
+    out.extend_from_slice(&(questions.len() as u16).to_be_bytes());
+    out.extend_from_slice(&[0; 6]);
+
+    let class = if unicast { CLASS_IN | CLASS_FLAG } else { CLASS_IN };
+    for (name, qtype) in questions {
+        write_name(&mut out, name);
+        out.extend_from_slice(&qtype.to_be_bytes());
+        out.extend_from_slice(&class.to_be_bytes());
+    }
+    out
+}
+
+/// Dots inside a label (DNS-SD instance names) are written as `\.` in dotted names.
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    let mut label = Vec::new();
+    let mut chars = name.trim_end_matches('.').chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.clone().next() == Some('.') => {
+                chars.next();
+                label.push(b'.');
+            }
+            '.' => write_label(out, &std::mem::take(&mut label)),
+            _ => label.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    write_label(out, &label);
+    out.push(0);
+}
+
+fn write_label(out: &mut Vec<u8>, label: &[u8]) {
+    if !label.is_empty() {
+        let label = &label[..label.len().min(63)];
+        out.push(label.len() as u8);
+        out.extend_from_slice(label);
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.buf.len())
+            .ok_or_else(|| format!("Truncated DNS message at offset {}", self.pos))?;
+        let slice = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Reads a possibly compressed name at the cursor.
+    fn name(&mut self) -> Result<String, String> {
+        let (name, next) = read_name(self.buf, self.pos)?;
+        self.pos = next;
+        Ok(name)
+    }
+
+    fn record(&mut self) -> Result<Record, String> {
+        let name = self.name()?;
+        let rtype = self.u16()?;
+        let class = self.u16()? & !CLASS_FLAG;
+        let ttl = self.u32()?;
+        let len = self.u16()? as usize;
+        let start = self.pos;
+        let rdata = self.take(len)?;
+
+        let data = match rtype {
+            TYPE_A if len == 4 => RData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3])),
+            TYPE_AAAA if len == 16 => {
+                let octets: [u8; 16] = rdata.try_into().map_err(|_| "Bad AAAA record")?;
+                RData::Aaaa(Ipv6Addr::from(octets))
+            }
+            // Names inside RDATA may point anywhere in the message, so decode against the whole buffer
+            TYPE_PTR => RData::Ptr(read_name(self.buf, start)?.0),
+            TYPE_SRV if len >= 7 => RData::Srv {
+                priority: u16::from_be_bytes([rdata[0], rdata[1]]),
+                weight: u16::from_be_bytes([rdata[2], rdata[3]]),
+                port: u16::from_be_bytes([rdata[4], rdata[5]]),
+                target: read_name(self.buf, start + 6)?.0,
+            },
+            TYPE_TXT => RData::Txt(parse_txt(rdata)),
+            _ => RData::Other(rdata.to_vec()),
+        };
+
+        Ok(Record { name, rtype, class, ttl, data })
+    }
+}
+
+/// Decodes the name at `offset`, following compression pointers.
+/// Returns the dotted name and the offset right behind it.
+fn read_name(buf: &[u8], offset: usize) -> Result<(String, usize), String> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut pos = offset;
+    let mut end = None; // where the caller continues once we followed a pointer
+    let mut jumps = 0;
+
+    loop {
+        let len = *buf.get(pos).ok_or("Truncated DNS name")? as usize;
+        match len {
+            0 => {
+                pos += 1;
+                break;
+            }
+            l if l & 0xc0 == 0xc0 => {
+                let low = *buf.get(pos + 1).ok_or("Truncated DNS name pointer")? as usize;
+                end.get_or_insert(pos + 2);
+                jumps += 1;
+                if jumps > MAX_POINTER_JUMPS {
+                    return Err("DNS name compression loop".into());
+                }
+                pos = ((l & 0x3f) << 8) | low;
+            }
+            l if l <= 63 => {
+                let label = buf.get(pos + 1..pos + 1 + l).ok_or("Truncated DNS label")?;
+                labels.push(String::from_utf8_lossy(label).replace('.', "\\."));
+                pos += 1 + l;
+            }
+            _ => return Err(format!("Invalid DNS label length {}", len)),
+        }
+    }
+
+    Ok((labels.join("."), end.unwrap_or(pos)))
+}
+
+/// TXT RDATA is a sequence of length-prefixed strings, usually `key=value`.
+fn parse_txt(mut rdata: &[u8]) -> Vec<String> {
+    let mut entries = Vec::new();
+    while let Some((&len, rest)) = rdata.split_first() {
+        let len = (len as usize).min(rest.len());
+        if len > 0 {
+            entries.push(String::from_utf8_lossy(&rest[..len]).into_owned());
+        }
+        rdata = &rest[len..];
+    }
+    entries
+}
+
+/// Looks up `key` in `key=value` TXT entries (keys are case-insensitive).
+pub fn txt_value<'a>(entries: &'a [String], key: &str) -> Option<&'a str> {
+    entries.iter().find_map(|e| {
+        let (k, v) = e.split_once('=')?;
+        (k.eq_ignore_ascii_case(key) && !v.is_empty()).then_some(v)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response with a PTR, a SRV + TXT for the instance and an A record,
+    /// names compressed against each other like real responders do.
+    fn airplay_response() -> Vec<u8> {
+        let mut m = vec![0, 0, 0x84, 0x00, 0, 0, 0, 4, 0, 0, 0, 0];
+        // 12: _airplay._tcp.local PTR Living Room._airplay._tcp.local
+        m.extend_from_slice(b"\x08_airplay\x04_tcp\x05local\x00");
+        m.extend_from_slice(&[0, 12, 0, 1, 0, 0, 0x11, 0x94, 0, 14]);
+        m.extend_from_slice(b"\x0bLiving Room\xc0\x0c");
+        let instance = 12 + 21 + 10; // offset of "Living Room" label
+        // Living Room._airplay._tcp.local SRV 0 0 7000 appletv.local
+        m.extend_from_slice(&[0xc0, instance as u8, 0, 33, 0x80, 1, 0, 0, 0, 120, 0, 16, 0, 0, 0, 0, 0x1b, 0x58]);
+        m.extend_from_slice(b"\x07appletv\xc0\x1a");
+        let host = m.len() - 10;
+        // TXT model=AppleTV6,2 deviceid=x
+        m.extend_from_slice(&[0xc0, instance as u8, 0, 16, 0x80, 1, 0, 0, 0x11, 0x94, 0, 28]);
+        m.extend_from_slice(b"\x10model=AppleTV6,2\x0adeviceid=x");
+        // appletv.local A 192.168.1.50
+        m.extend_from_slice(&[0xc0, host as u8, 0, 1, 0x80, 1, 0, 0, 0, 120, 0, 4, 192, 168, 1, 50]);
+        m
+    }
+
+    #[test]
+    fn test_parse_compressed_response() {
+        let msg = Message::parse(&airplay_response()).unwrap();
+        assert!(msg.is_response());
+        assert_eq!(msg.answers.len(), 4);
+
+        assert_eq!(msg.answers[0].data, RData::Ptr("Living Room._airplay._tcp.local".into()));
+        assert_eq!(msg.answers[1].name, "Living Room._airplay._tcp.local");
+        assert_eq!(msg.answers[1].class, CLASS_IN); // cache-flush bit stripped
+        assert_eq!(msg.answers[1].data, RData::Srv { priority: 0, weight: 0, port: 7000, target: "appletv.local".into() });
+        match &msg.answers[2].data {
+            RData::Txt(entries) => assert_eq!(txt_value(entries, "model"), Some("AppleTV6,2")),
+            other => panic!("expected TXT, got {:?}", other),
+        }
+        assert_eq!(msg.answers[3].name, "appletv.local");
+        assert_eq!(msg.answers[3].data, RData::A(Ipv4Addr::new(192, 168, 1, 50)));
+    }
+
+    #[test]
+    fn test_query_roundtrip() {
+        let query = build_query(7, &[("_services._dns-sd._udp.local", TYPE_PTR), ("host.local", TYPE_A)], true);
+        let msg = Message::parse(&query).unwrap();
+        assert!(!msg.is_response());
+        assert_eq!(msg.id, 7);
+        assert_eq!(msg.questions.len(), 2);
+        assert_eq!(msg.questions[0].name, "_services._dns-sd._udp.local");
+        assert!(msg.questions[0].unicast);
+        assert_eq!(msg.questions[1].qtype, TYPE_A);
+
+        // A dot inside an instance label survives the roundtrip
+        let query = build_query(0, &[("Kitchen\\.speaker._googlecast._tcp.local", TYPE_SRV)], false);
+        assert_eq!(&query[12..28], b"\x0fKitchen.speaker");
+        assert_eq!(Message::parse(&query).unwrap().questions[0].name, "Kitchen\\.speaker._googlecast._tcp.local");
+    }
+
+    #[test]
+    fn test_malformed() {
+        // Pointer to itself
+        let mut looped = vec![0, 0, 0x84, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        looped.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1]);
+        assert!(Message::parse(&looped).is_err());
+
+        // Truncated in the middle of a record
+        let response = airplay_response();
+        assert!(Message::parse(&response[..response.len() - 3]).is_err());
+        assert!(Message::parse(&[0, 1]).is_err());
+    }
+}
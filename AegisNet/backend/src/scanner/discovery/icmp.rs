@@ -1,48 +1,281 @@
-use tokio::process::Command;
-use std::time::Duration;
-use tokio::sync::mpsc;
+use std::collections::HashMap;
+use std::io;
+use std::mem::MaybeUninit;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use socket2::{Socket, Domain, Type, Protocol, SockAddr};
+use crate::scanner::target::ScanTarget;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation};
+use super::StopOnDrop;
+
+const ECHO_REQUEST: u8 = 8;
+const ECHO_REPLY: u8 = 0;
+const PAYLOAD: &[u8] = b"AegisNet-sweep!!";
+// How long to keep listening after the last echo went out
+const REPLY_WAIT: Duration = Duration::from_secs(1);
+
+/// An answered echo request.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IcmpReply {
+    pub rtt: Duration,
+    pub ttl: Option<u8>, // None when the socket can't report it
+    pub df: Option<bool>, // IP "don't fragment" bit, raw sockets only
+}
 
 pub struct IcmpScanner;
 
 impl IcmpScanner {
-    // Aggressive ping sweep of the subnet
-    pub async fn scan_subnet(subnet: &str) -> Vec<String> {
-        let base_ip = subnet.split('/').next().unwrap_or("127.0.0.1");
-        let parts: Vec<&str> = base_ip.split('.').collect();
-        if parts.len() != 4 { return vec![]; }
-        
-        let prefix = format!("{}.{}.{}.", parts[0], parts[1], parts[2]);
-        let (tx, mut rx) = mpsc::channel(255);
-
-        // Ping every host 1-254
-        for i in 1..255 {
-            let target = format!("{}{}", prefix, i);
-            let tx = tx.clone();
-            tokio::spawn(async move {
-                if ping_host(&target).await {
-                    let _ = tx.send(target).await;
-                }
-            });
+    /// Echo sweep of the target range from a single in-process socket.
+    /// Prefers a raw socket (needs CAP_NET_RAW / Administrator) and falls back
+    /// to an unprivileged datagram ICMP socket (Linux `ping_group_range`, macOS).
+    pub async fn scan_subnet(targets: &ScanTarget, interval: Duration) -> io::Result<HashMap<Ipv4Addr, IcmpReply>> {
+        let (socket, raw) = open_socket()?;
+        let socket = Arc::new(socket);
+
+        // The identifier tells our sweep apart from others. Replies are matched by
+        // source address: the 16-bit sequence number wraps on ranges over 64k hosts.
+        let hosts = targets.hosts();
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        let ident = std::process::id() as u16 ^ nanos as u16;
+        let sent: Arc<Mutex<HashMap<Ipv4Addr, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let receiver = {
+            let (socket, sent, stop) = (socket.clone(), sent.clone(), stop.clone());
+            tokio::task::spawn_blocking(move || receive(&socket, raw, ident, &sent, &stop))
+        };
+        let _guard = StopOnDrop(stop.clone());
+
+        for (seq, ip) in hosts.iter().enumerate() {
+            let packet = build_echo(ident, seq as u16);
+            if let Ok(mut sent) = sent.lock() {
+                sent.insert(*ip, Instant::now());
+            }
+            let addr: SockAddr = SocketAddr::new((*ip).into(), 0).into();
+            if let Err(e) = socket.send_to(&packet, &addr) {
+                tracing::debug!("ICMP echo to {} failed: {}", ip, e);
+            }
+            // Rate limit from the scan profile
+            if !interval.is_zero() {
+                tokio::time::sleep(interval).await;
+            }
+        }
+
+        tokio::time::sleep(REPLY_WAIT).await;
+        stop.store(true, Ordering::Relaxed);
+        Ok(receiver.await.unwrap_or_default())
+    }
+}
+
+impl DiscoveryModule for IcmpScanner {
+    fn name(&self) -> &'static str {
+        "icmp"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Icmp
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            let replies = Self::scan_subnet(ctx.target, ctx.profile.probe_interval()).await
+                .map_err(|e| format!("ICMP sweep unavailable: {}", e))?;
+            Ok(replies.into_iter()
+                .map(|(ip, reply)| Observation::new(self.name(), ip.into(), Evidence::Icmp(reply)))
+                .collect())
+        })
+    }
+}
+
+fn open_socket() -> io::Result<(Socket, bool)> {
+    let (socket, raw) = match Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)) {
+        Ok(s) => (s, true),
+        Err(_) => (Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::ICMPV4))?, false),
+    };
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+    #[cfg(target_os = "linux")]
+    if !raw {
+        // Datagram ICMP sockets strip the IP header, ask for the TTL as ancillary data
+        use std::os::fd::AsRawFd;
+        let on: libc::c_int = 1;
+        unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_IP,
+                libc::IP_RECVTTL,
+                &on as *const _ as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as u32,
+            );
         }
-        drop(tx);
-        
-        let mut alive = Vec::new();
-        while let Some(ip) = rx.recv().await {
-            alive.push(ip);
+    }
+    Ok((socket, raw))
+}
+
+fn receive(
+    socket: &Socket,
+    raw: bool,
+    ident: u16,
+    sent: &Mutex<HashMap<Ipv4Addr, Instant>>,
+    stop: &AtomicBool,
+) -> HashMap<Ipv4Addr, IcmpReply> {
+    let mut replies = HashMap::new();
+    let mut buf = [0u8; 1500];
+
+    while !stop.load(Ordering::Relaxed) {
+        let Ok((n, from, cmsg_ttl)) = recv(socket, raw, &mut buf) else { continue };
+
+        let parsed = if raw {
+            parse_ipv4(&buf[..n])
+        } else {
+            Some(Datagram { from, ttl: cmsg_ttl, df: None, payload: &buf[..n] })
+        };
+        let Some(Datagram { from, ttl, df, payload }) = parsed else { continue };
+        let Some((reply_ident, _)) = parse_echo_reply(payload) else { continue };
+
+        // Datagram sockets rewrite the identifier, the kernel already filtered for us
+        if raw && reply_ident != ident {
+            continue;
         }
-        alive
+        // Only hosts we already pinged, anything else is stray traffic
+        let Some(started) = sent.lock().ok().and_then(|s| s.get(&from).copied()) else { continue };
+
+        replies.entry(from).or_insert(IcmpReply { rtt: started.elapsed(), ttl, df });
     }
+    replies
 }
 
-async fn ping_host(ip: &str) -> bool {
-    // Windows ping: -n 1 (count), -w 200 (timeout in ms)
-    let output = Command::new("ping")
-        .args(&["-n", "1", "-w", "200", ip])
-        .output()
-        .await;
+/// Reads one datagram: payload length, source address and (datagram sockets on Linux) the TTL.
+fn recv(socket: &Socket, raw: bool, buf: &mut [u8]) -> io::Result<(usize, Ipv4Addr, Option<u8>)> {
+    #[cfg(target_os = "linux")]
+    if !raw {
+        return recv_with_ttl(socket, buf);
+    }
+    let _ = raw;
+
+    // Safety: recv_from only writes initialized bytes into the buffer
+    let uninit = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+    let (n, addr) = socket.recv_from(uninit)?;
+    let from = match addr.as_socket() {
+        Some(SocketAddr::V4(a)) => *a.ip(),
+        _ => Ipv4Addr::UNSPECIFIED,
+    };
+    Ok((n, from, None))
+}
+
+#[cfg(target_os = "linux")]
+fn recv_with_ttl(socket: &Socket, buf: &mut [u8]) -> io::Result<(usize, Ipv4Addr, Option<u8>)> {
+    use std::os::fd::AsRawFd;
+
+    let mut from: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    let mut control = [0u8; 64];
+    let mut iov = libc::iovec { iov_base: buf.as_mut_ptr() as *mut libc::c_void, iov_len: buf.len() };
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_name = &mut from as *mut _ as *mut libc::c_void;
+    msg.msg_namelen = std::mem::size_of::<libc::sockaddr_in>() as u32;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = control.len();
+
+    let n = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut ttl = None;
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::IPPROTO_IP && (*cmsg).cmsg_type == libc::IP_TTL {
+                let value = *(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+                ttl = u8::try_from(value).ok();
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    Ok((n as usize, Ipv4Addr::from(u32::from_be(from.sin_addr.s_addr)), ttl))
+}
+
+/// A received ICMP message and what the IP layer told us about it.
+struct Datagram<'a> {
+    from: Ipv4Addr,
+    ttl: Option<u8>,
+    df: Option<bool>,
+    payload: &'a [u8],
+}
+
+/// Splits a raw IPv4 datagram into header fields and ICMP payload.
+fn parse_ipv4(packet: &[u8]) -> Option<Datagram<'_>> {
+    if packet.len() < 20 || packet[0] >> 4 != 4 || packet[9] != 1 {
+        return None;
+    }
+    let ihl = ((packet[0] & 0x0f) as usize) * 4;
+    if packet.len() < ihl + 8 {
+        return None;
+    }
+    let from = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    Some(Datagram { from, ttl: Some(packet[8]), df: Some(packet[6] & 0x40 != 0), payload: &packet[ihl..] })
+}
+
+/// Identifier and sequence number of an echo reply.
+fn parse_echo_reply(icmp: &[u8]) -> Option<(u16, u16)> {
+    if icmp.len() < 8 || icmp[0] != ECHO_REPLY || icmp[1] != 0 {
+        return None;
+    }
+    Some((u16::from_be_bytes([icmp[4], icmp[5]]), u16::from_be_bytes([icmp[6], icmp[7]])))
+}
+
+fn build_echo(ident: u16, seq: u16) -> Vec<u8> {
+    let mut packet = vec![ECHO_REQUEST, 0, 0, 0];
+    packet.extend_from_slice(&ident.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(PAYLOAD);
+    let sum = checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    packet
+}
+
+/// RFC 1071 internet checksum.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data.chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_echo_checksum() {
+        let packet = build_echo(0x1234, 7);
+        // A correct checksum makes the whole packet sum to zero
+        assert_eq!(checksum(&packet), 0);
+        assert_eq!(&packet[4..8], &[0x12, 0x34, 0x00, 0x07]);
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        let mut icmp = build_echo(0xbeef, 42);
+        icmp[0] = ECHO_REPLY;
+        assert_eq!(parse_echo_reply(&icmp), Some((0xbeef, 42)));
+        assert_eq!(parse_echo_reply(&build_echo(0xbeef, 42)), None); // our own request
 
-    match output {
-        Ok(out) => out.status.success(),
-        Err(_) => false,
+        let mut packet = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 128, 1, 0, 0, 192, 168, 1, 20, 192, 168, 1, 10];
+        packet.extend_from_slice(&icmp);
+        let datagram = parse_ipv4(&packet).unwrap();
+        assert_eq!(datagram.from, Ipv4Addr::new(192, 168, 1, 20));
+        assert_eq!(datagram.ttl, Some(128));
+        assert_eq!(datagram.df, Some(false));
+        assert_eq!(parse_echo_reply(datagram.payload), Some((0xbeef, 42)));
     }
 }
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem::MaybeUninit;
+use std::net::{Ipv6Addr, SocketAddrV6};
+use crate::scanner::addr::MacAddr;
+use std::time::{Duration, Instant};
+use socket2::{Socket, Domain, Type, Protocol, SockAddr};
+use tokio::process::Command;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation};
+
+// ICMPv6 message types
+const ECHO_REQUEST: u8 = 128;
+const ECHO_REPLY: u8 = 129;
+const ROUTER_SOLICITATION: u8 = 133;
+const ROUTER_ADVERTISEMENT: u8 = 134;
+const NEIGHBOR_SOLICITATION: u8 = 135;
+const NEIGHBOR_ADVERTISEMENT: u8 = 136;
+
+// NDP options carrying the sender's / target's link-layer address
+const OPT_SOURCE_LLADDR: u8 = 1;
+const OPT_TARGET_LLADDR: u8 = 2;
+const NA_FLAG_ROUTER: u8 = 0x80;
+
+const ALL_NODES: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+const ALL_ROUTERS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 2);
+const PAYLOAD: &[u8] = b"AegisNet-sweep!!";
+const LISTEN_WINDOW: Duration = Duration::from_secs(3);
+
+/// An IPv6 address seen on the local link.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ipv6Neighbor {
+    pub mac: Option<MacAddr>,
+    pub router: bool,
+}
+
+pub struct Ipv6Scanner;
+
+impl Ipv6Scanner {
+    /// Pings all-nodes and solicits routers on every IPv6 interface, then
+    /// listens for echo replies and neighbor discovery traffic. Addresses
+    /// still in their duplicate address check (fresh SLAAC and privacy
+    /// addresses) show up through their neighbor solicitations.
+    /// The OS neighbor cache fills in the MACs the wire did not tell us.
+    pub async fn scan() -> HashMap<Ipv6Addr, Ipv6Neighbor> {
+        let mut neighbors = match tokio::task::spawn_blocking(sweep).await {
+            Ok(Ok(found)) => found,
+            Ok(Err(e)) => {
+                tracing::warn!("ICMPv6 sweep unavailable ({}), falling back to the neighbor cache", e);
+                HashMap::new()
+            }
+            Err(_) => HashMap::new(),
+        };
+
+        for (addr, cached) in neighbor_cache().await {
+            let entry = neighbors.entry(addr).or_default();
+            if entry.mac.is_none() { entry.mac = cached.mac; }
+            entry.router |= cached.router;
+        }
+        neighbors
+    }
+}
+
+impl DiscoveryModule for Ipv6Scanner {
+    fn name(&self) -> &'static str {
+        "ipv6"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Ipv6
+    }
+
+    fn discover<'a>(&'a self, _ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            // Falls back to the neighbor cache by itself, never fails outright
+            Ok(Self::scan().await.into_iter()
+                .map(|(addr, neighbor)| {
+                    Observation::new(self.name(), addr.into(), Evidence::Ndp { router: neighbor.router }).mac(neighbor.mac)
+                })
+                .collect())
+        })
+    }
+}
+
+/// Global addresses first, then unique-local, then link-local: the order in
+/// which an address is worth showing as a host's primary one.
+pub fn sort_addresses(addrs: &mut [Ipv6Addr]) {
+    addrs.sort_by_key(|ip| {
+        let rank = if ip.is_unicast_link_local() {
+            2
+        } else if ip.is_unique_local() {
+            1
+        } else {
+            0
+        };
+        (rank, *ip)
+    });
+}
+
+fn sweep() -> io::Result<HashMap<Ipv6Addr, Ipv6Neighbor>> {
+    // Raw ICMPv6 (CAP_NET_RAW / Administrator), the kernel fills in the checksum
+    let socket = Socket::new(Domain::IPV6, Type::RAW, Some(Protocol::ICMPV6))?;
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+    // NDP messages are only accepted with a hop limit of 255
+    socket.set_multicast_hops_v6(255)?;
+    socket.set_multicast_loop_v6(false)?;
+
+    let echo = build_echo();
+    let solicit = [ROUTER_SOLICITATION, 0, 0, 0, 0, 0, 0, 0];
+    for index in interfaces() {
+        if let Err(e) = socket.set_multicast_if_v6(index) {
+            tracing::debug!("Cannot send ICMPv6 on interface {}: {}", index, e);
+            continue;
+        }
+        for (packet, group) in [(&echo[..], ALL_NODES), (&solicit[..], ALL_ROUTERS)] {
+            let addr: SockAddr = SocketAddrV6::new(group, 0, 0, index).into();
+            if let Err(e) = socket.send_to(packet, &addr) {
+                tracing::debug!("ICMPv6 to {} on interface {} failed: {}", group, index, e);
+            }
+        }
+    }
+
+    let mut neighbors: HashMap<Ipv6Addr, Ipv6Neighbor> = HashMap::new();
+    let mut buf = [MaybeUninit::<u8>::uninit(); 1500];
+    let deadline = Instant::now() + LISTEN_WINDOW;
+    while Instant::now() < deadline {
+        let Ok((n, from)) = socket.recv_from(&mut buf) else { continue };
+        let Some(from) = from.as_socket_ipv6().map(|a| *a.ip()) else { continue };
+        // Safety: recv_from initialized the first n bytes
+        let packet: Vec<u8> = buf[..n].iter().map(|b| unsafe { b.assume_init() }).collect();
+
+        if let Some((addr, seen)) = parse_message(from, &packet) {
+            let entry = neighbors.entry(addr).or_default();
+            if entry.mac.is_none() { entry.mac = seen.mac; }
+            entry.router |= seen.router;
+        }
+    }
+    Ok(neighbors)
+}
+
+/// Interface indexes with IPv6 enabled (Linux), or the default interface elsewhere.
+fn interfaces() -> Vec<u32> {
+    let Ok(table) = std::fs::read_to_string("/proc/net/if_inet6") else { return vec![0] };
+    let mut indexes = parse_if_inet6(&table);
+    if indexes.is_empty() {
+        indexes.push(0);
+    }
+    indexes
+}
+
+/// `address ifindex prefixlen scope flags name`, one line per configured address.
+fn parse_if_inet6(table: &str) -> Vec<u32> {
+    let mut indexes = Vec::new();
+    for line in table.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 6 || parts[5] == "lo" {
+            continue;
+        }
+        if let Ok(index) = u32::from_str_radix(parts[1], 16) {
+            if !indexes.contains(&index) {
+                indexes.push(index);
+            }
+        }
+    }
+    indexes
+}
+
+/// The address a received ICMPv6 message vouches for, and what it says about it.
+fn parse_message(from: Ipv6Addr, icmp: &[u8]) -> Option<(Ipv6Addr, Ipv6Neighbor)> {
+    if icmp.len() < 8 {
+        return None;
+    }
+    let (addr, neighbor) = match icmp[0] {
+        ECHO_REPLY => (from, Ipv6Neighbor::default()),
+        ROUTER_ADVERTISEMENT if icmp.len() >= 16 => {
+            (from, Ipv6Neighbor { mac: lladdr_option(&icmp[16..], OPT_SOURCE_LLADDR), router: true })
+        }
+        NEIGHBOR_SOLICITATION if icmp.len() >= 24 => {
+            let target = ipv6_at(icmp, 8)?;
+            if from.is_unspecified() {
+                // Duplicate address detection: the sender is about to start using `target`
+                (target, Ipv6Neighbor::default())
+            } else {
+                (from, Ipv6Neighbor { mac: lladdr_option(&icmp[24..], OPT_SOURCE_LLADDR), router: false })
+            }
+        }
+        NEIGHBOR_ADVERTISEMENT if icmp.len() >= 24 => {
+            let router = icmp[4] & NA_FLAG_ROUTER != 0;
+            (ipv6_at(icmp, 8)?, Ipv6Neighbor { mac: lladdr_option(&icmp[24..], OPT_TARGET_LLADDR), router })
+        }
+        _ => return None,
+    };
+    (is_neighbor_address(&addr)).then_some((addr, neighbor))
+}
+
+fn ipv6_at(data: &[u8], offset: usize) -> Option<Ipv6Addr> {
+    let octets: [u8; 16] = data.get(offset..offset + 16)?.try_into().ok()?;
+    Some(Ipv6Addr::from(octets))
+}
+
+/// Ethernet address from a source/target link-layer address option.
+fn lladdr_option(mut options: &[u8], wanted: u8) -> Option<MacAddr> {
+    while options.len() >= 2 {
+        let len = options[1] as usize * 8; // in units of 8 octets, including type and length
+        if len == 0 || len > options.len() {
+            return None;
+        }
+        if options[0] == wanted && len >= 8 {
+            return MacAddr::from_slice(&options[2..8]);
+        }
+        options = &options[len..];
+    }
+    None
+}
+
+fn is_neighbor_address(addr: &Ipv6Addr) -> bool {
+    !addr.is_unspecified() && !addr.is_loopback() && !addr.is_multicast()
+}
+
+fn build_echo() -> Vec<u8> {
+    let ident = std::process::id() as u16;
+    let mut packet = vec![ECHO_REQUEST, 0, 0, 0];
+    packet.extend_from_slice(&ident.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet.extend_from_slice(PAYLOAD);
+    packet
+}
+
+/// The OS neighbor cache: `ip -6 neigh` (Linux), `ndp -an` (BSD/macOS),
+/// `netsh interface ipv6 show neighbors` (Windows).
+async fn neighbor_cache() -> HashMap<Ipv6Addr, Ipv6Neighbor> {
+    let commands: [(&str, &[&str]); 3] = [
+        ("ip", &["-6", "neigh", "show"]),
+        ("ndp", &["-an"]),
+        ("netsh", &["interface", "ipv6", "show", "neighbors"]),
+    ];
+    for (program, args) in commands {
+        if let Ok(o) = Command::new(program).args(args).output().await {
+            if o.status.success() {
+                return parse_neighbor_table(&String::from_utf8_lossy(&o.stdout));
+            }
+        }
+    }
+    HashMap::new()
+}
+
+/// One entry per line: the address first, a MAC somewhere after it and
+/// "router" (Linux, Windows) or an `R` flag (macOS) for routers.
+fn parse_neighbor_table(stdout: &str) -> HashMap<Ipv6Addr, Ipv6Neighbor> {
+    let mut map = HashMap::new();
+    for line in stdout.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let Some(first) = parts.first() else { continue };
+        let addr = first.split('%').next().unwrap_or(first);
+        let Ok(addr) = addr.parse::<Ipv6Addr>() else { continue };
+        let Some(mac) = parts.iter().skip(1).find_map(|p| p.parse::<MacAddr>().ok()) else { continue };
+        if !is_neighbor_address(&addr) || mac.is_unspecified() {
+            continue;
+        }
+        let router = parts.iter().skip(1).any(|p| p.eq_ignore_ascii_case("router") || p.eq_ignore_ascii_case("(router)") || *p == "R");
+        map.insert(addr, Ipv6Neighbor { mac: Some(mac), router });
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Ipv6Addr {
+        s.parse().unwrap()
+    }
+
+    fn mac(s: &str) -> Option<MacAddr> {
+        s.parse().ok()
+    }
+
+    #[test]
+    fn test_parse_ndp() {
+        let router = addr("fe80::1");
+        let mut na = vec![NEIGHBOR_ADVERTISEMENT, 0, 0, 0, 0xe0, 0, 0, 0];
+        na.extend_from_slice(&router.octets());
+        na.extend_from_slice(&[OPT_TARGET_LLADDR, 1, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        let (seen, neighbor) = parse_message(router, &na).unwrap();
+        assert_eq!(seen, router);
+        assert_eq!(neighbor, Ipv6Neighbor { mac: mac("00:11:22:33:44:55"), router: true });
+
+        // DAD probe for a new privacy address: unspecified source, no link-layer option
+        let tentative = addr("2001:db8::8d3a:91ff:2c4e:1a07");
+        let mut ns = vec![NEIGHBOR_SOLICITATION, 0, 0, 0, 0, 0, 0, 0];
+        ns.extend_from_slice(&tentative.octets());
+        let (seen, neighbor) = parse_message(Ipv6Addr::UNSPECIFIED, &ns).unwrap();
+        assert_eq!(seen, tentative);
+        assert_eq!(neighbor.mac, None);
+
+        let mut ra = vec![ROUTER_ADVERTISEMENT, 0, 0, 0, 64, 0, 0x07, 0x08, 0, 0, 0, 0, 0, 0, 0, 0];
+        ra.extend_from_slice(&[5, 1, 0, 0, 0, 0, 0x05, 0xdc]); // MTU option first
+        ra.extend_from_slice(&[OPT_SOURCE_LLADDR, 1, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        let (_, neighbor) = parse_message(router, &ra).unwrap();
+        assert!(neighbor.router);
+        assert_eq!(neighbor.mac, mac("00:11:22:33:44:55"));
+
+        let mut echo = build_echo();
+        echo[0] = ECHO_REPLY;
+        assert_eq!(parse_message(router, &echo).map(|(a, _)| a), Some(router));
+        assert_eq!(parse_message(router, &build_echo()), None); // our own request
+        assert_eq!(parse_message(router, &[NEIGHBOR_ADVERTISEMENT, 0, 0]), None);
+    }
+
+    #[test]
+    fn test_parse_neighbor_tables() {
+        let linux = "fe80::1 dev eth0 lladdr 00:11:22:33:44:55 router STALE\n\
+                     2001:db8::42 dev eth0 lladdr a4:83:e7:01:02:03 REACHABLE\n\
+                     2001:db8::99 dev eth0 FAILED\n";
+        let map = parse_neighbor_table(linux);
+        assert_eq!(map.len(), 2);
+        assert!(map[&addr("fe80::1")].router);
+        assert_eq!(map[&addr("2001:db8::42")].mac, mac("A4:83:E7:01:02:03"));
+
+        let macos = "Neighbor                        Linklayer Address  Netif Expire    St Flgs Prbs\n\
+                     fe80::1%en0                     0:11:22:33:44:55   en0 23h59m58s S  R\n";
+        assert_eq!(parse_neighbor_table(macos)[&addr("fe80::1")], Ipv6Neighbor { mac: mac("00:11:22:33:44:55"), router: true });
+
+        let windows = "Internet Address                              Physical Address   Type\n\
+                       fe80::1                                       00-11-22-33-44-55  Reachable (Router)\n\
+                       ff02::1                                       33-33-00-00-00-01  Permanent\n";
+        let map = parse_neighbor_table(windows);
+        assert_eq!(map.len(), 1);
+        assert!(map[&addr("fe80::1")].router);
+    }
+
+    #[test]
+    fn test_sort_addresses() {
+        let mut addrs = vec![addr("fe80::1"), addr("fd00::5"), addr("2001:db8::5")];
+        sort_addresses(&mut addrs);
+        assert_eq!(addrs, vec![addr("2001:db8::5"), addr("fd00::5"), addr("fe80::1")]);
+    }
+
+    #[test]
+    fn test_parse_if_inet6() {
+        let table = "00000000000000000000000000000001 01 80 10 80       lo\n\
+                     fe800000000000000211223344556677 02 40 20 80     eth0\n\
+                     20010db8000000000211223344556677 02 40 00 00     eth0\n\
+                     fe80000000000000021122fffe334455 0a 40 20 80    wlan0\n";
+        assert_eq!(parse_if_inet6(table), vec![2, 10]);
+    }
+}
@@ -1,56 +1,332 @@
 use tokio::net::UdpSocket;
-use std::net::{Ipv4Addr, SocketAddr};
-use tokio::sync::mpsc;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use socket2::{Socket, Domain, Type, Protocol};
 use std::time::Duration;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap};
+use super::dns::{self, Message, RData};
+use std::io;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation};
+
+const LLMNR_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 252);
+const LLMNR_PORT: u16 = 5355;
+const NBNS_PORT: u16 = 137;
+const TYPE_NB: u16 = 0x0020;
+
+// NBNS opcodes (bits 11-14 of the flags)
+const OPCODE_QUERY: u16 = 0;
+const OPCODE_REGISTRATION: u16 = 5;
+const OPCODE_REFRESH: u16 = 8;
+const OPCODE_REFRESH_ALT: u16 = 9; // RFC 1002 typo, still sent by some stacks
+const OPCODE_MULTIHOMED: u16 = 15; // multi-homed name registration
+// NB_FLAGS of a group name (workgroups), not owned by a single host
+const NB_GROUP: u8 = 0x80;
+
+const CANARY_ROUNDS: u32 = 2;
+const CANARY_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct LlmnrListener;
 
+/// Name resolution traffic seen from one host.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NameActivity {
+    pub queried: BTreeSet<String>,    // names it asked the segment for
+    pub registered: BTreeSet<String>, // unique NetBIOS names it claimed for itself
+    pub spoofed: BTreeSet<String>,    // names it answered for without owning them
+}
+
+impl NameActivity {
+    /// The host's own name, unless it answers for anything it is asked.
+    pub fn hostname(&self) -> Option<&str> {
+        if !self.spoofed.is_empty() {
+            return None;
+        }
+        self.registered.first().map(String::as_str)
+    }
+
+    /// Answering names it does not own is what LLMNR/NBNS poisoners (Responder) do.
+    pub fn is_poisoning(&self) -> bool {
+        !self.spoofed.is_empty()
+    }
+}
+
 impl LlmnrListener {
-    // Passive Listen on 5355
-    pub async fn listen(timeout: Duration) -> Vec<String> {
-        let (tx, mut rx) = mpsc::channel(100);
-        
-        tokio::spawn(async move {
-            let _ = listen_llmnr(tx).await;
-        });
-
-        let mut devices = HashSet::new();
+    /// Listens to LLMNR (5355) and NBNS broadcasts (137) for `timeout`. With
+    /// `canaries` it also asks both for a random name nobody owns: whoever answers
+    /// it is poisoning. Fails only when neither protocol gets a socket.
+    pub async fn listen(timeout: Duration, canaries: bool) -> io::Result<HashMap<Ipv4Addr, NameActivity>> {
+        let mut activity: HashMap<Ipv4Addr, NameActivity> = HashMap::new();
+
+        // Port 137 is often taken by nmbd, the canary still works from an ephemeral port
+        let nbns = match bind_udp(NBNS_PORT) {
+            Ok(s) => Some(s),
+            Err(e) if canaries => {
+                tracing::debug!("NBNS port 137 unavailable ({}), sending canaries only", e);
+                bind_udp(0).ok()
+            }
+            Err(e) => {
+                tracing::debug!("NBNS port 137 unavailable: {}", e);
+                None
+            }
+        };
+        let llmnr = match bind_llmnr() {
+            Ok(s) => Some(s),
+            Err(e) if nbns.is_none() => return Err(e),
+            Err(e) => {
+                tracing::warn!("LLMNR listener unavailable: {}", e);
+                None
+            }
+        };
+        if let Some(nbns) = &nbns {
+            let _ = nbns.set_broadcast(true);
+        }
+
+        // The canary is made up either way, so passive listening never flags anyone
+        let canary = canary_name();
+        let canary_rounds = if canaries { CANARY_ROUNDS } else { 0 };
+        let mut rounds = 0;
+        let mut ticker = tokio::time::interval(CANARY_INTERVAL);
+        let mut llmnr_buf = [0u8; 1500];
+        let mut nbns_buf = [0u8; 1500];
         let timeout_check = tokio::time::sleep(timeout);
         tokio::pin!(timeout_check);
 
         loop {
             tokio::select! {
-                _ = &mut timeout_check => {
-                    break;
+                _ = &mut timeout_check => break,
+                _ = ticker.tick(), if rounds < canary_rounds => {
+                    rounds += 1;
+                    send_canaries(llmnr.as_ref(), nbns.as_ref(), &canary).await;
+                }
+                Ok((len, addr)) = recv(llmnr.as_ref(), &mut llmnr_buf) => {
+                    if let (IpAddr::V4(source), Ok(msg)) = (addr.ip(), Message::parse(&llmnr_buf[..len])) {
+                        observe_llmnr(&mut activity, source, &msg, &canary);
+                    }
                 }
-                Some(ip) = rx.recv() => {
-                    devices.insert(ip);
+                Ok((len, addr)) = recv(nbns.as_ref(), &mut nbns_buf) => {
+                    if let (IpAddr::V4(source), Ok(msg)) = (addr.ip(), Message::parse(&nbns_buf[..len])) {
+                        observe_nbns(&mut activity, source, &msg, &canary);
+                    }
                 }
             }
         }
-        
-        devices.into_iter().collect()
+
+        for (ip, names) in activity.iter().filter(|(_, n)| n.is_poisoning()) {
+            tracing::warn!("{} answered name queries for {:?}, possible LLMNR/NBNS poisoning", ip, names.spoofed);
+        }
+        Ok(activity)
+    }
+}
+
+impl DiscoveryModule for LlmnrListener {
+    fn name(&self) -> &'static str {
+        "llmnr"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Llmnr
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            // Baiting poisoners with made-up names is active, passive profiles only listen
+            let activity = Self::listen(ctx.profile.llmnr_duration, ctx.profile.intrusive).await
+                .map_err(|e| format!("LLMNR/NBNS listener unavailable: {}", e))?;
+            Ok(activity.into_iter()
+                .map(|(ip, names)| {
+                    let name = names.hostname().map(str::to_string);
+                    Observation::new(self.name(), ip.into(), Evidence::Names(names)).name(name)
+                })
+                .collect())
+        })
+    }
+}
+
+fn bind_llmnr() -> std::io::Result<UdpSocket> {
+    let socket = bind_udp(LLMNR_PORT)?;
+    socket.join_multicast_v4(LLMNR_GROUP, Ipv4Addr::UNSPECIFIED)?;
+    Ok(socket)
+}
+
+// SO_REUSEADDR so we can share the port with the OS resolver
+fn bind_udp(port: u16) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(not(windows))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from(([0, 0, 0, 0], port)).into())?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+async fn recv(socket: Option<&UdpSocket>, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+    match socket {
+        Some(s) => s.recv_from(buf).await,
+        None => std::future::pending().await,
+    }
+}
+
+// Random single-label name, valid for both protocols (15 chars max for NetBIOS)
+fn canary_name() -> String {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    format!("AEGIS{}", &id[..10]).to_ascii_uppercase()
+}
+
+async fn send_canaries(llmnr: Option<&UdpSocket>, nbns: Option<&UdpSocket>, canary: &str) {
+    if let Some(socket) = llmnr {
+        let query = dns::build_query(0x4c4c, &[(canary, dns::TYPE_A)], false);
+        let _ = socket.send_to(&query, SocketAddr::from((LLMNR_GROUP, LLMNR_PORT))).await;
+    }
+    if let Some(socket) = nbns {
+        let mut query = dns::build_query(0x4e42, &[(&encode_nb_name(canary, 0x20), TYPE_NB)], false);
+        query[2..4].copy_from_slice(&0x0110u16.to_be_bytes()); // recursion desired + broadcast
+        let _ = socket.send_to(&query, SocketAddr::from((Ipv4Addr::BROADCAST, NBNS_PORT))).await;
+    }
+}
+
+fn observe_llmnr(activity: &mut HashMap<Ipv4Addr, NameActivity>, source: Ipv4Addr, msg: &Message, canary: &str) {
+    if msg.is_response() {
+        // Only answers to our own queries reach us, and we only ask for the canary
+        for record in &msg.answers {
+            if record.name.eq_ignore_ascii_case(canary) {
+                entry(activity, source).spoofed.insert(record.name.to_ascii_uppercase());
+            }
+        }
+        return;
+    }
+    for q in msg.questions.iter().filter(|q| !q.name.eq_ignore_ascii_case(canary)) {
+        entry(activity, source).queried.insert(q.name.to_ascii_lowercase());
     }
 }
 
-async fn listen_llmnr(tx: mpsc::Sender<String>) -> std::io::Result<()> {
-    let port = 5355;
-    let bind_addr = "224.0.0.252";
-    let socket = UdpSocket::bind(format!("0.0.0.0:{}", port)).await?;
-    
-    // Join multicast
-    if let Ok(addr) = bind_addr.parse::<Ipv4Addr>() {
-        let _ = socket.join_multicast_v4(addr, Ipv4Addr::UNSPECIFIED);
+fn observe_nbns(activity: &mut HashMap<Ipv4Addr, NameActivity>, source: Ipv4Addr, msg: &Message, canary: &str) {
+    let opcode = (msg.flags >> 11) & 0x0f;
+
+    if msg.is_response() {
+        for record in msg.answers.iter().filter(|r| r.rtype == TYPE_NB) {
+            if let Some((name, _)) = decode_nb_name(&record.name) {
+                if name.eq_ignore_ascii_case(canary) {
+                    entry(activity, source).spoofed.insert(name);
+                }
+            }
+        }
+        return;
     }
-    
-    let mut buf = [0u8; 1024];
-    loop {
-        if let Ok((_len, addr)) = socket.recv_from(&mut buf).await {
-            let ip = addr.ip().to_string();
-            if ip != "127.0.0.1" {
-                let _ = tx.send(ip).await;
+
+    match opcode {
+        OPCODE_QUERY => {
+            for q in &msg.questions {
+                if let Some((name, _)) = decode_nb_name(&q.name).filter(|(n, _)| !n.eq_ignore_ascii_case(canary)) {
+                    entry(activity, source).queried.insert(name);
+                }
+            }
+        }
+        OPCODE_REGISTRATION | OPCODE_REFRESH | OPCODE_REFRESH_ALT | OPCODE_MULTIHOMED => {
+            // The additional record carries the NB_FLAGS of the claimed name
+            for record in msg.additionals.iter().filter(|r| r.rtype == TYPE_NB) {
+                let group = matches!(&record.data, RData::Other(rdata) if rdata.first().is_some_and(|f| f & NB_GROUP != 0));
+                if let Some((name, _)) = decode_nb_name(&record.name).filter(|_| !group) {
+                    entry(activity, source).registered.insert(name);
+                }
             }
         }
+        _ => {}
+    }
+}
+
+fn entry(activity: &mut HashMap<Ipv4Addr, NameActivity>, source: Ipv4Addr) -> &mut NameActivity {
+    activity.entry(source).or_default()
+}
+
+/// First-level NetBIOS encoding (RFC 1001 14.1): 15 chars padded with spaces,
+/// the suffix byte, every nibble as 'A' + nibble.
+fn encode_nb_name(name: &str, suffix: u8) -> String {
+    let mut raw: Vec<u8> = name.to_ascii_uppercase().bytes().take(15).collect();
+    raw.resize(15, b' ');
+    raw.push(suffix);
+    raw.iter().flat_map(|b| [(b'A' + (b >> 4)) as char, (b'A' + (b & 0x0f)) as char]).collect()
+}
+
+/// Inverse of `encode_nb_name`, ignoring any scope ID after the first label.
+fn decode_nb_name(encoded: &str) -> Option<(String, u8)> {
+    let label = encoded.split('.').next()?.as_bytes();
+    if label.len() != 32 || !label.iter().all(|c| (b'A'..=b'P').contains(c)) {
+        return None;
+    }
+    let raw: Vec<u8> = label.chunks(2).map(|p| ((p[0] - b'A') << 4) | (p[1] - b'A')).collect();
+    let name = String::from_utf8_lossy(&raw[..15]).trim_end().to_string();
+    (!name.is_empty()).then_some((name, raw[15]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::discovery::dns::Record;
+
+    fn nb_record(name: &str, suffix: u8, data: RData) -> Record {
+        Record { name: encode_nb_name(name, suffix), rtype: TYPE_NB, class: dns::CLASS_IN, ttl: 0, data }
+    }
+
+    #[test]
+    fn test_nb_name_roundtrip() {
+        assert_eq!(encode_nb_name("*", 0)[..2], *"CK");
+        assert_eq!(encode_nb_name("FRED", 0x20), "EGFCEFEECACACACACACACACACACACACA");
+        assert_eq!(decode_nb_name("EGFCEFEECACACACACACACACACACACACA.corp.local"), Some(("FRED".into(), 0x20)));
+        assert_eq!(decode_nb_name("not-netbios"), None);
+    }
+
+    #[test]
+    fn test_passive_names() {
+        let mut activity = HashMap::new();
+        let ws = Ipv4Addr::new(192, 168, 1, 20);
+
+        // LLMNR multicast query from a workstation
+        let query = Message::parse(&dns::build_query(1, &[("fileserv", dns::TYPE_A)], false)).unwrap();
+        observe_llmnr(&mut activity, ws, &query, "AEGISCANARY");
+
+        // NBNS registration of its own name and of the workgroup
+        let registration = Message {
+            flags: OPCODE_REGISTRATION << 11,
+            additionals: vec![
+                nb_record("DESKTOP-7K2M", 0x00, RData::Other(vec![0x00, 0x00, 192, 168, 1, 20])),
+                nb_record("WORKGROUP", 0x00, RData::Other(vec![0x80, 0x00, 192, 168, 1, 20])),
+            ],
+            ..Default::default()
+        };
+        observe_nbns(&mut activity, ws, &registration, "AEGISCANARY");
+
+        let names = &activity[&ws];
+        assert!(names.queried.contains("fileserv"));
+        assert_eq!(names.registered.iter().collect::<Vec<_>>(), vec!["DESKTOP-7K2M"]);
+        assert_eq!(names.hostname(), Some("DESKTOP-7K2M"));
+        assert!(!names.is_poisoning());
+    }
+
+    #[test]
+    fn test_canary_answer_is_poisoning() {
+        let mut activity = HashMap::new();
+        let canary = canary_name();
+        assert!(canary.len() <= 15);
+        let attacker = Ipv4Addr::new(192, 168, 1, 66);
+
+        let llmnr_answer = Message {
+            flags: 0x8000,
+            answers: vec![Record { name: canary.to_ascii_lowercase(), rtype: dns::TYPE_A, class: dns::CLASS_IN, ttl: 30, data: RData::A(attacker) }],
+            ..Default::default()
+        };
+        observe_llmnr(&mut activity, attacker, &llmnr_answer, &canary);
+
+        let nbns_answer = Message {
+            flags: 0x8500,
+            answers: vec![nb_record(&canary, 0x20, RData::Other(vec![0, 0, 192, 168, 1, 66]))],
+            ..Default::default()
+        };
+        observe_nbns(&mut activity, attacker, &nbns_answer, &canary);
+
+        let names = &activity[&attacker];
+        assert!(names.is_poisoning());
+        assert_eq!(names.spoofed.len(), 1); // same canary over both protocols
+        assert_eq!(names.hostname(), None);
     }
 }
@@ -1,173 +1,449 @@
-use std::net::{Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use socket2::{Socket, Domain, Type, Protocol};
 use tokio::net::UdpSocket;
 use std::time::Duration;
-use std::collections::HashMap;
-use tokio::sync::mpsc;
-use dns_lookup::lookup_addr;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use super::dns::{self, Message, RData};
+use std::io;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation};
+
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+// DNS-SD meta-query: every responder lists the service types it advertises
+const META_QUERY: &str = "_services._dns-sd._udp.local";
+// Browsed even when nobody lists them (iPhones only answer when asked directly)
+const SEED_TYPES: &[&str] = &["_apple-mobdev2._tcp.local", "_googlecast._tcp.local"];
+const META_ROUNDS: u32 = 3;
+const QUERY_INTERVAL: Duration = Duration::from_millis(400);
+// Keeps each query well inside a single datagram
+const MAX_QUESTIONS: usize = 16;
+
+// TXT keys carrying a model name, best first: ty (IPP), md (Cast), model (AirPlay / device-info),
+// am (RAOP), usb_MDL (printers), rpMd (companion-link)
+const MODEL_KEYS: &[&str] = &["ty", "md", "model", "am", "usb_MDL", "rpMd"];
+
+// First matching service type decides the role
+const ROLES: &[(&str, &str)] = &[
+    ("_ipp._tcp", "printer"),
+    ("_ipps._tcp", "printer"),
+    ("_printer._tcp", "printer"),
+    ("_pdl-datastream._tcp", "printer"),
+    ("_rtsp._tcp", "camera"),
+    ("_axis-video._tcp", "camera"),
+    ("_hap._tcp", "smart-home"),
+    ("_hap._udp", "smart-home"),
+    ("_matter._tcp", "smart-home"),
+    ("_googlecast._tcp", "media"),
+    ("_airplay._tcp", "media"),
+    ("_raop._tcp", "media"),
+    ("_spotify-connect._tcp", "media"),
+    ("_sonos._tcp", "media"),
+    ("_companion-link._tcp", "mobile"),
+    ("_apple-mobdev2._tcp", "mobile"),
+    ("_adisk._tcp", "file-server"),
+    ("_smb._tcp", "file-server"),
+    ("_afpovertcp._tcp", "file-server"),
+    ("_nfs._tcp", "file-server"),
+    ("_ssh._tcp", "server"),
+    ("_sftp-ssh._tcp", "server"),
+];
 
 #[derive(Debug, Clone)]
 pub struct MdnsInfo {
-    pub ip: String,
-    pub hostname: Option<String>,
-    pub model: Option<String>, // extracted from TXT or PTR
-    pub device_role: Option<String>,
+    pub hostname: Option<String>, // without ".local"
+    pub model: Option<String>, // extracted from TXT
+    pub device_role: Option<String>, // "printer", "media", "smart-home", ...
+    pub services: Vec<MdnsService>,
+    pub ipv6: Vec<Ipv6Addr>, // AAAA records of the same host
+}
+
+/// One advertised DNS-SD service instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdnsService {
+    pub instance: String,     // "Living Room"
+    pub service_type: String, // "_airplay._tcp"
+    pub port: Option<u16>,
+    pub txt: Vec<String>,     // key=value entries
+}
+
+impl MdnsService {
+    /// "Living Room (model=AppleTV6,2; deviceid=x)"
+    pub fn describe(&self) -> String {
+        if self.txt.is_empty() {
+            self.instance.clone()
+        } else {
+            format!("{} ({})", self.instance, self.txt.join("; "))
+        }
+    }
 }
 
 pub struct MdnsScanner;
 
 impl MdnsScanner {
-    pub async fn scan(timeout: Duration) -> HashMap<String, MdnsInfo> {
-        let (tx, mut rx) = mpsc::channel(100);
-        
-        // Listener Task
-        let tx_listener = tx.clone();
-        tokio::spawn(async move {
-            let _ = listen_mdns(tx_listener).await;
-        });
-
-        // Broadcaster Task (Active Query) to wake devices
-        tokio::spawn(async move {
-            let _ = broadcast_query().await;
-        });
-
-        // Collect results
-        let mut devices: HashMap<String, MdnsInfo> = HashMap::new();
+    pub async fn scan(timeout: Duration) -> io::Result<HashMap<Ipv4Addr, MdnsInfo>> {
+        let socket = bind_mdns()?;
+
+        // Two-stage browse: the meta-query yields service types, every answer may raise
+        // follow-up questions (instances of a type, SRV/TXT of an instance, A of a SRV target)
+        let mut collector = MdnsCollector::default();
+        let mut asked: HashSet<(String, u16)> = HashSet::new();
+        let mut rounds = 0;
+        let mut ticker = tokio::time::interval(QUERY_INTERVAL);
+        let mut buf = [0u8; 9000]; // mDNS allows jumbo responses
         let timeout_check = tokio::time::sleep(timeout);
         tokio::pin!(timeout_check);
 
         loop {
             tokio::select! {
-                _ = &mut timeout_check => {
-                    break;
+                _ = &mut timeout_check => break,
+                _ = ticker.tick() => {
+                    let mut questions: Vec<(String, u16)> = Vec::new();
+                    if rounds < META_ROUNDS {
+                        questions.push((META_QUERY.to_string(), dns::TYPE_PTR));
+                        rounds += 1;
+                    }
+                    questions.extend(collector.follow_ups().into_iter().filter(|q| asked.insert(q.clone())));
+                    if let Err(e) = send_queries(&socket, &questions).await {
+                        tracing::debug!("mDNS query failed: {}", e);
+                    }
                 }
-                Some((ip, target_host, model_hint)) = rx.recv() => {
-                    let entry = devices.entry(ip.clone()).or_insert(MdnsInfo { 
-                        ip: ip.clone(), 
-                        hostname: None, 
-                        model: None,
-                        device_role: None // could be "printer", "tv"
-                    });
-                    
-                    if let Some(h) = target_host { entry.hostname = Some(h); }
-                    if let Some(m) = model_hint { 
-                        // Try to clean up model hint
-                         entry.model = Some(m); 
+                Ok((len, addr)) = socket.recv_from(&mut buf) => {
+                    let IpAddr::V4(source) = addr.ip() else { continue };
+                    if source.is_loopback() {
+                        continue;
+                    }
+                    match Message::parse(&buf[..len]) {
+                        Ok(msg) if msg.is_response() => collector.add(source, &msg),
+                        Ok(_) => {} // other queriers, including our own looped-back queries
+                        Err(e) => tracing::debug!("Malformed mDNS packet from {}: {}", source, e),
                     }
                 }
             }
         }
-        
-        devices
+
+        Ok(collector.finish())
+    }
+}
+
+impl DiscoveryModule for MdnsScanner {
+    fn name(&self) -> &'static str {
+        "mdns"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Mdns
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            let found = Self::scan(ctx.profile.mdns_duration).await
+                .map_err(|e| format!("mDNS listener unavailable: {}", e))?;
+            Ok(found.into_iter()
+                .map(|(ip, info)| {
+                    let name = info.hostname.clone();
+                    Observation::new(self.name(), ip.into(), Evidence::Mdns(info)).name(name)
+                })
+                .collect())
+        })
     }
 }
 
-async fn listen_mdns(tx: mpsc::Sender<(String, Option<String>, Option<String>)>) -> std::io::Result<()> {
+fn bind_mdns() -> std::io::Result<UdpSocket> {
     // Create a socket2 socket for advanced configuration (SO_REUSEADDR) - Vital for mDNS
     let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
     socket.set_reuse_address(true)?;
     #[cfg(not(windows))]
-    socket.set_reuse_port(true)?; 
+    socket.set_reuse_port(true)?;
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 5353));
+    let addr = SocketAddr::from(([0, 0, 0, 0], MDNS_PORT));
     socket.bind(&addr.into())?;
     socket.set_nonblocking(true)?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
 
-    // Join Multicast Group
-    let multi_addr = Ipv4Addr::new(224, 0, 0, 251);
-    let inter = Ipv4Addr::new(0, 0, 0, 0);
-    socket.join_multicast_v4(&multi_addr, &inter)?;
-
-    // Convert to Tokio UdpSocket
-    let udp_socket = UdpSocket::from_std(socket.into())?;
-    
-    let mut buf = [0u8; 4096]; // Increased buffer for full TXT records
-    loop {
-        if let Ok((len, addr)) = udp_socket.recv_from(&mut buf).await {
-            let ip = addr.ip().to_string();
-            if ip != "127.0.0.1" {
-                // Parse Packet Manually (Basic)
-                // We look for PTR records response or TXT records.
-                // This is a naive parser looking for keywords in the payload because full DNS parsing is heavy.
-                let payload = &buf[..len];
-                let payload_str = String::from_utf8_lossy(payload);
-                
-                let mut host_hint = None;
-                let mut model_hint = None;
-
-                // Look for ".local" in the payload which indicates hostname
-                if let Some(idx) = payload_str.find(".local") {
-                    // Try to extract the word before .local
-                    // This is very rough, a real parser would be better but requires `trust-dns` or similar crate dependency change.
-                    // For now, let's just send the IP.
-                }
+    UdpSocket::from_std(socket.into())
+}
+
+/// Queries leave from port 5353 so responders answer on the group we listen to
+/// (a query from any other port gets a legacy unicast reply to that port).
+async fn send_queries(socket: &UdpSocket, questions: &[(String, u16)]) -> std::io::Result<()> {
+    let target_addr = SocketAddr::from((MDNS_GROUP, MDNS_PORT));
+    for chunk in questions.chunks(MAX_QUESTIONS) {
+        let chunk: Vec<(&str, u16)> = chunk.iter().map(|(name, qtype)| (name.as_str(), *qtype)).collect();
+        socket.send_to(&dns::build_query(0, &chunk, false), target_addr).await?;
+    }
+    Ok(())
+}
 
-                // Apple specific: Look for "model="
-                if let Some(idx) = payload_str.find("model=") {
-                    let rest = &payload_str[idx+6..];
-                    if let Some(end) = rest.find(|c: char| !c.is_alphanumeric() && c != ',') {
-                        model_hint = Some(rest[..end].to_string());
-                    } else {
-                        model_hint = Some(rest.to_string());
+/// Accumulates records from every response and resolves them per host at the end.
+/// Responders spread one service over PTR, SRV, TXT and A records, often in separate packets.
+#[derive(Default)]
+struct MdnsCollector {
+    addresses: HashMap<String, Vec<Ipv4Addr>>,    // host.local -> A
+    addresses_v6: HashMap<String, Vec<Ipv6Addr>>, // host.local -> AAAA
+    srv: HashMap<String, (String, u16)>,          // instance fqdn -> (host.local, port)
+    txt: HashMap<String, Vec<String>>,            // instance fqdn -> TXT entries
+    instances: BTreeMap<String, Ipv4Addr>,        // instance fqdn -> first responder
+    service_types: BTreeSet<String>,              // answers to the meta-query
+    reverse: HashMap<Ipv4Addr, String>,           // in-addr.arpa PTR -> host.local
+    display: HashMap<String, String>,             // lowercased name -> name as advertised
+    responders: Vec<Ipv4Addr>,
+}
+
+impl MdnsCollector {
+    fn add(&mut self, source: Ipv4Addr, msg: &Message) {
+        if !self.responders.contains(&source) {
+            self.responders.push(source);
+        }
+
+        for record in msg.records() {
+            let name = record.name.to_ascii_lowercase();
+            match &record.data {
+                RData::A(addr) => {
+                    self.display.entry(name.clone()).or_insert_with(|| record.name.clone());
+                    push_unique(self.addresses.entry(name).or_default(), *addr);
+                }
+                RData::Aaaa(addr) => push_unique(self.addresses_v6.entry(name).or_default(), *addr),
+                RData::Ptr(target) => {
+                    if let Some(addr) = parse_reverse(&name) {
+                        self.display.entry(target.to_ascii_lowercase()).or_insert_with(|| target.clone());
+                        self.reverse.insert(addr, target.to_ascii_lowercase());
+                    } else if name == META_QUERY {
+                        self.service_types.insert(target.clone());
+                    } else if split_instance(target).is_some() {
+                        self.instances.entry(target.clone()).or_insert(source);
                     }
                 }
-                
-                // Device Info TXT
-                 if let Some(idx) = payload_str.find("device-info") {
-                     // often contains model info
-                 }
+                RData::Srv { port, target, .. } => {
+                    self.display.entry(target.to_ascii_lowercase()).or_insert_with(|| target.clone());
+                    self.srv.insert(record.name.clone(), (target.to_ascii_lowercase(), *port));
+                    self.instances.entry(record.name.clone()).or_insert(source);
+                }
+                RData::Txt(entries) => {
+                    if split_instance(&record.name).is_some() {
+                        self.txt.insert(record.name.clone(), entries.clone());
+                        self.instances.entry(record.name.clone()).or_insert(source);
+                    }
+                }
+                RData::Other(_) => {}
+            }
+        }
+    }
+
+    /// Questions that would complete what we have seen so far.
+    fn follow_ups(&self) -> Vec<(String, u16)> {
+        let mut questions: Vec<(String, u16)> = SEED_TYPES.iter().map(|t| t.to_string())
+            .chain(self.service_types.iter().cloned())
+            .map(|t| (t, dns::TYPE_PTR))
+            .collect();
+        for fqdn in self.instances.keys() {
+            if !self.srv.contains_key(fqdn) {
+                questions.push((fqdn.clone(), dns::TYPE_SRV));
+            }
+            if !self.txt.contains_key(fqdn) {
+                questions.push((fqdn.clone(), dns::TYPE_TXT));
+            }
+        }
+        for (target, _) in self.srv.values() {
+            if !self.addresses.contains_key(target) {
+                questions.push((self.display.get(target).unwrap_or(target).clone(), dns::TYPE_A));
+            }
+        }
+        questions
+    }
+
+    fn hostname(&self, name: &str) -> String {
+        strip_local(self.display.get(name).map(String::as_str).unwrap_or(name))
+    }
+
+    fn finish(self) -> HashMap<Ipv4Addr, MdnsInfo> {
+        let mut devices: HashMap<Ipv4Addr, MdnsInfo> = HashMap::new();
+        for ip in &self.responders {
+            info_for(&mut devices, *ip);
+        }
+
+        // Hostnames from A records (and reverse PTRs), AAAA from the same name
+        for (name, addrs) in &self.addresses {
+            for addr in addrs {
+                let info = info_for(&mut devices, *addr);
+                info.hostname.get_or_insert_with(|| self.hostname(name));
+                for v6 in self.addresses_v6.get(name).into_iter().flatten() {
+                    push_unique(&mut info.ipv6, *v6);
+                }
+            }
+        }
+        for (addr, name) in &self.reverse {
+            info_for(&mut devices, *addr).hostname.get_or_insert_with(|| self.hostname(name));
+        }
+
+        // Services land on the SRV target's address, or on whoever announced them
+        for (fqdn, responder) in &self.instances {
+            let Some((instance, service_type)) = split_instance(fqdn) else { continue };
+            let srv = self.srv.get(fqdn);
+            let ip = srv
+                .and_then(|(target, _)| self.addresses.get(target))
+                .and_then(|addrs| addrs.first().copied())
+                .unwrap_or(*responder);
 
-                let _ = tx.send((ip, host_hint, model_hint)).await;
+            let info = info_for(&mut devices, ip);
+            if let Some((target, _)) = srv {
+                info.hostname.get_or_insert_with(|| self.hostname(target));
             }
+            info.services.push(MdnsService {
+                instance,
+                service_type,
+                port: srv.map(|(_, port)| *port),
+                txt: self.txt.get(fqdn).cloned().unwrap_or_default(),
+            });
         }
+
+        for info in devices.values_mut() {
+            info.model = MODEL_KEYS.iter().find_map(|key| {
+                info.services.iter().find_map(|s| dns::txt_value(&s.txt, key)).map(str::to_string)
+            });
+            info.device_role = ROLES.iter()
+                .find(|(service_type, _)| info.services.iter().any(|s| s.service_type == *service_type))
+                .map(|(_, role)| role.to_string());
+        }
+
+        devices
     }
 }
 
-async fn broadcast_query() -> std::io::Result<()> {
-    // Use an ephemeral socket for sending
-    let socket = UdpSocket::bind("0.0.0.0:0").await?;
-    let target_addr: SocketAddr = "224.0.0.251:5353".parse().unwrap();
-
-    // 1. _services._dns-sd._udp.local
-    let query_services = [
-        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 
-        0x09, 0x5f, 0x73, 0x65, 0x72, 0x76, 0x69, 0x63, 0x65, 0x73, 
-        0x07, 0x5f, 0x64, 0x6e, 0x73, 0x2d, 0x73, 0x64, 
-        0x04, 0x5f, 0x75, 0x64, 0x70, 
-        0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 
-        0x00, 
-        0x00, 0x0c, 0x00, 0x01, 
-    ];
-
-    // 2. _apple-mobdev2._tcp.local
-    let query_apple = [
-        0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0x0e, 0x5f, 0x61, 0x70, 0x70, 0x6c, 0x65, 0x2d, 0x6d, 0x6f, 0x62, 0x64, 0x65, 0x76, 0x32,
-        0x04, 0x5f, 0x74, 0x63, 0x70,
-        0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c,
-        0x00,
-        0x00, 0x0c, 0x00, 0x01,
-    ];
-    
-    // 3. _googlecast._tcp.local
-     let query_google = [
-        0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0x0b, 0x5f, 0x67, 0x6f, 0x6f, 0x67, 0x6c, 0x65, 0x63, 0x61, 0x73, 0x74,
-        0x04, 0x5f, 0x74, 0x63, 0x70,
-        0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c,
-        0x00,
-        0x00, 0x0c, 0x00, 0x01,
-    ];
-
-
-    for _ in 0..3 {
-        let _ = socket.send_to(&query_services, target_addr).await;
-        tokio::time::sleep(Duration::from_millis(50)).await;
-        let _ = socket.send_to(&query_apple, target_addr).await;
-        tokio::time::sleep(Duration::from_millis(50)).await;
-        let _ = socket.send_to(&query_google, target_addr).await;
-        tokio::time::sleep(Duration::from_millis(200)).await;
-    }
-    
-    Ok(())
+fn info_for(devices: &mut HashMap<Ipv4Addr, MdnsInfo>, ip: Ipv4Addr) -> &mut MdnsInfo {
+    devices.entry(ip).or_insert_with(|| MdnsInfo {
+        hostname: None,
+        model: None,
+        device_role: None,
+        services: Vec::new(),
+        ipv6: Vec::new(),
+    })
+}
+
+fn push_unique<T: PartialEq>(list: &mut Vec<T>, value: T) {
+    if !list.contains(&value) {
+        list.push(value);
+    }
+}
+
+/// "Living Room._airplay._tcp.local" -> ("Living Room", "_airplay._tcp").
+/// Instance names may contain (escaped) dots themselves, so split from the right.
+fn split_instance(fqdn: &str) -> Option<(String, String)> {
+    let rest = fqdn.strip_suffix(".local")?;
+    let mut parts = rest.rsplitn(3, '.');
+    let proto = parts.next()?;
+    let service = parts.next()?;
+    let instance = parts.next()?;
+    if !(proto == "_tcp" || proto == "_udp") || !service.starts_with('_') || service == "_dns-sd" || instance.is_empty() {
+        return None;
+    }
+    Some((instance.replace("\\.", "."), format!("{}.{}", service, proto)))
+}
+
+/// "50.1.168.192.in-addr.arpa" -> 192.168.1.50
+fn parse_reverse(name: &str) -> Option<Ipv4Addr> {
+    let octets: Vec<u8> = name.strip_suffix(".in-addr.arpa")?
+        .split('.')
+        .map(|o| o.parse().ok())
+        .collect::<Option<_>>()?;
+    match octets[..] {
+        [d, c, b, a] => Some(Ipv4Addr::new(a, b, c, d)),
+        _ => None,
+    }
+}
+
+fn strip_local(name: &str) -> String {
+    let name = name.trim_end_matches('.');
+    name.strip_suffix(".local").unwrap_or(name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::discovery::dns::Record;
+
+    fn record(name: &str, data: RData) -> Record {
+        Record { name: name.into(), rtype: 0, class: dns::CLASS_IN, ttl: 120, data }
+    }
+
+    fn response(records: Vec<Record>) -> Message {
+        Message { flags: 0x8400, answers: records, ..Default::default() }
+    }
+
+    #[test]
+    fn test_collector_resolves_services() {
+        let mut collector = MdnsCollector::default();
+        let apple_tv = Ipv4Addr::new(192, 168, 1, 50);
+
+        // PTR in one packet, SRV/TXT/A in the next
+        collector.add(apple_tv, &response(vec![
+            record("_airplay._tcp.local", RData::Ptr("Living Room._airplay._tcp.local".into())),
+        ]));
+        collector.add(apple_tv, &response(vec![
+            record("Living Room._airplay._tcp.local", RData::Srv { priority: 0, weight: 0, port: 7000, target: "Apple-TV.local".into() }),
+            record("Living Room._airplay._tcp.local", RData::Txt(vec!["model=AppleTV6,2".into(), "deviceid=x".into()])),
+            record("Apple-TV.local", RData::A(apple_tv)),
+            record("Apple-TV.local", RData::Aaaa("fe80::1".parse().unwrap())),
+        ]));
+
+        // A printer whose record is relayed by another responder (sleep proxy)
+        collector.add(apple_tv, &response(vec![
+            record("Office._ipp._tcp.local", RData::Srv { priority: 0, weight: 0, port: 631, target: "printer.local".into() }),
+            record("Office._ipp._tcp.local", RData::Txt(vec!["ty=HP LaserJet M404".into()])),
+            record("printer.local", RData::A(Ipv4Addr::new(192, 168, 1, 60))),
+        ]));
+
+        let devices = collector.finish();
+        let tv = &devices[&apple_tv];
+        assert_eq!(tv.hostname.as_deref(), Some("Apple-TV"));
+        assert_eq!(tv.model.as_deref(), Some("AppleTV6,2"));
+        assert_eq!(tv.device_role.as_deref(), Some("media"));
+        assert_eq!(tv.ipv6, vec!["fe80::1".parse::<Ipv6Addr>().unwrap()]);
+        assert_eq!(tv.services, vec![MdnsService {
+            instance: "Living Room".into(),
+            service_type: "_airplay._tcp".into(),
+            port: Some(7000),
+            txt: vec!["model=AppleTV6,2".into(), "deviceid=x".into()],
+        }]);
+
+        let printer = &devices[&Ipv4Addr::new(192, 168, 1, 60)];
+        assert_eq!(printer.hostname.as_deref(), Some("printer"));
+        assert_eq!(printer.model.as_deref(), Some("HP LaserJet M404"));
+        assert_eq!(printer.device_role.as_deref(), Some("printer"));
+    }
+
+    #[test]
+    fn test_follow_ups() {
+        let mut collector = MdnsCollector::default();
+        let source = Ipv4Addr::new(192, 168, 1, 70);
+        let has = |c: &MdnsCollector, name: &str, qtype: u16| c.follow_ups().contains(&(name.to_string(), qtype));
+
+        // Stage one: the meta-query names a type, which we then browse
+        collector.add(source, &response(vec![record(META_QUERY, RData::Ptr("_hap._tcp.local".into()))]));
+        assert!(has(&collector, "_hap._tcp.local", dns::TYPE_PTR));
+        assert!(has(&collector, "_googlecast._tcp.local", dns::TYPE_PTR)); // seed
+
+        // Stage two: a bare instance needs SRV and TXT, the SRV target needs an address
+        collector.add(source, &response(vec![record("_hap._tcp.local", RData::Ptr("Bridge._hap._tcp.local".into()))]));
+        assert!(has(&collector, "Bridge._hap._tcp.local", dns::TYPE_SRV));
+        assert!(has(&collector, "Bridge._hap._tcp.local", dns::TYPE_TXT));
+
+        collector.add(source, &response(vec![
+            record("Bridge._hap._tcp.local", RData::Srv { priority: 0, weight: 0, port: 51826, target: "Hue-Bridge.local".into() }),
+        ]));
+        assert!(!has(&collector, "Bridge._hap._tcp.local", dns::TYPE_SRV));
+        assert!(has(&collector, "Hue-Bridge.local", dns::TYPE_A));
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(split_instance("Kitchen\\.speaker._googlecast._tcp.local"),
+                   Some(("Kitchen.speaker".into(), "_googlecast._tcp".into())));
+        assert_eq!(split_instance("_services._dns-sd._udp.local"), None);
+        assert_eq!(split_instance("host.local"), None);
+        assert_eq!(parse_reverse("50.1.168.192.in-addr.arpa"), Some(Ipv4Addr::new(192, 168, 1, 50)));
+        assert_eq!(strip_local("MacBook-Pro.local."), "MacBook-Pro");
+    }
 }
@@ -1,4 +1,5 @@
 pub mod arp;
+pub mod dns;
 pub mod tcp;
 pub mod passive;
 pub mod icmp;
@@ -7,5 +8,21 @@ pub mod llmnr;
 pub mod udp;
 pub mod mdns;
 pub mod ssdp;
+pub mod dhcp;
+pub mod ipv6;
+pub mod registry;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 pub struct DiscoveryEngine;
+
+/// Raises the stop flag of a blocking receiver when dropped, so the receiver
+/// also ends when a sweep bails out early or its task is aborted.
+pub(crate) struct StopOnDrop(pub Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
@@ -1,72 +1,95 @@
 use tokio::net::UdpSocket;
+use std::collections::HashMap;
 use std::time::Duration;
 use tokio::sync::mpsc;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
+use crate::scanner::addr::MacAddr;
+use crate::scanner::target::ScanTarget;
+use std::io;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation};
+
+const NBSTAT: u16 = 0x0021;
+// NAME_FLAGS: group name bit
+const GROUP_FLAG: u16 = 0x8000;
+
+// Name suffixes (16th byte of a NetBIOS name)
+const SUFFIX_WORKSTATION: u8 = 0x00; // unique: computer name, group: workgroup / domain
+const SUFFIX_MESSENGER: u8 = 0x03;   // computer name and logged-in user
+const SUFFIX_DOMAIN_CONTROLLERS: u8 = 0x1c;
 
 pub struct NetBiosScanner;
 
+/// Decoded node status (NBSTAT) response.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetBiosInfo {
+    pub hostname: Option<String>,
+    pub workgroup: Option<String>, // workgroup or domain
+    pub user: Option<String>,      // logged-in user, from a 0x03 name that isn't the computer's
+    pub mac: Option<MacAddr>,      // adapter MAC from the statistics block
+    pub domain_controller: bool,   // registers <1C>
+    pub names: Vec<NetBiosName>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetBiosName {
+    pub name: String,
+    pub suffix: u8,
+    pub group: bool,
+}
+
 impl NetBiosScanner {
-    // Unicast "Node Status" query to every IP in the subnet
-    pub async fn scan_subnet(subnet: &str) -> Vec<(String, String)> {
-        // Return Vec<(IP, Hostname)>
-        let base_ip = subnet.split('/').next().unwrap_or("127.0.0.1");
-        let parts: Vec<&str> = base_ip.split('.').collect();
-        if parts.len() != 4 { return vec![]; }
-        
-        let prefix = format!("{}.{}.{}.", parts[0], parts[1], parts[2]);
+    // Unicast "Node Status" query to every IP in the target range
+    pub async fn scan_subnet(targets: &ScanTarget, interval: Duration) -> io::Result<HashMap<Ipv4Addr, NetBiosInfo>> {
+        let hosts = targets.hosts();
         let (tx, mut rx) = mpsc::channel(255);
 
         // Bind a socket for sending/receiving
-        // We need to be careful about port binding. NBT uses 137. 
+        // We need to be careful about port binding. NBT uses 137.
         // Binding to 0 (ephemeral) usually works for sending active probes.
-        let socket = match UdpSocket::bind("0.0.0.0:0").await {
-            Ok(s) => std::sync::Arc::new(s),
-            Err(_) => return vec![],
-        };
+        let socket = std::sync::Arc::new(UdpSocket::bind("0.0.0.0:0").await?);
 
         // Header: ID (2), Flags (2), QD (2), AN (2), NS (2), AR (2)
         // Query: Name (34 bytes), Type (2), Class (2)
         // WILDCARD NAME for Node Status: "*" encoded.
         // CKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA (32 bytes) + Termuators
-        let mut packet = vec![
+        let packet = vec![
             0xAB, 0xCD, // ID
             0x00, 0x00, // Flags (Query, etc)
             0x00, 0x01, // QDCOUNT = 1
             0x00, 0x00, // ANCOUNT
             0x00, 0x00, // NSCOUNT
             0x00, 0x00, // ARCOUNT
-            
+
             // NAME "CKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA" (Encoded *)
             0x20, // Length 32
-            0x43, 0x4B, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 
+            0x43, 0x4B, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41,
+            0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41,
+            0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41,
             0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41,
-            0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 
-            0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 0x41, 
             0x00, // Terminator
-            
+
             0x00, 0x21, // TYPE = NBSTAT (Node Status)
             0x00, 0x01, // CLASS = IN
         ];
 
+        // Sender paces at `interval` per host (at least 1ms to prevent flood)
+        let interval = interval.max(Duration::from_millis(1));
+        let listen_for = interval * hosts.len() as u32 + Duration::from_secs(2);
+
         // Receiver Task
         let socket_recv = socket.clone();
         let tx_res = tx.clone();
         tokio::spawn(async move {
             let mut buf = [0u8; 1024];
-            // Listen for 5 seconds max (covering the scan duration)
-            let _ = tokio::time::timeout(Duration::from_secs(6), async {
+            // Listen for the sweep duration plus a grace period for late replies
+            let _ = tokio::time::timeout(listen_for, async {
                 loop {
-                    if let Ok((len, addr)) = socket_recv.recv_from(&mut buf).await {
-                        // Parse Hostname from response
-                        // Very basic parsing: identifying byte patterns
-                        if len > 57 {
-                            // Extract Number of Names (Byte 56 approx)
-                            // Names start around offset 57.
-                            // Each name is 15 bytes + 1 byte type + 2 byte flags.
-                            // We just grab the first name as "Hostname"
-                            let name_chunk = &buf[57..57+15];
-                            let hostname = String::from_utf8_lossy(name_chunk).trim().to_string();
-                            let _ = tx_res.send((addr.ip().to_string(), hostname)).await;
+                    if let Ok((len, SocketAddr::V4(addr))) = socket_recv.recv_from(&mut buf).await {
+                        match parse_node_status(&buf[..len]) {
+                            Some(info) => { let _ = tx_res.send((*addr.ip(), info)).await; }
+                            None => tracing::debug!("Unparseable NBSTAT response from {}", addr),
                         }
                     }
                 }
@@ -74,25 +97,167 @@ impl NetBiosScanner {
         });
 
         // Sender Loop
-        for i in 1..255 {
-            let target = format!("{}{}:137", prefix, i);
-            if let Ok(addr) = target.parse::<SocketAddr>() {
-                let socket_send = socket.clone();
-                let packet = packet.clone();
-                tokio::spawn(async move {
-                    let _ = socket_send.send_to(&packet, addr).await;
-                });
-                // Small delay to prevent flood
-                tokio::time::sleep(Duration::from_millis(5)).await;
-            }
+        for ip in hosts {
+            let addr = SocketAddr::from((ip, 137));
+            let socket_send = socket.clone();
+            let packet = packet.clone();
+            tokio::spawn(async move {
+                let _ = socket_send.send_to(&packet, addr).await;
+            });
+            // Small delay to prevent flood
+            tokio::time::sleep(interval).await;
         }
-        
+
         drop(tx);
-        
-        let mut results = Vec::new();
-        while let Some(pair) = rx.recv().await {
-            results.push(pair);
+
+        let mut results = HashMap::new();
+        while let Some((ip, info)) = rx.recv().await {
+            results.insert(ip, info);
+        }
+        Ok(results)
+    }
+}
+
+impl DiscoveryModule for NetBiosScanner {
+    fn name(&self) -> &'static str {
+        "netbios"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Netbios
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            let found = Self::scan_subnet(ctx.target, ctx.profile.probe_interval()).await
+                .map_err(|e| format!("NetBIOS sweep unavailable: {}", e))?;
+            Ok(found.into_iter()
+                .map(|(ip, info)| {
+                    let (mac, name) = (info.mac, info.hostname.clone());
+                    Observation::new(self.name(), ip.into(), Evidence::NetBios(info)).mac(mac).name(name)
+                })
+                .collect())
+        })
+    }
+}
+
+/// Parses an NBSTAT response (RFC 1002 4.2.18): name table plus statistics.
+fn parse_node_status(packet: &[u8]) -> Option<NetBiosInfo> {
+    // Response bit set and exactly one answer
+    if packet.len() < 12 || packet[2] & 0x80 == 0 || u16::from_be_bytes([packet[6], packet[7]]) != 1 {
+        return None;
+    }
+
+    // RR_NAME is the encoded "*" (or a pointer back to the question in odd stacks)
+    let mut pos = 12;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 { pos += 1; break; }
+        if len & 0xc0 == 0xc0 { pos += 2; break; }
+        pos += 1 + len;
+    }
+
+    let rtype = u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]);
+    if rtype != NBSTAT {
+        return None;
+    }
+    pos += 8; // type, class, ttl
+    let rdlength = u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]) as usize;
+    let rdata = packet.get(pos + 2..(pos + 2 + rdlength).min(packet.len()))?;
+
+    let count = *rdata.first()? as usize;
+    let table = rdata.get(1..1 + count * 18)?;
+    let names: Vec<NetBiosName> = table.chunks_exact(18).map(|entry| {
+        let flags = u16::from_be_bytes([entry[16], entry[17]]);
+        NetBiosName {
+            name: String::from_utf8_lossy(&entry[..15]).trim_end_matches([' ', '\0']).to_string(),
+            suffix: entry[15],
+            group: flags & GROUP_FLAG != 0,
         }
-        results
+    }).collect();
+
+    let find = |suffix: u8, group: bool| names.iter()
+        .find(|n| n.suffix == suffix && n.group == group && !n.name.is_empty())
+        .map(|n| n.name.clone());
+
+    let hostname = find(SUFFIX_WORKSTATION, false);
+    let workgroup = find(SUFFIX_WORKSTATION, true);
+    // Every machine registers its own name with <03> too, a second one is the user
+    let user = names.iter()
+        .find(|n| n.suffix == SUFFIX_MESSENGER && !n.group && Some(&n.name) != hostname.as_ref() && !n.name.is_empty())
+        .map(|n| n.name.clone());
+    let domain_controller = names.iter().any(|n| n.suffix == SUFFIX_DOMAIN_CONTROLLERS && n.group);
+
+    // Statistics start with the unit ID; Samba and some stacks leave it zeroed
+    let mac = rdata.get(1 + count * 18..)
+        .and_then(MacAddr::from_slice)
+        .filter(|mac| !mac.is_unspecified());
+
+    Some(NetBiosInfo { hostname, workgroup, user, mac, domain_controller, names })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, suffix: u8, group: bool) -> Vec<u8> {
+        let mut e = format!("{:<15}", name).into_bytes();
+        e.push(suffix);
+        e.extend_from_slice(&if group { [0x84, 0x00] } else { [0x04, 0x00] });
+        e
+    }
+
+    fn response(names: &[Vec<u8>], mac: [u8; 6]) -> Vec<u8> {
+        let mut rdata = vec![names.len() as u8];
+        names.iter().for_each(|n| rdata.extend_from_slice(n));
+        rdata.extend_from_slice(&mac);
+        rdata.extend_from_slice(&[0; 40]); // rest of the statistics
+
+        let mut p = vec![0xAB, 0xCD, 0x84, 0x00, 0, 0, 0, 1, 0, 0, 0, 0, 0x20];
+        p.extend_from_slice(b"CKAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\0");
+        p.extend_from_slice(&[0x00, 0x21, 0x00, 0x01, 0, 0, 0, 0]);
+        p.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        p.extend_from_slice(&rdata);
+        p
+    }
+
+    #[test]
+    fn test_parse_windows_workstation() {
+        let packet = response(&[
+            entry("DESKTOP-7K2M", 0x00, false),
+            entry("CORP", 0x00, true),
+            entry("DESKTOP-7K2M", 0x20, false),
+            entry("DESKTOP-7K2M", 0x03, false),
+            entry("ALICE", 0x03, false),
+            entry("CORP", 0x1e, true),
+        ], [0x00, 0x15, 0x5d, 0x01, 0x02, 0x03]);
+
+        let info = parse_node_status(&packet).unwrap();
+        assert_eq!(info.hostname.as_deref(), Some("DESKTOP-7K2M"));
+        assert_eq!(info.workgroup.as_deref(), Some("CORP"));
+        assert_eq!(info.user.as_deref(), Some("ALICE"));
+        assert_eq!(info.mac.map(|m| m.to_string()).as_deref(), Some("00:15:5D:01:02:03"));
+        assert!(!info.domain_controller);
+        assert_eq!(info.names.len(), 6);
+        assert_eq!(info.names[2], NetBiosName { name: "DESKTOP-7K2M".into(), suffix: 0x20, group: false });
+    }
+
+    #[test]
+    fn test_parse_samba_and_garbage() {
+        let packet = response(&[
+            entry("FILESRV", 0x00, false),
+            entry("FILESRV", 0x03, false),
+            entry("WORKGROUP", 0x00, true),
+            entry("WORKGROUP", 0x1c, true),
+        ], [0; 6]);
+        let info = parse_node_status(&packet).unwrap();
+        assert_eq!(info.user, None);
+        assert_eq!(info.mac, None); // Samba zeroes the unit ID
+        assert!(info.domain_controller);
+
+        assert_eq!(parse_node_status(&packet[..60]), None); // truncated name table
+        let mut query = packet.clone();
+        query[2] = 0; // not a response
+        assert_eq!(parse_node_status(&query), None);
     }
 }
@@ -8,7 +8,7 @@ use tokio::sync::mpsc;
 pub struct PassiveSniffer;
 
 impl PassiveSniffer {
-    pub async fn listen(timeout: Duration) -> Vec<String> {
+    pub async fn listen(timeout: Duration) -> Vec<IpAddr> {
         let (tx, mut rx) = mpsc::channel(100);
         
         // Spawn listeners for mDNS and SSDP
@@ -42,7 +42,7 @@ impl PassiveSniffer {
     }
 }
 
-async fn listen_multicast(port: u16, bind_addr: &str, tx: mpsc::Sender<String>, active_mdns: bool) -> std::io::Result<()> {
+async fn listen_multicast(port: u16, bind_addr: &str, tx: mpsc::Sender<IpAddr>, active_mdns: bool) -> std::io::Result<()> {
     // Standard UDP socket bind
     let socket = UdpSocket::bind(format!("0.0.0.0:{}", port)).await?;
     
@@ -84,9 +84,9 @@ async fn listen_multicast(port: u16, bind_addr: &str, tx: mpsc::Sender<String>,
     let mut buf = [0u8; 1024];
     loop {
         if let Ok((_len, addr)) = socket.recv_from(&mut buf).await {
-            let ip = addr.ip().to_string();
+            let ip = addr.ip();
             // Filter local IPs/Loopback if needed, but for now just send it
-            if ip != "127.0.0.1" {
+            if !ip.is_loopback() {
                 let _ = tx.send(ip).await;
             }
         }
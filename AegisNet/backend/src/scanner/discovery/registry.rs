@@ -0,0 +1,246 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::Instant;
+use chrono::{DateTime, Utc};
+use futures::future::{self, BoxFuture};
+use serde::Serialize;
+use crate::scanner::addr::MacAddr;
+use crate::scanner::profile::{DiscoveryMethod, ScanProfile};
+use crate::scanner::target::ScanTarget;
+use super::{arp, icmp, ipv6, llmnr, mdns, netbios, ssdp, tcp, udp};
+
+/// What a module learned about a host, beyond the address itself.
+#[derive(Clone, Debug)]
+pub enum Evidence {
+    Reachable, // answered a probe, nothing more to say
+    Arp, // the MAC came from an ARP reply or the OS neighbour cache
+    Icmp(icmp::IcmpReply),
+    NetBios(netbios::NetBiosInfo),
+    Mdns(mdns::MdnsInfo),
+    Upnp(ssdp::UpnpDevice),
+    Names(llmnr::NameActivity),
+    Ndp { router: bool },
+}
+
+/// One host sighting, the common currency of all discovery modules.
+#[derive(Clone, Debug)]
+pub struct Observation {
+    pub ip: IpAddr,
+    pub mac: Option<MacAddr>,
+    pub names: Vec<String>, // hostnames the host claimed for itself
+    pub source: &'static str, // module name
+    pub seen_at: DateTime<Utc>,
+    pub reliable: bool, // the host itself answered; caches and bare port probes are not proof of life
+    pub evidence: Evidence,
+}
+
+impl Observation {
+    pub fn new(source: &'static str, ip: IpAddr, evidence: Evidence) -> Self {
+        Self { ip, mac: None, names: Vec::new(), source, seen_at: Utc::now(), reliable: true, evidence }
+    }
+
+    pub fn mac(mut self, mac: Option<MacAddr>) -> Self {
+        self.mac = mac;
+        self
+    }
+
+    pub fn name(mut self, name: Option<String>) -> Self {
+        if let Some(name) = name.filter(|n| !n.is_empty()) {
+            self.names.push(name);
+        }
+        self
+    }
+
+    pub fn unreliable(mut self) -> Self {
+        self.reliable = false;
+        self
+    }
+}
+
+/// When a module runs relative to the others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Probe,
+    /// Reads state the probes leave behind (the OS neighbour cache), runs once they are done.
+    Cache,
+}
+
+pub struct DiscoveryContext<'a> {
+    pub target: &'a ScanTarget,
+    pub profile: &'a ScanProfile,
+    pub earlier: &'a [ModuleReport], // reports of the modules of previous stages
+}
+
+impl DiscoveryContext<'_> {
+    pub fn succeeded(&self, module: &str) -> bool {
+        self.earlier.iter().any(|r| r.module == module && r.error.is_none())
+    }
+}
+
+/// A host discovery technique. Implementations live next to the scanner they wrap.
+pub trait DiscoveryModule: Send + Sync {
+    /// Short identifier used in reports and host provenance, e.g. "icmp".
+    fn name(&self) -> &'static str;
+
+    /// The profile switch that enables the module.
+    fn method(&self) -> DiscoveryMethod;
+
+    fn stage(&self) -> Stage {
+        Stage::Probe
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>>;
+}
+
+/// How one module fared during a scan.
+#[derive(Serialize, Clone, Debug)]
+pub struct ModuleReport {
+    pub module: &'static str,
+    pub method: DiscoveryMethod,
+    pub duration_ms: u64,
+    pub observations: usize,
+    pub hosts: usize, // distinct addresses, in scope or not
+    pub error: Option<String>,
+}
+
+/// The discovery modules a scan can use.
+pub struct DiscoveryRegistry {
+    modules: Vec<Box<dyn DiscoveryModule>>,
+}
+
+impl DiscoveryRegistry {
+    pub fn new() -> Self {
+        Self { modules: Vec::new() }
+    }
+
+    pub fn register(mut self, module: impl DiscoveryModule + 'static) -> Self {
+        self.modules.push(Box::new(module));
+        self
+    }
+
+    pub fn builtin() -> Self {
+        Self::new()
+            .register(mdns::MdnsScanner)
+            .register(icmp::IcmpScanner)
+            .register(tcp::TcpDiscovery)
+            .register(netbios::NetBiosScanner)
+            .register(llmnr::LlmnrListener)
+            .register(udp::UdpScanner)
+            .register(ssdp::SsdpScanner)
+            .register(ipv6::Ipv6Scanner)
+            .register(arp::ArpScanner)
+            .register(arp::ArpCache)
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = &dyn DiscoveryModule> {
+        self.modules.iter().map(|m| m.as_ref())
+    }
+
+    /// Runs every module the profile enables, stage by stage, modules of a stage concurrently.
+    pub async fn run(&self, target: &ScanTarget, profile: &ScanProfile) -> (Vec<Observation>, Vec<ModuleReport>) {
+        let mut observations = Vec::new();
+        let mut reports = Vec::new();
+
+        for stage in [Stage::Probe, Stage::Cache] {
+            let ctx = DiscoveryContext { target, profile, earlier: &reports };
+            let runs = self.modules()
+                .filter(|m| m.stage() == stage && profile.uses(m.method()))
+                .map(|m| run_module(m, &ctx));
+            let results = future::join_all(runs).await;
+
+            for (found, report) in results {
+                observations.extend(found);
+                reports.push(report);
+            }
+        }
+        (observations, reports)
+    }
+}
+
+impl Default for DiscoveryRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+async fn run_module(module: &dyn DiscoveryModule, ctx: &DiscoveryContext<'_>) -> (Vec<Observation>, ModuleReport) {
+    let started = Instant::now();
+    let result = module.discover(ctx).await;
+    let elapsed = started.elapsed();
+
+    let (found, error) = match result {
+        Ok(found) => (found, None),
+        Err(e) => {
+            tracing::warn!("Discovery module {} failed after {:?}: {}", module.name(), elapsed, e);
+            (Vec::new(), Some(e))
+        }
+    };
+    let hosts = found.iter().map(|o| o.ip).collect::<HashSet<_>>().len();
+    if error.is_none() {
+        tracing::info!("Discovery module {}: {} hosts in {:?}", module.name(), hosts, elapsed);
+    }
+
+    let report = ModuleReport {
+        module: module.name(),
+        method: module.method(),
+        duration_ms: elapsed.as_millis() as u64,
+        observations: found.len(),
+        hosts,
+        error,
+    };
+    (found, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, Stage, Result<Vec<&'static str>, &'static str>);
+
+    impl DiscoveryModule for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn method(&self) -> DiscoveryMethod {
+            DiscoveryMethod::Icmp
+        }
+
+        fn stage(&self) -> Stage {
+            self.1
+        }
+
+        fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+            Box::pin(async move {
+                // The cache stage only reports when the probe stage worked
+                if self.1 == Stage::Cache && !ctx.succeeded("probe") {
+                    return Ok(Vec::new());
+                }
+                let ips = self.2.clone().map_err(str::to_string)?;
+                Ok(ips.iter().map(|ip| Observation::new(self.0, ip.parse().unwrap(), Evidence::Reachable)).collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_per_module() {
+        let registry = DiscoveryRegistry::new()
+            .register(Fixed("cache", Stage::Cache, Ok(vec!["10.0.0.3"])))
+            .register(Fixed("probe", Stage::Probe, Ok(vec!["10.0.0.1", "10.0.0.2", "10.0.0.1"])))
+            .register(Fixed("broken", Stage::Probe, Err("no raw sockets")));
+        let target = ScanTarget::parse("10.0.0.0/24").unwrap();
+
+        let (observations, reports) = registry.run(&target, &ScanProfile::default()).await;
+        assert_eq!(observations.len(), 4);
+        let names: Vec<_> = reports.iter().map(|r| r.module).collect();
+        assert_eq!(names, vec!["probe", "broken", "cache"]);
+        assert_eq!((reports[0].observations, reports[0].hosts), (3, 2));
+        assert_eq!(reports[1].error.as_deref(), Some("no raw sockets"));
+        assert_eq!(reports[2].hosts, 1);
+
+        // Disabled by the profile
+        let passive = ScanProfile { methods: vec![DiscoveryMethod::Mdns], ..ScanProfile::default() };
+        let (observations, reports) = registry.run(&target, &passive).await;
+        assert!(observations.is_empty() && reports.is_empty());
+    }
+}
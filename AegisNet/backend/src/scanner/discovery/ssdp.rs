@@ -1,90 +1,307 @@
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::time::Duration;
 use tokio::net::UdpSocket;
 use std::collections::HashMap;
+use regex::Regex;
+use reqwest::Client;
+use std::io;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation};
+
+// Device descriptions are a few KB, anything much larger is not one
+const MAX_DESCRIPTION: usize = 256 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Service types that let anyone on the LAN open ports on the router
+const PORT_MAPPING_SERVICES: &[&str] = &["WANIPConnection", "WANPPPConnection", "WANIPv6FirewallControl"];
 
 pub struct SsdpScanner;
 
 #[derive(Debug, Clone)]
 pub struct UpnpDevice {
-    pub ip: String,
+    pub ip: Ipv4Addr,
     pub manufacturer: Option<String>,
     pub model_name: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
     pub friendly_name: Option<String>,
+    pub udn: Option<String>, // "uuid:..."
     pub server: Option<String>,
+    pub location: Option<String>, // URL of the device description
+    pub services: Vec<UpnpService>, // root and embedded devices
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpnpService {
+    pub service_type: String, // "urn:schemas-upnp-org:service:WANIPConnection:1"
+    pub control_url: Option<String>,
+}
+
+impl UpnpDevice {
+    pub fn new(ip: Ipv4Addr) -> Self {
+        Self {
+            ip,
+            manufacturer: None,
+            model_name: None,
+            model_number: None,
+            serial_number: None,
+            friendly_name: None,
+            udn: None,
+            server: None,
+            location: None,
+            services: Vec::new(),
+        }
+    }
+
+    /// Internet gateway that accepts port mapping requests (IGD WANIPConnection & co).
+    pub fn exposes_port_mapping(&self) -> bool {
+        self.port_mapping_services().next().is_some()
+    }
+
+    pub fn port_mapping_services(&self) -> impl Iterator<Item = &UpnpService> {
+        self.services.iter().filter(|s| {
+            PORT_MAPPING_SERVICES.iter().any(|name| s.service_type.contains(&format!(":{}:", name)))
+        })
+    }
+
+    /// Port the description was served from, the UPnP HTTP endpoint.
+    pub fn http_port(&self) -> Option<u16> {
+        reqwest::Url::parse(self.location.as_deref()?).ok()?.port_or_known_default()
+    }
 }
 
 impl SsdpScanner {
-    // Sends M-SEARCH and parses responses
-    pub async fn scan(timeout: Duration) -> HashMap<String, UpnpDevice> {
-        let mut devices = HashMap::new();
-        
-        let socket = match UdpSocket::bind("0.0.0.0:0").await {
-            Ok(s) => s,
-            Err(_) => return devices,
-        };
-        
+    // Sends M-SEARCH, parses responses and fetches each device description
+    pub async fn scan(timeout: Duration) -> io::Result<HashMap<Ipv4Addr, UpnpDevice>> {
+        let mut devices: HashMap<Ipv4Addr, UpnpDevice> = HashMap::new();
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+
         // UPnP M-SEARCH Packet
         let msg = "M-SEARCH * HTTP/1.1\r\n\
                    HOST: 239.255.255.250:1900\r\n\
                    MAN: \"ssdp:discover\"\r\n\
                    MX: 1\r\n\
                    ST: ssdp:all\r\n\r\n";
-        
+
         let target: SocketAddr = "239.255.255.250:1900".parse().unwrap();
-        
+
         // Blast it out a few times
         for _ in 0..3 {
             let _ = socket.send_to(msg.as_bytes(), target).await;
             tokio::time::sleep(Duration::from_millis(100)).await;
         }
-        
+
         let mut buf = [0u8; 2048];
         let end_time = tokio::time::Instant::now() + timeout;
-        
+
         while tokio::time::Instant::now() < end_time {
-             if let Ok(res) = tokio::time::timeout(Duration::from_millis(500), socket.recv_from(&mut buf)).await {
-                 if let Ok((len, addr)) = res {
-                     let response = String::from_utf8_lossy(&buf[..len]);
-                     let ip = addr.ip().to_string();
-                     
-                     let mut device = UpnpDevice {
-                         ip: ip.clone(),
-                         manufacturer: None,
-                         model_name: None,
-                         friendly_name: None, 
-                         server: None,
-                     };
-                     
-                     // Quick Header Parse
-                     for line in response.lines() {
-                         let lower = line.to_lowercase();
-                         if lower.starts_with("server:") {
-                             device.server = Some(line[7..].trim().to_string());
-                         }
-                         if lower.starts_with("usn:") {
-                             // Unique Service Name
-                         }
-                         // LOCATION header points to XML description
-                         if lower.starts_with("location:") {
-                             let url = line[9..].trim();
-                             // Fetch XML details asynchronously if possible (ignoring for now to keep it fast, or maybe minimal fetch?)
-                             // Just seeing "Philips Hue" in SERVER header is often enough.
-                         }
-                     }
-                     
-                     // Heuristic based on SERVER header
-                     if let Some(srv) = &device.server {
-                         if srv.contains("Philips Hue") { device.manufacturer = Some("Philips".into()); device.model_name = Some("Hue Bridge".into()); }
-                         if srv.contains("Sonos") { device.manufacturer = Some("Sonos".into()); }
-                         if srv.contains("Samsung") { device.manufacturer = Some("Samsung".into()); }
-                     }
-
-                    devices.insert(ip, device);
+             if let Ok(Ok((len, SocketAddr::V4(addr)))) = tokio::time::timeout(Duration::from_millis(500), socket.recv_from(&mut buf)).await {
+                 let response = String::from_utf8_lossy(&buf[..len]);
+                 let ip = *addr.ip();
+                 let headers = parse_headers(&response);
+
+                 // One response per service type, the first LOCATION wins
+                 let device = devices.entry(ip).or_insert_with(|| UpnpDevice::new(ip));
+                 if device.server.is_none() {
+                     device.server = headers.get("server").cloned();
+                 }
+                 if device.location.is_none() {
+                     device.location = headers.get("location").cloned();
                  }
              }
         }
-        
-        devices
+
+        // A redirect could point anywhere, defeating the responder-only check
+        let client = match Client::builder().timeout(FETCH_TIMEOUT).redirect(reqwest::redirect::Policy::none()).build() {
+            Ok(c) => c,
+            Err(_) => return Ok(devices),
+        };
+        let fetches = devices.values_mut().map(|device| {
+            let client = &client;
+            async move {
+                if let Some(xml) = fetch_description(client, device).await {
+                    parse_description(&xml, device);
+                }
+                apply_server_hints(device);
+            }
+        });
+        futures::future::join_all(fetches).await;
+
+        Ok(devices)
+    }
+}
+
+impl DiscoveryModule for SsdpScanner {
+    fn name(&self) -> &'static str {
+        "ssdp"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Ssdp
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            let devices = Self::scan(ctx.profile.ssdp_duration).await
+                .map_err(|e| format!("SSDP search unavailable: {}", e))?;
+            Ok(devices.into_iter()
+                .map(|(ip, device)| Observation::new(self.name(), ip.into(), Evidence::Upnp(device)))
+                .collect())
+        })
+    }
+}
+
+fn parse_headers(response: &str) -> HashMap<String, String> {
+    response.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .filter(|(_, value)| !value.is_empty())
+        .collect()
+}
+
+async fn fetch_description(client: &Client, device: &UpnpDevice) -> Option<String> {
+    let url = reqwest::Url::parse(device.location.as_deref()?).ok()?;
+    // Only follow LOCATION back to the responder itself
+    if url.host_str() != Some(device.ip.to_string().as_str()) {
+        tracing::debug!("Ignoring SSDP location {} announced by {}", url, device.ip);
+        return None;
+    }
+
+    let mut resp = client.get(url).send().await.ok()?;
+    if !resp.status().is_success() || resp.content_length().is_some_and(|len| len as usize > MAX_DESCRIPTION) {
+        return None;
+    }
+    // Chunked bodies have no length up front
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.ok()? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_DESCRIPTION {
+            return None;
+        }
+    }
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Fills the device from its UPnP device description. The root device's
+/// fields come before its `deviceList`, so the first occurrence belongs to it;
+/// services are collected from the root and all embedded devices.
+fn parse_description(xml: &str, device: &mut UpnpDevice) {
+    device.friendly_name = element(xml, "friendlyName").or(device.friendly_name.take());
+    device.manufacturer = element(xml, "manufacturer").or(device.manufacturer.take());
+    device.model_name = element(xml, "modelName").or(device.model_name.take());
+    device.model_number = element(xml, "modelNumber");
+    device.serial_number = element(xml, "serialNumber");
+    device.udn = element(xml, "UDN");
+
+    device.services = blocks(xml, "service").into_iter()
+        .filter_map(|block| Some(UpnpService {
+            service_type: element(block, "serviceType")?,
+            control_url: element(block, "controlURL"),
+        }))
+        .collect();
+}
+
+// Fallback for devices that answer SSDP but serve no (readable) description
+fn apply_server_hints(device: &mut UpnpDevice) {
+    let Some(srv) = device.server.clone() else { return };
+    if srv.contains("Philips Hue") {
+        device.manufacturer.get_or_insert_with(|| "Philips".into());
+        device.model_name.get_or_insert_with(|| "Hue Bridge".into());
+    }
+    if srv.contains("Sonos") { device.manufacturer.get_or_insert_with(|| "Sonos".into()); }
+    if srv.contains("Samsung") { device.manufacturer.get_or_insert_with(|| "Samsung".into()); }
+}
+
+/// Text of the first `<name>` element (any namespace prefix), unescaped.
+fn element(xml: &str, name: &str) -> Option<String> {
+    let re = Regex::new(&format!(r"(?is)<(?:\w+:)?{0}(?:\s[^>]*)?>(.*?)</(?:\w+:)?{0}\s*>", regex::escape(name))).ok()?;
+    let text = unescape(re.captures(xml)?.get(1)?.as_str().trim());
+    (!text.is_empty()).then_some(text)
+}
+
+/// Inner XML of every `<name>` element.
+fn blocks<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let Ok(re) = Regex::new(&format!(r"(?is)<(?:\w+:)?{0}(?:\s[^>]*)?>(.*?)</(?:\w+:)?{0}\s*>", regex::escape(name))) else {
+        return Vec::new();
+    };
+    re.captures_iter(xml).filter_map(|c| c.get(1)).map(|m| m.as_str()).collect()
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTER: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+    <friendlyName>FRITZ!Box 7590</friendlyName>
+    <manufacturer>AVM Berlin</manufacturer>
+    <modelName>FRITZ!Box 7590</modelName>
+    <modelNumber>avm</modelNumber>
+    <serialNumber>3C:A6:2F:00:11:22</serialNumber>
+    <UDN>uuid:75802409-bccb-40e7-8e6c-3ca62f001122</UDN>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-any-com:service:Any:1</serviceType>
+        <controlURL>/igdupnp/control/any</controlURL>
+      </service>
+    </serviceList>
+    <deviceList>
+      <device>
+        <deviceType>urn:schemas-upnp-org:device:WANConnectionDevice:1</deviceType>
+        <friendlyName>WANConnectionDevice - FRITZ!Box</friendlyName>
+        <serviceList>
+          <service>
+            <serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+            <controlURL>/igdupnp/control/WANIPConn1</controlURL>
+          </service>
+        </serviceList>
+      </device>
+    </deviceList>
+  </device>
+</root>"#;
+
+    #[test]
+    fn test_parse_description() {
+        let mut device = UpnpDevice::new(Ipv4Addr::new(192, 168, 178, 1));
+        parse_description(ROUTER, &mut device);
+
+        assert_eq!(device.friendly_name.as_deref(), Some("FRITZ!Box 7590"));
+        assert_eq!(device.manufacturer.as_deref(), Some("AVM Berlin"));
+        assert_eq!(device.model_name.as_deref(), Some("FRITZ!Box 7590"));
+        assert_eq!(device.model_number.as_deref(), Some("avm"));
+        assert_eq!(device.serial_number.as_deref(), Some("3C:A6:2F:00:11:22"));
+        assert_eq!(device.udn.as_deref(), Some("uuid:75802409-bccb-40e7-8e6c-3ca62f001122"));
+        assert_eq!(device.services.len(), 2);
+        assert!(device.exposes_port_mapping());
+        assert_eq!(device.port_mapping_services().next().unwrap().control_url.as_deref(), Some("/igdupnp/control/WANIPConn1"));
+    }
+
+    #[test]
+    fn test_headers_and_hints() {
+        let response = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=100\r\nLocation: http://192.168.1.2:80/description.xml\r\nSERVER: Linux/3.14.0 UPnP/1.0 IpBridge/1.26.0 Philips Hue\r\n\r\n";
+        let headers = parse_headers(response);
+        assert_eq!(headers["location"], "http://192.168.1.2:80/description.xml");
+
+        let mut device = UpnpDevice::new(Ipv4Addr::new(192, 168, 1, 2));
+        device.server = headers.get("server").cloned();
+        device.location = headers.get("location").cloned();
+        apply_server_hints(&mut device);
+        assert_eq!(device.manufacturer.as_deref(), Some("Philips"));
+        assert_eq!(device.http_port(), Some(80));
+        assert!(!device.exposes_port_mapping());
+
+        assert_eq!(element("<a:modelName attr=\"x\">Tom &amp; Jerry</a:modelName>", "modelName").as_deref(), Some("Tom & Jerry"));
     }
 }
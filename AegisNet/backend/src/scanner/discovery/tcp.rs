@@ -1,40 +1,48 @@
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use tokio::net::TcpStream;
 use std::time::Duration;
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
 use crate::scanner::Host;
+use crate::scanner::target::ScanTarget;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation};
+
+// Max hosts probed concurrently
+const MAX_IN_FLIGHT: usize = 256;
 
 pub struct TcpDiscovery;
 
 impl TcpDiscovery {
-    pub async fn scan_subnet(subnet: &str) -> Vec<String> {
-        // Assume subnet is /24 for now, taking the base IP
+    pub async fn scan_subnet(targets: &ScanTarget, interval: Duration) -> Vec<Ipv4Addr> {
         // Logic similar to previous "touch_host" but better structured
-        let base_ip = subnet.split('/').next().unwrap_or("127.0.0.1");
-        let parts: Vec<&str> = base_ip.split('.').collect();
-        if parts.len() != 4 { return vec![]; }
-        
-        let prefix = format!("{}.{}.{}.", parts[0], parts[1], parts[2]);
         let (tx, mut rx) = mpsc::channel(255);
+        let limiter = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
 
-        for i in 1..255 {
-            let target = format!("{}{}", prefix, i);
+        for ip in targets.hosts() {
             let tx = tx.clone();
+            let limiter = limiter.clone();
             tokio::spawn(async move {
+                let _permit = limiter.acquire().await;
                 // Check common ports to trigger ARP and find services
-                if is_port_open(&target, 80).await || // HTTP
-                   is_port_open(&target, 443).await || // HTTPS
-                   is_port_open(&target, 445).await || // SMB
-                   is_port_open(&target, 22).await || // SSH
-                   is_port_open(&target, 53).await || // DNS
-                   is_port_open(&target, 3389).await || // RDP
-                   is_port_open(&target, 62078).await || // iOS Sync
-                   is_port_open(&target, 5000).await || // AirPlay Legacy
-                   is_port_open(&target, 7000).await || // AirPlay
-                   is_port_open(&target, 8080).await { // Alt HTTP
-                    let _ = tx.send(target).await;
+                if is_port_open(SocketAddr::new(IpAddr::V4(ip), 80)).await || // HTTP
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 443)).await || // HTTPS
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 445)).await || // SMB
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 22)).await || // SSH
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 53)).await || // DNS
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 3389)).await || // RDP
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 62078)).await || // iOS Sync
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 5000)).await || // AirPlay Legacy
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 7000)).await || // AirPlay
+                   is_port_open(SocketAddr::new(IpAddr::V4(ip), 8080)).await { // Alt HTTP
+                    let _ = tx.send(ip).await;
                 }
             });
+            // Rate limit from the scan profile
+            if !interval.is_zero() {
+                tokio::time::sleep(interval).await;
+            }
         }
         drop(tx);
         
@@ -46,11 +54,27 @@ impl TcpDiscovery {
     }
 }
 
-async fn is_port_open(ip: &str, port: u16) -> bool {
-    // Connect with timeout - increased for Wi-Fi reliability
-    let addr = format!("{}:{}", ip, port);
-    if let Ok(socket_addr) = addr.parse::<SocketAddr>() {
-        return tokio::time::timeout(Duration::from_millis(150), TcpStream::connect(&socket_addr)).await.is_ok();
+impl DiscoveryModule for TcpDiscovery {
+    fn name(&self) -> &'static str {
+        "tcp"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Tcp
     }
-    false
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            let alive = Self::scan_subnet(ctx.target, ctx.profile.probe_interval()).await;
+            // A timely connect attempt is no proof of life, enrichment re-verifies the ports
+            Ok(alive.into_iter()
+                .map(|ip| Observation::new(self.name(), ip.into(), Evidence::Reachable).unreliable())
+                .collect())
+        })
+    }
+}
+
+async fn is_port_open(addr: SocketAddr) -> bool {
+    // Connect with timeout - increased for Wi-Fi reliability
+    tokio::time::timeout(Duration::from_millis(150), TcpStream::connect(addr)).await.is_ok()
 }
@@ -1,26 +1,24 @@
 use tokio::net::UdpSocket;
 use std::time::Duration;
 use tokio::sync::mpsc;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
+use crate::scanner::target::ScanTarget;
+use std::io;
+use futures::future::BoxFuture;
+use crate::scanner::profile::DiscoveryMethod;
+use super::registry::{DiscoveryContext, DiscoveryModule, Evidence, Observation};
 
 pub struct UdpScanner;
 
 impl UdpScanner {
-    // Scan subnet for DNS (53) and NTP (123)
-    pub async fn scan_subnet(subnet: &str) -> Vec<String> {
-        let base_ip = subnet.split('/').next().unwrap_or("127.0.0.1");
-        let parts: Vec<&str> = base_ip.split('.').collect();
-        if parts.len() != 4 { return vec![]; }
-        
-        let prefix = format!("{}.{}.{}.", parts[0], parts[1], parts[2]);
+    // Scan target range for DNS (53) and NTP (123)
+    pub async fn scan_subnet(targets: &ScanTarget, interval: Duration) -> io::Result<Vec<Ipv4Addr>> {
+        let hosts = targets.hosts();
         let (tx, mut rx) = mpsc::channel(255);
 
         // Bind ephemeral
-        let socket = match UdpSocket::bind("0.0.0.0:0").await {
-            Ok(s) => Arc::new(s),
-            Err(_) => return vec![],
-        };
+        let socket = Arc::new(UdpSocket::bind("0.0.0.0:0").await?);
 
         // 1. DNS Query Packet (Standard Query for "google.com")
         // Header: ID=0x1234, Flags=0x0100 (Recursive), QD=1, AN=0...
@@ -36,39 +34,36 @@ impl UdpScanner {
         let mut ntp_packet = [0u8; 48];
         ntp_packet[0] = 0x1B;
 
+        // Sender paces at `interval` per host
+        let listen_for = interval * hosts.len() as u32 + Duration::from_secs(4);
+
         // Receiver Task
         let socket_recv = socket.clone();
         let tx_res = tx.clone();
         tokio::spawn(async move {
             let mut buf = [0u8; 1024];
-            let _ = tokio::time::timeout(Duration::from_secs(4), async {
+            let _ = tokio::time::timeout(listen_for, async {
                 loop {
-                    if let Ok((_len, addr)) = socket_recv.recv_from(&mut buf).await {
-                         let _ = tx_res.send(addr.ip().to_string()).await;
+                    if let Ok((_len, SocketAddr::V4(addr))) = socket_recv.recv_from(&mut buf).await {
+                         let _ = tx_res.send(*addr.ip()).await;
                     }
                 }
             }).await;
         });
 
         // Sender Loop
-        for i in 1..255 {
-            let target_ip = format!("{}{}", prefix, i);
-            
+        for ip in hosts {
             let socket_send = socket.clone();
             
             // Probe DNS (53)
-            if let Ok(addr) = format!("{}:53", target_ip).parse::<SocketAddr>() {
-                let _ = socket_send.send_to(&dns_packet, addr).await;
-            }
+            let _ = socket_send.send_to(&dns_packet, SocketAddr::from((ip, 53))).await;
             
             // Probe NTP (123)
-            if let Ok(addr) = format!("{}:123", target_ip).parse::<SocketAddr>() {
-                let _ = socket_send.send_to(&ntp_packet, addr).await;
-            }
+            let _ = socket_send.send_to(&ntp_packet, SocketAddr::from((ip, 123))).await;
 
-            // Pace it slightly
-            if i % 10 == 0 {
-                tokio::time::sleep(Duration::from_millis(5)).await;
+            // Rate limit from the scan profile
+            if !interval.is_zero() {
+                tokio::time::sleep(interval).await;
             }
         }
         
@@ -78,6 +73,26 @@ impl UdpScanner {
         while let Some(ip) = rx.recv().await {
             results.push(ip);
         }
-        results
+        Ok(results)
+    }
+}
+
+impl DiscoveryModule for UdpScanner {
+    fn name(&self) -> &'static str {
+        "udp"
+    }
+
+    fn method(&self) -> DiscoveryMethod {
+        DiscoveryMethod::Udp
+    }
+
+    fn discover<'a>(&'a self, ctx: &'a DiscoveryContext<'a>) -> BoxFuture<'a, Result<Vec<Observation>, String>> {
+        Box::pin(async move {
+            let alive = Self::scan_subnet(ctx.target, ctx.profile.probe_interval()).await
+                .map_err(|e| format!("UDP sweep unavailable: {}", e))?;
+            Ok(alive.into_iter()
+                .map(|ip| Observation::new(self.name(), ip.into(), Evidence::Reachable))
+                .collect())
+        })
     }
 }
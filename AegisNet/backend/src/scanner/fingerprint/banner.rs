@@ -1,36 +1,138 @@
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::time::Instant;
+use std::net::{IpAddr, SocketAddr};
 use std::time::Duration;
+use super::probes::{self, Probe, ProbeDb, ServiceMatch};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+// Once data arrives, a pause this long ends the response
+const READ_IDLE: Duration = Duration::from_millis(200);
+const MAX_RESPONSE: usize = 4096;
+const BANNER_LEN: usize = 512;
+// Connections per port, NULL included
+const MAX_PROBES: usize = 6;
 
 pub struct ServiceBanner;
 
+/// The first response a port gave and what the probe database made of it.
+#[derive(Clone, Debug, Default)]
+pub struct Detection {
+    pub banner: String,
+    pub service: Option<ServiceMatch>,
+}
+
 impl ServiceBanner {
-    pub async fn grab(ip: &str, port: u16) -> String {
-        let addr = format!("{}:{}", ip, port);
-        // Short timeout for banner grab
-        let timeout = Duration::from_millis(500);
-
-        let connect_result = tokio::time::timeout(timeout, TcpStream::connect(&addr)).await;
-        
-        if let Ok(Ok(mut stream)) = connect_result {
-             // Send a probe depending on port
-             let probe: &[u8] = match port {
-                 80 | 8080 => b"HEAD / HTTP/1.0\r\n\r\n",
-                 _ => b"\r\n", 
-             };
-             
-             let _ = stream.write_all(probe).await;
-             
-             let mut buffer = [0; 512];
-             let read_result = tokio::time::timeout(timeout, stream.read(&mut buffer)).await;
-             
-             if let Ok(Ok(n)) = read_result {
-                 if n > 0 {
-                     return String::from_utf8_lossy(&buffer[..n]).trim().to_string();
-                 }
-             }
+    /// Sends the probes for `port` one connection at a time until one is matched.
+    /// A softmatch narrows the remaining probes to those that can name its product.
+    /// Without `intrusive` it only waits for a greeting (the NULL probe).
+    pub async fn grab(ip: IpAddr, port: u16, intrusive: bool) -> Detection {
+        let addr = SocketAddr::new(ip, port);
+        let db = probes::database();
+        let mut banner: Option<String> = None;
+        let mut soft: Option<ServiceMatch> = None;
+
+        for probe in schedule(db, port, intrusive) {
+            if soft.as_ref().is_some_and(|s| !probe.identifies(&s.service)) {
+                continue;
+            }
+            // A port that stops accepting connections won't answer the next probe either
+            let Some(response) = exchange(addr, probe).await else { break };
+            if response.is_empty() {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&response[..response.len().min(BANNER_LEN)]).trim().to_string();
+            match db.identify(probe, &response) {
+                Some(found) if !found.soft => return Detection { banner: banner.unwrap_or(text), service: Some(found) },
+                Some(found) => { soft.get_or_insert(found); }
+                None => {}
+            }
+            banner.get_or_insert(text);
+        }
+
+        Detection { banner: banner.unwrap_or_else(|| "Unknown".into()), service: soft }
+    }
+}
+
+/// The probes `grab` sends to `port`, in order.
+fn schedule(db: &ProbeDb, port: u16, intrusive: bool) -> Vec<&Probe> {
+    db.for_port(port).into_iter()
+        .filter(|p| intrusive || p.payload.is_empty())
+        .take(MAX_PROBES)
+        .collect()
+}
+
+/// One connection: send the payload, read until the probe's wait or a pause after data.
+async fn exchange(addr: SocketAddr, probe: &Probe) -> Option<Vec<u8>> {
+    let mut stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await.ok()?.ok()?;
+    if !probe.payload.is_empty() && stream.write_all(&probe.payload).await.is_err() {
+        return Some(Vec::new());
+    }
+
+    let deadline = Instant::now() + probe.wait;
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while response.len() < MAX_RESPONSE {
+        let until = if response.is_empty() { deadline } else { deadline.min(Instant::now() + READ_IDLE) };
+        match tokio::time::timeout_at(until, stream.read(&mut buffer)).await {
+            Ok(Ok(n)) if n > 0 => response.extend_from_slice(&buffer[..n]),
+            _ => break,
+        }
+    }
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Accepts connections forever: sends `greeting`, then answers any request with `reply`.
+    async fn server(greeting: &'static [u8], reply: &'static [u8]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _ = stream.write_all(greeting).await;
+                    let mut buf = [0u8; 512];
+                    if let Ok(n) = stream.read(&mut buf).await {
+                        if n > 0 {
+                            let _ = stream.write_all(reply).await;
+                        }
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_grab() {
+        let localhost = IpAddr::from([127, 0, 0, 1]);
+
+        let ssh = server(b"SSH-2.0-OpenSSH_9.6\r\n", b"").await;
+        let detection = ServiceBanner::grab(localhost, ssh, false).await;
+        assert_eq!(detection.banner, "SSH-2.0-OpenSSH_9.6");
+        let found = detection.service.unwrap();
+        assert_eq!((found.service.as_str(), found.version.as_deref()), ("ssh", Some("9.6")));
+
+        // Silent until asked: NULL times out, GetRequest names the server
+        let http = server(b"", b"HTTP/1.1 200 OK\r\nServer: lighttpd/1.4.73\r\n\r\n").await;
+        let found = ServiceBanner::grab(localhost, http, true).await.service.unwrap();
+        assert_eq!((found.describe().as_str(), found.cpe[0].as_str()), ("lighttpd 1.4.73", "cpe:/a:lighttpd:lighttpd:1.4.73"));
+        // and without intrusive probes it stays silent
+        assert!(ServiceBanner::grab(localhost, http, false).await.service.is_none());
+    }
+
+    #[test]
+    fn test_non_intrusive_schedule() {
+        let db = probes::database();
+        for port in [80, 443, 8080, 22, 3306, 9100] {
+            let names: Vec<&str> = schedule(db, port, false).iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(names, ["NULL"], "port {}", port);
         }
-        
-        return "Unknown".to_string();
+        let intrusive: Vec<&str> = schedule(db, 80, true).iter().map(|p| p.name.as_str()).collect();
+        assert!(intrusive.contains(&"GetRequest"));
     }
 }
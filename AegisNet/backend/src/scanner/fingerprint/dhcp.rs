@@ -0,0 +1,164 @@
+use std::sync::OnceLock;
+use serde::Serialize;
+use crate::scanner::discovery::dhcp::DhcpRequest;
+
+const BUILTIN_SIGNATURES: &str = include_str!("dhcp_fingerprints.fp");
+const SIGNATURE_FILE: &str = "dhcp_fingerprints.fp";
+
+// Option 55 is chosen by the DHCP client implementation, so an exact match is a strong hint
+const CONF_PARAMS_AND_VENDOR: u8 = 90;
+const CONF_PARAMS: u8 = 80;
+const CONF_VENDOR: u8 = 50;
+
+static SIGNATURES: OnceLock<Vec<DhcpSignature>> = OnceLock::new();
+
+/// What a DHCP request says about the client.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DhcpGuess {
+    pub family: String,
+    pub device_type: String,
+    pub label: String,
+    pub confidence: u8, // 0-100
+}
+
+/// One line of the fingerprint database.
+#[derive(Clone, Debug)]
+pub struct DhcpSignature {
+    family: String,
+    device_type: String,
+    label: String,
+    params: Option<Vec<u8>>,
+    vendor: Option<String>, // lowercase prefix
+}
+
+impl DhcpSignature {
+    /// `family | device type | label | parameter list | vendor class`
+    fn parse(line: &str) -> Option<Self> {
+        let cols: Vec<&str> = line.split('|').map(str::trim).collect();
+        let [family, device_type, label, params, vendor] = cols[..] else { return None };
+
+        let params = match params {
+            "*" => None,
+            list => Some(list.split(',').map(|p| p.trim().parse().ok()).collect::<Option<Vec<u8>>>()?),
+        };
+        let vendor = (vendor != "*").then(|| vendor.to_lowercase());
+        if params.is_none() && vendor.is_none() {
+            return None; // would match everything
+        }
+
+        Some(Self {
+            family: family.to_string(),
+            device_type: device_type.to_string(),
+            label: label.to_string(),
+            params,
+            vendor,
+        })
+    }
+
+    fn score(&self, request: &DhcpRequest) -> Option<u8> {
+        let vendor = request.vendor_class.as_deref().map(str::to_lowercase);
+        let vendor_match = match (&self.vendor, &vendor) {
+            (None, _) => None,
+            (Some(prefix), Some(v)) if v.starts_with(prefix.as_str()) => Some(true),
+            (Some(_), _) => return None,
+        };
+        match (&self.params, vendor_match) {
+            (Some(params), _) if *params != request.param_list => None,
+            (Some(_), Some(true)) => Some(CONF_PARAMS_AND_VENDOR),
+            (Some(_), _) => Some(CONF_PARAMS),
+            (None, _) => Some(CONF_VENDOR),
+        }
+    }
+}
+
+fn parse_signatures(text: &str) -> Vec<DhcpSignature> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let sig = DhcpSignature::parse(line);
+            if sig.is_none() {
+                tracing::warn!("Skipping malformed DHCP fingerprint: {}", line);
+            }
+            sig
+        })
+        .collect()
+}
+
+/// The fingerprint database: `dhcp_fingerprints.fp` from the working directory if present, else the built-in set.
+pub fn signatures() -> &'static [DhcpSignature] {
+    SIGNATURES.get_or_init(|| {
+        let custom = std::fs::read_to_string(SIGNATURE_FILE).ok().map(|t| parse_signatures(&t));
+        match custom {
+            Some(sigs) if !sigs.is_empty() => {
+                tracing::info!("Loaded {} DHCP fingerprints from {}", sigs.len(), SIGNATURE_FILE);
+                sigs
+            }
+            _ => parse_signatures(BUILTIN_SIGNATURES),
+        }
+    })
+}
+
+pub struct DhcpFingerprint;
+
+impl DhcpFingerprint {
+    pub fn identify(request: &DhcpRequest) -> Option<DhcpGuess> {
+        Self::match_signatures(signatures(), request)
+    }
+
+    fn match_signatures(sigs: &[DhcpSignature], request: &DhcpRequest) -> Option<DhcpGuess> {
+        let mut best: Option<(&DhcpSignature, u8)> = None;
+        for sig in sigs {
+            if let Some(score) = sig.score(request) {
+                if best.is_none_or(|(_, s)| score > s) {
+                    best = Some((sig, score));
+                }
+            }
+        }
+        best.map(|(sig, confidence)| DhcpGuess {
+            family: sig.family.clone(),
+            device_type: sig.device_type.clone(),
+            label: sig.label.clone(),
+            confidence,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::discovery::dhcp::tests::windows_request;
+
+    #[test]
+    fn test_builtin_database_parses() {
+        let sigs = parse_signatures(BUILTIN_SIGNATURES);
+        assert_eq!(sigs.len(), BUILTIN_SIGNATURES.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#')).count());
+        assert!(DhcpSignature::parse("Linux | Server | any | * | *").is_none());
+        assert!(DhcpSignature::parse("Linux | Server | bad | 1,3,x | *").is_none());
+    }
+
+    #[test]
+    fn test_identify() {
+        let sigs = parse_signatures(BUILTIN_SIGNATURES);
+        let mut request = DhcpRequest::parse(&windows_request()).unwrap();
+
+        let guess = DhcpFingerprint::match_signatures(&sigs, &request).unwrap();
+        assert_eq!(guess.label, "Windows 10 / 11");
+        assert_eq!(guess.device_type, "Workstation (Windows)");
+        assert_eq!(guess.confidence, CONF_PARAMS_AND_VENDOR);
+
+        // Unknown parameter list: the vendor class alone still says Windows
+        request.param_list = vec![1, 3, 6];
+        let guess = DhcpFingerprint::match_signatures(&sigs, &request).unwrap();
+        assert_eq!((guess.label.as_str(), guess.confidence), ("Windows", CONF_VENDOR));
+
+        // An iPhone sends no vendor class
+        request.vendor_class = None;
+        request.param_list = vec![1, 121, 3, 6, 15, 108, 114, 119, 252];
+        let guess = DhcpFingerprint::match_signatures(&sigs, &request).unwrap();
+        assert_eq!((guess.family.as_str(), guess.confidence), ("iOS/macOS", CONF_PARAMS));
+
+        request.param_list = vec![1, 2, 3];
+        assert_eq!(DhcpFingerprint::match_signatures(&sigs, &request), None);
+    }
+}
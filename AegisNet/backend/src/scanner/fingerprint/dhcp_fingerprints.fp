@@ -0,0 +1,34 @@
+# AegisNet DHCP fingerprints (option 55 parameter request list).
+#
+# family | device type | label | parameter list | vendor class
+#
+#   parameter list   option 55 codes in the client's order, or * to match on the vendor class alone
+#   vendor class     prefix of option 60 (case-insensitive), or *
+#
+# A copy of this file named dhcp_fingerprints.fp in the working directory
+# replaces the built-in set.
+
+Windows    | Workstation (Windows) | Windows 10 / 11          | 1,3,6,15,31,33,43,44,46,47,119,121,249,252 | MSFT 5.0
+Windows    | Workstation (Windows) | Windows 8+               | 1,3,6,15,31,33,43,44,46,47,119,121,249,252 | *
+Windows    | Workstation (Windows) | Windows 7 / Vista        | 1,15,3,6,44,46,47,31,33,121,249,43,252     | MSFT 5.0
+Windows    | Workstation (Windows) | Windows XP               | 1,15,3,6,44,46,47,31,33,249,43             | MSFT 5.0
+Windows    | Workstation (Windows) | Windows                  | *                                          | MSFT
+iOS/macOS  | Mobile/Tablet         | iOS 10-13                | 1,121,3,6,15,119,252                       | *
+iOS/macOS  | Mobile/Tablet         | iOS 14+                  | 1,121,3,6,15,108,114,119,252               | *
+iOS/macOS  | Workstation           | macOS                    | 1,121,3,6,15,119,252,95,44,46              | *
+iOS/macOS  | Workstation           | macOS 12+                | 1,121,3,6,15,108,114,119,252,95,44,46      | *
+Android    | Mobile/Tablet         | Android 8-10             | 1,3,6,15,26,28,51,58,59,43                 | *
+Android    | Mobile/Tablet         | Android 11+              | 1,3,6,15,26,28,51,58,59,43,114,108         | *
+Android    | Mobile/Tablet         | Android                  | *                                          | android-dhcp
+ChromeOS   | Workstation           | Chrome OS                | 1,121,33,3,6,12,15,26,28,51,54,58,59,119,252 | *
+Linux      | Server                | Linux (dhclient)         | 1,28,2,3,15,6,119,12,44,47,26,121,42       | *
+Linux      | Server                | Linux (NetworkManager)   | 1,28,2,121,15,6,12,40,41,42,26,119,3,121,249,33,252,42 | *
+Linux      | Server                | Linux (systemd-networkd) | 1,3,6,12,15,26,28,42,119,121               | *
+Linux      | Server                | Linux (dhcpcd)           | *                                          | dhcpcd
+Embedded   | IoT/Smart Home        | BusyBox udhcpc           | 1,3,6,12,15,28,42                          | *
+Embedded   | IoT/Smart Home        | BusyBox udhcpc           | *                                          | udhcp
+Embedded   | IoT/Smart Home        | ESP8266 / ESP32 (lwIP)   | 1,3,28,6                                   | *
+Embedded   | IoT/Smart Home        | ESP-IDF                  | 1,3,28,6,15,44,46,47,31,33,121,43          | *
+Embedded   | Printer               | HP JetDirect             | *                                          | Hewlett-Packard JetDirect
+Embedded   | Media Player          | Roku                     | 1,3,6,15,12                                | *
+Game Console | Media Player        | PlayStation              | 1,3,15,6                                   | *
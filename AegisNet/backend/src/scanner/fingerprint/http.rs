@@ -1,4 +1,5 @@
 use reqwest::Client;
+use std::net::{IpAddr, SocketAddr};
 use std::time::Duration;
 use regex::Regex;
 
@@ -9,9 +10,10 @@ pub struct HttpFingerprint {
     pub status: u16,
 }
 
-pub async fn analyze(ip: &str, port: u16) -> Option<HttpFingerprint> {
+pub async fn analyze(ip: IpAddr, port: u16) -> Option<HttpFingerprint> {
     let scheme = if port == 443 { "https" } else { "http" };
-    let url = format!("{}://{}:{}/", scheme, ip, port);
+    // SocketAddr puts IPv6 literals in brackets
+    let url = format!("{}://{}/", scheme, SocketAddr::new(ip, port));
     
     // Ignore cert errors for scanning
     let client = Client::builder()
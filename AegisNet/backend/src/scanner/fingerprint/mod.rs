@@ -1,9 +1,13 @@
 pub mod oui;
 pub mod os;
+pub mod stack;
 pub mod banner;
+pub mod probes;
 pub mod http;
 pub mod snmp;
 pub mod smb;
 pub mod oui_live;
+pub mod dhcp;
+pub mod tls;
 
 pub struct FingerprintEngine;
@@ -1,6 +1,192 @@
+use std::sync::OnceLock;
+use serde::Serialize;
+use super::stack::StackTraits;
+
+const BUILTIN_SIGNATURES: &str = include_str!("os_signatures.fp");
+const SIGNATURE_FILE: &str = "os_signatures.fp";
+
+// Weights of the matched traits, the confidence is the share of the maximum
+const W_TTL: u32 = 2;
+const W_OPTIONS: u32 = 3;
+const W_WINDOW: u32 = 2;
+const W_MSS: u32 = 1;
+const W_DF: u32 = 1;
+const W_MAX: u32 = W_TTL + W_OPTIONS + W_WINDOW + W_MSS + W_DF;
+
+static SIGNATURES: OnceLock<Vec<Signature>> = OnceLock::new();
+
+/// Best guess for a host's operating system.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OsGuess {
+    pub family: String,
+    pub label: String,
+    pub confidence: u8, // 0-100
+}
+
+impl OsGuess {
+    pub fn unknown() -> Self {
+        Self { family: "Unknown".into(), label: "Unknown".into(), confidence: 0 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum WindowSig {
+    Any,
+    Exact(u16),
+    MssMultiple(u16),
+}
+
+/// One line of the signature database.
+#[derive(Clone, Debug)]
+pub struct Signature {
+    family: String,
+    label: String,
+    ittl: u8,
+    mss: Option<u16>,
+    window: WindowSig,
+    options: Option<Vec<String>>,
+    df: Option<bool>,
+}
+
+impl Signature {
+    /// `family | label | ittl:mss:window:options:quirks`
+    fn parse(line: &str) -> Option<Self> {
+        let mut cols = line.split('|').map(str::trim);
+        let (family, label, sig) = (cols.next()?, cols.next()?, cols.next()?);
+        let fields: Vec<&str> = sig.split(':').collect();
+        if fields.len() != 5 {
+            return None;
+        }
+
+        let window = match fields[2] {
+            "*" => WindowSig::Any,
+            w => match w.strip_prefix("mss*") {
+                Some(n) => WindowSig::MssMultiple(n.parse().ok()?),
+                None => WindowSig::Exact(w.parse().ok()?),
+            },
+        };
+
+        Some(Self {
+            family: family.to_string(),
+            label: label.to_string(),
+            ittl: fields[0].parse().ok()?,
+            mss: if fields[1] == "*" { None } else { Some(fields[1].parse().ok()?) },
+            window,
+            options: if fields[3] == "*" { None } else { Some(fields[3].split(',').map(String::from).collect()) },
+            df: match fields[4] { "df" => Some(true), "!df" => Some(false), _ => None },
+        })
+    }
+
+    /// Weighted score, `None` when a hard trait (TTL, options, MSS) contradicts the signature.
+    fn score(&self, traits: &StackTraits) -> Option<u32> {
+        let mut score = 0;
+
+        if traits.initial_ttl()? != self.ittl { return None; }
+        score += W_TTL;
+
+        if let Some(layout) = &self.options {
+            if !layout.iter().map(String::as_str).eq(traits.options.iter().copied()) { return None; }
+            score += W_OPTIONS;
+        }
+
+        if let Some(mss) = self.mss {
+            if traits.mss != Some(mss) { return None; }
+            score += W_MSS;
+        }
+
+        // Window and DF get tuned by admins and middleboxes, a mismatch only costs points
+        let window_match = match (&self.window, traits.window, traits.mss) {
+            (WindowSig::Exact(w), Some(win), _) => win == *w,
+            (WindowSig::MssMultiple(n), Some(win), Some(mss)) => win as u32 == mss as u32 * *n as u32,
+            _ => false,
+        };
+        if window_match { score += W_WINDOW; }
+
+        if self.df.is_some() && self.df == traits.df { score += W_DF; }
+
+        Some(score)
+    }
+}
+
+fn parse_signatures(text: &str) -> Vec<Signature> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let sig = Signature::parse(line);
+            if sig.is_none() {
+                tracing::warn!("Skipping malformed OS signature: {}", line);
+            }
+            sig
+        })
+        .collect()
+}
+
+/// The signature database: `os_signatures.fp` from the working directory if present, else the built-in set.
+pub fn signatures() -> &'static [Signature] {
+    SIGNATURES.get_or_init(|| {
+        let custom = std::fs::read_to_string(SIGNATURE_FILE).ok().map(|t| parse_signatures(&t));
+        match custom {
+            Some(sigs) if !sigs.is_empty() => {
+                tracing::info!("Loaded {} OS signatures from {}", sigs.len(), SIGNATURE_FILE);
+                sigs
+            }
+            _ => parse_signatures(BUILTIN_SIGNATURES),
+        }
+    })
+}
+
 pub struct OsFingerprint;
 
 impl OsFingerprint {
+    /// Matches the observed stack traits against the signature database.
+    /// Without a SYN-ACK only the TTL is known, which gives a coarse, low confidence guess.
+    pub fn identify(traits: &StackTraits, open_ports: &[u16]) -> OsGuess {
+        let mut guess = if traits.has_tcp() {
+            Self::match_signatures(signatures(), traits)
+        } else {
+            None
+        };
+
+        if guess.is_none() {
+            guess = traits.initial_ttl().map(|ittl| {
+                let label = Self::infer(ittl, open_ports);
+                // The label names a guess, the family must match the signature families
+                let family = if label.starts_with("macOS") || label.starts_with("iOS") { "iOS/macOS" }
+                             else if ittl <= 64 { "Linux" }
+                             else if ittl <= 128 { "Windows" }
+                             else { "Network Device" };
+                OsGuess { family: family.into(), label, confidence: 30 }
+            });
+        }
+        let mut guess = guess.unwrap_or_else(OsGuess::unknown);
+
+        // Services only some systems run back up (or sharpen) the stack guess
+        if open_ports.contains(&62078) && traits.initial_ttl() == Some(64) {
+            guess = OsGuess { family: "iOS/macOS".into(), label: "iOS Device".into(), confidence: guess.confidence.max(70) };
+        } else if guess.family == "Windows" && (open_ports.contains(&445) || open_ports.contains(&135)) {
+            guess.confidence = (guess.confidence + 10).min(100);
+        }
+
+        guess
+    }
+
+    fn match_signatures(sigs: &[Signature], traits: &StackTraits) -> Option<OsGuess> {
+        let mut best: Option<(&Signature, u32)> = None;
+        for sig in sigs {
+            if let Some(score) = sig.score(traits) {
+                if best.is_none_or(|(_, s)| score > s) {
+                    best = Some((sig, score));
+                }
+            }
+        }
+        best.map(|(sig, score)| OsGuess {
+            family: sig.family.clone(),
+            label: sig.label.clone(),
+            confidence: (score * 100 / W_MAX) as u8,
+        })
+    }
+
     pub fn infer(ttl: u8, open_ports: &[u16]) -> String {
         let mut os = String::from("Unknown OS");
 
@@ -46,4 +232,59 @@ mod tests {
     fn test_unknown_ttl() {
         assert_eq!(OsFingerprint::infer(255, &[]), "Network Appliance (Cisco/Solaris)");
     }
+
+    fn syn_ack(ttl: u8, window: u16, options: &[&'static str], df: bool) -> StackTraits {
+        StackTraits {
+            ttl: Some(ttl),
+            df: Some(df),
+            window: Some(window),
+            mss: Some(1460),
+            wscale: Some(7),
+            options: options.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_builtin_signatures_parse() {
+        let sigs = parse_signatures(BUILTIN_SIGNATURES);
+        let lines = BUILTIN_SIGNATURES.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#')).count();
+        assert_eq!(sigs.len(), lines);
+    }
+
+    #[test]
+    fn test_signature_matching() {
+        let sigs = parse_signatures(BUILTIN_SIGNATURES);
+
+        let linux = syn_ack(61, 65160, &["mss", "sok", "ts", "nop", "ws"], true);
+        let guess = OsFingerprint::match_signatures(&sigs, &linux).unwrap();
+        assert_eq!(guess.family, "Linux");
+
+        let windows = syn_ack(127, 8192, &["mss", "nop", "ws", "nop", "nop", "sok"], true);
+        let guess = OsFingerprint::match_signatures(&sigs, &windows).unwrap();
+        assert_eq!(guess.family, "Windows");
+        assert_eq!(guess.confidence, 88); // everything but the MSS matched
+
+        let mac = syn_ack(64, 65535, &["mss", "nop", "ws", "nop", "nop", "ts", "sok", "eol"], true);
+        assert_eq!(OsFingerprint::match_signatures(&sigs, &mac).unwrap().family, "iOS/macOS");
+
+        // Unknown option layout
+        let odd = syn_ack(64, 1024, &["ws", "mss"], false);
+        assert!(OsFingerprint::match_signatures(&sigs, &odd).is_none());
+    }
+
+    #[test]
+    fn test_identify_fallbacks() {
+        // TTL only: coarse guess
+        let guess = OsFingerprint::identify(&StackTraits::from_ttl(Some(120), None), &[135, 445]);
+        assert_eq!((guess.family.as_str(), guess.label.as_str()), ("Windows", "Windows PC"));
+        assert_eq!(guess.confidence, 40); // SMB/RPC back up the Windows guess
+
+        let guess = OsFingerprint::identify(&StackTraits::from_ttl(Some(60), None), &[22]);
+        assert_eq!((guess.family.as_str(), guess.label.as_str()), ("Linux", "Linux Server"));
+        let guess = OsFingerprint::identify(&StackTraits::from_ttl(Some(250), None), &[]);
+        assert_eq!(guess.family, "Network Device");
+
+        // Nothing observed at all
+        assert_eq!(OsFingerprint::identify(&StackTraits::default(), &[22]), OsGuess::unknown());
+    }
 }
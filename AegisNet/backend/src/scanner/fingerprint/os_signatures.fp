@@ -0,0 +1,34 @@
+# AegisNet OS signatures (SYN-ACK / stack traits), loosely modelled on p0f v3.
+#
+# family | label | ittl:mss:window:options:quirks
+#
+#   ittl     initial TTL (32, 64, 128, 255)
+#   mss      maximum segment size, or *
+#   window   window size, "mss*N" for multiples of the MSS, or *
+#   options  TCP option layout in wire order (mss, nop, ws, sok, ts, eol), or *
+#   quirks   df / !df, or *
+#
+# A copy of this file named os_signatures.fp in the working directory
+# replaces the built-in set.
+
+Linux            | Linux 3.x-6.x                | 64:*:*:mss,sok,ts,nop,ws:df
+Linux            | Linux (no timestamps)        | 64:*:*:mss,nop,nop,sok,nop,ws:df
+Linux            | Linux 2.4 / embedded         | 64:*:5840:mss,sok,ts,nop,ws:df
+Linux            | Linux 2.6 (old window)       | 64:*:mss*4:mss,sok,ts,nop,ws:df
+iOS/macOS        | macOS / iOS                  | 64:*:65535:mss,nop,ws,nop,nop,ts,sok,eol:df
+iOS/macOS        | macOS / iOS (no timestamps)  | 64:*:65535:mss,nop,ws,sok,eol:df
+FreeBSD          | FreeBSD / pfSense / OPNsense | 64:*:65535:mss,nop,ws,sok,ts:df
+OpenBSD          | OpenBSD                      | 64:*:16384:mss,nop,nop,sok,nop,ws,nop,nop,ts:df
+Windows          | Windows 7 / 10 / 11          | 128:*:65535:mss,nop,ws,sok,ts:df
+Windows          | Windows 7 / 10 / 11          | 128:*:65535:mss,nop,ws,nop,nop,sok:df
+Windows          | Windows 7 / 10 / 11          | 128:*:8192:mss,nop,ws,nop,nop,sok:df
+Windows          | Windows Server 2016+         | 128:*:8192:mss,nop,ws,sok,ts:df
+Windows          | Windows XP / 2003            | 128:*:65535:mss,nop,nop,sok:df
+Windows          | Windows XP / 2003            | 128:*:64240:mss,nop,nop,sok:df
+Cisco IOS        | Cisco IOS                    | 255:*:4128:mss:!df
+Cisco IOS        | Cisco IOS                    | 255:*:*:mss:!df
+Solaris          | Solaris 10 / 11              | 64:*:*:nop,nop,ts,mss,nop,ws,nop,nop,sok:df
+Solaris          | Solaris 8 / 9                | 255:*:*:nop,ws,nop,nop,ts,nop,nop,sok,mss:df
+Embedded         | lwIP / RTOS (IoT)            | 64:*:*:mss:*
+Embedded         | lwIP / RTOS (IoT)            | 255:*:*:mss:*
+Embedded         | HP JetDirect (printer)       | 64:*:*:mss,nop,ws,sok,ts:!df
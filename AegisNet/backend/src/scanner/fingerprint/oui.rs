@@ -1,26 +1,23 @@
+use crate::scanner::addr::MacAddr;
+
 pub struct OuiDb;
 
 impl OuiDb {
-    pub fn lookup(mac: &str) -> String {
+    pub fn lookup(mac: &MacAddr) -> String {
         // 1. Try Live DB
         if let Some(vendor) = super::oui_live::OuiLive::lookup(mac) {
              return vendor;
         }
 
-        let clean = mac.replace(":", "").replace("-", "").to_uppercase();
-        if clean.len() < 6 { return "Unknown".to_string(); }
-        
         // 2. Check for Locally Administered Address (LAA) / Private MAC
-        // If the second least significant bit of the first byte is 1, it's LAA.
-        if let Ok(first_byte) = u8::from_str_radix(&clean[0..2], 16) {
-            if (first_byte & 0b0000_0010) != 0 {
-                return "Private / Randomized Device".to_string();
-            }
+        if mac.is_randomized() {
+            return "Private / Randomized Device".to_string();
         }
 
-        let prefix = &clean[0..6];
+        let [a, b, c] = mac.oui();
+        let prefix = format!("{:02X}{:02X}{:02X}", a, b, c);
 
-        match prefix {
+        match prefix.as_str() {
             // APPLE
             "0017F2" | "0019E3" | "001B63" | "001C27" | "001D4F" | "001E52" | "001F5B" | "001F5C" |
             "0021E9" | "002241" | "002312" | "002332" | "00236C" | "0023DF" | "002436" | "002500" |
@@ -68,23 +65,32 @@ impl OuiDb {
 mod tests {
     use super::*;
 
+    fn lookup(mac: &str) -> String {
+        OuiDb::lookup(&mac.parse().unwrap())
+    }
+
     #[test]
     fn test_apple_oui() {
-        assert_eq!(OuiDb::lookup("00:17:F2:00:00:00"), "Apple, Inc.");
+        assert_eq!(lookup("00:17:F2:00:00:00"), "Apple, Inc.");
     }
 
     #[test]
     fn test_samsung_oui() {
-        assert_eq!(OuiDb::lookup("00-12-47-11-22-33"), "Samsung Electronics");
+        assert_eq!(lookup("00-12-47-11-22-33"), "Samsung Electronics");
     }
 
     #[test]
     fn test_unknown_oui() {
-        assert_eq!(OuiDb::lookup("FF:FF:FF:00:00:00"), "Unknown Vendor");
+        assert_eq!(lookup("FF:FF:FF:00:00:00"), "Unknown Vendor");
+    }
+
+    #[test]
+    fn test_private_oui() {
+        assert_eq!(lookup("DA:A1:19:12:34:56"), "Private / Randomized Device");
     }
     
     #[test]
     fn test_malformed_input() {
-        assert_eq!(OuiDb::lookup("123"), "Unknown");
+        assert!("123".parse::<MacAddr>().is_err());
     }
 }
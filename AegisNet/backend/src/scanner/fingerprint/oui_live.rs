@@ -3,6 +3,7 @@ use std::sync::OnceLock;
 use std::path::Path;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
+use crate::scanner::addr::MacAddr;
 
 // Global OUI Database
 static OUI_CACHE: OnceLock<DashMap<String, String>> = OnceLock::new();
@@ -14,12 +15,11 @@ impl OuiLive {
         OUI_CACHE.get_or_init(|| DashMap::new())
     }
 
-    pub fn lookup(mac: &str) -> Option<String> {
-        let clean = mac.replace(":", "").replace("-", "").to_uppercase();
-        if clean.len() < 6 { return None; }
-        let prefix = &clean[0..6];
+    pub fn lookup(mac: &MacAddr) -> Option<String> {
+        let [a, b, c] = mac.oui();
+        let prefix = format!("{:02X}{:02X}{:02X}", a, b, c);
         
-        if let Some(vendor) = Self::get_db().get(prefix) {
+        if let Some(vendor) = Self::get_db().get(&prefix) {
             return Some(vendor.clone());
         }
         None
@@ -0,0 +1,403 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+use regex::bytes::{Captures, Regex, RegexBuilder};
+use serde::Serialize;
+use crate::scanner::ports::PortSpec;
+
+const BUILTIN_PROBES: &str = include_str!("service_probes.fp");
+const PROBE_FILE: &str = "service_probes.fp";
+
+const DEFAULT_WAIT: Duration = Duration::from_millis(1000);
+const DEFAULT_RARITY: u8 = 5;
+// Probes not hinted for the port are only tried when they are this common
+pub const MAX_RARITY: u8 = 5;
+
+static DATABASE: OnceLock<ProbeDb> = OnceLock::new();
+
+/// What a matched response says about the service behind a port.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ServiceMatch {
+    pub service: String, // ssh, http, mysql
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub info: Option<String>,
+    pub hostname: Option<String>,
+    pub os: Option<String>,
+    pub device_type: Option<String>,
+    pub cpe: Vec<String>, // e.g. "cpe:/a:openbsd:openssh:8.9p1"
+    pub soft: bool,       // service known, product not
+}
+
+impl ServiceMatch {
+    /// nmap's VERSION column, e.g. "OpenSSH 8.9p1 Ubuntu 3 (Ubuntu Linux; protocol 2.0)".
+    pub fn describe(&self) -> String {
+        let mut text = [&self.product, &self.version].into_iter().flatten().cloned().collect::<Vec<_>>().join(" ");
+        if let Some(info) = &self.info {
+            text = if text.is_empty() { info.clone() } else { format!("{} ({})", text, info) };
+        }
+        text
+    }
+}
+
+/// One `match` or `softmatch` line.
+#[derive(Clone, Debug)]
+struct Matcher {
+    service: String,
+    pattern: Regex,
+    soft: bool,
+    product: Option<String>,
+    version: Option<String>,
+    info: Option<String>,
+    hostname: Option<String>,
+    os: Option<String>,
+    device_type: Option<String>,
+    cpe: Vec<String>,
+}
+
+impl Matcher {
+    /// `<service> m|regex|flags [p/../] [v/../] [i/../] [h/../] [o/../] [d/../] [cpe:/../]`
+    fn parse(line: &str, soft: bool) -> Option<Self> {
+        let (service, rest) = line.split_once(' ')?;
+        let rest = rest.trim_start().strip_prefix('m')?;
+        let (pattern, rest) = delimited(rest)?;
+        let flags: String = rest.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+        let mut rest = &rest[flags.len()..];
+
+        let pattern = RegexBuilder::new(&format!("(?-u){}", translate_escapes(pattern)))
+            .case_insensitive(flags.contains('i'))
+            .dot_matches_new_line(flags.contains('s'))
+            .build()
+            .ok()?;
+
+        let mut matcher = Self {
+            service: service.to_string(),
+            pattern,
+            soft,
+            product: None,
+            version: None,
+            info: None,
+            hostname: None,
+            os: None,
+            device_type: None,
+            cpe: Vec::new(),
+        };
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            let (field, after) = match rest.strip_prefix("cpe:") {
+                Some(after) => ("cpe", after),
+                None => rest.split_at(rest.chars().next()?.len_utf8()),
+            };
+            let (value, after) = delimited(after)?;
+            // cpe:/../a marks an application/OS part, it has no meaning here
+            rest = after.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+            let value = Some(value.to_string());
+            match field {
+                "p" => matcher.product = value,
+                "v" => matcher.version = value,
+                "i" => matcher.info = value,
+                "h" => matcher.hostname = value,
+                "o" => matcher.os = value,
+                "d" => matcher.device_type = value,
+                "cpe" => matcher.cpe.push(format!("cpe:/{}", value?)),
+                _ => return None,
+            }
+        }
+        Some(matcher)
+    }
+
+    fn apply(&self, response: &[u8]) -> Option<ServiceMatch> {
+        let captures = self.pattern.captures(response)?;
+        let fill = |template: &Option<String>| template.as_deref().map(|t| substitute(t, &captures)).filter(|v| !v.is_empty());
+        Some(ServiceMatch {
+            service: self.service.clone(),
+            product: fill(&self.product),
+            version: fill(&self.version),
+            info: fill(&self.info),
+            hostname: fill(&self.hostname),
+            os: fill(&self.os),
+            device_type: fill(&self.device_type),
+            cpe: self.cpe.iter().map(|c| substitute(c, &captures).replace(' ', "_")).collect(),
+            soft: self.soft,
+        })
+    }
+}
+
+/// Splits `|text|rest` on its delimiter, which is whatever character comes first.
+fn delimited(s: &str) -> Option<(&str, &str)> {
+    let delimiter = s.chars().next()?;
+    let body = &s[delimiter.len_utf8()..];
+    let end = body.find(delimiter)?;
+    Some((&body[..end], &body[end + delimiter.len_utf8()..]))
+}
+
+/// nmap patterns write NUL as `\0`, which the regex crate does not take.
+fn translate_escapes(pattern: &str) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => out.push_str("\\x00"),
+            Some(next) => {
+                out.push('\\');
+                out.push(next);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Replaces `$1`-`$9` with capture groups; bytes that are not printable ASCII are dropped.
+fn substitute(template: &str, captures: &Captures) -> String {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        let group = chars.peek().and_then(|d| d.to_digit(10));
+        match (c, group) {
+            ('$', Some(n)) => {
+                chars.next();
+                let bytes = captures.get(n as usize).map(|m| m.as_bytes()).unwrap_or_default();
+                out.extend(bytes.iter().filter(|b| b.is_ascii_graphic() || **b == b' ').map(|b| *b as char));
+            }
+            (c, _) => out.push(c),
+        }
+    }
+    out.trim().to_string()
+}
+
+/// Decodes a `q|..|` payload.
+fn unescape(payload: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut bytes = payload.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        out.push(match bytes.next()? {
+            b'r' => b'\r',
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'0' => 0,
+            b'x' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?
+            }
+            other => other,
+        });
+    }
+    Some(out)
+}
+
+/// One `Probe` block: what to send and how to read the answer.
+#[derive(Clone, Debug)]
+pub struct Probe {
+    pub name: String,
+    pub payload: Vec<u8>,
+    pub wait: Duration,
+    ports: Option<PortSpec>,
+    rarity: u8,
+    fallback: Vec<String>,
+    matches: Vec<Matcher>,
+}
+
+impl Probe {
+    /// `Probe TCP <name> q|payload|`
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(3, ' ');
+        if parts.next()? != "TCP" {
+            return None;
+        }
+        let name = parts.next()?.to_string();
+        let (payload, _) = delimited(parts.next()?.trim().strip_prefix('q')?)?;
+        Some(Self {
+            name,
+            payload: unescape(payload)?,
+            wait: DEFAULT_WAIT,
+            ports: None,
+            rarity: DEFAULT_RARITY,
+            fallback: Vec::new(),
+            matches: Vec::new(),
+        })
+    }
+
+    fn hinted(&self, port: u16) -> bool {
+        self.ports.as_ref().is_some_and(|p| p.ports().contains(&port))
+    }
+
+    /// Whether a hard match of this probe can name `service`, worth sending after a softmatch.
+    pub fn identifies(&self, service: &str) -> bool {
+        self.matches.iter().any(|m| !m.soft && m.service == service)
+    }
+}
+
+/// The parsed probe file.
+#[derive(Clone, Debug, Default)]
+pub struct ProbeDb {
+    probes: Vec<Probe>,
+}
+
+impl ProbeDb {
+    pub fn parse(text: &str) -> Self {
+        let mut probes: Vec<Probe> = Vec::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let (directive, args) = line.split_once(' ').unwrap_or((line, ""));
+            let args = args.trim();
+            if directive == "Probe" {
+                match Probe::parse(args) {
+                    Some(probe) => probes.push(probe),
+                    None => tracing::warn!("Skipping malformed service probe: {}", line),
+                }
+                continue;
+            }
+            let Some(probe) = probes.last_mut() else {
+                tracing::warn!("Service probe directive outside a probe: {}", line);
+                continue;
+            };
+            let ok = match directive {
+                "match" | "softmatch" => Matcher::parse(args, directive == "softmatch").map(|m| probe.matches.push(m)).is_some(),
+                "ports" => PortSpec::parse(args).map(|p| probe.ports = Some(p)).is_ok(),
+                "rarity" => args.parse().map(|r| probe.rarity = r).is_ok(),
+                "totalwaitms" => args.parse().map(|ms| probe.wait = Duration::from_millis(ms)).is_ok(),
+                "fallback" => {
+                    probe.fallback = args.split(',').map(|s| s.trim().to_string()).collect();
+                    true
+                }
+                _ => true, // sslports, tcpwrappedms and the like: not used here
+            };
+            if !ok {
+                tracing::warn!("Skipping malformed service probe line: {}", line);
+            }
+        }
+        Self { probes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.probes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.probes.is_empty()
+    }
+
+    fn get(&self, name: &str) -> Option<&Probe> {
+        self.probes.iter().find(|p| p.name == name)
+    }
+
+    /// The probes to send to `port`, in order: NULL, those hinted for the port, then the
+    /// common ones by rarity.
+    pub fn for_port(&self, port: u16) -> Vec<&Probe> {
+        let (null, rest): (Vec<&Probe>, Vec<&Probe>) = self.probes.iter().partition(|p| p.payload.is_empty());
+        let (hinted, mut common): (Vec<&Probe>, Vec<&Probe>) = rest.into_iter().partition(|p| p.hinted(port));
+        common.retain(|p| p.rarity <= MAX_RARITY);
+        common.sort_by_key(|p| p.rarity);
+        null.into_iter().chain(hinted).chain(common).collect()
+    }
+
+    /// Matches a probe's response: its own matches, then its fallbacks', then NULL's.
+    /// The first hard match wins, else the first softmatch.
+    pub fn identify(&self, probe: &Probe, response: &[u8]) -> Option<ServiceMatch> {
+        let fallbacks = probe.fallback.iter().filter_map(|name| self.get(name));
+        let null = self.probes.iter().filter(|p| p.payload.is_empty() && p.name != probe.name);
+        let matchers = std::iter::once(probe).chain(fallbacks).chain(null).flat_map(|p| &p.matches);
+
+        let mut soft = None;
+        for matcher in matchers {
+            match matcher.apply(response) {
+                Some(found) if !found.soft => return Some(found),
+                Some(found) => { soft.get_or_insert(found); }
+                None => {}
+            }
+        }
+        soft
+    }
+}
+
+/// The probe database: `service_probes.fp` from the working directory if present, else the built-in set.
+pub fn database() -> &'static ProbeDb {
+    DATABASE.get_or_init(|| {
+        let custom = std::fs::read_to_string(PROBE_FILE).ok().map(|t| ProbeDb::parse(&t));
+        match custom {
+            Some(db) if !db.is_empty() => {
+                tracing::info!("Loaded {} service probes from {}", db.len(), PROBE_FILE);
+                db
+            }
+            _ => ProbeDb::parse(BUILTIN_PROBES),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> ProbeDb {
+        ProbeDb::parse(BUILTIN_PROBES)
+    }
+
+    #[test]
+    fn test_builtin_database_parses() {
+        let db = db();
+        let directives = |prefix: &str| BUILTIN_PROBES.lines().filter(|l| l.starts_with(prefix)).count();
+        assert_eq!(db.len(), directives("Probe "));
+        let matchers: usize = db.probes.iter().map(|p| p.matches.len()).sum();
+        assert_eq!(matchers, directives("match ") + directives("softmatch "));
+
+        assert_eq!(db.get("RedisInfo").unwrap().payload, b"*1\r\n$4\r\ninfo\r\n");
+        assert_eq!(db.get("SSLSessionReq").unwrap().payload, [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f]);
+        assert!(Matcher::parse("http m|^HTTP| p/x/ z/bad/", false).is_none());
+        assert!(Matcher::parse("http m|(unclosed|", false).is_none());
+    }
+
+    #[test]
+    fn test_probe_order() {
+        let db = db();
+        let names = |port| db.for_port(port).iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names(6379)[..3], ["NULL", "GenericLines", "RedisInfo"]);
+        assert_eq!(names(8080)[..3], ["NULL", "GetRequest", "HTTPOptions"]);
+        // Rare probes only go to their own ports
+        assert!(!names(22).contains(&"Memcache"));
+        assert!(names(22).contains(&"GetRequest"));
+    }
+
+    #[test]
+    fn test_identify() {
+        let db = db();
+        let null = db.get("NULL").unwrap();
+
+        let ssh = db.identify(null, b"SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.6\r\n").unwrap();
+        assert_eq!(ssh.service, "ssh");
+        assert_eq!(ssh.describe(), "OpenSSH 8.9p1 Ubuntu 3ubuntu0.6 (Ubuntu Linux; protocol 2.0)");
+        assert_eq!(ssh.cpe, ["cpe:/a:openbsd:openssh:8.9p1", "cpe:/o:canonical:ubuntu_linux"]);
+
+        let mysql = db.identify(null, b"\x4a\0\0\0\x0a8.0.36-0ubuntu0.22.04.1\0\x0b\0\0\0").unwrap();
+        assert_eq!((mysql.product.as_deref(), mysql.version.as_deref()), (Some("MySQL"), Some("8.0.36")));
+
+        let get = db.get("GetRequest").unwrap();
+        let nginx = db.identify(get, b"HTTP/1.1 200 OK\r\nDate: Sat\r\nServer: nginx/1.24.0\r\n\r\n<html>").unwrap();
+        assert_eq!((nginx.service.as_str(), nginx.describe().as_str()), ("http", "nginx 1.24.0"));
+        assert_eq!(nginx.cpe, ["cpe:/a:igor_sysoev:nginx:1.24.0"]);
+
+        // Fallback: an OPTIONS answer is matched with the GET matchers
+        let options = db.get("HTTPOptions").unwrap();
+        let iis = db.identify(options, b"HTTP/1.1 200 OK\r\nAllow: GET\r\nServer: Microsoft-IIS/10.0\r\n\r\n").unwrap();
+        assert_eq!((iis.version.as_deref(), iis.os.as_deref()), (Some("10.0"), Some("Windows")));
+
+        // A greeting seen on a later probe still matches the NULL matchers
+        let ftp = db.identify(get, b"220 (vsFTPd 3.0.5)\r\n").unwrap();
+        assert_eq!((ftp.service.as_str(), ftp.version.as_deref()), ("ftp", Some("3.0.5")));
+
+        let soft = db.identify(get, b"HTTP/1.0 404 Not Found\r\nServer: acme-embedded\r\n\r\n").unwrap();
+        assert!(soft.soft);
+        assert_eq!((soft.service.as_str(), soft.describe().as_str()), ("http", ""));
+
+        assert!(db.identify(null, b"\x00\x01garbage").is_none());
+    }
+}
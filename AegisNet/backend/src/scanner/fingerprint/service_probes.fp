@@ -0,0 +1,152 @@
+# AegisNet service probes, a subset of the nmap-service-probes format.
+#
+#   Probe TCP <name> q|<payload>|     starts a probe; the payload takes \r \n \t \0 \xHH \\ escapes
+#   ports <port spec>                 ports the probe is tried on first, e.g. 80,8000-8100
+#   rarity <1-9>                      unhinted probes above MAX_RARITY are skipped
+#   totalwaitms <ms>                  how long to wait for the response
+#   fallback <probe>[,<probe>]        also try these probes' matches on the response
+#   match <service> m|<regex>|[is] [p/product/] [v/version/] [i/info/] [h/host/] [o/os/] [d/device/] [cpe:/cpe/]...
+#   softmatch <service> m|<regex>|[is]
+#
+# The NULL probe sends nothing and reads the greeting; its matches apply to every probe.
+# Regexes are Rust regex syntax over raw bytes: no lookaround, no backreferences.
+# $1-$9 in templates insert capture groups.
+#
+# A copy of this file named service_probes.fp in the working directory
+# replaces the built-in set.
+
+##############################################################################
+Probe TCP NULL q||
+totalwaitms 1000
+
+# SSH
+match ssh m|^SSH-([\d.]+)-OpenSSH_([\w.]+) Ubuntu-(\S+)\r?\n| p/OpenSSH/ v/$2 Ubuntu $3/ i/Ubuntu Linux; protocol $1/ o/Linux/ cpe:/a:openbsd:openssh:$2/ cpe:/o:canonical:ubuntu_linux/a
+match ssh m|^SSH-([\d.]+)-OpenSSH_([\w.]+) Debian-(\S+)\r?\n| p/OpenSSH/ v/$2 Debian $3/ i/protocol $1/ o/Linux/ cpe:/a:openbsd:openssh:$2/ cpe:/o:debian:debian_linux/a
+match ssh m|^SSH-([\d.]+)-OpenSSH_for_Windows_([\w.]+)\r?\n| p/OpenSSH for Windows/ v/$2/ i/protocol $1/ o/Windows/ cpe:/a:openbsd:openssh:$2/ cpe:/o:microsoft:windows/a
+match ssh m|^SSH-([\d.]+)-OpenSSH_([\w.]+)[ \r\n]| p/OpenSSH/ v/$2/ i/protocol $1/ cpe:/a:openbsd:openssh:$2/
+match ssh m|^SSH-([\d.]+)-dropbear_([\w.]+)\r?\n| p/Dropbear sshd/ v/$2/ i/protocol $1/ o/Linux/ cpe:/a:matt_johnston:dropbear_ssh_server:$2/
+match ssh m|^SSH-([\d.]+)-Cisco-([\d.]+)\r?\n| p/Cisco SSH/ v/$2/ i/protocol $1/ o/IOS/ d/router/ cpe:/o:cisco:ios/a
+match ssh m|^SSH-([\d.]+)-ROSSSH\r?\n| p/MikroTik RouterOS sshd/ i/protocol $1/ o/RouterOS/ d/router/ cpe:/o:mikrotik:routeros/a
+match ssh m|^SSH-([\d.]+)-libssh[_-]([\w.]+)\r?\n| p/libssh/ v/$2/ i/protocol $1/ cpe:/a:libssh:libssh:$2/
+softmatch ssh m|^SSH-([\d.]+)-|
+
+# FTP
+match ftp m|^220 \(vsFTPd ([\w.-]+)\)\r\n| p/vsftpd/ v/$1/ o/Unix/ cpe:/a:vsftpd_project:vsftpd:$1/
+match ftp m|^220 ProFTPD ([\w.]+) Server| p/ProFTPD/ v/$1/ cpe:/a:proftpd:proftpd:$1/
+match ftp m|^220[ -]FileZilla Server(?: version)? ([\w.]+)| p/FileZilla ftpd/ v/$1/ o/Windows/ cpe:/a:filezilla-project:filezilla_server:$1/ cpe:/o:microsoft:windows/a
+match ftp m|^220-+ Welcome to Pure-FTPd| p/Pure-FTPd/ cpe:/a:pureftpd:pure-ftpd/
+match ftp m|^220[ -]Microsoft FTP Service\r\n| p/Microsoft ftpd/ o/Windows/ cpe:/a:microsoft:ftp_service/ cpe:/o:microsoft:windows/a
+match ftp m|^220 .* FTP server \(MikroTik ([\w.]+)\) ready\r\n| p/MikroTik router ftpd/ v/$1/ o/RouterOS/ d/router/ cpe:/o:mikrotik:routeros:$1/
+softmatch ftp m|^220[ -]|
+
+# Mail
+match smtp m|^220 ([-\w.]+) ESMTP Postfix| p/Postfix smtpd/ h/$1/ cpe:/a:postfix:postfix/
+match smtp m|^220 ([-\w.]+) ESMTP Exim ([\d.]+)| p/Exim smtpd/ v/$2/ h/$1/ cpe:/a:exim:exim:$2/
+match smtp m|^220 ([-\w.]+) ESMTP Sendmail ([\w.]+)/| p/Sendmail/ v/$2/ h/$1/ cpe:/a:sendmail:sendmail:$2/
+match smtp m|^220 ([-\w.]+) Microsoft ESMTP MAIL Service| p/Microsoft Exchange smtpd/ h/$1/ o/Windows/ cpe:/a:microsoft:exchange_server/ cpe:/o:microsoft:windows/a
+softmatch smtp m|^220 [-\w.]+ E?SMTP|
+match pop3 m|^\+OK Dovecot| p/Dovecot pop3d/ cpe:/a:dovecot:dovecot/
+softmatch pop3 m|^\+OK|
+match imap m|^\* OK \[CAPABILITY IMAP4rev1.*Dovecot|s p/Dovecot imapd/ cpe:/a:dovecot:dovecot/
+match imap m|^\* OK The Microsoft Exchange IMAP4 service is ready| p/Microsoft Exchange imapd/ o/Windows/ cpe:/a:microsoft:exchange_server/ cpe:/o:microsoft:windows/a
+softmatch imap m|^\* OK|
+
+# Databases
+match mysql m|^.\0\0\0\x0a(?:5\.5\.5-)?([\d.]+)-MariaDB|s p/MariaDB/ v/$1/ cpe:/a:mariadb:mariadb:$1/
+match mysql m|^.\0\0\0\x0a([\d.]+)[\w.-]*\0|s p/MySQL/ v/$1/ cpe:/a:mysql:mysql:$1/
+match mysql m|^.\0\0\0\xffj\x04Host '[^']+' is not allowed to connect to this MySQL server|s p/MySQL/ i/unauthorized/ cpe:/a:mysql:mysql/
+
+# Remote access
+match vnc m|^RFB 003\.00(\d)\n| p/VNC/ i/protocol 3.$1/
+match vnc m|^RFB (\d{3})\.(\d{3})\n| p/VNC/ i/protocol $1.$2/
+match telnet m|^\xff\xfd\x18\xff\xfd \xff\xfd#\xff\xfd'| p/Linux telnetd/ o/Linux/ cpe:/o:linux:linux_kernel/a
+softmatch telnet m|^\xff[\xfb-\xfe]|
+
+# TLS: a plaintext request draws a fatal alert
+softmatch ssl m|^\x15\x03[\0-\x04]\0\x02\x02|
+
+##############################################################################
+Probe TCP GetRequest q|GET / HTTP/1.0\r\n\r\n|
+rarity 1
+ports 80-85,591,631,1880,3000,3128,5000,5357,5985,7070,8000-8010,8060,8080-8090,8123,8200,8888,9000,9080,9090,9100,10000,32400
+
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: nginx/([\d.]+)|s p/nginx/ v/$1/ cpe:/a:igor_sysoev:nginx:$1/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: nginx\r\n|s p/nginx/ cpe:/a:igor_sysoev:nginx/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Apache/([\d.]+) \(([^)\r\n]+)\)|s p/Apache httpd/ v/$1/ i/$2/ cpe:/a:apache:http_server:$1/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Apache/([\d.]+)|s p/Apache httpd/ v/$1/ cpe:/a:apache:http_server:$1/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Apache\r\n|s p/Apache httpd/ cpe:/a:apache:http_server/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Microsoft-IIS/([\d.]+)|s p/Microsoft IIS httpd/ v/$1/ o/Windows/ cpe:/a:microsoft:internet_information_services:$1/ cpe:/o:microsoft:windows/a
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Microsoft-HTTPAPI/([\d.]+)|s p/Microsoft HTTPAPI httpd/ v/$1/ i|SSDP/UPnP| o/Windows/ cpe:/o:microsoft:windows/a
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: lighttpd/([\d.]+)|s p/lighttpd/ v/$1/ cpe:/a:lighttpd:lighttpd:$1/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Jetty\(([\w.-]+)\)|s p/Jetty/ v/$1/ cpe:/a:eclipse:jetty:$1/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Caddy\r\n|s p/Caddy httpd/ cpe:/a:caddyserver:caddy/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Werkzeug/([\d.]+) Python/([\d.]+)|s p/Werkzeug httpd/ v/$1/ i/Python $2/ cpe:/a:palletsprojects:werkzeug:$1/ cpe:/a:python:python:$2/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: MiniServ/([\d.]+)|s p/MiniServ/ v/$1/ i/Webmin httpd/ cpe:/a:webmin:webmin:$1/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: lwIP/([\w.-]+)|s p/lwIP httpd/ v/$1/ d/specialized/ cpe:/a:lwip_project:lwip:$1/
+match http m%^HTTP/1\.[01] \d\d\d .*\r\nServer: GoAhead-(?:Webs|http)%s p/GoAhead WebServer/ d/specialized/ cpe:/a:embedthis:goahead/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: CUPS/([\d.]+) IPP/([\d.]+)|s p/CUPS/ v/$1/ i/IPP $2/ d/printer/ cpe:/a:apple:cups:$1/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: HP HTTP Server; HP ([^;\r\n]+)|s p/HP printer http config/ i/$1/ d/printer/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Plex Media Server|s p/Plex Media Server httpd/ d/media device/ cpe:/a:plex:plex_media_server/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: Python/([\d.]+) aiohttp/([\d.]+)|s p/aiohttp/ v/$2/ i/Python $1/ cpe:/a:aiohttp:aiohttp:$2/
+match http m|^HTTP/1\.[01] \d\d\d .*\r\nServer: SimpleHTTP/([\d.]+) Python/([\d.]+)|s p/SimpleHTTPServer/ v/$1/ i/Python $2/ cpe:/a:python:python:$2/
+match http-proxy m|^HTTP/1\.[01] \d\d\d .*\r\nServer: squid/([\d.]+)|s p/Squid http proxy/ v/$1/ cpe:/a:squid-cache:squid:$1/
+match redis m|^-ERR wrong number of arguments for 'get' command\r\n| p/Redis key-value store/ cpe:/a:redislabs:redis/
+softmatch http m|^HTTP/1\.[01] \d\d\d|
+
+##############################################################################
+Probe TCP HTTPOptions q|OPTIONS / HTTP/1.0\r\n\r\n|
+rarity 4
+ports 80-85,3000,5000,8000-8010,8080-8090,8888
+fallback GetRequest
+
+##############################################################################
+Probe TCP RTSPRequest q|OPTIONS / RTSP/1.0\r\n\r\n|
+rarity 5
+ports 554,7070,8554
+fallback GetRequest
+
+match rtsp m|^RTSP/1\.0 \d\d\d .*\r\nServer: GStreamer RTSP server|s p/GStreamer rtspd/ cpe:/a:gstreamer_project:gstreamer/
+match rtsp m|^RTSP/1\.0 \d\d\d .*\r\nServer: Hikvision|s p/Hikvision IP camera rtspd/ d/webcam/
+match rtsp m|^RTSP/1\.0 \d\d\d .*\r\nServer: AirTunes/([\d.]+)|s p/Apple AirTunes rtspd/ v/$1/ d/media device/
+softmatch rtsp m|^RTSP/1\.0 \d\d\d|
+
+##############################################################################
+Probe TCP GenericLines q|\r\n\r\n|
+rarity 1
+ports 21,23,25,110,143,513,514,1521,2000,6379
+
+match ftp m|^500 OOPS: | p/vsftpd/ i/misconfigured/ cpe:/a:vsftpd_project:vsftpd/
+match redis m|^-ERR unknown command| p/Redis key-value store/ cpe:/a:redislabs:redis/
+match smtp m|^500 5\.5\.2 Error: bad syntax\r\n| p/Postfix smtpd/ cpe:/a:postfix:postfix/
+
+##############################################################################
+Probe TCP RedisInfo q|*1\r\n$4\r\ninfo\r\n|
+rarity 8
+ports 6379
+
+match redis m|^\$\d+\r\n# Server\r\nredis_version:([\d.]+)\r\n| p/Redis key-value store/ v/$1/ cpe:/a:redislabs:redis:$1/
+match redis m|^-NOAUTH Authentication required| p/Redis key-value store/ i/authentication required/ cpe:/a:redislabs:redis/
+
+##############################################################################
+Probe TCP Memcache q|stats\r\n|
+rarity 8
+ports 11211
+
+match memcached m|^STAT pid \d+\r\nSTAT uptime \d+\r\nSTAT time \d+\r\nSTAT version ([\d.]+)\r\n| p/Memcached/ v/$1/ cpe:/a:memcached:memcached:$1/
+
+##############################################################################
+Probe TCP SSLSessionReq q|\0\0\0\x08\x04\xd2\x16\x2f|
+rarity 8
+ports 5432
+
+match postgresql m|^[NS]$| p/PostgreSQL DB/ cpe:/a:postgresql:postgresql/
+match postgresql m|^E\0\0\0.S[^\0]+\0.*Munsupported frontend protocol|s p/PostgreSQL DB/ cpe:/a:postgresql:postgresql/
+
+##############################################################################
+Probe TCP TerminalServer q|\x03\0\0\x13\x0e\xe0\0\0\0\0\0\x01\0\x08\0\x03\0\0\0|
+rarity 6
+ports 3389
+
+match ms-wbt-server m|^\x03\0\0\x13\x0e\xd0\0\0\x124\0\x02| p/Microsoft Terminal Services/ o/Windows/ cpe:/o:microsoft:windows/a
+match ms-wbt-server m|^\x03\0\0\x0b\x06\xd0\0\0\x124\0| p/xrdp/ o/Linux/ cpe:/a:neutrinolabs:xrdp/
+softmatch ms-wbt-server m|^\x03\0\0[\x0b\x13]|
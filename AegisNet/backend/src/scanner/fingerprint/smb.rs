@@ -1,20 +1,250 @@
 use tokio::net::TcpStream;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
 use std::time::Duration;
 
+const SMB_PORT: u16 = 445;
+const TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_MESSAGE: usize = 64 * 1024;
+
+const SMB2_MAGIC: &[u8] = b"\xfeSMB";
+const SMB1_MAGIC: &[u8] = b"\xffSMB";
+const HEADER_LEN: usize = 64;
+
+// SMB2 commands and status codes
+const CMD_NEGOTIATE: u16 = 0x0000;
+const CMD_SESSION_SETUP: u16 = 0x0001;
+const STATUS_SUCCESS: u32 = 0x0000_0000;
+const STATUS_MORE_PROCESSING_REQUIRED: u32 = 0xC000_0016;
+
+// NEGOTIATE SecurityMode bits
+const SIGNING_ENABLED: u16 = 0x0001;
+const SIGNING_REQUIRED: u16 = 0x0002;
+
+const PREAUTH_INTEGRITY_CAPABILITIES: u16 = 0x0001;
+const HASH_SHA512: u16 = 0x0001;
+
+// NTLMSSP negotiate flags
+const NTLM_UNICODE: u32 = 0x0000_0001;
+const NTLM_OEM: u32 = 0x0000_0002;
+const NTLM_REQUEST_TARGET: u32 = 0x0000_0004;
+const NTLM_NTLM: u32 = 0x0000_0200;
+const NTLM_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NTLM_EXTENDED_SESSION_SECURITY: u32 = 0x0008_0000;
+const NTLM_TARGET_INFO: u32 = 0x0080_0000;
+const NTLM_VERSION: u32 = 0x0200_0000;
+const NTLM_128: u32 = 0x2000_0000;
+const NTLM_56: u32 = 0x8000_0000;
+
+// AV_PAIR ids in the challenge's target info
+const AV_EOL: u16 = 0;
+const AV_NB_COMPUTER: u16 = 1;
+const AV_NB_DOMAIN: u16 = 2;
+const AV_DNS_COMPUTER: u16 = 3;
+const AV_DNS_DOMAIN: u16 = 4;
+const AV_DNS_TREE: u16 = 5;
+
+const SPNEGO_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x02]; // 1.3.6.1.5.5.2
+const NTLMSSP_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x02, 0x0a]; // 1.3.6.1.4.1.311.2.2.10
+
+/// An SMB2/3 dialect revision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Dialect {
+    Smb202,
+    Smb21,
+    Smb30,
+    Smb302,
+    Smb311,
+}
+
+impl Dialect {
+    pub const ALL: [Dialect; 5] = [Dialect::Smb202, Dialect::Smb21, Dialect::Smb30, Dialect::Smb302, Dialect::Smb311];
+
+    pub fn code(&self) -> u16 {
+        match self {
+            Dialect::Smb202 => 0x0202,
+            Dialect::Smb21 => 0x0210,
+            Dialect::Smb30 => 0x0300,
+            Dialect::Smb302 => 0x0302,
+            Dialect::Smb311 => 0x0311,
+        }
+    }
+
+    pub fn from_code(code: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.code() == code)
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dialect::Smb202 => "2.0.2",
+            Dialect::Smb21 => "2.1",
+            Dialect::Smb30 => "3.0",
+            Dialect::Smb302 => "3.0.2",
+            Dialect::Smb311 => "3.1.1",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct SmbInfo {
-    pub native_os: String,
-    pub native_lan_man: String,
+    pub native_os: String,       // e.g. "Windows 10 / Server 2019 (build 17763)", "Samba", "Unknown"
+    pub native_lan_man: String,  // best protocol spoken, e.g. "SMB 3.1.1"
+    pub dialects: Vec<Dialect>,  // SMB2/3 dialects the server accepts, oldest first
+    pub smb1: bool,              // still answers an SMB1 "NT LM 0.12" negotiate
+    pub signing_enabled: bool,
+    pub signing_required: bool,  // false leaves the host open to NTLM relaying
+    pub ntlm: Option<NtlmInfo>,
 }
 
-pub async fn probe(ip: &str) -> Option<SmbInfo> {
-    let addr = format!("{}:445", ip);
-    let mut stream = tokio::time::timeout(Duration::from_secs(1), TcpStream::connect(addr)).await.ok()?.ok()?;
+impl SmbInfo {
+    /// One-line summary for the service banner.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self.native_lan_man.clone()];
+        if !self.dialects.is_empty() {
+            let dialects: Vec<String> = self.dialects.iter().map(Dialect::to_string).collect();
+            parts.push(format!("dialects {}", dialects.join(", ")));
+        }
+        if self.native_os != "Unknown" {
+            parts.push(self.native_os.clone());
+        }
+        if let Some(name) = self.ntlm.as_ref().and_then(|n| n.netbios_computer.as_ref()) {
+            parts.push(format!("name {}", name));
+        }
+        parts.push(match (self.signing_required, self.signing_enabled) {
+            (true, _) => "signing required".into(),
+            (false, true) => "signing not required".into(),
+            (false, false) => "signing disabled".into(),
+        });
+        if self.smb1 {
+            parts.push("SMBv1 enabled".into());
+        }
+        format!("SMB: {}", parts.join(" | "))
+    }
+
+    /// `Windows` when the NTLM version names a Windows release.
+    pub fn os_family(&self) -> Option<&'static str> {
+        self.ntlm.as_ref()?.version?.release().map(|_| "Windows")
+    }
+}
 
+/// What an anonymous NTLMSSP challenge gives away.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NtlmInfo {
+    pub netbios_computer: Option<String>,
+    pub netbios_domain: Option<String>, // domain or workgroup
+    pub dns_computer: Option<String>,
+    pub dns_domain: Option<String>,
+    pub dns_forest: Option<String>,
+    pub version: Option<OsVersion>,
+}
+
+/// Product version from the NTLM challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub build: u16,
+}
+
+impl OsVersion {
+    /// Samba answers with a fixed 6.1 and no build number.
+    pub fn is_samba(&self) -> bool {
+        self.build == 0
+    }
+
+    /// Windows release the version belongs to. Client and server editions share
+    /// version numbers, only a few server builds are unambiguous.
+    pub fn release(&self) -> Option<&'static str> {
+        if self.is_samba() {
+            return None;
+        }
+        Some(match (self.major, self.minor) {
+            (10, 0) => match self.build {
+                26100.. => "Windows 11 / Server 2025",
+                22000.. => "Windows 11",
+                20348 => "Windows Server 2022",
+                17763 => "Windows 10 / Server 2019",
+                14393 => "Windows 10 / Server 2016",
+                _ => "Windows 10",
+            },
+            (6, 3) => "Windows 8.1 / Server 2012 R2",
+            (6, 2) => "Windows 8 / Server 2012",
+            (6, 1) => "Windows 7 / Server 2008 R2",
+            (6, 0) => "Windows Vista / Server 2008",
+            (5, 2) => "Windows XP x64 / Server 2003",
+            (5, 1) => "Windows XP",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for OsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.build)
+    }
+}
+
+/// The parts of an SMB2 NEGOTIATE response we report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Negotiated {
+    dialect: Dialect,
+    security_mode: u16,
+}
+
+/// Negotiates every SMB2/3 dialect on its own connection, checks whether SMB1 is still
+/// spoken, then asks for an anonymous NTLM challenge over the best dialect.
+pub async fn probe(ip: IpAddr) -> Option<SmbInfo> {
+    let addr = SocketAddr::new(ip, SMB_PORT);
+    let negotiations = Dialect::ALL.map(|d| negotiate(addr, d));
+    let (smb1, negotiated) = tokio::join!(smb1_supported(addr), futures::future::join_all(negotiations));
+
+    let mut info = SmbInfo { smb1, ..Default::default() };
+    let mut best = None;
+    for (stream, negotiated) in negotiated.into_iter().flatten() {
+        info.dialects.push(negotiated.dialect);
+        info.signing_enabled |= negotiated.security_mode & SIGNING_ENABLED != 0;
+        info.signing_required |= negotiated.security_mode & SIGNING_REQUIRED != 0;
+        best = Some((stream, negotiated.dialect)); // Dialect::ALL is ascending
+    }
+    if best.is_none() && !smb1 {
+        return None;
+    }
+
+    if let Some((mut stream, dialect)) = best {
+        info.native_lan_man = format!("SMB {}", dialect);
+        info.ntlm = ntlm_challenge(&mut stream).await;
+    } else {
+        info.native_lan_man = "SMB 1".into();
+    }
+
+    let version = info.ntlm.as_ref().and_then(|n| n.version);
+    info.native_os = match version {
+        Some(v) if v.is_samba() => "Samba".into(),
+        Some(v) => match v.release() {
+            Some(release) => format!("{} (build {})", release, v.build),
+            None => format!("Windows {}", v),
+        },
+        None => "Unknown".into(),
+    };
+    Some(info)
+}
+
+async fn negotiate(addr: SocketAddr, dialect: Dialect) -> Option<(TcpStream, Negotiated)> {
+    let mut stream = tokio::time::timeout(TIMEOUT, TcpStream::connect(addr)).await.ok()?.ok()?;
+    send(&mut stream, &build_negotiate(dialect)).await?;
+    let response = recv(&mut stream).await?;
+    let negotiated = parse_negotiate_response(&response)?;
+    // A server that does not speak the dialect fails the request or picks another one
+    (negotiated.dialect == dialect).then_some((stream, negotiated))
+}
+
+async fn smb1_supported(addr: SocketAddr) -> bool {
     // SMB1 Negotiate Protocol Request (NT LM 0.12)
-    // Needs NetBIOS Session Service Header (4 bytes) + SMB Header (32 bytes) + WordCount (1) + ByteCount (2) + Body
-    let packet: [u8; 51] = [
-        0x00, 0x00, 0x00, 0x2F, // NetBIOS Length (47)
+    // SMB Header (32 bytes) + WordCount (1) + ByteCount (2) + Body
+    let packet: [u8; 47] = [
         0xFF, 0x53, 0x4D, 0x42, // Protocol: 0xFF SMB
         0x72, // Command: Negotiate Protocol
         0x00, 0x00, 0x00, 0x00, // Status
@@ -33,32 +263,319 @@ pub async fn probe(ip: &str) -> Option<SmbInfo> {
         0x02, 0x4E, 0x54, 0x20, 0x4C, 0x4D, 0x20, 0x30, 0x2E, 0x31, 0x32, 0x00, // "NT LM 0.12"
     ];
 
-    stream.write_all(&packet).await.ok()?;
-
-    let mut buf = [0u8; 1024];
-    let n = tokio::time::timeout(Duration::from_secs(1), stream.read(&mut buf)).await.ok()?.ok()?;
-    
-    if n > 40 {
-        // Very basic extraction of strings from the end of the packet
-        // SMB1 response puts Native OS and Native Lan Man at the end as ASCII/Unicode strings.
-        // We can just scan for printable strings > 4 chars
-        let content = &buf[..n];
-        let s = String::from_utf8_lossy(content);
-        
-        // Split by null bytes or common patterns?
-        // Heuristic: Find "Windows"
-        let mut native_os = "Unknown".to_string();
-        if let Some(idx) = s.find("Windows") {
-             // Take until next weird char
-             let end = s[idx..].find(|c: char| !c.is_ascii_graphic() && c != ' ').unwrap_or(s.len() - idx);
-             native_os = s[idx..idx+end].to_string();
+    let exchange = async {
+        let mut stream = tokio::time::timeout(TIMEOUT, TcpStream::connect(addr)).await.ok()?.ok()?;
+        send(&mut stream, &packet).await?;
+        recv(&mut stream).await
+    };
+    // Hosts with SMB1 disabled reset the connection
+    exchange.await.is_some_and(|r| accepts_smb1(&r))
+}
+
+/// SMB1 negotiate response that picked our only dialect (index 0, not 0xFFFF).
+fn accepts_smb1(msg: &[u8]) -> bool {
+    msg.len() >= 35
+        && msg.starts_with(SMB1_MAGIC)
+        && msg[4] == 0x72
+        && u32_le(msg, 5) == Some(STATUS_SUCCESS)
+        && msg[32] > 0
+        && u16_le(msg, 33) == Some(0)
+}
+
+async fn ntlm_challenge(stream: &mut TcpStream) -> Option<NtlmInfo> {
+    send(stream, &build_session_setup(&spnego_init(&ntlm_negotiate()))).await?;
+    let response = recv(stream).await?;
+    let status = u32_le(&response, 8)?;
+    if !response.starts_with(SMB2_MAGIC) || status != STATUS_MORE_PROCESSING_REQUIRED {
+        return None;
+    }
+    // The challenge sits inside a SPNEGO NegTokenResp, its signature is enough to find it
+    let start = response.windows(8).position(|w| w == b"NTLMSSP\0")?;
+    parse_challenge(&response[start..])
+}
+
+/// Direct TCP transport: a 4-byte length prefix per message.
+async fn send(stream: &mut TcpStream, msg: &[u8]) -> Option<()> {
+    let len = msg.len() as u32;
+    let mut frame = Vec::with_capacity(msg.len() + 4);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(msg);
+    tokio::time::timeout(TIMEOUT, stream.write_all(&frame)).await.ok()?.ok()
+}
+
+async fn recv(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let read = async {
+        let mut prefix = [0u8; 4];
+        stream.read_exact(&mut prefix).await.ok()?;
+        let len = u32::from_be_bytes(prefix) as usize & 0x00FF_FFFF;
+        if len > MAX_MESSAGE {
+            return None;
         }
+        let mut msg = vec![0u8; len];
+        stream.read_exact(&mut msg).await.ok()?;
+        Some(msg)
+    };
+    tokio::time::timeout(TIMEOUT, read).await.ok()?
+}
 
-        return Some(SmbInfo {
-            native_os,
-            native_lan_man: "Unknown".to_string(), // Keep simple
-        });
+fn build_header(command: u16, message_id: u64) -> Vec<u8> {
+    let mut h = Vec::with_capacity(HEADER_LEN);
+    h.extend_from_slice(SMB2_MAGIC);
+    h.extend_from_slice(&(HEADER_LEN as u16).to_le_bytes()); // StructureSize
+    h.extend_from_slice(&0u16.to_le_bytes()); // CreditCharge
+    h.extend_from_slice(&0u32.to_le_bytes()); // Status
+    h.extend_from_slice(&command.to_le_bytes());
+    h.extend_from_slice(&1u16.to_le_bytes()); // CreditRequest
+    h.extend_from_slice(&0u32.to_le_bytes()); // Flags
+    h.extend_from_slice(&0u32.to_le_bytes()); // NextCommand
+    h.extend_from_slice(&message_id.to_le_bytes());
+    h.extend_from_slice(&0u32.to_le_bytes()); // Reserved (ProcessId)
+    h.extend_from_slice(&0u32.to_le_bytes()); // TreeId
+    h.extend_from_slice(&0u64.to_le_bytes()); // SessionId
+    h.extend_from_slice(&[0u8; 16]); // Signature
+    h
+}
+
+fn build_negotiate(dialect: Dialect) -> Vec<u8> {
+    let mut m = build_header(CMD_NEGOTIATE, 0);
+    m.extend_from_slice(&36u16.to_le_bytes()); // StructureSize
+    m.extend_from_slice(&1u16.to_le_bytes()); // DialectCount
+    m.extend_from_slice(&SIGNING_ENABLED.to_le_bytes());
+    m.extend_from_slice(&0u16.to_le_bytes()); // Reserved
+    m.extend_from_slice(&0u32.to_le_bytes()); // Capabilities
+    m.extend_from_slice(&[0u8; 16]); // ClientGuid, must be zero for 2.0.2
+
+    if dialect != Dialect::Smb311 {
+        m.extend_from_slice(&0u64.to_le_bytes()); // ClientStartTime
+        m.extend_from_slice(&dialect.code().to_le_bytes());
+        return m;
+    }
+
+    // 3.1.1 requires negotiate contexts, at least the pre-authentication integrity one
+    let context_offset = (m.len() + 8 + 2).next_multiple_of(8);
+    m.extend_from_slice(&(context_offset as u32).to_le_bytes());
+    m.extend_from_slice(&1u16.to_le_bytes()); // NegotiateContextCount
+    m.extend_from_slice(&0u16.to_le_bytes()); // Reserved2
+    m.extend_from_slice(&dialect.code().to_le_bytes());
+    m.resize(context_offset, 0);
+
+    let salt = [0u8; 32]; // only feeds the session key derivation, which we never get to
+    m.extend_from_slice(&PREAUTH_INTEGRITY_CAPABILITIES.to_le_bytes());
+    m.extend_from_slice(&(6 + salt.len() as u16).to_le_bytes()); // DataLength
+    m.extend_from_slice(&0u32.to_le_bytes()); // Reserved
+    m.extend_from_slice(&1u16.to_le_bytes()); // HashAlgorithmCount
+    m.extend_from_slice(&(salt.len() as u16).to_le_bytes());
+    m.extend_from_slice(&HASH_SHA512.to_le_bytes());
+    m.extend_from_slice(&salt);
+    m
+}
+
+fn parse_negotiate_response(msg: &[u8]) -> Option<Negotiated> {
+    if !msg.starts_with(SMB2_MAGIC) || u16_le(msg, 12)? != CMD_NEGOTIATE || u32_le(msg, 8)? != STATUS_SUCCESS {
+        return None;
+    }
+    let body = msg.get(HEADER_LEN..)?;
+    if u16_le(body, 0)? != 65 {
+        return None;
+    }
+    Some(Negotiated {
+        security_mode: u16_le(body, 2)?,
+        dialect: Dialect::from_code(u16_le(body, 4)?)?,
+    })
+}
+
+fn build_session_setup(token: &[u8]) -> Vec<u8> {
+    let mut m = build_header(CMD_SESSION_SETUP, 1);
+    m.extend_from_slice(&25u16.to_le_bytes()); // StructureSize
+    m.push(0); // Flags
+    m.push(SIGNING_ENABLED as u8); // SecurityMode
+    m.extend_from_slice(&0u32.to_le_bytes()); // Capabilities
+    m.extend_from_slice(&0u32.to_le_bytes()); // Channel
+    m.extend_from_slice(&((HEADER_LEN + 24) as u16).to_le_bytes()); // SecurityBufferOffset
+    m.extend_from_slice(&(token.len() as u16).to_le_bytes());
+    m.extend_from_slice(&0u64.to_le_bytes()); // PreviousSessionId
+    m.extend_from_slice(token);
+    m
+}
+
+fn ntlm_negotiate() -> Vec<u8> {
+    let flags = NTLM_UNICODE | NTLM_OEM | NTLM_REQUEST_TARGET | NTLM_NTLM | NTLM_ALWAYS_SIGN
+        | NTLM_EXTENDED_SESSION_SECURITY | NTLM_TARGET_INFO | NTLM_VERSION | NTLM_128 | NTLM_56;
+    let mut m = b"NTLMSSP\0".to_vec();
+    m.extend_from_slice(&1u32.to_le_bytes()); // MessageType: NEGOTIATE
+    m.extend_from_slice(&flags.to_le_bytes());
+    m.extend_from_slice(&[0u8; 8]); // DomainNameFields
+    m.extend_from_slice(&[0u8; 8]); // WorkstationFields
+    m.extend_from_slice(&[10, 0, 0x61, 0x4a, 0, 0, 0, 15]); // Version: 10.0.19041, NTLM revision 15
+    m
+}
+
+/// GSS-API InitialContextToken offering only NTLMSSP.
+fn spnego_init(mech_token: &[u8]) -> Vec<u8> {
+    let mech_types = der(0xa0, &der(0x30, &der(0x06, NTLMSSP_OID)));
+    let token = der(0xa2, &der(0x04, mech_token));
+    let neg_token_init = der(0xa0, &der(0x30, &[mech_types, token].concat()));
+    der(0x60, &[der(0x06, SPNEGO_OID), neg_token_init].concat())
+}
+
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// Decodes an NTLMSSP CHALLENGE message (MS-NLMP 2.2.1.2).
+fn parse_challenge(msg: &[u8]) -> Option<NtlmInfo> {
+    if !msg.starts_with(b"NTLMSSP\0") || u32_le(msg, 8)? != 2 {
+        return None;
     }
+    let flags = u32_le(msg, 20)?;
+    let info_len = u16_le(msg, 40)? as usize;
+    let info_offset = u32_le(msg, 44)? as usize;
 
-    None
+    let mut info = NtlmInfo::default();
+    // The version field exists when the flag is set and the payload starts after it
+    if flags & NTLM_VERSION != 0 && info_offset >= 56 {
+        let v = msg.get(48..52)?;
+        info.version = Some(OsVersion { major: v[0], minor: v[1], build: u16::from_le_bytes([v[2], v[3]]) });
+    }
+
+    let mut pairs = msg.get(info_offset..info_offset.checked_add(info_len)?).unwrap_or_default();
+    while pairs.len() >= 4 {
+        let (id, len) = (u16_le(pairs, 0)?, u16_le(pairs, 2)? as usize);
+        let Some(value) = pairs.get(4..4 + len) else { break };
+        let text = || Some(utf16le(value)).filter(|s| !s.is_empty());
+        match id {
+            AV_EOL => break,
+            AV_NB_COMPUTER => info.netbios_computer = text(),
+            AV_NB_DOMAIN => info.netbios_domain = text(),
+            AV_DNS_COMPUTER => info.dns_computer = text(),
+            AV_DNS_DOMAIN => info.dns_domain = text(),
+            AV_DNS_TREE => info.dns_forest = text(),
+            _ => {}
+        }
+        pairs = &pairs[4 + len..];
+    }
+    Some(info)
+}
+
+fn utf16le(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn u16_le(buf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(buf.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(buf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(buf.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn av(id: u16, value: &str) -> Vec<u8> {
+        let bytes: Vec<u8> = value.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        [id.to_le_bytes().as_slice(), &(bytes.len() as u16).to_le_bytes(), &bytes].concat()
+    }
+
+    fn challenge(version: [u8; 4]) -> Vec<u8> {
+        let target_info = [
+            av(AV_NB_DOMAIN, "CORP"),
+            av(AV_NB_COMPUTER, "DESKTOP-1"),
+            av(AV_DNS_DOMAIN, "corp.example.com"),
+            av(AV_DNS_COMPUTER, "desktop-1.corp.example.com"),
+            av(AV_DNS_TREE, "example.com"),
+            av(AV_EOL, ""),
+        ].concat();
+        let mut m = b"NTLMSSP\0".to_vec();
+        m.extend_from_slice(&2u32.to_le_bytes());
+        m.extend_from_slice(&[0, 0, 0, 0, 56, 0, 0, 0]); // empty TargetName at 56
+        m.extend_from_slice(&(NTLM_UNICODE | NTLM_TARGET_INFO | NTLM_VERSION).to_le_bytes());
+        m.extend_from_slice(&[0x11; 8]); // ServerChallenge
+        m.extend_from_slice(&[0; 8]);
+        m.extend_from_slice(&(target_info.len() as u16).to_le_bytes());
+        m.extend_from_slice(&(target_info.len() as u16).to_le_bytes());
+        m.extend_from_slice(&56u32.to_le_bytes());
+        m.extend_from_slice(&version);
+        m.extend_from_slice(&[0, 0, 0, 15]);
+        m.extend_from_slice(&target_info);
+        m
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let info = parse_challenge(&challenge([10, 0, 0x63, 0x45])).unwrap();
+        assert_eq!(info.netbios_computer.as_deref(), Some("DESKTOP-1"));
+        assert_eq!(info.netbios_domain.as_deref(), Some("CORP"));
+        assert_eq!(info.dns_computer.as_deref(), Some("desktop-1.corp.example.com"));
+        assert_eq!(info.dns_forest.as_deref(), Some("example.com"));
+        let version = info.version.unwrap();
+        assert_eq!(version.to_string(), "10.0.17763");
+        assert_eq!(version.release(), Some("Windows 10 / Server 2019"));
+
+        let samba = parse_challenge(&challenge([6, 1, 0, 0])).unwrap().version.unwrap();
+        assert!(samba.is_samba() && samba.release().is_none());
+
+        assert!(parse_challenge(b"NTLMSSP\0\x01\0\0\0").is_none());
+        let truncated = challenge([10, 0, 0x61, 0x4a]);
+        assert!(parse_challenge(&truncated[..60]).is_some_and(|i| i.netbios_computer.is_none()));
+    }
+
+    #[test]
+    fn test_negotiate_request() {
+        let m = build_negotiate(Dialect::Smb21);
+        assert_eq!(m.len(), HEADER_LEN + 38);
+        assert_eq!(u16_le(&m, HEADER_LEN + 36), Some(0x0210));
+
+        // 3.1.1: dialect, then the contexts 8-byte aligned
+        let m = build_negotiate(Dialect::Smb311);
+        let offset = u32_le(&m, HEADER_LEN + 28).unwrap() as usize;
+        assert_eq!(offset % 8, 0);
+        assert_eq!(u16_le(&m, HEADER_LEN + 36), Some(0x0311));
+        assert_eq!(u16_le(&m, offset), Some(PREAUTH_INTEGRITY_CAPABILITIES));
+        assert_eq!(m.len(), offset + 8 + 38);
+    }
+
+    #[test]
+    fn test_parse_negotiate_response() {
+        let mut m = build_header(CMD_NEGOTIATE, 0);
+        m.extend_from_slice(&65u16.to_le_bytes());
+        m.extend_from_slice(&(SIGNING_ENABLED | SIGNING_REQUIRED).to_le_bytes());
+        m.extend_from_slice(&0x0302u16.to_le_bytes());
+        m.resize(HEADER_LEN + 64, 0);
+        let negotiated = parse_negotiate_response(&m).unwrap();
+        assert_eq!(negotiated.dialect, Dialect::Smb302);
+        assert_eq!(negotiated.security_mode & SIGNING_REQUIRED, SIGNING_REQUIRED);
+
+        m[8] = 0x22; // STATUS_ACCESS_DENIED & co
+        assert!(parse_negotiate_response(&m).is_none());
+    }
+
+    #[test]
+    fn test_describe() {
+        let info = SmbInfo {
+            native_os: "Windows 10 / Server 2019 (build 17763)".into(),
+            native_lan_man: "SMB 3.1.1".into(),
+            dialects: vec![Dialect::Smb302, Dialect::Smb311],
+            smb1: true,
+            signing_enabled: true,
+            signing_required: false,
+            ntlm: Some(NtlmInfo { netbios_computer: Some("DESKTOP-1".into()), ..Default::default() }),
+        };
+        assert_eq!(
+            info.describe(),
+            "SMB: SMB 3.1.1 | dialects 3.0.2, 3.1.1 | Windows 10 / Server 2019 (build 17763) | name DESKTOP-1 | signing not required | SMBv1 enabled"
+        );
+    }
 }
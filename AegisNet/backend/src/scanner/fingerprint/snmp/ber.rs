@@ -0,0 +1,371 @@
+use std::fmt;
+use std::net::Ipv4Addr;
+
+// Universal tags
+pub const INTEGER: u8 = 0x02;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+
+// SNMP application types (RFC 2578)
+pub const IP_ADDRESS: u8 = 0x40;
+pub const COUNTER32: u8 = 0x41;
+pub const GAUGE32: u8 = 0x42;
+pub const TIME_TICKS: u8 = 0x43;
+pub const OPAQUE: u8 = 0x44;
+pub const COUNTER64: u8 = 0x46;
+
+// Varbind exceptions (RFC 3416)
+pub const NO_SUCH_OBJECT: u8 = 0x80;
+pub const NO_SUCH_INSTANCE: u8 = 0x81;
+pub const END_OF_MIB_VIEW: u8 = 0x82;
+
+/// An object identifier, e.g. `1.3.6.1.2.1.1.5.0`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Oid(Vec<u32>);
+
+impl Oid {
+    pub fn new(arcs: &[u32]) -> Self {
+        Self(arcs.to_vec())
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let arcs = s.trim_start_matches('.').split('.').map(|a| a.parse().ok()).collect::<Option<Vec<u32>>>()?;
+        (arcs.len() >= 2).then_some(Self(arcs))
+    }
+
+    pub fn arcs(&self) -> &[u32] {
+        &self.0
+    }
+
+    pub fn starts_with(&self, prefix: &Oid) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+
+    /// The arcs after `prefix`, i.e. the row index of a table column.
+    pub fn suffix(&self, prefix: &Oid) -> Option<&[u32]> {
+        self.0.strip_prefix(prefix.0.as_slice())
+    }
+
+    pub fn child(&self, arcs: &[u32]) -> Oid {
+        Self([self.0.as_slice(), arcs].concat())
+    }
+}
+
+impl fmt::Display for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arcs: Vec<String> = self.0.iter().map(u32::to_string).collect();
+        f.write_str(&arcs.join("."))
+    }
+}
+
+/// The value of a varbind.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    OctetString(Vec<u8>),
+    Null,
+    Oid(Oid),
+    IpAddress(Ipv4Addr),
+    Counter32(u32),
+    Gauge32(u32),
+    TimeTicks(u32),
+    Opaque(Vec<u8>),
+    Counter64(u64),
+    NoSuchObject,
+    NoSuchInstance,
+    EndOfMibView,
+}
+
+impl Value {
+    /// Printable text of a DisplayString, `None` for binary octets (MACs, addresses).
+    pub fn as_text(&self) -> Option<String> {
+        let Value::OctetString(bytes) = self else { return None };
+        let text = std::str::from_utf8(bytes).ok()?.trim_end_matches('\0').trim();
+        let printable = text.chars().all(|c| !c.is_control() || c.is_whitespace());
+        (printable && !text.is_empty()).then(|| text.to_string())
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::OctetString(b) | Value::Opaque(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Integer(i) => u64::try_from(i).ok(),
+            Value::Counter32(n) | Value::Gauge32(n) | Value::TimeTicks(n) => Some(n as u64),
+            Value::Counter64(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_oid(&self) -> Option<&Oid> {
+        match self {
+            Value::Oid(oid) => Some(oid),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Value::Integer(i) => integer(*i),
+            Value::OctetString(b) => tlv(OCTET_STRING, b),
+            Value::Null => tlv(NULL, &[]),
+            Value::Oid(oid) => object_identifier(oid),
+            Value::IpAddress(ip) => tlv(IP_ADDRESS, &ip.octets()),
+            Value::Counter32(n) => unsigned(COUNTER32, *n as u64),
+            Value::Gauge32(n) => unsigned(GAUGE32, *n as u64),
+            Value::TimeTicks(n) => unsigned(TIME_TICKS, *n as u64),
+            Value::Opaque(b) => tlv(OPAQUE, b),
+            Value::Counter64(n) => unsigned(COUNTER64, *n),
+            Value::NoSuchObject => tlv(NO_SUCH_OBJECT, &[]),
+            Value::NoSuchInstance => tlv(NO_SUCH_INSTANCE, &[]),
+            Value::EndOfMibView => tlv(END_OF_MIB_VIEW, &[]),
+        }
+    }
+}
+
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// A constructed value (SEQUENCE, PDU) from already encoded parts.
+pub fn constructed(tag: u8, parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(tag, &parts.concat())
+}
+
+pub fn integer(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    // Drop leading bytes that only repeat the sign
+    let mut start = 0;
+    while start < 7 {
+        let (b, next) = (bytes[start], bytes[start + 1]);
+        if (b == 0x00 && next & 0x80 == 0) || (b == 0xff && next & 0x80 != 0) {
+            start += 1;
+        } else {
+            break;
+        }
+    }
+    tlv(INTEGER, &bytes[start..])
+}
+
+pub fn unsigned(tag: u8, value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(7);
+    // A set high bit would read as negative, keep a zero in front
+    if bytes[start] & 0x80 != 0 {
+        tlv(tag, &[&[0], &bytes[start..]].concat())
+    } else {
+        tlv(tag, &bytes[start..])
+    }
+}
+
+pub fn octets(value: &[u8]) -> Vec<u8> {
+    tlv(OCTET_STRING, value)
+}
+
+pub fn object_identifier(oid: &Oid) -> Vec<u8> {
+    let arcs = oid.arcs();
+    let mut out = Vec::new();
+    let first = arcs.first().copied().unwrap_or(0) * 40 + arcs.get(1).copied().unwrap_or(0);
+    for arc in std::iter::once(first).chain(arcs.iter().skip(2).copied()) {
+        let mut chunk = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            chunk.push(0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        out.extend(chunk.into_iter().rev());
+    }
+    tlv(OBJECT_IDENTIFIER, &out)
+}
+
+/// Reads TLVs off a buffer, front to back.
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Next tag and its content.
+    pub fn read(&mut self) -> Result<(u8, &'a [u8]), String> {
+        let (&tag, rest) = self.buf.split_first().ok_or("Truncated BER: missing tag")?;
+        let (&first, rest) = rest.split_first().ok_or("Truncated BER: missing length")?;
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 || rest.len() < n {
+                return Err(format!("Unsupported BER length form 0x{:02x}", first));
+            }
+            let len = rest[..n].iter().fold(0usize, |acc, b| acc << 8 | *b as usize);
+            (len, &rest[n..])
+        };
+        if rest.len() < len {
+            return Err(format!("Truncated BER: tag 0x{:02x} wants {} bytes, {} left", tag, len, rest.len()));
+        }
+        self.buf = &rest[len..];
+        Ok((tag, &rest[..len]))
+    }
+
+    pub fn expect(&mut self, tag: u8) -> Result<&'a [u8], String> {
+        match self.read()? {
+            (t, content) if t == tag => Ok(content),
+            (t, _) => Err(format!("Expected BER tag 0x{:02x}, got 0x{:02x}", tag, t)),
+        }
+    }
+
+    /// Enters a constructed value with the given tag.
+    pub fn sequence(&mut self, tag: u8) -> Result<Reader<'a>, String> {
+        self.expect(tag).map(Reader::new)
+    }
+
+    pub fn integer(&mut self) -> Result<i64, String> {
+        decode_integer(self.expect(INTEGER)?)
+    }
+
+    pub fn octets(&mut self) -> Result<&'a [u8], String> {
+        self.expect(OCTET_STRING)
+    }
+
+    pub fn oid(&mut self) -> Result<Oid, String> {
+        decode_oid(self.expect(OBJECT_IDENTIFIER)?)
+    }
+
+    pub fn value(&mut self) -> Result<Value, String> {
+        let (tag, content) = self.read()?;
+        Ok(match tag {
+            INTEGER => Value::Integer(decode_integer(content)?),
+            OCTET_STRING => Value::OctetString(content.to_vec()),
+            NULL => Value::Null,
+            OBJECT_IDENTIFIER => Value::Oid(decode_oid(content)?),
+            IP_ADDRESS => {
+                let octets: [u8; 4] = content.try_into().map_err(|_| "IpAddress is not 4 bytes")?;
+                Value::IpAddress(Ipv4Addr::from(octets))
+            }
+            COUNTER32 => Value::Counter32(decode_unsigned(content)? as u32),
+            GAUGE32 => Value::Gauge32(decode_unsigned(content)? as u32),
+            TIME_TICKS => Value::TimeTicks(decode_unsigned(content)? as u32),
+            OPAQUE => Value::Opaque(content.to_vec()),
+            COUNTER64 => Value::Counter64(decode_unsigned(content)?),
+            NO_SUCH_OBJECT => Value::NoSuchObject,
+            NO_SUCH_INSTANCE => Value::NoSuchInstance,
+            END_OF_MIB_VIEW => Value::EndOfMibView,
+            other => return Err(format!("Unknown BER value tag 0x{:02x}", other)),
+        })
+    }
+}
+
+fn decode_integer(content: &[u8]) -> Result<i64, String> {
+    if content.is_empty() || content.len() > 8 {
+        return Err(format!("INTEGER of {} bytes", content.len()));
+    }
+    let init = if content[0] & 0x80 != 0 { -1i64 } else { 0 };
+    Ok(content.iter().fold(init, |acc, b| acc << 8 | *b as i64))
+}
+
+fn decode_unsigned(content: &[u8]) -> Result<u64, String> {
+    let content = match content {
+        [0, rest @ ..] => rest,
+        _ => content,
+    };
+    if content.len() > 8 {
+        return Err(format!("Unsigned of {} bytes", content.len()));
+    }
+    Ok(content.iter().fold(0u64, |acc, b| acc << 8 | *b as u64))
+}
+
+fn decode_oid(content: &[u8]) -> Result<Oid, String> {
+    let mut arcs = Vec::new();
+    let mut arc: u32 = 0;
+    for &b in content {
+        arc = arc.checked_mul(128).ok_or("OID arc overflows")? | (b & 0x7f) as u32;
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.extend([first, arc - first * 40]);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        }
+    }
+    if arcs.is_empty() {
+        return Err("Empty OID".into());
+    }
+    Ok(Oid(arcs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_roundtrip() {
+        for (value, encoded) in [
+            (0i64, vec![0x02, 0x01, 0x00]),
+            (127, vec![0x02, 0x01, 0x7f]),
+            (128, vec![0x02, 0x02, 0x00, 0x80]),
+            (-1, vec![0x02, 0x01, 0xff]),
+            (-129, vec![0x02, 0x02, 0xff, 0x7f]),
+            (0x1234_5678, vec![0x02, 0x04, 0x12, 0x34, 0x56, 0x78]),
+        ] {
+            assert_eq!(integer(value), encoded, "{}", value);
+            assert_eq!(Reader::new(&encoded).integer(), Ok(value));
+        }
+        assert_eq!(unsigned(COUNTER32, 0xffff_ffff), vec![0x41, 0x05, 0x00, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(Reader::new(&unsigned(COUNTER32, 0xffff_ffff)).value(), Ok(Value::Counter32(0xffff_ffff)));
+    }
+
+    #[test]
+    fn test_oid_roundtrip() {
+        let oid = Oid::parse("1.3.6.1.4.1.311.21.20").unwrap();
+        let encoded = object_identifier(&oid);
+        assert_eq!(encoded, vec![0x06, 0x09, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x15, 0x14]);
+        assert_eq!(Reader::new(&encoded).oid(), Ok(oid.clone()));
+        assert_eq!(oid.to_string(), "1.3.6.1.4.1.311.21.20");
+
+        let lldp = Oid::parse("1.0.8802.1.1.2").unwrap();
+        assert_eq!(Reader::new(&object_identifier(&lldp)).oid(), Ok(lldp));
+        assert_eq!(Oid::parse("1.3.6.1.2.1.1.5.0").unwrap().suffix(&Oid::parse("1.3.6.1.2.1.1").unwrap()), Some(&[5, 0][..]));
+    }
+
+    #[test]
+    fn test_reader() {
+        // Long form length and a nested sequence
+        let text = vec![b'x'; 200];
+        let encoded = constructed(SEQUENCE, &[octets(&text), tlv(NULL, &[]), unsigned(TIME_TICKS, 42)]);
+        assert_eq!(&encoded[..4], &[0x30, 0x81, 0xd0, 0x04]);
+        let mut seq = Reader::new(&encoded).sequence(SEQUENCE).unwrap();
+        assert_eq!(seq.octets().unwrap(), text.as_slice());
+        assert_eq!(seq.value(), Ok(Value::Null));
+        assert_eq!(seq.value(), Ok(Value::TimeTicks(42)));
+        assert!(seq.is_empty());
+
+        assert!(Reader::new(&[0x04, 0x05, b'a']).read().is_err());
+        assert!(Reader::new(&[0x02, 0x01, 0x00]).octets().is_err());
+        assert_eq!(Value::OctetString(b"switch-1\0".to_vec()).as_text().as_deref(), Some("switch-1"));
+        assert_eq!(Value::OctetString(vec![0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e]).as_text(), None);
+    }
+}
@@ -0,0 +1,167 @@
+use super::ber::{self, Oid, Reader, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Version {
+    V1,
+    V2c,
+    V3, // user-based security, see `usm`
+}
+
+impl Version {
+    pub fn code(&self) -> i64 {
+        match self {
+            Version::V1 => 0,
+            Version::V2c => 1,
+            Version::V3 => 3,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Version::V1 => "v1",
+            Version::V2c => "v2c",
+            Version::V3 => "v3",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PduType {
+    Get,
+    GetNext,
+    Response,
+    GetBulk,
+    Report, // v3 engine discovery and USM errors
+}
+
+impl PduType {
+    fn tag(&self) -> u8 {
+        match self {
+            PduType::Get => 0xa0,
+            PduType::GetNext => 0xa1,
+            PduType::Response => 0xa2,
+            PduType::GetBulk => 0xa5,
+            PduType::Report => 0xa8,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        [PduType::Get, PduType::GetNext, PduType::Response, PduType::GetBulk, PduType::Report].into_iter().find(|t| t.tag() == tag)
+    }
+}
+
+// error-status values we act on
+pub const NO_ERROR: i64 = 0;
+pub const NO_SUCH_NAME: i64 = 2; // v1 end of a walk
+
+pub type VarBind = (Oid, Value);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pdu {
+    pub kind: PduType,
+    pub request_id: i32,
+    pub error_status: i64, // non-repeaters for GetBulk
+    pub error_index: i64,  // max-repetitions for GetBulk
+    pub varbinds: Vec<VarBind>,
+}
+
+impl Pdu {
+    fn request(kind: PduType, request_id: i32, oids: &[Oid]) -> Self {
+        let varbinds = oids.iter().map(|o| (o.clone(), Value::Null)).collect();
+        Self { kind, request_id, error_status: 0, error_index: 0, varbinds }
+    }
+
+    pub fn get(request_id: i32, oids: &[Oid]) -> Self {
+        Self::request(PduType::Get, request_id, oids)
+    }
+
+    pub fn get_next(request_id: i32, oids: &[Oid]) -> Self {
+        Self::request(PduType::GetNext, request_id, oids)
+    }
+
+    pub fn get_bulk(request_id: i32, max_repetitions: i64, oids: &[Oid]) -> Self {
+        Self { error_index: max_repetitions, ..Self::request(PduType::GetBulk, request_id, oids) }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let varbinds: Vec<Vec<u8>> = self.varbinds.iter()
+            .map(|(oid, value)| ber::constructed(ber::SEQUENCE, &[ber::object_identifier(oid), value.encode()]))
+            .collect();
+        ber::constructed(self.kind.tag(), &[
+            ber::integer(self.request_id as i64),
+            ber::integer(self.error_status),
+            ber::integer(self.error_index),
+            ber::constructed(ber::SEQUENCE, &varbinds),
+        ])
+    }
+
+    pub fn decode(reader: &mut Reader) -> Result<Self, String> {
+        let (tag, content) = reader.read()?;
+        let kind = PduType::from_tag(tag).ok_or_else(|| format!("Unknown PDU type 0x{:02x}", tag))?;
+        let mut pdu = Reader::new(content);
+        let request_id = pdu.integer()? as i32;
+        let error_status = pdu.integer()?;
+        let error_index = pdu.integer()?;
+        let mut list = pdu.sequence(ber::SEQUENCE)?;
+        let mut varbinds = Vec::new();
+        while !list.is_empty() {
+            let mut varbind = list.sequence(ber::SEQUENCE)?;
+            varbinds.push((varbind.oid()?, varbind.value()?));
+        }
+        Ok(Self { kind, request_id, error_status, error_index, varbinds })
+    }
+}
+
+/// v1/v2c message: version, community, PDU.
+pub fn encode_community(version: Version, community: &str, pdu: &Pdu) -> Vec<u8> {
+    ber::constructed(ber::SEQUENCE, &[ber::integer(version.code()), ber::octets(community.as_bytes()), pdu.encode()])
+}
+
+pub fn decode_community(buf: &[u8]) -> Result<(Version, Vec<u8>, Pdu), String> {
+    let mut msg = Reader::new(buf).sequence(ber::SEQUENCE)?;
+    let version = match msg.integer()? {
+        0 => Version::V1,
+        1 => Version::V2c,
+        other => return Err(format!("Not a community-based message (version {})", other)),
+    };
+    let community = msg.octets()?.to_vec();
+    Ok((version, community, Pdu::decode(&mut msg)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_request_bytes() {
+        // The hand-built sysDescr GET the scanner used to send
+        let sys_descr = Oid::parse("1.3.6.1.2.1.1.1.0").unwrap();
+        let msg = encode_community(Version::V2c, "public", &Pdu::get(0x1234_5678, &[sys_descr]));
+        assert_eq!(msg, vec![
+            0x30, 0x29, 0x02, 0x01, 0x01, 0x04, 0x06, 0x70, 0x75, 0x62, 0x6c, 0x69, 0x63,
+            0xa0, 0x1c, 0x02, 0x04, 0x12, 0x34, 0x56, 0x78, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00,
+            0x30, 0x0e, 0x30, 0x0c, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00, 0x05, 0x00,
+        ]);
+    }
+
+    #[test]
+    fn test_response_roundtrip() {
+        let response = Pdu {
+            kind: PduType::Response,
+            request_id: 7,
+            error_status: NO_ERROR,
+            error_index: 0,
+            varbinds: vec![
+                (Oid::parse("1.3.6.1.2.1.1.5.0").unwrap(), Value::OctetString(b"core-sw1".to_vec())),
+                (Oid::parse("1.3.6.1.2.1.1.3.0").unwrap(), Value::TimeTicks(123_456)),
+                (Oid::parse("1.3.6.1.2.1.1.9.0").unwrap(), Value::NoSuchObject),
+            ],
+        };
+        let msg = encode_community(Version::V1, "private", &response);
+        let (version, community, decoded) = decode_community(&msg).unwrap();
+        assert_eq!((version, community.as_slice()), (Version::V1, b"private".as_slice()));
+        assert_eq!(decoded, response);
+
+        assert!(decode_community(&msg[..msg.len() - 3]).is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use serde::{Deserialize, Serialize};
+use crate::scanner::addr::MacAddr;
+use super::ber::{Oid, Value};
+use super::message::VarBind;
+use super::Session;
+
+// MIB-II system group, the scalars live at <column>.0
+const SYSTEM: &[u32] = &[1, 3, 6, 1, 2, 1, 1];
+const IF_ENTRY: &[u32] = &[1, 3, 6, 1, 2, 1, 2, 2, 1];
+const IF_X_ENTRY: &[u32] = &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1];
+const IP_NET_TO_MEDIA_PHYS_ADDRESS: &[u32] = &[1, 3, 6, 1, 2, 1, 4, 22, 1, 2];
+const LLDP_LOC_PORT_ENTRY: &[u32] = &[1, 0, 8802, 1, 1, 2, 1, 3, 7, 1];
+const LLDP_REM_ENTRY: &[u32] = &[1, 0, 8802, 1, 1, 2, 1, 4, 1, 1];
+const LLDP_REM_MAN_ADDR_ENTRY: &[u32] = &[1, 0, 8802, 1, 1, 2, 1, 4, 2, 1];
+const CDP_CACHE_ENTRY: &[u32] = &[1, 3, 6, 1, 4, 1, 9, 9, 23, 1, 2, 1, 1];
+const ENTERPRISES: &[u32] = &[1, 3, 6, 1, 4, 1];
+
+/// Rows walked per table column, keeps core router ARP tables in check.
+const MAX_ROWS: usize = 4096;
+
+/// Private enterprise numbers (sysObjectID prefixes) of common network gear vendors.
+const VENDORS: &[(u32, &str)] = &[
+    (9, "Cisco"),
+    (11, "HP"),
+    (43, "3Com"),
+    (171, "D-Link"),
+    (253, "Xerox"),
+    (311, "Microsoft"),
+    (318, "APC"),
+    (367, "Ricoh"),
+    (641, "Lexmark"),
+    (674, "Dell"),
+    (1347, "Kyocera"),
+    (1588, "Brocade"),
+    (1602, "Canon"),
+    (1916, "Extreme Networks"),
+    (2011, "Huawei"),
+    (2435, "Brother"),
+    (2636, "Juniper Networks"),
+    (3375, "F5 Networks"),
+    (4526, "Netgear"),
+    (6574, "Synology"),
+    (6876, "VMware"),
+    (11863, "TP-Link"),
+    (12356, "Fortinet"),
+    (14823, "Aruba Networks"),
+    (14988, "MikroTik"),
+    (24681, "QNAP"),
+    (25461, "Palo Alto Networks"),
+    (25506, "H3C"),
+    (41112, "Ubiquiti"),
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SnmpData {
+    pub version: String, // v1, v2c, v3
+    #[serde(skip)]
+    pub community: String, // a credential, kept out of snapshots
+    pub user: Option<String>, // the v3 user that was accepted
+    pub engine_id: Option<String>, // hex snmpEngineID, from v3 discovery
+    pub engine_boots: Option<u32>,
+    pub sys_descr: String,
+    pub sys_object_id: Option<String>, // e.g. "1.3.6.1.4.1.9.1.1208"
+    pub sys_name: Option<String>,
+    pub sys_contact: Option<String>,
+    pub sys_location: Option<String>,
+    pub uptime_secs: Option<u64>,
+    pub interfaces: Vec<Interface>,
+    pub arp_table: Vec<ArpEntry>, // the agent's ipNetToMedia cache
+    pub neighbors: Vec<Neighbor>, // LLDP / CDP
+}
+
+impl SnmpData {
+    /// Private enterprise number from sysObjectID, else from an RFC 3411 format engine ID.
+    pub fn enterprise(&self) -> Option<u32> {
+        if let Some(oid) = self.sys_object_id.as_deref().and_then(Oid::parse) {
+            return oid.suffix(&Oid::new(ENTERPRISES))?.first().copied();
+        }
+        let prefix = u32::from_str_radix(self.engine_id.as_deref()?.get(..8)?, 16).ok()?;
+        (prefix & 0x8000_0000 != 0).then_some(prefix & 0x7fff_ffff)
+    }
+
+    pub fn manufacturer(&self) -> Option<&'static str> {
+        let enterprise = self.enterprise()?;
+        VENDORS.iter().find(|(n, _)| *n == enterprise).map(|(_, name)| *name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Interface {
+    pub index: u32,
+    pub name: String, // ifName, else ifDescr
+    pub descr: Option<String>,
+    pub if_type: u32, // IANAifType, 6 = ethernetCsmacd
+    pub mtu: u32,
+    pub speed: u64, // bits per second
+    pub mac: Option<MacAddr>,
+    pub admin_up: bool,
+    pub oper_up: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ArpEntry {
+    pub if_index: u32,
+    pub ip: IpAddr,
+    pub mac: MacAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Neighbor {
+    pub protocol: String, // lldp, cdp
+    pub local_port: String,
+    pub name: Option<String>, // remote sysName / CDP device id
+    pub port: Option<String>, // remote port id
+    pub platform: Option<String>, // remote sysDescr / CDP platform
+    pub address: Option<IpAddr>, // management address
+    pub mac: Option<MacAddr>, // LLDP chassis id, when it is a MAC
+}
+
+pub fn system_oids() -> Vec<Oid> {
+    (1..=6).map(|column| Oid::new(SYSTEM).child(&[column, 0])).collect()
+}
+
+/// Builds the inventory view of an agent from its system group and table walks.
+pub async fn collect(session: &mut Session, system: &[VarBind]) -> SnmpData {
+    let sys = |column: u32| {
+        let oid = Oid::new(SYSTEM).child(&[column, 0]);
+        system.iter().find(|(o, _)| *o == oid).map(|(_, v)| v)
+    };
+    let text = |column| sys(column).and_then(Value::as_text);
+    let mut data = SnmpData {
+        version: session.version().as_str().into(),
+        community: session.community().unwrap_or_default().into(),
+        user: session.user().map(String::from),
+        sys_descr: text(1).unwrap_or_else(|| "SNMP Device".into()),
+        sys_object_id: sys(2).and_then(Value::as_oid).map(Oid::to_string),
+        uptime_secs: sys(3).and_then(Value::as_u64).map(|ticks| ticks / 100),
+        sys_contact: text(4),
+        sys_name: text(5),
+        sys_location: text(6),
+        ..Default::default()
+    };
+
+    let if_table = walk_table(session, IF_ENTRY, &[2, 3, 4, 5, 6, 7, 8]).await;
+    let if_x_table = walk_table(session, IF_X_ENTRY, &[1]).await;
+    data.interfaces = parse_interfaces(&if_table, &if_x_table);
+
+    let arp = walk(session, IP_NET_TO_MEDIA_PHYS_ADDRESS).await;
+    data.arp_table = parse_arp_table(&arp);
+
+    let lldp = walk_table(session, LLDP_REM_ENTRY, &[4, 5, 7, 9, 10]).await;
+    if !lldp.is_empty() {
+        let addresses = walk_table(session, LLDP_REM_MAN_ADDR_ENTRY, &[3]).await;
+        let local_ports = walk_table(session, LLDP_LOC_PORT_ENTRY, &[3]).await;
+        data.neighbors.extend(parse_lldp(&lldp, &addresses, &local_ports, &data.interfaces));
+    }
+    let cdp = walk_table(session, CDP_CACHE_ENTRY, &[3, 4, 6, 7, 8]).await;
+    data.neighbors.extend(parse_cdp(&cdp, &data.interfaces));
+
+    tracing::debug!(
+        "SNMP {}: {} interfaces, {} ARP entries, {} neighbors",
+        data.sys_name.as_deref().unwrap_or(&data.sys_descr), data.interfaces.len(), data.arp_table.len(), data.neighbors.len()
+    );
+    data
+}
+
+async fn walk(session: &mut Session, root: &[u32]) -> Vec<VarBind> {
+    let root = Oid::new(root);
+    session.walk(&root, MAX_ROWS).await.unwrap_or_else(|e| {
+        tracing::debug!("SNMP walk of {} failed: {}", root, e);
+        Vec::new()
+    })
+}
+
+/// Walks the given columns of a table, stopping early when the first one is empty.
+async fn walk_table(session: &mut Session, entry: &[u32], columns: &[u32]) -> Table {
+    let mut varbinds = Vec::new();
+    for (i, column) in columns.iter().enumerate() {
+        let rows = walk(session, &[entry, &[*column]].concat()).await;
+        if i == 0 && rows.is_empty() {
+            break;
+        }
+        varbinds.extend(rows);
+    }
+    Table::new(entry, varbinds)
+}
+
+/// Conceptual table: row index -> column -> value.
+#[derive(Default)]
+struct Table {
+    rows: BTreeMap<Vec<u32>, HashMap<u32, Value>>,
+}
+
+impl Table {
+    fn new(entry: &[u32], varbinds: Vec<VarBind>) -> Self {
+        let entry = Oid::new(entry);
+        let mut table = Table::default();
+        for (oid, value) in varbinds {
+            if let Some([column, index @ ..]) = oid.suffix(&entry) {
+                table.rows.entry(index.to_vec()).or_default().insert(*column, value);
+            }
+        }
+        table
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn get(&self, index: &[u32], column: u32) -> Option<&Value> {
+        self.rows.get(index)?.get(&column)
+    }
+
+    fn text(&self, index: &[u32], column: u32) -> Option<String> {
+        self.get(index, column).and_then(Value::as_text)
+    }
+}
+
+fn parse_interfaces(if_table: &Table, if_x_table: &Table) -> Vec<Interface> {
+    if_table.rows.iter()
+        .filter_map(|(index, row)| {
+            let &[if_index] = index.as_slice() else { return None };
+            let number = |column| row.get(&column).and_then(Value::as_u64).unwrap_or_default();
+            let descr = row.get(&2).and_then(Value::as_text);
+            let name = if_x_table.text(index, 1).or_else(|| descr.clone()).unwrap_or_else(|| format!("if{}", if_index));
+            let mac = row.get(&6).and_then(Value::as_bytes)
+                .filter(|b| b.len() == 6)
+                .and_then(MacAddr::from_slice)
+                .filter(|m| !m.is_unspecified());
+            Some(Interface {
+                index: if_index,
+                name,
+                descr,
+                if_type: number(3) as u32,
+                mtu: number(4) as u32,
+                speed: number(5),
+                mac,
+                admin_up: number(7) == 1,
+                oper_up: number(8) == 1,
+            })
+        })
+        .collect()
+}
+
+/// ipNetToMediaPhysAddress rows are indexed by ifIndex and the IPv4 address.
+fn parse_arp_table(varbinds: &[VarBind]) -> Vec<ArpEntry> {
+    let root = Oid::new(IP_NET_TO_MEDIA_PHYS_ADDRESS);
+    varbinds.iter()
+        .filter_map(|(oid, value)| {
+            let &[if_index, a, b, c, d] = oid.suffix(&root)? else { return None };
+            let octet = |o: u32| u8::try_from(o).ok();
+            let ip = Ipv4Addr::new(octet(a)?, octet(b)?, octet(c)?, octet(d)?);
+            let mac = value.as_bytes().filter(|b| b.len() == 6).and_then(MacAddr::from_slice)?;
+            (!mac.is_unspecified() && !mac.is_multicast()).then_some(ArpEntry { if_index, ip: IpAddr::V4(ip), mac })
+        })
+        .collect()
+}
+
+fn port_name(if_index: u32, interfaces: &[Interface]) -> String {
+    interfaces.iter().find(|i| i.index == if_index).map(|i| i.name.clone()).unwrap_or_else(|| if_index.to_string())
+}
+
+/// An identifier that is either text or a MAC address (chassis / port ids).
+fn id_text(value: Option<&Value>) -> Option<String> {
+    let value = value?;
+    value.as_text().or_else(|| {
+        let bytes = value.as_bytes()?;
+        (bytes.len() == 6).then(|| MacAddr::from_slice(bytes)).flatten().map(|m| m.to_string())
+    })
+}
+
+fn parse_lldp(remotes: &Table, addresses: &Table, local_ports: &Table, interfaces: &[Interface]) -> Vec<Neighbor> {
+    remotes.rows.keys()
+        .filter_map(|index| {
+            // lldpRemTimeMark.lldpRemLocalPortNum.lldpRemIndex
+            let &[_, local_port, _] = index.as_slice() else { return None };
+            let chassis = remotes.get(index, 5).and_then(Value::as_bytes);
+            let mac = match remotes.get(index, 4) {
+                Some(Value::Integer(4)) => chassis.filter(|b| b.len() == 6).and_then(MacAddr::from_slice),
+                _ => None,
+            };
+            // lldpRemManAddrTable extends the index by subtype, length and address bytes
+            let address = addresses.rows.keys()
+                .filter_map(|a| a.strip_prefix(index.as_slice()))
+                .find_map(management_address);
+            Some(Neighbor {
+                protocol: "lldp".into(),
+                local_port: local_ports.text(&[local_port], 3).unwrap_or_else(|| port_name(local_port, interfaces)),
+                name: remotes.text(index, 9).or_else(|| remotes.get(index, 5).and_then(Value::as_text)),
+                port: id_text(remotes.get(index, 7)),
+                platform: remotes.text(index, 10),
+                address,
+                mac,
+            })
+        })
+        .collect()
+}
+
+fn management_address(index: &[u32]) -> Option<IpAddr> {
+    let (&subtype, rest) = index.split_first()?;
+    let (&len, bytes) = rest.split_first()?;
+    let bytes: Vec<u8> = bytes.iter().map(|b| u8::try_from(*b).ok()).collect::<Option<_>>()?;
+    if bytes.len() != len as usize {
+        return None;
+    }
+    match subtype {
+        1 => Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?))),
+        2 => Some(IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?))),
+        _ => None,
+    }
+}
+
+fn parse_cdp(cache: &Table, interfaces: &[Interface]) -> Vec<Neighbor> {
+    cache.rows.keys()
+        .filter_map(|index| {
+            // cdpCacheIfIndex.cdpCacheDeviceIndex
+            let &[if_index, _] = index.as_slice() else { return None };
+            let address = match cache.get(index, 3) {
+                Some(Value::Integer(1)) => cache.get(index, 4)
+                    .and_then(Value::as_bytes)
+                    .and_then(|b| <[u8; 4]>::try_from(b).ok())
+                    .map(|b| IpAddr::V4(Ipv4Addr::from(b))),
+                _ => None,
+            };
+            Some(Neighbor {
+                protocol: "cdp".into(),
+                local_port: port_name(if_index, interfaces),
+                name: cache.text(index, 6),
+                port: cache.text(index, 7),
+                platform: cache.text(index, 8),
+                address,
+                mac: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vb(oid: &[u32], value: Value) -> VarBind {
+        (Oid::new(oid), value)
+    }
+
+    fn text(s: &str) -> Value {
+        Value::OctetString(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_interfaces_and_arp() {
+        let entry = |column: u32, index: u32| [IF_ENTRY, &[column, index]].concat();
+        let if_table = Table::new(IF_ENTRY, vec![
+            vb(&entry(2, 1), text("GigabitEthernet0/1")),
+            vb(&entry(3, 1), Value::Integer(6)),
+            vb(&entry(5, 1), Value::Gauge32(1_000_000_000)),
+            vb(&entry(6, 1), Value::OctetString(vec![0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e])),
+            vb(&entry(7, 1), Value::Integer(1)),
+            vb(&entry(8, 1), Value::Integer(2)),
+            vb(&entry(2, 2), text("lo")),
+            vb(&entry(6, 2), Value::OctetString(Vec::new())),
+        ]);
+        let if_x_table = Table::new(IF_X_ENTRY, vec![vb(&[IF_X_ENTRY, &[1, 1]].concat(), text("Gi0/1"))]);
+        let interfaces = parse_interfaces(&if_table, &if_x_table);
+        assert_eq!(interfaces.len(), 2);
+        assert_eq!(interfaces[0].name, "Gi0/1");
+        assert_eq!(interfaces[0].mac, Some("00:1B:21:3C:4D:5E".parse().unwrap()));
+        assert!(interfaces[0].admin_up && !interfaces[0].oper_up);
+        assert_eq!(interfaces[0].speed, 1_000_000_000);
+        assert_eq!((interfaces[1].name.as_str(), interfaces[1].mac), ("lo", None));
+
+        let arp = parse_arp_table(&[
+            vb(&[IP_NET_TO_MEDIA_PHYS_ADDRESS, &[1, 192, 168, 1, 20]].concat(), Value::OctetString(vec![0xa4, 0x83, 0xe7, 1, 2, 3])),
+            vb(&[IP_NET_TO_MEDIA_PHYS_ADDRESS, &[1, 192, 168, 1, 255]].concat(), Value::OctetString(vec![0xff; 6])),
+        ]);
+        assert_eq!(arp, vec![ArpEntry { if_index: 1, ip: "192.168.1.20".parse().unwrap(), mac: "A4:83:E7:01:02:03".parse().unwrap() }]);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let interfaces = vec![Interface {
+            index: 3, name: "Gi0/3".into(), descr: None, if_type: 6, mtu: 1500, speed: 0, mac: None, admin_up: true, oper_up: true,
+        }];
+        let rem = |column: u32| [LLDP_REM_ENTRY, &[column, 0, 3, 1]].concat();
+        let remotes = Table::new(LLDP_REM_ENTRY, vec![
+            vb(&rem(4), Value::Integer(4)),
+            vb(&rem(5), Value::OctetString(vec![0x00, 0x11, 0x22, 0x33, 0x44, 0x55])),
+            vb(&rem(7), text("ge-0/0/1")),
+            vb(&rem(9), text("core-sw2")),
+            vb(&rem(10), text("Juniper Networks, Inc. ex2300")),
+        ]);
+        let addresses = Table::new(LLDP_REM_MAN_ADDR_ENTRY, vec![
+            vb(&[LLDP_REM_MAN_ADDR_ENTRY, &[3, 0, 3, 1, 1, 4, 10, 0, 0, 2]].concat(), Value::Integer(2)),
+        ]);
+        let lldp = parse_lldp(&remotes, &addresses, &Table::default(), &interfaces);
+        assert_eq!(lldp, vec![Neighbor {
+            protocol: "lldp".into(),
+            local_port: "Gi0/3".into(),
+            name: Some("core-sw2".into()),
+            port: Some("ge-0/0/1".into()),
+            platform: Some("Juniper Networks, Inc. ex2300".into()),
+            address: Some("10.0.0.2".parse().unwrap()),
+            mac: Some("00:11:22:33:44:55".parse().unwrap()),
+        }]);
+
+        let cache = |column: u32| [CDP_CACHE_ENTRY, &[column, 3, 1]].concat();
+        let cdp = parse_cdp(&Table::new(CDP_CACHE_ENTRY, vec![
+            vb(&cache(3), Value::Integer(1)),
+            vb(&cache(4), Value::OctetString(vec![10, 0, 0, 3])),
+            vb(&cache(6), text("ap-lobby.example.com")),
+            vb(&cache(8), text("cisco AIR-AP2802I-E-K9")),
+        ]), &interfaces);
+        assert_eq!(cdp[0].address, Some("10.0.0.3".parse().unwrap()));
+        assert_eq!((cdp[0].local_port.as_str(), cdp[0].name.as_deref()), ("Gi0/3", Some("ap-lobby.example.com")));
+    }
+
+    #[test]
+    fn test_manufacturer() {
+        let data = SnmpData { sys_object_id: Some("1.3.6.1.4.1.9.1.1208".into()), ..Default::default() };
+        assert_eq!((data.enterprise(), data.manufacturer()), (Some(9), Some("Cisco")));
+        let net_snmp = SnmpData { sys_object_id: Some("1.3.6.1.4.1.8072.3.2.10".into()), ..Default::default() };
+        assert_eq!((net_snmp.enterprise(), net_snmp.manufacturer()), (Some(8072), None));
+    }
+}
@@ -0,0 +1,401 @@
+pub mod ber;
+pub mod message;
+pub mod mib;
+pub mod usm;
+
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use ber::{Oid, Value};
+use message::{Pdu, PduType, VarBind, Version};
+use usm::{Credential, Engine, LocalUser, UsmUser};
+
+pub use mib::{Interface, Neighbor, SnmpData};
+
+const SNMP_PORT: u16 = 161;
+const OPEN_TIMEOUT: Duration = Duration::from_millis(500);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+const RETRIES: u32 = 1;
+const MAX_REPETITIONS: i64 = 25;
+
+/// Communities tried when a profile does not name any.
+pub const DEFAULT_COMMUNITIES: [&str; 2] = ["public", "private"];
+
+/// Queries an agent as the first v3 user in scope it accepts, else with the first community
+/// it accepts: the system group, then the interface, ARP (ipNetToMedia) and LLDP/CDP
+/// neighbor tables. A v3 agent that takes none of them still reports its engine ID.
+pub async fn fingerprint(ip: IpAddr, communities: &[String], credentials: &[Credential]) -> Option<SnmpData> {
+    let target = SocketAddr::new(ip, SNMP_PORT);
+    let oids = mib::system_oids();
+    let (engine, community) = tokio::join!(Session::discover_engine(target), Session::open(target, communities, &oids));
+
+    let mut opened = None;
+    if let Some(engine) = &engine {
+        for credential in credentials.iter().filter(|c| c.scope.contains_ip(&ip)) {
+            match Session::open_usm(target, engine, &credential.user, &oids).await {
+                Ok(session) => {
+                    opened = Some(session);
+                    break;
+                }
+                Err(e) => tracing::debug!("SNMPv3 user {} not accepted by {}: {}", credential.user.name, ip, e),
+            }
+        }
+    }
+
+    let mut data = match opened.or(community) {
+        Some((mut session, system)) => mib::collect(&mut session, &system).await,
+        None if engine.is_some() => SnmpData { version: Version::V3.as_str().into(), ..Default::default() },
+        None => return None,
+    };
+    if let Some(engine) = engine {
+        data.engine_id = Some(engine.id.iter().map(|b| format!("{:02x}", b)).collect());
+        data.engine_boots = Some(engine.boots);
+    }
+    Some(data)
+}
+
+enum Security {
+    Community(String),
+    Usm { engine: Engine, user: LocalUser, salt: u64 },
+}
+
+/// An agent and the community or v3 user it answered.
+pub struct Session {
+    socket: UdpSocket,
+    target: SocketAddr,
+    version: Version,
+    security: Security,
+    next_id: i32,
+}
+
+async fn bind(target: SocketAddr) -> Option<UdpSocket> {
+    UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).await.ok()
+}
+
+impl Session {
+    /// Sends the GET with every community, as v2c and as v1, at once and keeps the first
+    /// answer. Agents silently drop unknown communities, so silent hosts cost one timeout.
+    pub async fn open(target: SocketAddr, communities: &[String], oids: &[Oid]) -> Option<(Self, Vec<VarBind>)> {
+        let socket = bind(target).await?;
+
+        let mut attempts = HashMap::new();
+        for (i, community) in communities.iter().enumerate() {
+            for (j, version) in [Version::V2c, Version::V1].into_iter().enumerate() {
+                let id = (i * 2 + j + 1) as i32;
+                let msg = message::encode_community(version, community, &Pdu::get(id, oids));
+                socket.send_to(&msg, target).await.ok()?;
+                attempts.insert(id, (version, community));
+            }
+        }
+
+        let deadline = Instant::now() + OPEN_TIMEOUT;
+        let mut buf = vec![0u8; 65535];
+        loop {
+            let (len, _) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await.ok()?.ok()?;
+            let Ok((version, _, pdu)) = message::decode_community(&buf[..len]) else { continue };
+            let Some(&(sent_version, community)) = attempts.get(&pdu.request_id) else { continue };
+            if pdu.kind == PduType::Response && version == sent_version && pdu.error_status == message::NO_ERROR {
+                let next_id = attempts.len() as i32 + 1;
+                let session = Self { socket, target, version, security: Security::Community(community.clone()), next_id };
+                return Some((session, pdu.varbinds));
+            }
+        }
+    }
+
+    /// Engine discovery (RFC 3414 4): an empty unauthenticated GET, answered with a Report
+    /// that carries the agent's engine ID, boots and time.
+    pub async fn discover_engine(target: SocketAddr) -> Option<Engine> {
+        let socket = bind(target).await?;
+        let msg = usm::encode(1, &Engine::unknown(), None, &Pdu::get(1, &[]), 0);
+        socket.send_to(&msg, target).await.ok()?;
+
+        let deadline = Instant::now() + OPEN_TIMEOUT;
+        let mut buf = vec![0u8; 65535];
+        loop {
+            let (len, _) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await.ok()?.ok()?;
+            let Ok(incoming) = usm::decode(&buf[..len], None) else { continue };
+            if incoming.pdu.kind == PduType::Report && !incoming.engine.id.is_empty() {
+                return Some(incoming.engine);
+            }
+        }
+    }
+
+    /// Opens an authenticated (and, with a privacy key, encrypted) v3 session.
+    pub async fn open_usm(target: SocketAddr, engine: &Engine, user: &UsmUser, oids: &[Oid]) -> Result<(Self, Vec<VarBind>), String> {
+        let socket = bind(target).await.ok_or("Cannot bind a UDP socket")?;
+        let security = Security::Usm {
+            engine: engine.clone(),
+            user: user.localize(&engine.id),
+            salt: uuid::Uuid::new_v4().as_u64_pair().0,
+        };
+        let mut session = Self { socket, target, version: Version::V3, security, next_id: 0 };
+        let id = session.next_id();
+        let response = session.request(Pdu::get(id, oids)).await?;
+        if response.error_status != message::NO_ERROR {
+            return Err(format!("{} answered error-status {}", target, response.error_status));
+        }
+        Ok((session, response.varbinds))
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn community(&self) -> Option<&str> {
+        match &self.security {
+            Security::Community(community) => Some(community),
+            Security::Usm { .. } => None,
+        }
+    }
+
+    pub fn user(&self) -> Option<&str> {
+        match &self.security {
+            Security::Community(_) => None,
+            Security::Usm { user, .. } => Some(&user.name),
+        }
+    }
+
+    fn next_id(&mut self) -> i32 {
+        self.next_id = self.next_id.wrapping_add(1) & 0x7fff_ffff;
+        self.next_id
+    }
+
+    fn encode(&mut self, pdu: &Pdu) -> Vec<u8> {
+        match &mut self.security {
+            Security::Community(community) => message::encode_community(self.version, community, pdu),
+            Security::Usm { engine, user, salt } => {
+                *salt = salt.wrapping_add(1);
+                usm::encode(pdu.request_id, engine, Some(user), pdu, *salt)
+            }
+        }
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<Pdu, String> {
+        match &mut self.security {
+            Security::Community(_) => message::decode_community(buf).map(|(_, _, pdu)| pdu),
+            Security::Usm { engine, user, .. } => {
+                let incoming = usm::decode(buf, Some(user))?;
+                // Only Reports may come in below the session's security level; anything else could be spoofed
+                if incoming.pdu.kind != PduType::Report && (!incoming.authenticated || (user.has_privacy() && !incoming.encrypted)) {
+                    return Err("Response below the session's security level".into());
+                }
+                // The notInTimeWindow Report carries the agent's current clock
+                if usm::report_counter(&incoming.pdu) == Some(usm::NOT_IN_TIME_WINDOW) {
+                    *engine = Engine::new(engine.id.clone(), incoming.engine.boots, incoming.engine.time());
+                }
+                Ok(incoming.pdu)
+            }
+        }
+    }
+
+    /// Sends a request and waits for the response with its request-id, retrying on timeout.
+    async fn request(&mut self, pdu: Pdu) -> Result<Pdu, String> {
+        let mut resynced = false;
+        let mut buf = vec![0u8; 65535];
+        let mut attempt = 0;
+        while attempt <= RETRIES {
+            attempt += 1;
+            let msg = self.encode(&pdu);
+            self.socket.send_to(&msg, self.target).await.map_err(|e| e.to_string())?;
+            let deadline = Instant::now() + REQUEST_TIMEOUT;
+            while let Ok(received) = tokio::time::timeout_at(deadline, self.socket.recv_from(&mut buf)).await {
+                let (len, _) = received.map_err(|e| e.to_string())?;
+                // Late answers to earlier requests are skipped
+                let response = match self.decode(&buf[..len]) {
+                    Ok(response) if response.request_id == pdu.request_id => response,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        tracing::debug!("Malformed SNMP response from {}: {}", self.target, e);
+                        continue;
+                    }
+                };
+                if response.kind != PduType::Report {
+                    return Ok(response);
+                }
+                // Resend once with the clock the agent reported
+                if usm::report_counter(&response) == Some(usm::NOT_IN_TIME_WINDOW) && !resynced {
+                    resynced = true;
+                    attempt = 0;
+                    break;
+                }
+                return Err(format!("{} rejected the request: {}", self.target, usm::describe_report(&response)));
+            }
+        }
+        Err(format!("No SNMP response from {}", self.target))
+    }
+    /// Every varbind below `root`: GETBULK on v2c and v3, GETNEXT on v1.
+    pub async fn walk(&mut self, root: &Oid, max_rows: usize) -> Result<Vec<VarBind>, String> {
+        let mut rows: Vec<VarBind> = Vec::new();
+        let mut last = root.clone();
+
+        while rows.len() < max_rows {
+            let id = self.next_id();
+            let pdu = match self.version {
+                Version::V1 => Pdu::get_next(id, &[last.clone()]),
+                Version::V2c | Version::V3 => Pdu::get_bulk(id, MAX_REPETITIONS, &[last.clone()]),
+            };
+            let response = self.request(pdu).await?;
+            // v1 signals the end of the MIB with noSuchName
+            if self.version == Version::V1 && response.error_status == message::NO_SUCH_NAME {
+                break;
+            }
+            if response.error_status != message::NO_ERROR {
+                return Err(format!("{} answered error-status {}", self.target, response.error_status));
+            }
+            if response.varbinds.is_empty() {
+                break;
+            }
+            for (oid, value) in response.varbinds {
+                // Left the subtree, or an agent that does not move forward
+                if !oid.starts_with(root) || oid <= last || value == Value::EndOfMibView {
+                    return Ok(rows);
+                }
+                last = oid.clone();
+                rows.push((oid, value));
+            }
+        }
+        rows.truncate(max_rows);
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers GET/GETNEXT/GETBULK from a fixed, sorted MIB.
+    async fn agent(community: &'static str, mib: Vec<VarBind>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 65535];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let Ok((version, c, request)) = message::decode_community(&buf[..len]) else { continue };
+                if c != community.as_bytes() {
+                    continue;
+                }
+                let (oid, _) = &request.varbinds[0];
+                let varbinds: Vec<VarBind> = match request.kind {
+                    PduType::Get => request.varbinds.iter()
+                        .map(|(o, _)| mib.iter().find(|(m, _)| m == o).cloned().unwrap_or((o.clone(), Value::NoSuchObject)))
+                        .collect(),
+                    PduType::GetNext => mib.iter().find(|(m, _)| m > oid).cloned().into_iter().collect(),
+                    _ => mib.iter().filter(|(m, _)| m > oid).take(request.error_index as usize).cloned().collect(),
+                };
+                let response = Pdu { kind: PduType::Response, error_status: 0, error_index: 0, varbinds, ..request };
+                let msg = message::encode_community(version, community, &response);
+                socket.send_to(&msg, peer).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_open_and_walk() {
+        let oid = |s: &str| Oid::parse(s).unwrap();
+        let mut mib = vec![
+            (oid("1.3.6.1.2.1.1.1.0"), Value::OctetString(b"Test agent".to_vec())),
+            (oid("1.3.6.1.2.1.1.5.0"), Value::OctetString(b"sw1".to_vec())),
+        ];
+        mib.extend((1..=60).map(|i| (oid(&format!("1.3.6.1.2.1.2.2.1.2.{}", i)), Value::OctetString(format!("eth{}", i).into_bytes()))));
+        mib.push((oid("1.3.6.1.2.1.2.2.1.3.1"), Value::Integer(6)));
+        let addr = agent("s3cret", mib).await;
+
+        let communities = vec!["public".to_string(), "s3cret".to_string()];
+        let (mut session, system) = Session::open(addr, &communities, &[oid("1.3.6.1.2.1.1.5.0")]).await.unwrap();
+        assert_eq!(session.community(), Some("s3cret"));
+        assert_eq!(system[0].1.as_text().as_deref(), Some("sw1"));
+
+        let descr = session.walk(&oid("1.3.6.1.2.1.2.2.1.2"), 1000).await.unwrap();
+        assert_eq!(descr.len(), 60);
+        assert_eq!(descr[59].1.as_text().as_deref(), Some("eth60"));
+        assert_eq!(session.walk(&oid("1.3.6.1.2.1.2.2.1.2"), 10).await.unwrap().len(), 10);
+        assert!(session.walk(&oid("1.3.6.1.2.1.4.22"), 1000).await.unwrap().is_empty());
+    }
+
+    /// A v3 agent: answers discovery, rejects the first authenticated request as out of
+    /// its time window, then serves the MIB to the one user it knows.
+    async fn usm_agent(user: UsmUser, mib: Vec<VarBind>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let engine = Engine::new(vec![0x80, 0x00, 0x1f, 0x88, 0x04, 0x61, 0x65, 0x67, 0x69, 0x73], 3, 5000);
+        let local = user.localize(&engine.id);
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 65535];
+            let mut synced = false;
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let report = |counter: u32, request_id| Pdu {
+                    kind: PduType::Report,
+                    request_id,
+                    error_status: 0,
+                    error_index: 0,
+                    varbinds: vec![(Oid::parse("1.3.6.1.6.3.15.1.1").unwrap().child(&[counter, 0]), Value::Counter32(1))],
+                };
+                let msg = match usm::decode(&buf[..len], None) {
+                    Ok(discovery) => usm::encode(discovery.pdu.request_id, &engine, None, &report(4, discovery.pdu.request_id), 0),
+                    Err(_) => match usm::decode(&buf[..len], Some(&local)) {
+                        Ok(request) if !synced => {
+                            synced = true;
+                            usm::encode(request.pdu.request_id, &engine, Some(&local), &report(usm::NOT_IN_TIME_WINDOW, request.pdu.request_id), 1)
+                        }
+                        Ok(request) => {
+                            let varbinds = request.pdu.varbinds.iter()
+                                .map(|(o, _)| mib.iter().find(|(m, _)| m == o).cloned().unwrap_or((o.clone(), Value::NoSuchObject)))
+                                .collect();
+                            let response = Pdu { kind: PduType::Response, varbinds, ..request.pdu };
+                            usm::encode(response.request_id, &engine, Some(&local), &response, 2)
+                        }
+                        Err(_) => continue,
+                    },
+                };
+                socket.send_to(&msg, peer).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_usm_session() {
+        let oid = |s: &str| Oid::parse(s).unwrap();
+        let user = UsmUser::from_passwords("monitor", usm::AuthProtocol::Sha256, "authpass123", Some((usm::PrivProtocol::Aes128, "privpass123")));
+        let mib = vec![(oid("1.3.6.1.2.1.1.5.0"), Value::OctetString(b"edge-rtr".to_vec()))];
+        let addr = usm_agent(user.clone(), mib).await;
+
+        let engine = Session::discover_engine(addr).await.unwrap();
+        assert_eq!((engine.id.len(), engine.boots), (10, 3));
+
+        let (session, system) = Session::open_usm(addr, &engine, &user, &[oid("1.3.6.1.2.1.1.5.0")]).await.unwrap();
+        assert_eq!((session.version(), session.user(), session.community()), (Version::V3, Some("monitor"), None));
+        assert_eq!(system[0].1.as_text().as_deref(), Some("edge-rtr"));
+
+        let stranger = UsmUser::from_passwords("monitor", usm::AuthProtocol::Sha256, "wrongpass123", None);
+        assert!(Session::open_usm(addr, &engine, &stranger, &[oid("1.3.6.1.2.1.1.5.0")]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_usm_rejects_unauthenticated_response() {
+        let oid = |s: &str| Oid::parse(s).unwrap();
+        let user = UsmUser::from_passwords("monitor", usm::AuthProtocol::Sha1, "authpass123", Some((usm::PrivProtocol::Aes128, "privpass123")));
+        let engine = Engine::new(vec![0x80, 0x00, 0x1f, 0x88, 0x04, 0x61, 0x65, 0x67, 0x69, 0x73], 3, 5000);
+        let local = user.localize(&engine.id);
+
+        // Someone on the path answers with a noAuthNoPriv Response
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let agent_engine = engine.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 65535];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let Ok(request) = usm::decode(&buf[..len], Some(&local)) else { continue };
+                let varbinds = vec![(oid("1.3.6.1.2.1.1.5.0"), Value::OctetString(b"forged".to_vec()))];
+                let response = Pdu { kind: PduType::Response, varbinds, ..request.pdu };
+                let msg = usm::encode(response.request_id, &agent_engine, None, &response, 0);
+                socket.send_to(&msg, peer).await.unwrap();
+            }
+        });
+
+        assert!(Session::open_usm(addr, &engine, &user, &[oid("1.3.6.1.2.1.1.5.0")]).await.is_err());
+    }
+}
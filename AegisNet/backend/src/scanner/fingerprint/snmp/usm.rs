@@ -0,0 +1,451 @@
+use std::time::Instant;
+use aes::cipher::{AsyncStreamCipher, KeyIvInit};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use crate::scanner::target::ScanTarget;
+use super::ber::{self, Oid, Reader};
+use super::message::{Pdu, Version};
+
+const MAX_MESSAGE_SIZE: i64 = 65507;
+const USM_SECURITY_MODEL: i64 = 3;
+
+// msgFlags
+const FLAG_AUTH: u8 = 0x01;
+const FLAG_PRIV: u8 = 0x02;
+const FLAG_REPORTABLE: u8 = 0x04;
+
+// usmStats counters, the varbind of a Report PDU names the failure (RFC 3414 5)
+const USM_STATS: &[u32] = &[1, 3, 6, 1, 6, 3, 15, 1, 1];
+pub const NOT_IN_TIME_WINDOW: u32 = 2;
+
+/// Password stretching input, RFC 3414 A.2.
+const PASSWORD_BYTES: usize = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthProtocol {
+    Sha1,   // usmHMACSHAAuthProtocol
+    Sha256, // usmHMAC192SHA256AuthProtocol (RFC 7860)
+}
+
+impl AuthProtocol {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "sha" | "sha1" => Some(AuthProtocol::Sha1),
+            "sha256" | "sha-256" => Some(AuthProtocol::Sha256),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthProtocol::Sha1 => "sha",
+            AuthProtocol::Sha256 => "sha256",
+        }
+    }
+
+    /// Length of the truncated HMAC carried in msgAuthenticationParameters.
+    fn mac_len(&self) -> usize {
+        match self {
+            AuthProtocol::Sha1 => 12,
+            AuthProtocol::Sha256 => 24,
+        }
+    }
+
+    /// Ku: the password repeated to 1 MB and hashed. Independent of the agent, so this is
+    /// what we store instead of the password.
+    pub fn password_key(&self, password: &str) -> Vec<u8> {
+        match self {
+            AuthProtocol::Sha1 => stretch::<Sha1>(password.as_bytes()),
+            AuthProtocol::Sha256 => stretch::<Sha256>(password.as_bytes()),
+        }
+    }
+
+    /// Kul: Ku bound to one agent's engine ID.
+    pub fn localize(&self, key: &[u8], engine_id: &[u8]) -> Vec<u8> {
+        match self {
+            AuthProtocol::Sha1 => Sha1::new().chain_update(key).chain_update(engine_id).chain_update(key).finalize().to_vec(),
+            AuthProtocol::Sha256 => Sha256::new().chain_update(key).chain_update(engine_id).chain_update(key).finalize().to_vec(),
+        }
+    }
+
+    /// Checks a truncated HMAC in constant time.
+    fn verify(&self, key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+        if tag.len() != self.mac_len() {
+            return false;
+        }
+        match self {
+            AuthProtocol::Sha1 => {
+                let mut m = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+                m.update(data);
+                m.verify_truncated_left(tag).is_ok()
+            }
+            AuthProtocol::Sha256 => {
+                let mut m = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+                m.update(data);
+                m.verify_truncated_left(tag).is_ok()
+            }
+        }
+    }
+
+    fn hmac(&self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = match self {
+            AuthProtocol::Sha1 => {
+                let mut m = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+                m.update(data);
+                m.finalize().into_bytes().to_vec()
+            }
+            AuthProtocol::Sha256 => {
+                let mut m = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+                m.update(data);
+                m.finalize().into_bytes().to_vec()
+            }
+        };
+        mac.truncate(self.mac_len());
+        mac
+    }
+}
+
+fn stretch<D: Digest>(password: &[u8]) -> Vec<u8> {
+    let mut digest = D::new();
+    if password.is_empty() {
+        return digest.finalize().to_vec();
+    }
+    let mut chunk = [0u8; 64];
+    let mut at = 0;
+    for _ in 0..PASSWORD_BYTES / chunk.len() {
+        for b in chunk.iter_mut() {
+            *b = password[at % password.len()];
+            at += 1;
+        }
+        digest.update(chunk);
+    }
+    digest.finalize().to_vec()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrivProtocol {
+    Aes128, // usmAesCfb128Protocol (RFC 3826)
+}
+
+impl PrivProtocol {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "aes" | "aes128" => Some(PrivProtocol::Aes128),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrivProtocol::Aes128 => "aes",
+        }
+    }
+}
+
+/// An SNMPv3 user. Keys are password keys (Ku), localized per agent when a session opens.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UsmUser {
+    pub name: String,
+    pub auth: AuthProtocol,
+    pub auth_key: Vec<u8>,
+    pub privacy: Option<(PrivProtocol, Vec<u8>)>, // authPriv when set, else authNoPriv
+}
+
+impl UsmUser {
+    pub fn from_passwords(name: &str, auth: AuthProtocol, auth_password: &str, privacy: Option<(PrivProtocol, &str)>) -> Self {
+        Self {
+            name: name.to_string(),
+            auth,
+            auth_key: auth.password_key(auth_password),
+            // The privacy key is derived with the authentication hash
+            privacy: privacy.map(|(protocol, password)| (protocol, auth.password_key(password))),
+        }
+    }
+
+    pub fn localize(&self, engine_id: &[u8]) -> LocalUser {
+        let priv_key = self.privacy.as_ref().map(|(PrivProtocol::Aes128, key)| {
+            let mut aes_key = [0u8; 16];
+            aes_key.copy_from_slice(&self.auth.localize(key, engine_id)[..16]);
+            aes_key
+        });
+        LocalUser {
+            name: self.name.clone(),
+            auth: self.auth,
+            auth_key: self.auth.localize(&self.auth_key, engine_id),
+            priv_key,
+        }
+    }
+}
+
+/// A user whose keys are bound to one agent.
+pub struct LocalUser {
+    pub name: String,
+    auth: AuthProtocol,
+    auth_key: Vec<u8>,
+    priv_key: Option<[u8; 16]>,
+}
+
+impl LocalUser {
+    pub fn has_privacy(&self) -> bool {
+        self.priv_key.is_some()
+    }
+}
+
+/// A user and the target range it is configured for.
+#[derive(Clone, Debug)]
+pub struct Credential {
+    pub scope: ScanTarget,
+    pub user: UsmUser,
+}
+
+/// The agent's SNMP engine: its ID and clock, learned through discovery.
+#[derive(Clone, Debug)]
+pub struct Engine {
+    pub id: Vec<u8>,
+    pub boots: u32,
+    time: u32,
+    synced: Instant,
+}
+
+impl Engine {
+    /// What a discovery request carries: nothing.
+    pub fn unknown() -> Self {
+        Self::new(Vec::new(), 0, 0)
+    }
+
+    pub fn new(id: Vec<u8>, boots: u32, time: u32) -> Self {
+        Self { id, boots, time, synced: Instant::now() }
+    }
+
+    /// The agent's engine time now, extrapolated from the last sync.
+    pub fn time(&self) -> u32 {
+        self.time.saturating_add(self.synced.elapsed().as_secs() as u32)
+    }
+}
+
+/// A decoded v3 message.
+pub struct Incoming {
+    pub engine: Engine,
+    pub pdu: Pdu,
+    pub authenticated: bool, // carried a valid HMAC
+    pub encrypted: bool,
+}
+
+fn iv(boots: u32, time: u32, salt: &[u8]) -> Result<[u8; 16], String> {
+    let salt: [u8; 8] = salt.try_into().map_err(|_| "AES privacy parameters are not 8 bytes")?;
+    let mut iv = [0u8; 16];
+    iv[..4].copy_from_slice(&boots.to_be_bytes());
+    iv[4..8].copy_from_slice(&time.to_be_bytes());
+    iv[8..].copy_from_slice(&salt);
+    Ok(iv)
+}
+
+type Aes128CfbEnc = cfb_mode::Encryptor<aes::Aes128>;
+type Aes128CfbDec = cfb_mode::Decryptor<aes::Aes128>;
+
+/// AES-128 in CFB-128 mode without padding (RFC 3826 3.1.3).
+fn cfb_encrypt(key: &[u8; 16], iv: &[u8; 16], plaintext: &[u8]) -> Vec<u8> {
+    let mut buf = plaintext.to_vec();
+    Aes128CfbEnc::new(key.into(), iv.into()).encrypt(&mut buf);
+    buf
+}
+
+fn cfb_decrypt(key: &[u8; 16], iv: &[u8; 16], ciphertext: &[u8]) -> Vec<u8> {
+    let mut buf = ciphertext.to_vec();
+    Aes128CfbDec::new(key.into(), iv.into()).decrypt(&mut buf);
+    buf
+}
+
+/// Builds a v3 message. Without a user it is the unauthenticated discovery request.
+pub fn encode(msg_id: i32, engine: &Engine, user: Option<&LocalUser>, pdu: &Pdu, salt: u64) -> Vec<u8> {
+    let (boots, time) = (engine.boots, engine.time());
+    let scoped = ber::constructed(ber::SEQUENCE, &[ber::octets(&engine.id), ber::octets(b""), pdu.encode()]);
+
+    let (flags, priv_params, data) = match user {
+        Some(LocalUser { priv_key: Some(key), .. }) => {
+            let salt = salt.to_be_bytes();
+            let iv = iv(boots, time, &salt).expect("salt is 8 bytes");
+            (FLAG_AUTH | FLAG_PRIV | FLAG_REPORTABLE, salt.to_vec(), ber::octets(&cfb_encrypt(key, &iv, &scoped)))
+        }
+        Some(_) => (FLAG_AUTH | FLAG_REPORTABLE, Vec::new(), scoped),
+        None => (FLAG_REPORTABLE, Vec::new(), scoped),
+    };
+
+    let mac_len = user.map_or(0, |u| u.auth.mac_len());
+    let name = user.map_or(&[][..], |u| u.name.as_bytes());
+    let head = [ber::octets(&engine.id), ber::integer(boots as i64), ber::integer(time as i64), ber::octets(name)].concat();
+    let params_content = [head.as_slice(), &ber::octets(&vec![0; mac_len]), &ber::octets(&priv_params)].concat();
+    let params = ber::tlv(ber::SEQUENCE, &params_content);
+    let wrapped_params = ber::octets(&params);
+
+    let version = ber::integer(Version::V3.code());
+    let global = ber::constructed(ber::SEQUENCE, &[
+        ber::integer(msg_id as i64),
+        ber::integer(MAX_MESSAGE_SIZE),
+        ber::octets(&[flags]),
+        ber::integer(USM_SECURITY_MODEL),
+    ]);
+    let content = [version.as_slice(), &global, &wrapped_params, &data].concat();
+    let mut msg = ber::tlv(ber::SEQUENCE, &content);
+
+    // The HMAC covers the whole message with zeros in its own slot
+    if let Some(user) = user {
+        let params_at = (msg.len() - content.len()) + version.len() + global.len() + (wrapped_params.len() - params.len());
+        let auth_at = params_at + (params.len() - params_content.len()) + head.len() + 2;
+        let mac = user.auth.hmac(&user.auth_key, &msg);
+        msg[auth_at..auth_at + mac_len].copy_from_slice(&mac);
+    }
+    msg
+}
+
+/// Decodes a v3 message, checking its HMAC and decrypting it with the user's keys.
+pub fn decode(buf: &[u8], user: Option<&LocalUser>) -> Result<Incoming, String> {
+    let mut msg = Reader::new(buf).sequence(ber::SEQUENCE)?;
+    if msg.integer()? != Version::V3.code() {
+        return Err("Not an SNMPv3 message".into());
+    }
+    let mut global = msg.sequence(ber::SEQUENCE)?;
+    let _msg_id = global.integer()?;
+    let _max_size = global.integer()?;
+    let flags = global.octets()?.first().copied().unwrap_or(0);
+    if global.integer()? != USM_SECURITY_MODEL {
+        return Err("Not a USM message".into());
+    }
+
+    let mut params = Reader::new(msg.octets()?).sequence(ber::SEQUENCE)?;
+    let engine_id = params.octets()?.to_vec();
+    let boots = params.integer()?.clamp(0, u32::MAX as i64) as u32;
+    let time = params.integer()?.clamp(0, u32::MAX as i64) as u32;
+    let _user_name = params.octets()?;
+    let auth_params = params.octets()?;
+    let priv_params = params.octets()?;
+
+    if flags & FLAG_AUTH != 0 {
+        let user = user.ok_or("Authenticated message without a user to check it")?;
+        // auth_params borrows from buf, its offset is where the HMAC sits
+        let at = auth_params.as_ptr() as usize - buf.as_ptr() as usize;
+        let mut zeroed = buf.to_vec();
+        zeroed[at..at + auth_params.len()].fill(0);
+        if !user.auth.verify(&user.auth_key, &zeroed, auth_params) {
+            return Err("Message failed authentication".into());
+        }
+    }
+
+    let decrypted;
+    let mut scoped = if flags & FLAG_PRIV != 0 {
+        let key = user.and_then(|u| u.priv_key.as_ref()).ok_or("Encrypted message without a privacy key")?;
+        decrypted = cfb_decrypt(key, &iv(boots, time, priv_params)?, msg.octets()?);
+        Reader::new(&decrypted).sequence(ber::SEQUENCE).map_err(|_| "Decryption failed, wrong privacy password?")?
+    } else {
+        msg.sequence(ber::SEQUENCE)?
+    };
+    let _context_engine_id = scoped.octets()?;
+    let _context_name = scoped.octets()?;
+    let pdu = Pdu::decode(&mut scoped)?;
+    Ok(Incoming {
+        engine: Engine::new(engine_id, boots, time),
+        pdu,
+        authenticated: flags & FLAG_AUTH != 0,
+        encrypted: flags & FLAG_PRIV != 0,
+    })
+}
+
+/// The usmStats counter a Report PDU carries.
+pub fn report_counter(pdu: &Pdu) -> Option<u32> {
+    let (oid, _) = pdu.varbinds.first()?;
+    oid.suffix(&Oid::new(USM_STATS))?.first().copied()
+}
+
+pub fn describe_report(pdu: &Pdu) -> String {
+    match report_counter(pdu) {
+        Some(1) => "unsupported security level".into(),
+        Some(NOT_IN_TIME_WINDOW) => "not in time window".into(),
+        Some(3) => "unknown user name".into(),
+        Some(4) => "unknown engine ID".into(),
+        Some(5) => "wrong digest, bad authentication password?".into(),
+        Some(6) => "decryption error, bad privacy password?".into(),
+        _ => match pdu.varbinds.first() {
+            Some((oid, _)) => format!("report {}", oid),
+            None => "empty report".into(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ber::Value;
+    use super::super::message::PduType;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_key_localization() {
+        // RFC 3414 A.3.2
+        let ku = AuthProtocol::Sha1.password_key("maplesyrup");
+        assert_eq!(ku, hex("9fb5cc0381497b3793528939ff788d5d79145211"));
+        let kul = AuthProtocol::Sha1.localize(&ku, &hex("000000000000000000000002"));
+        assert_eq!(kul, hex("6695febc9288e36282235fc7151f128497b38f3f"));
+    }
+
+    #[test]
+    fn test_cfb() {
+        // SP 800-38A F.3.13, plus a partial final block
+        let key: [u8; 16] = hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap();
+        let iv: [u8; 16] = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+        let plaintext = hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+        let ciphertext = cfb_encrypt(&key, &iv, &plaintext);
+        assert_eq!(ciphertext, hex("3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b"));
+        assert_eq!(cfb_decrypt(&key, &iv, &ciphertext), plaintext);
+
+        let partial = cfb_encrypt(&key, &iv, &plaintext[..21]);
+        assert_eq!(partial, ciphertext[..21]);
+        assert_eq!(cfb_decrypt(&key, &iv, &partial), plaintext[..21]);
+    }
+
+    #[test]
+    fn test_auth_priv_roundtrip() {
+        let user = UsmUser::from_passwords("monitor", AuthProtocol::Sha1, "authpass123", Some((PrivProtocol::Aes128, "privpass123")));
+        let engine = Engine::new(hex("80001f8880e9bd0c1d12667a5100000000"), 7, 1234);
+        let local = user.localize(&engine.id);
+        let pdu = Pdu::get(42, &[Oid::parse("1.3.6.1.2.1.1.5.0").unwrap()]);
+
+        let msg = encode(42, &engine, Some(&local), &pdu, 0x0102_0304_0506_0708);
+        // Encrypted: the OID does not show in the clear
+        assert!(!msg.windows(3).any(|w| w == [0x2b, 0x06, 0x01]));
+        let incoming = decode(&msg, Some(&local)).unwrap();
+        assert_eq!(incoming.pdu, pdu);
+        assert_eq!((incoming.engine.id, incoming.engine.boots), (engine.id.clone(), 7));
+
+        let mut tampered = msg.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0x01;
+        assert_eq!(decode(&tampered, Some(&local)).err().as_deref(), Some("Message failed authentication"));
+
+        let other = UsmUser::from_passwords("monitor", AuthProtocol::Sha256, "authpass123", None).localize(&engine.id);
+        assert!(decode(&msg, Some(&other)).is_err());
+    }
+
+    #[test]
+    fn test_discovery_report() {
+        let request = encode(1, &Engine::unknown(), None, &Pdu::get(1, &[]), 0);
+        assert_eq!(decode(&request, None).unwrap().engine.id, Vec::<u8>::new());
+
+        // The agent's answer: a Report of usmStatsUnknownEngineIDs with its engine ID and clock
+        let report = Pdu {
+            kind: PduType::Report,
+            request_id: 1,
+            error_status: 0,
+            error_index: 0,
+            varbinds: vec![(Oid::new(USM_STATS).child(&[4, 0]), Value::Counter32(3))],
+        };
+        let engine = Engine::new(hex("8000000903000c29aabbcc"), 12, 99_000);
+        let incoming = decode(&encode(1, &engine, None, &report, 0), None).unwrap();
+        assert_eq!(incoming.engine.boots, 12);
+        assert_eq!(incoming.engine.id, engine.id);
+        assert_eq!(describe_report(&incoming.pdu), "unknown engine ID");
+    }
+}
@@ -0,0 +1,171 @@
+use std::mem::MaybeUninit;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use socket2::{Socket, Domain, Type, Protocol};
+use tokio::net::TcpStream;
+
+const SYN: u8 = 0x02;
+const ACK: u8 = 0x10;
+
+/// TCP/IP stack traits of a host, the raw material for OS fingerprinting.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StackTraits {
+    pub ttl: Option<u8>,         // as received, see `initial_ttl()`
+    pub df: Option<bool>,        // IP "don't fragment" bit
+    pub window: Option<u16>,     // SYN-ACK window size
+    pub mss: Option<u16>,
+    pub wscale: Option<u8>,
+    pub options: Vec<&'static str>, // option layout in wire order, e.g. ["mss","sok","ts","nop","ws"]
+}
+
+impl StackTraits {
+    /// Traits we get from an ICMP echo reply alone.
+    pub fn from_ttl(ttl: Option<u8>, df: Option<bool>) -> Self {
+        Self { ttl, df, ..Self::default() }
+    }
+
+    /// Stacks start at 32, 64, 128 or 255, every router on the way takes one off.
+    pub fn initial_ttl(&self) -> Option<u8> {
+        let ttl = self.ttl?;
+        [32u8, 64, 128, 255].into_iter().find(|&i| ttl <= i)
+    }
+
+    pub fn has_tcp(&self) -> bool {
+        self.window.is_some()
+    }
+
+    /// Fills in what the other observation was missing (e.g. DF from ICMP, options from TCP).
+    pub fn merge(mut self, other: StackTraits) -> Self {
+        self.ttl = self.ttl.or(other.ttl);
+        self.df = self.df.or(other.df);
+        if !self.has_tcp() {
+            self.window = other.window;
+            self.mss = other.mss;
+            self.wscale = other.wscale;
+            self.options = other.options;
+        }
+        self
+    }
+}
+
+/// Opens a normal connection to an open port and captures the SYN-ACK on a
+/// raw TCP socket. Needs CAP_NET_RAW / Administrator, returns `None` otherwise.
+pub async fn probe_syn_ack(ip: Ipv4Addr, port: u16, timeout: Duration) -> Option<StackTraits> {
+    // The raw socket has to exist before the SYN-ACK arrives
+    let socket = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::TCP)).ok()?;
+    socket.set_read_timeout(Some(Duration::from_millis(50))).ok()?;
+
+    let capture = tokio::task::spawn_blocking(move || {
+        let deadline = Instant::now() + timeout;
+        let mut buf = [MaybeUninit::<u8>::uninit(); 1500];
+        while Instant::now() < deadline {
+            let Ok(n) = socket.recv(&mut buf) else { continue };
+            // Safety: recv initialized the first n bytes
+            let packet: Vec<u8> = buf[..n].iter().map(|b| unsafe { b.assume_init() }).collect();
+            if let Some((from, src_port, traits)) = parse_syn_ack(&packet) {
+                if from == ip && src_port == port {
+                    return Some(traits);
+                }
+            }
+        }
+        None
+    });
+
+    let _ = tokio::time::timeout(timeout, TcpStream::connect(SocketAddr::new(ip.into(), port))).await;
+    capture.await.ok().flatten()
+}
+
+/// Source address, source port and stack traits of a raw IPv4 SYN-ACK.
+pub fn parse_syn_ack(packet: &[u8]) -> Option<(Ipv4Addr, u16, StackTraits)> {
+    if packet.len() < 20 || packet[0] >> 4 != 4 || packet[9] != 6 {
+        return None;
+    }
+    let ihl = ((packet[0] & 0x0f) as usize) * 4;
+    let tcp = packet.get(ihl..)?;
+    if tcp.len() < 20 || tcp[13] & (SYN | ACK) != (SYN | ACK) {
+        return None;
+    }
+
+    let from = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    let src_port = u16::from_be_bytes([tcp[0], tcp[1]]);
+    let data_offset = ((tcp[12] >> 4) as usize) * 4;
+
+    let mut traits = StackTraits {
+        ttl: Some(packet[8]),
+        df: Some(packet[6] & 0x40 != 0),
+        window: Some(u16::from_be_bytes([tcp[14], tcp[15]])),
+        ..StackTraits::default()
+    };
+    parse_options(tcp.get(20..data_offset.max(20))?, &mut traits);
+
+    Some((from, src_port, traits))
+}
+
+fn parse_options(mut opts: &[u8], traits: &mut StackTraits) {
+    while let Some(&kind) = opts.first() {
+        match kind {
+            0 => { traits.options.push("eol"); break; }
+            1 => { traits.options.push("nop"); opts = &opts[1..]; continue; }
+            _ => {}
+        }
+        let len = opts.get(1).copied().unwrap_or(0) as usize;
+        if len < 2 || len > opts.len() {
+            break; // malformed
+        }
+        let body = &opts[2..len];
+        match kind {
+            2 if body.len() == 2 => {
+                traits.options.push("mss");
+                traits.mss = Some(u16::from_be_bytes([body[0], body[1]]));
+            }
+            3 if body.len() == 1 => {
+                traits.options.push("ws");
+                traits.wscale = Some(body[0]);
+            }
+            4 => traits.options.push("sok"),
+            5 => traits.options.push("sack"),
+            8 => traits.options.push("ts"),
+            _ => traits.options.push("?"),
+        }
+        opts = &opts[len..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Linux SYN-ACK from 192.168.1.20:22, TTL 64, DF, window 65160, mss 1460, sok, ts, nop, ws 7
+    const LINUX_SYN_ACK: &[u8] = &[
+        0x45, 0x00, 0x00, 0x3c, 0x00, 0x00, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00,
+        192, 168, 1, 20, 192, 168, 1, 10,
+        0x00, 0x16, 0xc3, 0x50, 0, 0, 0, 1, 0, 0, 0, 1, 0xa0, 0x12, 0xfe, 0x88, 0x00, 0x00, 0x00, 0x00,
+        0x02, 0x04, 0x05, 0xb4, 0x04, 0x02, 0x08, 0x0a, 0, 0, 0, 1, 0, 0, 0, 1, 0x01, 0x03, 0x03, 0x07,
+    ];
+
+    #[test]
+    fn test_parse_syn_ack() {
+        let (from, port, traits) = parse_syn_ack(LINUX_SYN_ACK).unwrap();
+        assert_eq!(from, Ipv4Addr::new(192, 168, 1, 20));
+        assert_eq!(port, 22);
+        assert_eq!(traits.ttl, Some(64));
+        assert_eq!(traits.df, Some(true));
+        assert_eq!(traits.window, Some(65160));
+        assert_eq!(traits.mss, Some(1460));
+        assert_eq!(traits.wscale, Some(7));
+        assert_eq!(traits.options, vec!["mss", "sok", "ts", "nop", "ws"]);
+
+        // A bare SYN is not a SYN-ACK
+        let mut syn = LINUX_SYN_ACK.to_vec();
+        syn[33] = SYN;
+        assert!(parse_syn_ack(&syn).is_none());
+    }
+
+    #[test]
+    fn test_initial_ttl() {
+        assert_eq!(StackTraits::from_ttl(Some(57), None).initial_ttl(), Some(64));
+        assert_eq!(StackTraits::from_ttl(Some(116), None).initial_ttl(), Some(128));
+        assert_eq!(StackTraits::from_ttl(Some(250), None).initial_ttl(), Some(255));
+        assert_eq!(StackTraits::default().initial_ttl(), None);
+    }
+}
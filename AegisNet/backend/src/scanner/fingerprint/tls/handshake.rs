@@ -0,0 +1,450 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+pub const SSL3: u16 = 0x0300;
+pub const TLS10: u16 = 0x0301;
+pub const TLS11: u16 = 0x0302;
+pub const TLS12: u16 = 0x0303;
+pub const TLS13: u16 = 0x0304;
+pub const VERSIONS: [u16; 5] = [SSL3, TLS10, TLS11, TLS12, TLS13];
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+// Certificate chains rarely pass 16K; a server sending more is not worth reading
+const MAX_RESPONSE: usize = 64 * 1024;
+
+// Record content types
+const CHANGE_CIPHER_SPEC: u8 = 20;
+const ALERT: u8 = 21;
+const HANDSHAKE: u8 = 22;
+const APPLICATION_DATA: u8 = 23;
+
+// Handshake message types
+const CLIENT_HELLO: u8 = 1;
+const SERVER_HELLO: u8 = 2;
+const CERTIFICATE: u8 = 11;
+const SERVER_HELLO_DONE: u8 = 14;
+
+// Extensions
+const SUPPORTED_GROUPS: u16 = 0x000a;
+const EC_POINT_FORMATS: u16 = 0x000b;
+const SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const ALPN: u16 = 0x0010;
+const EXTENDED_MASTER_SECRET: u16 = 0x0017;
+const SESSION_TICKET: u16 = 0x0023;
+const SUPPORTED_VERSIONS: u16 = 0x002b;
+const PSK_KEY_EXCHANGE_MODES: u16 = 0x002d;
+const KEY_SHARE: u16 = 0x0033;
+
+const X25519: u16 = 0x001d;
+// x25519, secp256r1, secp384r1, secp521r1
+const GROUPS: [u16; 4] = [X25519, 0x0017, 0x0018, 0x0019];
+// ECDSA, RSA-PSS, RSA PKCS#1 and their SHA-1 forms, Ed25519
+const SIGNATURE_SCHEMES: [u16; 12] = [0x0403, 0x0503, 0x0603, 0x0804, 0x0805, 0x0806, 0x0401, 0x0501, 0x0601, 0x0203, 0x0201, 0x0807];
+const RENEGOTIATION_INFO_SCSV: u16 = 0x00ff;
+
+pub const TLS13_SUITES: [u16; 3] = [0x1301, 0x1302, 0x1303];
+
+/// The suites a hello offers, strongest first. Names are the IANA registry's.
+pub const SUITES: &[(u16, &str)] = &[
+    (0x1301, "TLS_AES_128_GCM_SHA256"),
+    (0x1302, "TLS_AES_256_GCM_SHA384"),
+    (0x1303, "TLS_CHACHA20_POLY1305_SHA256"),
+    (0xc02b, "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256"),
+    (0xc02c, "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384"),
+    (0xc02f, "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"),
+    (0xc030, "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"),
+    (0xcca9, "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xcca8, "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256"),
+    (0x009e, "TLS_DHE_RSA_WITH_AES_128_GCM_SHA256"),
+    (0x009f, "TLS_DHE_RSA_WITH_AES_256_GCM_SHA384"),
+    (0xccaa, "TLS_DHE_RSA_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xc023, "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256"),
+    (0xc024, "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384"),
+    (0xc027, "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256"),
+    (0xc028, "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384"),
+    (0xc009, "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA"),
+    (0xc00a, "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA"),
+    (0xc013, "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA"),
+    (0xc014, "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA"),
+    (0x0067, "TLS_DHE_RSA_WITH_AES_128_CBC_SHA256"),
+    (0x006b, "TLS_DHE_RSA_WITH_AES_256_CBC_SHA256"),
+    (0x0033, "TLS_DHE_RSA_WITH_AES_128_CBC_SHA"),
+    (0x0039, "TLS_DHE_RSA_WITH_AES_256_CBC_SHA"),
+    (0x009c, "TLS_RSA_WITH_AES_128_GCM_SHA256"),
+    (0x009d, "TLS_RSA_WITH_AES_256_GCM_SHA384"),
+    (0x003c, "TLS_RSA_WITH_AES_128_CBC_SHA256"),
+    (0x003d, "TLS_RSA_WITH_AES_256_CBC_SHA256"),
+    (0x002f, "TLS_RSA_WITH_AES_128_CBC_SHA"),
+    (0x0035, "TLS_RSA_WITH_AES_256_CBC_SHA"),
+    (0x0041, "TLS_RSA_WITH_CAMELLIA_128_CBC_SHA"),
+    (0x0084, "TLS_RSA_WITH_CAMELLIA_256_CBC_SHA"),
+    (0xc012, "TLS_ECDHE_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0x0016, "TLS_DHE_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0x000a, "TLS_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0xc011, "TLS_ECDHE_RSA_WITH_RC4_128_SHA"),
+    (0xc007, "TLS_ECDHE_ECDSA_WITH_RC4_128_SHA"),
+    (0x0005, "TLS_RSA_WITH_RC4_128_SHA"),
+    (0x0004, "TLS_RSA_WITH_RC4_128_MD5"),
+    (0x0015, "TLS_DHE_RSA_WITH_DES_CBC_SHA"),
+    (0x0009, "TLS_RSA_WITH_DES_CBC_SHA"),
+    (0x0003, "TLS_RSA_EXPORT_WITH_RC4_40_MD5"),
+    (0x0008, "TLS_RSA_EXPORT_WITH_DES40_CBC_SHA"),
+    (0x0014, "TLS_DHE_RSA_EXPORT_WITH_DES40_CBC_SHA"),
+    (0x0018, "TLS_DH_anon_WITH_RC4_128_MD5"),
+    (0x0034, "TLS_DH_anon_WITH_AES_128_CBC_SHA"),
+    (0xc018, "TLS_ECDH_anon_WITH_AES_128_CBC_SHA"),
+    (0x0001, "TLS_RSA_WITH_NULL_MD5"),
+    (0x0002, "TLS_RSA_WITH_NULL_SHA"),
+    (0x003b, "TLS_RSA_WITH_NULL_SHA256"),
+    (0xc010, "TLS_ECDHE_RSA_WITH_NULL_SHA"),
+];
+
+pub fn version_name(version: u16) -> String {
+    match version {
+        SSL3 => "SSLv3".into(),
+        TLS10 => "TLSv1.0".into(),
+        TLS11 => "TLSv1.1".into(),
+        TLS12 => "TLSv1.2".into(),
+        TLS13 => "TLSv1.3".into(),
+        other => format!("0x{:04x}", other),
+    }
+}
+
+pub fn suite_name(id: u16) -> String {
+    SUITES.iter().find(|(s, _)| *s == id).map_or_else(|| format!("0x{:04x}", id), |(_, name)| name.to_string())
+}
+
+/// Broken or unauthenticated: RC4, (3)DES, export grade, anonymous, NULL, MD5 MACs.
+pub fn is_weak(suite: &str) -> bool {
+    ["RC4", "DES", "EXPORT", "anon", "NULL", "MD5"].iter().any(|w| suite.contains(w))
+}
+
+/// The suites worth offering at `version`.
+pub fn suites_for(version: u16) -> Vec<u16> {
+    SUITES.iter().map(|(id, _)| *id).filter(|id| (version >= TLS13) == TLS13_SUITES.contains(id)).collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct ClientHello {
+    pub min_version: u16,
+    pub max_version: u16,
+    pub ciphers: Vec<u16>,
+    pub alpn: Vec<&'static str>,
+}
+
+impl ClientHello {
+    /// Offers exactly one version.
+    pub fn pinned(version: u16, ciphers: Vec<u16>) -> Self {
+        Self { min_version: version, max_version: version, ciphers, alpn: Vec::new() }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let random = || uuid::Uuid::new_v4().into_bytes();
+        let legacy_version = self.max_version.min(TLS12);
+
+        let mut body = legacy_version.to_be_bytes().to_vec();
+        body.extend(random());
+        body.extend(random());
+        body.push(0); // no session to resume
+        let ciphers: Vec<u8> = self.ciphers.iter().chain([&RENEGOTIATION_INFO_SCSV]).flat_map(|c| c.to_be_bytes()).collect();
+        push_u16_prefixed(&mut body, &ciphers);
+        body.extend([1, 0]); // null compression
+
+        // SSLv3 predates extensions
+        if self.max_version > SSL3 {
+            let mut extensions = Vec::new();
+            let groups: Vec<u8> = GROUPS.iter().flat_map(|g| g.to_be_bytes()).collect();
+            push_extension(&mut extensions, SUPPORTED_GROUPS, &u16_prefixed(&groups));
+            push_extension(&mut extensions, EC_POINT_FORMATS, &[1, 0]);
+            let schemes: Vec<u8> = SIGNATURE_SCHEMES.iter().flat_map(|s| s.to_be_bytes()).collect();
+            push_extension(&mut extensions, SIGNATURE_ALGORITHMS, &u16_prefixed(&schemes));
+            if !self.alpn.is_empty() {
+                let protocols: Vec<u8> = self.alpn.iter().flat_map(|p| std::iter::once(p.len() as u8).chain(p.bytes())).collect();
+                push_extension(&mut extensions, ALPN, &u16_prefixed(&protocols));
+            }
+            push_extension(&mut extensions, EXTENDED_MASTER_SECRET, &[]);
+            push_extension(&mut extensions, SESSION_TICKET, &[]);
+            if self.max_version >= TLS13 {
+                let versions: Vec<u8> = (self.min_version.max(TLS10)..=self.max_version).rev().flat_map(|v| v.to_be_bytes()).collect();
+                let mut data = vec![versions.len() as u8];
+                data.extend(versions);
+                push_extension(&mut extensions, SUPPORTED_VERSIONS, &data);
+                push_extension(&mut extensions, PSK_KEY_EXCHANGE_MODES, &[1, 1]); // psk_dhe_ke
+                // Any 32 bytes are an x25519 public key; the handshake stops before it matters
+                let mut share = X25519.to_be_bytes().to_vec();
+                share.extend([0, 32]);
+                share.extend(random());
+                share.extend(random());
+                push_extension(&mut extensions, KEY_SHARE, &u16_prefixed(&share));
+            }
+            push_u16_prefixed(&mut body, &extensions);
+        }
+
+        let mut handshake = vec![CLIENT_HELLO];
+        handshake.extend(&(body.len() as u32).to_be_bytes()[1..]);
+        handshake.extend(body);
+        // Old servers choke on a record version above TLS 1.0
+        let record_version = if self.max_version == SSL3 { SSL3 } else { TLS10 };
+        let mut record = vec![HANDSHAKE];
+        record.extend(record_version.to_be_bytes());
+        push_u16_prefixed(&mut record, &handshake);
+        record
+    }
+}
+
+fn u16_prefixed(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 2);
+    push_u16_prefixed(&mut out, data);
+    out
+}
+
+fn push_u16_prefixed(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend((data.len() as u16).to_be_bytes());
+    out.extend(data);
+}
+
+fn push_extension(out: &mut Vec<u8>, kind: u16, data: &[u8]) {
+    out.extend(kind.to_be_bytes());
+    push_u16_prefixed(out, data);
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ServerHello {
+    pub legacy_version: u16,
+    pub version: u16, // supported_versions wins over the legacy field
+    pub cipher: u16,
+    pub extensions: Vec<u16>, // in the server's order
+    pub alpn: Option<String>,
+    pub certificates: Vec<Vec<u8>>, // DER, leaf first; TLS 1.3 encrypts them
+}
+
+#[derive(Clone, Debug)]
+pub enum Response {
+    Hello(ServerHello),
+    Alert(u8), // description, e.g. 40 handshake_failure, 70 protocol_version
+}
+
+/// Sends `hello` and reads the server's flight in the clear: up to ServerHelloDone,
+/// or just the ServerHello once TLS 1.3 is chosen. `None` if the port doesn't speak TLS.
+pub async fn exchange(addr: SocketAddr, hello: &ClientHello) -> Option<Response> {
+    let mut stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await.ok()?.ok()?;
+    stream.write_all(&hello.encode()).await.ok()?;
+
+    let deadline = Instant::now() + READ_TIMEOUT;
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 4096];
+    loop {
+        let n = match tokio::time::timeout_at(deadline, stream.read(&mut buffer)).await {
+            Ok(Ok(n)) => n,
+            _ => 0,
+        };
+        data.extend_from_slice(&buffer[..n]);
+        let (response, done) = parse(&data)?;
+        if done || n == 0 || data.len() >= MAX_RESPONSE {
+            return response;
+        }
+    }
+}
+
+/// What the server has said so far and whether that's all we need. `None` on non-TLS data.
+pub fn parse(data: &[u8]) -> Option<(Option<Response>, bool)> {
+    let mut records = Cursor(data);
+    let mut handshake = Vec::new();
+    let mut alert = None;
+    let mut ended = false;
+    while records.0.len() >= 5 {
+        let (kind, version) = (records.u8()?, records.u16()?);
+        if version >> 8 != 3 {
+            return None;
+        }
+        let Some(fragment) = records.u16_prefixed() else { break }; // partial record
+        match kind {
+            HANDSHAKE => handshake.extend_from_slice(fragment),
+            ALERT => {
+                alert = fragment.get(1).copied();
+                ended = true;
+                break;
+            }
+            // Everything after is encrypted
+            CHANGE_CIPHER_SPEC | APPLICATION_DATA => {
+                ended = true;
+                break;
+            }
+            _ => return None,
+        }
+    }
+
+    let mut messages = Cursor(&handshake);
+    let mut hello: Option<ServerHello> = None;
+    while let Some((kind, body)) = messages.message() {
+        match (kind, hello.as_mut()) {
+            (SERVER_HELLO, None) => {
+                let parsed = server_hello(body)?;
+                ended |= parsed.version >= TLS13;
+                hello = Some(parsed);
+            }
+            (CERTIFICATE, Some(hello)) => hello.certificates = certificates(body).unwrap_or_default(),
+            (SERVER_HELLO_DONE, Some(_)) => ended = true,
+            (_, None) => return None, // a server speaks first with its hello
+            _ => {}
+        }
+    }
+
+    let response = hello.map(Response::Hello).or(alert.map(Response::Alert));
+    Some((response, ended))
+}
+
+fn server_hello(body: &[u8]) -> Option<ServerHello> {
+    let mut hello = Cursor(body);
+    let legacy_version = hello.u16()?;
+    hello.take(32)?; // random
+    let session = hello.u8()? as usize;
+    hello.take(session)?;
+    let cipher = hello.u16()?;
+    hello.u8()?; // compression
+
+    let mut parsed = ServerHello { legacy_version, version: legacy_version, cipher, ..Default::default() };
+    let mut extensions = Cursor(hello.u16_prefixed().unwrap_or_default());
+    while !extensions.0.is_empty() {
+        let kind = extensions.u16()?;
+        let mut data = Cursor(extensions.u16_prefixed()?);
+        parsed.extensions.push(kind);
+        match kind {
+            SUPPORTED_VERSIONS => parsed.version = data.u16()?,
+            ALPN => {
+                let mut protocols = Cursor(data.u16_prefixed()?);
+                let len = protocols.u8()? as usize;
+                parsed.alpn = Some(String::from_utf8_lossy(protocols.take(len)?).into_owned());
+            }
+            _ => {}
+        }
+    }
+    Some(parsed)
+}
+
+fn certificates(body: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut list = Cursor(Cursor(body).u24_prefixed()?);
+    let mut chain = Vec::new();
+    while !list.0.is_empty() {
+        chain.push(list.u24_prefixed()?.to_vec());
+    }
+    Some(chain)
+}
+
+/// Big-endian reads off the front of a buffer.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u16_prefixed(&mut self) -> Option<&'a [u8]> {
+        let len = Cursor(self.0).u16()? as usize;
+        if self.0.len() < 2 + len {
+            return None;
+        }
+        self.take(2)?;
+        self.take(len)
+    }
+
+    fn u24_prefixed(&mut self) -> Option<&'a [u8]> {
+        let len = self.0.get(..3)?.iter().fold(0usize, |acc, b| acc << 8 | *b as usize);
+        if self.0.len() < 3 + len {
+            return None;
+        }
+        self.take(3)?;
+        self.take(len)
+    }
+
+    /// A complete handshake message: type and body.
+    fn message(&mut self) -> Option<(u8, &'a [u8])> {
+        let kind = *self.0.first()?;
+        let mut rest = Cursor(&self.0[1..]);
+        let body = rest.u24_prefixed()?;
+        self.0 = rest.0;
+        Some((kind, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(kind: u8, fragment: &[u8]) -> Vec<u8> {
+        let mut out = vec![kind, 3, 3];
+        push_u16_prefixed(&mut out, fragment);
+        out
+    }
+
+    fn message(kind: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![kind];
+        out.extend(&(body.len() as u32).to_be_bytes()[1..]);
+        out.extend(body);
+        out
+    }
+
+    #[test]
+    fn test_client_hello() {
+        let hello = ClientHello { min_version: TLS12, max_version: TLS13, ciphers: vec![0x1301, 0xc02f], alpn: vec!["h2"] };
+        let encoded = hello.encode();
+        assert_eq!(&encoded[..3], &[HANDSHAKE, 3, 1]);
+        assert_eq!(u16::from_be_bytes([encoded[3], encoded[4]]) as usize, encoded.len() - 5);
+        let body = &encoded[9..];
+        assert_eq!(&body[..2], &[3, 3]); // legacy version caps at TLS 1.2
+        assert_eq!(&body[35..43], &[0, 6, 0x13, 0x01, 0xc0, 0x2f, 0x00, 0xff]);
+        // supported_versions: 1.3 then 1.2
+        let versions = [0x00, 0x2b, 0, 5, 4, 3, 4, 3, 3];
+        assert!(encoded.windows(versions.len()).any(|w| w == versions));
+
+        let ssl3 = ClientHello::pinned(SSL3, vec![0x000a]).encode();
+        assert_eq!(&ssl3[..3], &[HANDSHAKE, 3, 0]);
+        assert_eq!(ssl3.len(), 5 + 4 + 2 + 32 + 1 + 2 + 4 + 2); // no extensions
+    }
+
+    #[test]
+    fn test_parse() {
+        // TLS 1.2 ServerHello with ALPN and renegotiation_info, then Certificate and ServerHelloDone
+        let mut hello = vec![3, 3];
+        hello.extend([0xaa; 32]);
+        hello.extend([0, 0xc0, 0x2f, 0]);
+        let extensions = [0xff, 0x01, 0, 1, 0, 0x00, 0x10, 0, 5, 0, 3, 2, b'h', b'2'];
+        push_u16_prefixed(&mut hello, &extensions);
+        let certificate = message(CERTIFICATE, &[0, 0, 6, 0, 0, 3, 0x30, 1, 0]);
+
+        let mut flight = record(HANDSHAKE, &message(SERVER_HELLO, &hello));
+        flight.extend(record(HANDSHAKE, &certificate[..5]));
+        let (response, done) = parse(&flight).unwrap();
+        assert!(!done);
+        assert!(matches!(response, Some(Response::Hello(_))));
+
+        flight.extend(record(HANDSHAKE, &[&certificate[5..], &message(SERVER_HELLO_DONE, &[])[..]].concat()));
+        let (Some(Response::Hello(parsed)), true) = parse(&flight).unwrap() else { panic!("incomplete flight") };
+        assert_eq!((parsed.version, parsed.cipher), (TLS12, 0xc02f));
+        assert_eq!(parsed.extensions, [0xff01, ALPN]);
+        assert_eq!(parsed.alpn.as_deref(), Some("h2"));
+        assert_eq!(parsed.certificates, [vec![0x30, 1, 0]]);
+
+        let (Some(Response::Alert(70)), true) = parse(&record(ALERT, &[2, 70])).unwrap() else { panic!("no alert") };
+        assert!(parse(b"HTTP/1.1 400 Bad Request\r\n").is_none());
+
+        assert!(is_weak("TLS_RSA_WITH_3DES_EDE_CBC_SHA") && !is_weak("TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"));
+    }
+}
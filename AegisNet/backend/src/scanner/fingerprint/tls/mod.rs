@@ -0,0 +1,391 @@
+pub mod handshake;
+pub mod x509;
+
+use chrono::{DateTime, Utc};
+use md5::Md5;
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::net::TcpStream;
+use crate::scanner::vuln::db::Vulnerability;
+use handshake::{ClientHello, Response, ServerHello, SSL3, TLS10, TLS11, TLS12, TLS13};
+use x509::Certificate;
+
+// For the rustls handshake that fetches a TLS 1.3 server's chain
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+const ALPN: [&str; 2] = ["h2", "http/1.1"];
+
+// JARM-style probes: (lowest, highest version, suites reversed, ALPN)
+const JARM_PROBES: [(u16, u16, bool, bool); 8] = [
+    (TLS12, TLS12, false, true),
+    (TLS12, TLS12, true, true),
+    (TLS12, TLS12, false, false),
+    (TLS11, TLS11, false, true),
+    (TLS10, TLS10, false, true),
+    (TLS12, TLS13, false, true),
+    (TLS12, TLS13, true, true),
+    (TLS13, TLS13, false, false),
+];
+
+/// A protocol version the server accepted and the suites it picked, in its order of preference.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Protocol {
+    pub version: String, // TLSv1.2
+    pub ciphers: Vec<String>,
+}
+
+/// What a TLS port gave away during handshakes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TlsInfo {
+    pub protocols: Vec<Protocol>,
+    pub alpn: Option<String>,
+    pub ja3s: String, // MD5 over the ServerHello to a modern client hello
+    pub jarm: String, // our own probe set; not comparable with published JARM hashes
+    pub chain: Vec<Certificate>, // leaf first
+}
+
+impl TlsInfo {
+    pub fn versions(&self) -> impl Iterator<Item = &str> {
+        self.protocols.iter().map(|p| p.version.as_str())
+    }
+
+    pub fn weak_ciphers(&self) -> Vec<&str> {
+        let mut weak: Vec<&str> = self.protocols.iter()
+            .flat_map(|p| p.ciphers.iter().map(String::as_str))
+            .filter(|c| handshake::is_weak(c))
+            .collect();
+        weak.sort_unstable();
+        weak.dedup();
+        weak
+    }
+
+    /// `TLSv1.2, TLSv1.3 | Subject: example.com | Issuer: R11 | Expires: 2026-01-01`
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self.versions().collect::<Vec<_>>().join(", ")];
+        if let Some(leaf) = self.chain.first() {
+            parts.push(format!("Subject: {}", leaf.common_name()));
+            parts.push(if leaf.self_signed { "Self-signed".into() } else {
+                let issuer = leaf.issuer.split(", ").find_map(|rdn| rdn.strip_prefix("CN=")).unwrap_or(&leaf.issuer);
+                format!("Issuer: {}", issuer)
+            });
+            parts.push(format!("Expires: {}", leaf.not_after.format("%Y-%m-%d")));
+        }
+        parts.join(" | ")
+    }
+
+    /// Expired, self-signed or weak certificates and deprecated protocols or suites.
+    pub fn findings(&self, now: DateTime<Utc>) -> Vec<Vulnerability> {
+        let finding = |id: &str, cvss: f32, description: String, url: &str| Vulnerability {
+            id: id.into(),
+            cvss,
+            severity: if cvss >= 7.0 { "HIGH".into() } else { "MEDIUM".into() },
+            description,
+            url: url.into(),
+        };
+        let mut findings = Vec::new();
+
+        if let Some(leaf) = self.chain.first() {
+            if leaf.expired(now) {
+                let when = if now < leaf.not_before { "not valid before" } else { "expired" };
+                let date = if now < leaf.not_before { leaf.not_before } else { leaf.not_after };
+                findings.push(finding("AUDIT-TLS-EXPIRED", 5.3,
+                    format!("Certificate for {} {} {}", leaf.common_name(), when, date.format("%Y-%m-%d")),
+                    "https://cwe.mitre.org/data/definitions/298.html"));
+            }
+            if leaf.self_signed {
+                findings.push(finding("AUDIT-TLS-SELF-SIGNED", 4.8,
+                    format!("Self-signed certificate for {}", leaf.common_name()),
+                    "https://cwe.mitre.org/data/definitions/295.html"));
+            }
+        }
+        if let Some(cert) = self.chain.iter().find(|c| c.weak_key()) {
+            findings.push(finding("AUDIT-TLS-WEAK-KEY", 5.9,
+                format!("{}-bit {} key in certificate for {}", cert.key_bits, cert.key_type, cert.common_name()),
+                "https://cwe.mitre.org/data/definitions/326.html"));
+        }
+        // A trust anchor's own signature is never checked
+        let root = self.chain.len() > 1 && self.chain.last().is_some_and(|c| c.self_signed);
+        let signed = &self.chain[..self.chain.len() - root as usize];
+        if let Some(cert) = signed.iter().find(|c| c.weak_signature()) {
+            findings.push(finding("AUDIT-TLS-WEAK-SIGNATURE", 5.9,
+                format!("Certificate for {} signed with {}", cert.common_name(), cert.signature_algorithm),
+                "https://cwe.mitre.org/data/definitions/328.html"));
+        }
+
+        let weak = self.weak_ciphers();
+        if !weak.is_empty() {
+            findings.push(finding("AUDIT-TLS-WEAK-CIPHER", 5.9,
+                format!("Weak cipher suites accepted: {}", weak.join(", ")),
+                "https://cwe.mitre.org/data/definitions/327.html"));
+        }
+        let deprecated: Vec<&str> = self.versions().filter(|v| ["SSLv3", "TLSv1.0", "TLSv1.1"].contains(v)).collect();
+        if !deprecated.is_empty() {
+            // POODLE makes SSLv3 a practical attack rather than a policy issue
+            let cvss = if deprecated.contains(&"SSLv3") { 7.5 } else { 5.3 };
+            findings.push(finding("AUDIT-TLS-DEPRECATED-PROTOCOL", cvss,
+                format!("Deprecated protocols accepted: {}", deprecated.join(", ")),
+                "https://www.rfc-editor.org/rfc/rfc8996"));
+        }
+        findings
+    }
+}
+
+/// Handshakes with a TLS port: every version and the suites each accepts, the
+/// certificate chain, a JA3S and a JARM-style fingerprint. `None` if it doesn't speak TLS.
+pub async fn probe(ip: IpAddr, port: u16) -> Option<TlsInfo> {
+    let addr = SocketAddr::new(ip, port);
+    // What a current browser would offer
+    let mut modern = ClientHello { min_version: TLS10, max_version: TLS13, ciphers: handshake::SUITES.iter().map(|(id, _)| *id).collect(), alpn: ALPN.to_vec() };
+    let first = match handshake::exchange(addr, &modern).await? {
+        Response::Hello(hello) => Some(hello),
+        // TLS, but nothing in common with a modern client
+        Response::Alert(description) => {
+            tracing::debug!("{} refused a modern client hello with alert {}", addr, description);
+            None
+        }
+    };
+
+    let mut protocols = Vec::new();
+    let mut certificates = Vec::new();
+    for version in handshake::VERSIONS {
+        let hellos = accepted(addr, version).await;
+        if hellos.is_empty() {
+            continue;
+        }
+        if certificates.is_empty() {
+            certificates = hellos.iter().map(|h| h.certificates.clone()).find(|c| !c.is_empty()).unwrap_or_default();
+        }
+        protocols.push(Protocol {
+            version: handshake::version_name(version),
+            ciphers: hellos.iter().map(|h| handshake::suite_name(h.cipher)).collect(),
+        });
+    }
+    if protocols.is_empty() && first.is_none() {
+        return None;
+    }
+    // TLS 1.3 encrypts the Certificate message; let a real client decrypt it
+    if certificates.is_empty() {
+        certificates = fetch_chain(addr).await;
+    }
+
+    let mut jarm = Vec::new();
+    for (min_version, max_version, reversed, alpn) in JARM_PROBES {
+        modern.min_version = min_version;
+        modern.max_version = max_version;
+        modern.ciphers = handshake::SUITES.iter().map(|(id, _)| *id).collect();
+        if reversed {
+            modern.ciphers.reverse();
+        }
+        modern.alpn = if alpn { ALPN.to_vec() } else { Vec::new() };
+        jarm.push(match handshake::exchange(addr, &modern).await {
+            Some(Response::Hello(hello)) => Some(hello),
+            _ => None,
+        });
+    }
+
+    Some(TlsInfo {
+        protocols,
+        alpn: first.as_ref().and_then(|h| h.alpn.clone()),
+        ja3s: first.as_ref().map(ja3s).unwrap_or_default(),
+        jarm: jarm_hash(&jarm),
+        chain: certificates.iter().filter_map(|der| x509::parse(der).ok()).collect(),
+    })
+}
+
+/// The hellos of one connection per suite `version` accepts, offering all but those
+/// already picked until the server refuses.
+async fn accepted(addr: SocketAddr, version: u16) -> Vec<ServerHello> {
+    let mut offered = handshake::suites_for(version);
+    let mut hellos = Vec::new();
+    while !offered.is_empty() {
+        match handshake::exchange(addr, &ClientHello::pinned(version, offered.clone())).await {
+            Some(Response::Hello(hello)) if hello.version == version && offered.contains(&hello.cipher) => {
+                offered.retain(|c| *c != hello.cipher);
+                hellos.push(hello);
+            }
+            _ => break,
+        }
+    }
+    hellos
+}
+
+/// `md5("version,cipher,ext-ext-ext")`, all decimal.
+fn ja3s(hello: &ServerHello) -> String {
+    let extensions: Vec<String> = hello.extensions.iter().map(u16::to_string).collect();
+    let text = format!("{},{},{}", hello.legacy_version, hello.cipher, extensions.join("-"));
+    Md5::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Per probe the chosen suite's table index and a version letter (`000` for no
+/// hello), then a truncated SHA-256 over each hello's ALPN and extensions.
+fn jarm_hash(hellos: &[Option<ServerHello>]) -> String {
+    if hellos.iter().all(Option::is_none) {
+        return "0".repeat(hellos.len() * 3 + 32);
+    }
+    let mut hash = String::new();
+    let mut extensions = Vec::new();
+    for hello in hellos {
+        let Some(hello) = hello else {
+            hash.push_str("000");
+            extensions.push(String::new());
+            continue;
+        };
+        let index = handshake::SUITES.iter().position(|(id, _)| *id == hello.cipher).map_or(0, |i| i + 1);
+        let letter = match hello.version {
+            SSL3 => 'a',
+            TLS10 => 'b',
+            TLS11 => 'c',
+            TLS12 => 'd',
+            TLS13 => 'e',
+            _ => '0',
+        };
+        hash.push_str(&format!("{:02x}{}", index, letter));
+        let ids: Vec<String> = hello.extensions.iter().map(|e| format!("{:04x}", e)).collect();
+        extensions.push(format!("{}|{}", hello.alpn.as_deref().unwrap_or(""), ids.join("-")));
+    }
+    let digest: String = Sha256::digest(extensions.join(",").as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+    hash + &digest[..32]
+}
+
+/// Accepts any certificate, keeping the chain it was shown.
+#[derive(Default)]
+struct CaptureChain(Mutex<Vec<Vec<u8>>>);
+
+impl ServerCertVerifier for CaptureChain {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let chain = std::iter::once(end_entity).chain(intermediates).map(|c| c.0.clone()).collect();
+        *self.0.lock().unwrap() = chain;
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+async fn fetch_chain(addr: SocketAddr) -> Vec<Vec<u8>> {
+    let capture = Arc::new(CaptureChain::default());
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(capture.clone())
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+    let Ok(Ok(stream)) = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await else {
+        return Vec::new();
+    };
+    let _ = tokio::time::timeout(HANDSHAKE_TIMEOUT, connector.connect(rustls::ServerName::IpAddress(addr.ip()), stream)).await;
+    let chain = std::mem::take(&mut *capture.0.lock().unwrap());
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    /// Serves the self-signed test certificate over rustls with the given versions.
+    async fn server(versions: &[&'static rustls::SupportedProtocolVersion]) -> u16 {
+        let config = rustls::ServerConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(versions)
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![rustls::Certificate(x509::tests::self_signed_cert())], rustls::PrivateKey(x509::tests::self_signed_key()))
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    if let Ok(mut tls) = acceptor.accept(stream).await {
+                        let _ = tls.shutdown().await;
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        let localhost = IpAddr::from([127, 0, 0, 1]);
+
+        let port = server(&[&rustls::version::TLS12, &rustls::version::TLS13]).await;
+        let info = probe(localhost, port).await.unwrap();
+        assert_eq!(info.versions().collect::<Vec<_>>(), ["TLSv1.2", "TLSv1.3"]);
+        // An ECDSA certificate: only the ECDSA suites of rustls' defaults
+        let mut tls12 = info.protocols[0].ciphers.clone();
+        tls12.sort();
+        assert_eq!(tls12, [
+            "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+            "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+            "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+        ]);
+        assert_eq!(info.protocols[1].ciphers.len(), 3);
+        assert_eq!(info.alpn, None); // the server offers no ALPN
+        assert_eq!(info.ja3s.len(), 32);
+        assert_eq!(info.jarm.len(), 8 * 3 + 32);
+        assert_eq!(&info.jarm[9..12], "000"); // no TLS 1.1
+        assert_eq!(info.chain.len(), 1);
+        assert_eq!(info.chain[0].common_name(), "aegis-test.local");
+        assert_eq!(info.describe(), "TLSv1.2, TLSv1.3 | Subject: aegis-test.local | Self-signed | Expires: 2126-09-23");
+
+        let findings = info.findings(Utc::now());
+        assert_eq!(findings.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(), ["AUDIT-TLS-SELF-SIGNED"]);
+        let later = info.chain[0].not_after + chrono::Duration::days(1);
+        assert!(info.findings(later).iter().any(|f| f.id == "AUDIT-TLS-EXPIRED"));
+
+        // TLS 1.3 only: the chain comes from a completed handshake
+        let port = server(&[&rustls::version::TLS13]).await;
+        let info = probe(localhost, port).await.unwrap();
+        assert_eq!(info.versions().collect::<Vec<_>>(), ["TLSv1.3"]);
+        assert_eq!(info.chain.len(), 1);
+        assert!(info.chain[0].self_signed);
+
+        // Not TLS at all
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+            }
+        });
+        assert!(probe(localhost, port).await.is_none());
+    }
+
+    #[test]
+    fn test_findings() {
+        let mut leaf = x509::parse(&x509::tests::self_signed_cert()).unwrap();
+        leaf.self_signed = false;
+        leaf.key_type = "RSA".into();
+        leaf.key_bits = 1024;
+        leaf.signature_algorithm = "sha1WithRSAEncryption".into();
+        let info = TlsInfo {
+            protocols: vec![
+                Protocol { version: "SSLv3".into(), ciphers: vec!["TLS_RSA_WITH_RC4_128_MD5".into()] },
+                Protocol { version: "TLSv1.2".into(), ciphers: vec!["TLS_RSA_WITH_AES_128_GCM_SHA256".into(), "TLS_RSA_WITH_3DES_EDE_CBC_SHA".into()] },
+            ],
+            alpn: None,
+            ja3s: String::new(),
+            jarm: String::new(),
+            chain: vec![leaf],
+        };
+        let findings = info.findings(Utc::now());
+        let ids: Vec<&str> = findings.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, ["AUDIT-TLS-WEAK-KEY", "AUDIT-TLS-WEAK-SIGNATURE", "AUDIT-TLS-WEAK-CIPHER", "AUDIT-TLS-DEPRECATED-PROTOCOL"]);
+        assert_eq!(findings[2].description, "Weak cipher suites accepted: TLS_RSA_WITH_3DES_EDE_CBC_SHA, TLS_RSA_WITH_RC4_128_MD5");
+        assert_eq!((findings[3].cvss, findings[3].severity.as_str()), (7.5, "HIGH"));
+    }
+}
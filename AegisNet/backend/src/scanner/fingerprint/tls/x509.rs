@@ -0,0 +1,312 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use crate::scanner::fingerprint::snmp::ber::{self, Oid, Reader};
+
+// DER tags beyond the ones BER/SNMP needs
+const BOOLEAN: u8 = 0x01;
+const BIT_STRING: u8 = 0x03;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const BMP_STRING: u8 = 0x1e;
+const EXPLICIT_VERSION: u8 = 0xa0;
+const EXPLICIT_EXTENSIONS: u8 = 0xa3;
+const SAN_DNS: u8 = 0x82;
+const SAN_IP: u8 = 0x87;
+
+const SUBJECT_ALT_NAME: &[u32] = &[2, 5, 29, 17];
+
+// Below these a key is considered breakable (NIST SP 800-57)
+const MIN_RSA_BITS: u32 = 2048;
+const MIN_EC_BITS: u32 = 224;
+
+/// The fields of an X.509 certificate an audit cares about.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Certificate {
+    pub subject: String, // "CN=example.com, O=Example"
+    pub issuer: String,
+    pub serial: String,  // hex
+    pub sans: Vec<String>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub key_type: String, // RSA, EC, Ed25519, DSA
+    pub key_bits: u32,
+    pub signature_algorithm: String, // e.g. "sha256WithRSAEncryption"
+    pub self_signed: bool, // issuer equals subject
+    pub sha256: String,    // fingerprint of the DER encoding
+}
+
+impl Certificate {
+    pub fn expired(&self, now: DateTime<Utc>) -> bool {
+        now > self.not_after || now < self.not_before
+    }
+
+    pub fn weak_key(&self) -> bool {
+        match self.key_type.as_str() {
+            "RSA" | "DSA" => self.key_bits < MIN_RSA_BITS,
+            "EC" => self.key_bits < MIN_EC_BITS,
+            _ => false,
+        }
+    }
+
+    /// MD5 and SHA-1 signatures can be forged.
+    pub fn weak_signature(&self) -> bool {
+        let algorithm = self.signature_algorithm.to_lowercase();
+        algorithm.starts_with("md") || algorithm.contains("sha1")
+    }
+
+    /// The subject's common name, else the whole subject.
+    pub fn common_name(&self) -> &str {
+        self.subject.split(", ").find_map(|rdn| rdn.strip_prefix("CN=")).unwrap_or(&self.subject)
+    }
+}
+
+fn attribute_name(oid: &Oid) -> String {
+    match oid.arcs() {
+        [2, 5, 4, 3] => "CN".into(),
+        [2, 5, 4, 6] => "C".into(),
+        [2, 5, 4, 7] => "L".into(),
+        [2, 5, 4, 8] => "ST".into(),
+        [2, 5, 4, 10] => "O".into(),
+        [2, 5, 4, 11] => "OU".into(),
+        [1, 2, 840, 113549, 1, 9, 1] => "E".into(),
+        _ => oid.to_string(),
+    }
+}
+
+fn signature_name(oid: &Oid) -> String {
+    match oid.arcs() {
+        [1, 2, 840, 113549, 1, 1, 4] => "md5WithRSAEncryption".into(),
+        [1, 2, 840, 113549, 1, 1, 5] => "sha1WithRSAEncryption".into(),
+        [1, 2, 840, 113549, 1, 1, 10] => "rsassaPss".into(),
+        [1, 2, 840, 113549, 1, 1, 11] => "sha256WithRSAEncryption".into(),
+        [1, 2, 840, 113549, 1, 1, 12] => "sha384WithRSAEncryption".into(),
+        [1, 2, 840, 113549, 1, 1, 13] => "sha512WithRSAEncryption".into(),
+        [1, 2, 840, 10040, 4, 3] => "dsa-with-sha1".into(),
+        [1, 2, 840, 10045, 4, 1] => "ecdsa-with-SHA1".into(),
+        [1, 2, 840, 10045, 4, 3, 2] => "ecdsa-with-SHA256".into(),
+        [1, 2, 840, 10045, 4, 3, 3] => "ecdsa-with-SHA384".into(),
+        [1, 2, 840, 10045, 4, 3, 4] => "ecdsa-with-SHA512".into(),
+        [1, 3, 101, 112] => "Ed25519".into(),
+        [1, 3, 101, 113] => "Ed448".into(),
+        _ => oid.to_string(),
+    }
+}
+
+fn text(tag: u8, content: &[u8]) -> String {
+    match tag {
+        BMP_STRING => {
+            let units: Vec<u16> = content.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(content).into_owned(),
+    }
+}
+
+/// `CN=.., O=..` from a Name: SEQUENCE OF SET OF { type, value }.
+fn name(content: &[u8]) -> Result<String, String> {
+    let mut rdns = Reader::new(content);
+    let mut parts = Vec::new();
+    while !rdns.is_empty() {
+        let mut set = Reader::new(rdns.read()?.1);
+        while !set.is_empty() {
+            let mut attribute = set.sequence(ber::SEQUENCE)?;
+            let oid = attribute.oid()?;
+            let (tag, value) = attribute.read()?;
+            parts.push(format!("{}={}", attribute_name(&oid), text(tag, value)));
+        }
+    }
+    Ok(parts.join(", "))
+}
+
+fn time(reader: &mut Reader) -> Result<DateTime<Utc>, String> {
+    let (tag, content) = reader.read()?;
+    let s = std::str::from_utf8(content).map_err(|_| "Certificate time is not ASCII")?;
+    let parsed = match tag {
+        // Two-digit years: 50-99 are 19xx (RFC 5280 4.1.2.5.1)
+        UTC_TIME => {
+            let century = if s.get(..2).and_then(|y| y.parse::<u8>().ok()).is_some_and(|y| y >= 50) { "19" } else { "20" };
+            NaiveDateTime::parse_from_str(&format!("{}{}", century, s), "%Y%m%d%H%M%SZ")
+        }
+        GENERALIZED_TIME => NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%SZ"),
+        other => return Err(format!("Unexpected certificate time tag 0x{:02x}", other)),
+    };
+    parsed.map(|t| t.and_utc()).map_err(|e| format!("Bad certificate time '{}': {}", s, e))
+}
+
+/// Key algorithm and size from a SubjectPublicKeyInfo.
+fn public_key(content: &[u8]) -> Result<(String, u32), String> {
+    let mut info = Reader::new(content);
+    let mut algorithm = info.sequence(ber::SEQUENCE)?;
+    let oid = algorithm.oid()?;
+    let key = info.expect(BIT_STRING)?.get(1..).unwrap_or_default(); // skip the unused-bits byte
+
+    // Bits of a big-endian INTEGER, without the sign padding
+    let int_bits = |bytes: &[u8]| {
+        let bytes = match bytes {
+            [0, rest @ ..] => rest,
+            _ => bytes,
+        };
+        bytes.first().map_or(0, |b| (bytes.len() as u32 - 1) * 8 + (8 - b.leading_zeros()))
+    };
+
+    Ok(match oid.arcs() {
+        [1, 2, 840, 113549, 1, 1, 1] | [1, 2, 840, 113549, 1, 1, 10] => {
+            let mut rsa = Reader::new(key).sequence(ber::SEQUENCE)?;
+            ("RSA".into(), int_bits(rsa.expect(ber::INTEGER)?))
+        }
+        [1, 2, 840, 10045, 2, 1] => {
+            let bits = match algorithm.oid().ok().as_ref().map(Oid::arcs) {
+                Some([1, 2, 840, 10045, 3, 1, 1]) => 192,
+                Some([1, 3, 132, 0, 33]) => 224,
+                Some([1, 2, 840, 10045, 3, 1, 7]) => 256,
+                Some([1, 3, 132, 0, 34]) => 384,
+                Some([1, 3, 132, 0, 35]) => 521,
+                _ => 0,
+            };
+            ("EC".into(), bits)
+        }
+        [1, 2, 840, 10040, 4, 1] => {
+            let mut params = algorithm.sequence(ber::SEQUENCE)?;
+            ("DSA".into(), int_bits(params.expect(ber::INTEGER)?))
+        }
+        [1, 3, 101, 112] => ("Ed25519".into(), 256),
+        [1, 3, 101, 113] => ("Ed448".into(), 456),
+        _ => (oid.to_string(), 0),
+    })
+}
+
+/// dNSName and iPAddress entries of a subjectAltName extension.
+fn alt_names(content: &[u8]) -> Result<Vec<String>, String> {
+    let mut names = Reader::new(content).sequence(ber::SEQUENCE)?;
+    let mut sans = Vec::new();
+    while !names.is_empty() {
+        match names.read()? {
+            (SAN_DNS, dns) => sans.push(String::from_utf8_lossy(dns).into_owned()),
+            (SAN_IP, ip) => {
+                let ip = match ip.len() {
+                    4 => <[u8; 4]>::try_from(ip).map(IpAddr::from).ok(),
+                    16 => <[u8; 16]>::try_from(ip).map(IpAddr::from).ok(),
+                    _ => None,
+                };
+                sans.extend(ip.map(|ip| ip.to_string()));
+            }
+            _ => {}
+        }
+    }
+    Ok(sans)
+}
+
+/// Parses a DER certificate. Signatures are not verified.
+pub fn parse(der: &[u8]) -> Result<Certificate, String> {
+    let mut cert = Reader::new(der).sequence(ber::SEQUENCE)?;
+    let mut tbs = cert.sequence(ber::SEQUENCE)?;
+    let signature_algorithm = cert.sequence(ber::SEQUENCE)?.oid()?;
+
+    let (mut tag, mut content) = tbs.read()?;
+    if tag == EXPLICIT_VERSION {
+        (tag, content) = tbs.read()?;
+    }
+    if tag != ber::INTEGER {
+        return Err("Certificate has no serial number".into());
+    }
+    let serial = content.iter().map(|b| format!("{:02x}", b)).collect();
+    tbs.expect(ber::SEQUENCE)?; // signature, repeated below the TBS
+    let issuer = tbs.expect(ber::SEQUENCE)?;
+    let mut validity = tbs.sequence(ber::SEQUENCE)?;
+    let (not_before, not_after) = (time(&mut validity)?, time(&mut validity)?);
+    let subject = tbs.expect(ber::SEQUENCE)?;
+    let (key_type, key_bits) = public_key(tbs.expect(ber::SEQUENCE)?)?;
+
+    let mut sans = Vec::new();
+    while !tbs.is_empty() {
+        let (tag, content) = tbs.read()?;
+        if tag != EXPLICIT_EXTENSIONS {
+            continue; // issuer/subject unique IDs
+        }
+        let mut extensions = Reader::new(content).sequence(ber::SEQUENCE)?;
+        while !extensions.is_empty() {
+            let mut extension = extensions.sequence(ber::SEQUENCE)?;
+            let oid = extension.oid()?;
+            let (mut tag, mut value) = extension.read()?;
+            if tag == BOOLEAN {
+                (tag, value) = extension.read()?; // critical flag
+            }
+            if tag == ber::OCTET_STRING && oid == Oid::new(SUBJECT_ALT_NAME) {
+                sans = alt_names(value)?;
+            }
+        }
+    }
+
+    Ok(Certificate {
+        subject: name(subject)?,
+        issuer: name(issuer)?,
+        serial,
+        sans,
+        not_before,
+        not_after,
+        key_type,
+        key_bits,
+        signature_algorithm: signature_name(&signature_algorithm),
+        self_signed: issuer == subject,
+        sha256: Sha256::digest(der).iter().map(|b| format!("{:02x}", b)).collect(),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    /// `openssl req -x509 -newkey ec -pkeyopt ec_paramgen_curve:P-256 -days 36500
+    ///  -subj "/CN=aegis-test.local/O=AegisNet" -addext "subjectAltName=DNS:aegis-test.local,IP:127.0.0.1"`
+    pub fn self_signed_cert() -> Vec<u8> {
+        hex(concat!(
+            "308201c33082016aa00302010202021234300a06082a8648ce3d040302302e3119301706035504030c1061656769732d",
+            "746573742e6c6f63616c3111300f060355040a0c0841656769734e65743020170d3236313031373035333735375a180f",
+            "32313236303932333035333735375a302e3119301706035504030c1061656769732d746573742e6c6f63616c3111300f",
+            "060355040a0c0841656769734e65743059301306072a8648ce3d020106082a8648ce3d0301070342000477f5606c3a15",
+            "3f2b4cab33e22ce93a2088e9824bd973c91a3c9abea38994f7f6db8d72bcffc7060a074dd16adc77133a84a92a2681ac",
+            "02ff4c94a82edaf2a22aa3763074301d0603551d0e0416041401384ecce59d6692fd4653d61bc296f51ffeee47301f06",
+            "03551d2304183016801401384ecce59d6692fd4653d61bc296f51ffeee47300f0603551d130101ff040530030101ff30",
+            "210603551d11041a3018821061656769732d746573742e6c6f63616c87047f000001300a06082a8648ce3d0403020347",
+            "003044022015a8d227edfc42e992c469c8774d53e3f6291f9ddd64aa25f63094181454285202205ec3a6c8dbf8f88fe6",
+            "62b3475f49e95f913a1ea435175a4e3a06762233f4ca8c",
+        ))
+    }
+
+    /// The PKCS#8 key of `self_signed_cert`.
+    pub fn self_signed_key() -> Vec<u8> {
+        hex(concat!(
+            "308187020100301306072a8648ce3d020106082a8648ce3d030107046d306b02010104203d611b2a7e59b404246dd11c",
+            "df237b4cd1e20e8574d3f3687efad1db3a985538a1440342000477f5606c3a153f2b4cab33e22ce93a2088e9824bd973",
+            "c91a3c9abea38994f7f6db8d72bcffc7060a074dd16adc77133a84a92a2681ac02ff4c94a82edaf2a22a",
+        ))
+    }
+
+    #[test]
+    fn test_parse() {
+        let cert = parse(&self_signed_cert()).unwrap();
+        assert_eq!(cert.subject, "CN=aegis-test.local, O=AegisNet");
+        assert_eq!(cert.common_name(), "aegis-test.local");
+        assert_eq!(cert.serial, "1234");
+        assert_eq!(cert.sans, ["aegis-test.local", "127.0.0.1"]);
+        // UTCTime, then GeneralizedTime past 2049
+        assert_eq!(cert.not_before.to_rfc3339(), "2026-10-17T05:37:57+00:00");
+        assert_eq!(cert.not_after.to_rfc3339(), "2126-09-23T05:37:57+00:00");
+        assert_eq!((cert.key_type.as_str(), cert.key_bits), ("EC", 256));
+        assert_eq!(cert.signature_algorithm, "ecdsa-with-SHA256");
+        assert!(cert.self_signed);
+        assert_eq!(cert.sha256, "0a57ff1bbc491adcb4ac2ad514b77c07bc3af349e11d7b1d20e99796baff7fac");
+
+        assert!(!cert.weak_key() && !cert.weak_signature());
+        assert!(!cert.expired(cert.not_before));
+        assert!(cert.expired(cert.not_after + chrono::Duration::days(1)));
+
+        assert!(parse(&self_signed_cert()[..200]).is_err());
+    }
+}
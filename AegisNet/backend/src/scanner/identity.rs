@@ -0,0 +1,347 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Each repeated sighting adds 5% to a candidate's score, at most 50%
+const SEEN_BONUS: f64 = 0.05;
+const MAX_BONUS_SIGHTINGS: u32 = 10;
+
+static WEIGHTS: OnceLock<IdentityWeights> = OnceLock::new();
+
+/// Host attributes that several sources may disagree on.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Attribute {
+    Hostname,
+    Manufacturer,
+    Model,
+    FriendlyName,
+}
+
+impl Attribute {
+    pub const ALL: [Attribute; 4] = [Attribute::Hostname, Attribute::Manufacturer, Attribute::Model, Attribute::FriendlyName];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Attribute::Hostname => "hostname",
+            Attribute::Manufacturer => "manufacturer",
+            Attribute::Model => "model",
+            Attribute::FriendlyName => "friendly_name",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.as_str() == s)
+    }
+}
+
+/// Where a candidate value came from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Netbios, // node status response
+    Mdns,    // A/SRV host name, TXT records
+    Nbns,    // name registrations broadcast by the host
+    Ssdp,    // UPnP device description
+    Dhcp,    // host name option of a DHCP request
+    Smb,     // NTLM challenge of an SMB session setup
+    Snmp,    // the agent's own system group
+    Lldp,    // LLDP/CDP neighbor table of a switch or router
+}
+
+impl Source {
+    pub const ALL: [Source; 8] = [
+        Source::Netbios, Source::Mdns, Source::Nbns, Source::Ssdp, Source::Dhcp, Source::Smb, Source::Snmp, Source::Lldp,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Netbios => "netbios",
+            Source::Mdns => "mdns",
+            Source::Nbns => "nbns",
+            Source::Ssdp => "ssdp",
+            Source::Dhcp => "dhcp",
+            Source::Smb => "smb",
+            Source::Snmp => "snmp",
+            Source::Lldp => "lldp",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|src| src.as_str() == s)
+    }
+
+    /// Human readable name, as used in provenance summaries.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Source::Netbios => "NetBIOS",
+            Source::Mdns => "mDNS",
+            Source::Nbns => "NBNS",
+            Source::Ssdp => "UPnP",
+            Source::Dhcp => "DHCP",
+            Source::Smb => "SMB",
+            Source::Snmp => "SNMP",
+            Source::Lldp => "LLDP/CDP",
+        }
+    }
+}
+
+/// One value a source reported for an attribute.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub attribute: Attribute,
+    pub value: String,
+    pub source: Source,
+    pub confidence: u8, // how much the source itself is to be trusted on the value, 0-100
+    pub seen: u32, // sightings, across scans once persisted
+    pub last_seen: NaiveDateTime,
+}
+
+impl Candidate {
+    pub fn new(attribute: Attribute, source: Source, confidence: u8, value: impl Into<String>) -> Self {
+        Self { attribute, value: value.into(), source, confidence: confidence.min(100), seen: 1, last_seen: Utc::now().naive_utc() }
+    }
+
+    pub fn score(&self, weights: &IdentityWeights) -> f64 {
+        let sightings = self.seen.saturating_sub(1).min(MAX_BONUS_SIGHTINGS);
+        self.confidence as f64 * weights.weight(self.attribute, self.source) * (1.0 + SEEN_BONUS * sightings as f64)
+    }
+
+    /// e.g. "DESKTOP-1 via NetBIOS, seen 3 times"
+    pub fn summary(&self) -> String {
+        let times = if self.seen == 1 { "once".to_string() } else { format!("{} times", self.seen) };
+        format!("{} via {}, seen {}", self.value, self.source.label(), times)
+    }
+}
+
+/// Every candidate value for a host's identity attributes. Serializes as the candidate list.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct Identity {
+    candidates: Vec<Candidate>,
+}
+
+impl Identity {
+    /// Adds a candidate, ignoring empty values and repeats of one the source already gave.
+    pub fn offer(&mut self, attribute: Attribute, source: Source, confidence: u8, value: Option<&str>) {
+        self.offer_at(attribute, source, confidence, value, Utc::now().naive_utc());
+    }
+
+    /// Like `offer`, for a value the source reported at `seen_at`.
+    pub fn offer_at(&mut self, attribute: Attribute, source: Source, confidence: u8, value: Option<&str>, seen_at: NaiveDateTime) {
+        let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else { return };
+        let known = self.candidates.iter().any(|c| c.attribute == attribute && c.source == source && c.value == value);
+        if !known {
+            self.candidates.push(Candidate { last_seen: seen_at, ..Candidate::new(attribute, source, confidence, value) });
+        }
+    }
+
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    /// Best value under the configured weights.
+    pub fn best(&self, attribute: Attribute) -> Option<&Candidate> {
+        self.best_with(attribute, IdentityWeights::global())
+    }
+
+    /// Highest scoring current candidate. A source that changed its mind (a renamed host)
+    /// only counts with its latest value, however often the old one was seen.
+    pub fn best_with(&self, attribute: Attribute, weights: &IdentityWeights) -> Option<&Candidate> {
+        self.current(attribute)
+            .filter(|c| c.score(weights) > 0.0)
+            .max_by(|a, b| {
+                a.score(weights).total_cmp(&b.score(weights))
+                    .then(a.last_seen.cmp(&b.last_seen))
+                    .then(b.value.cmp(&a.value))
+            })
+    }
+
+    /// Per attribute: the chosen value and how every candidate scored.
+    pub fn provenance(&self, weights: &IdentityWeights) -> BTreeMap<Attribute, Provenance> {
+        let mut out = BTreeMap::new();
+        for attribute in Attribute::ALL {
+            let Some(best) = self.best_with(attribute, weights) else { continue };
+            let current: Vec<&Candidate> = self.current(attribute).collect();
+            let mut candidates: Vec<ScoredCandidate> = self.candidates.iter()
+                .filter(|c| c.attribute == attribute)
+                .map(|c| ScoredCandidate {
+                    value: c.value.clone(),
+                    source: c.source,
+                    confidence: c.confidence,
+                    seen: c.seen,
+                    last_seen: c.last_seen.to_string(),
+                    score: (c.score(weights) * 10.0).round() / 10.0,
+                    superseded: !current.iter().any(|cur| std::ptr::eq(*cur, c)),
+                })
+                .collect();
+            candidates.sort_by(|a, b| a.superseded.cmp(&b.superseded).then(b.score.total_cmp(&a.score)));
+            out.insert(attribute, Provenance {
+                value: best.value.clone(),
+                source: best.source,
+                seen: best.seen,
+                summary: best.summary(),
+                candidates,
+            });
+        }
+        out
+    }
+
+    /// The latest value of each source.
+    fn current(&self, attribute: Attribute) -> impl Iterator<Item = &Candidate> {
+        let mut latest: HashMap<Source, &Candidate> = HashMap::new();
+        for c in self.candidates.iter().filter(|c| c.attribute == attribute) {
+            let newer = latest.get(&c.source).is_none_or(|l| (c.last_seen, c.seen) > (l.last_seen, l.seen));
+            if newer {
+                latest.insert(c.source, c);
+            }
+        }
+        latest.into_values()
+    }
+}
+
+impl From<Vec<Candidate>> for Identity {
+    fn from(candidates: Vec<Candidate>) -> Self {
+        Self { candidates }
+    }
+}
+
+/// How an attribute was resolved, for the host API.
+#[derive(Serialize, Clone, Debug)]
+pub struct Provenance {
+    pub value: String,
+    pub source: Source,
+    pub seen: u32,
+    pub summary: String,
+    pub candidates: Vec<ScoredCandidate>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ScoredCandidate {
+    pub value: String,
+    pub source: Source,
+    pub confidence: u8,
+    pub seen: u32,
+    pub last_seen: String,
+    pub score: f64,
+    pub superseded: bool, // the source reported a different value since
+}
+
+/// Per-source multipliers applied to candidate confidences, 1.0 unless configured.
+/// Read from `IDENTITY_WEIGHTS`, e.g. `dhcp=0.5,hostname.mdns=1.5,ssdp=0`: a bare source
+/// applies to every attribute, `attribute.source` to one; 0 ignores the source.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IdentityWeights {
+    by_source: HashMap<Source, f64>,
+    by_attribute: HashMap<(Attribute, Source), f64>,
+}
+
+impl IdentityWeights {
+    pub fn global() -> &'static IdentityWeights {
+        WEIGHTS.get_or_init(|| {
+            let Ok(spec) = std::env::var("IDENTITY_WEIGHTS") else { return Self::default() };
+            Self::parse(&spec).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring IDENTITY_WEIGHTS: {}", e);
+                Self::default()
+            })
+        })
+    }
+
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut weights = Self::default();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, value) = entry.split_once('=').ok_or_else(|| format!("Expected key=weight, got '{}'", entry))?;
+            let weight: f64 = value.trim().parse().ok()
+                .filter(|w: &f64| w.is_finite() && *w >= 0.0)
+                .ok_or_else(|| format!("Invalid weight in '{}'", entry))?;
+            let key = key.trim();
+            let parse_source = |s: &str| Source::parse(s).ok_or_else(|| format!("Unknown source '{}'", s));
+            match key.split_once('.') {
+                Some((attribute, source)) => {
+                    let attribute = Attribute::parse(attribute).ok_or_else(|| format!("Unknown attribute '{}'", attribute))?;
+                    weights.by_attribute.insert((attribute, parse_source(source)?), weight);
+                }
+                None => { weights.by_source.insert(parse_source(key)?, weight); }
+            }
+        }
+        Ok(weights)
+    }
+
+    pub fn weight(&self, attribute: Attribute, source: Source) -> f64 {
+        self.by_attribute.get(&(attribute, source))
+            .or_else(|| self.by_source.get(&source))
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn candidate(attribute: Attribute, source: Source, confidence: u8, value: &str, seen: u32, age_days: i64) -> Candidate {
+        Candidate {
+            seen,
+            last_seen: Utc::now().naive_utc() - Duration::days(age_days),
+            ..Candidate::new(attribute, source, confidence, value)
+        }
+    }
+
+    #[test]
+    fn test_best_by_confidence_and_sightings() {
+        let mut identity = Identity::default();
+        identity.offer(Attribute::Hostname, Source::Netbios, 90, Some("DESKTOP-1"));
+        identity.offer(Attribute::Hostname, Source::Mdns, 85, Some("desktop-1"));
+        identity.offer(Attribute::Hostname, Source::Nbns, 70, Some("  "));
+        let weights = IdentityWeights::default();
+        assert_eq!(identity.candidates().len(), 2);
+        assert_eq!(identity.best_with(Attribute::Hostname, &weights).unwrap().value, "DESKTOP-1");
+        assert!(identity.best_with(Attribute::Model, &weights).is_none());
+
+        // Seen often enough, the weaker source wins
+        let identity = Identity::from(vec![
+            candidate(Attribute::Hostname, Source::Netbios, 90, "DESKTOP-1", 1, 0),
+            candidate(Attribute::Hostname, Source::Mdns, 85, "desktop-1", 3, 0),
+        ]);
+        let best = identity.best_with(Attribute::Hostname, &weights).unwrap();
+        assert_eq!(best.summary(), "desktop-1 via mDNS, seen 3 times");
+    }
+
+    #[test]
+    fn test_renamed_host_supersedes() {
+        let identity = Identity::from(vec![
+            candidate(Attribute::Hostname, Source::Netbios, 90, "OLD-NAME", 12, 3),
+            candidate(Attribute::Hostname, Source::Netbios, 90, "NEW-NAME", 1, 0),
+        ]);
+        let weights = IdentityWeights::default();
+        assert_eq!(identity.best_with(Attribute::Hostname, &weights).unwrap().value, "NEW-NAME");
+
+        let provenance = identity.provenance(&weights);
+        let hostname = &provenance[&Attribute::Hostname];
+        assert_eq!(hostname.summary, "NEW-NAME via NetBIOS, seen once");
+        assert_eq!(hostname.candidates.len(), 2);
+        assert!(!hostname.candidates[0].superseded && hostname.candidates[1].superseded);
+    }
+
+    #[test]
+    fn test_weights() {
+        let weights = IdentityWeights::parse("dhcp=2, hostname.mdns=0.5, ssdp=0").unwrap();
+        assert_eq!(weights.weight(Attribute::Hostname, Source::Dhcp), 2.0);
+        assert_eq!(weights.weight(Attribute::Hostname, Source::Mdns), 0.5);
+        assert_eq!(weights.weight(Attribute::Model, Source::Mdns), 1.0);
+
+        let mut identity = Identity::default();
+        identity.offer(Attribute::Hostname, Source::Netbios, 90, Some("DESKTOP-1"));
+        identity.offer(Attribute::Hostname, Source::Dhcp, 60, Some("laptop"));
+        identity.offer(Attribute::Model, Source::Ssdp, 70, Some("UE55NU7179"));
+        assert_eq!(identity.best_with(Attribute::Hostname, &weights).unwrap().value, "laptop");
+        assert!(identity.best_with(Attribute::Model, &weights).is_none());
+
+        assert!(IdentityWeights::parse("dhcp").is_err());
+        assert!(IdentityWeights::parse("telnet=1").is_err());
+        assert!(IdentityWeights::parse("colour.mdns=1").is_err());
+        assert!(IdentityWeights::parse("mdns=-1").is_err());
+    }
+}
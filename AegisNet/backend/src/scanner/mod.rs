@@ -1,37 +1,64 @@
+pub mod addr;
 pub mod discovery;
 pub mod fingerprint;
 pub mod traffic;
 pub mod vuln;
 pub mod core;
+pub mod progress;
+pub mod target;
+pub mod ports;
+pub mod profile;
+pub mod identity;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv6Addr};
+use addr::MacAddr;
+use identity::Identity;
+use fingerprint::snmp::SnmpData;
+use fingerprint::tls::TlsInfo;
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Host {
-    pub ip: String,
-    pub mac: String,
+    pub ip: IpAddr, // IPv4 when the host has one, else its preferred IPv6 address
+    #[serde(default)]
+    pub ipv6: Vec<Ipv6Addr>, // every IPv6 address linked to the host (NDP, mDNS AAAA)
+    pub mac: MacAddr, // all zero when unknown (routed hosts)
     pub hostname: String,
     pub vendor: String,
     pub manufacturer: Option<String>, // e.g. "Apple Inc.", "Samsung Electronics"
     pub model: Option<String>,        // e.g. "MacBookPro18,3", "UE55NU7179"
     pub friendly_name: Option<String>, // e.g. "Living Room TV", "Dave's iPhone"
     pub os_family: String, // Windows, Linux, MacOS, iOS, Android
+    #[serde(default)]
+    pub os_confidence: u8, // 0-100, how sure the stack fingerprint is
     pub device_type: String, // Server, Desktop, Phone, IoT, Router
+    #[serde(default)]
+    pub workgroup: Option<String>, // NetBIOS workgroup / Windows domain
+    #[serde(default)]
+    pub logged_in_user: Option<String>, // NetBIOS <03> user name
+    #[serde(default)]
+    pub spoofed_names: Vec<String>, // LLMNR/NBNS names it answered for without owning them
+    #[serde(default)]
+    pub discovered_by: Vec<String>, // discovery modules that saw it, e.g. ["arp", "mdns"]
+    #[serde(default)]
+    pub identity: Identity, // every hostname/manufacturer/model candidate and where it came from
+    #[serde(default)]
+    pub snmp: Option<SnmpData>, // system group, interfaces, ARP table and neighbors of its SNMP agent
     pub open_ports: Vec<u16>,
     pub services: Vec<Service>,
     pub risk_score: u8,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Service {
     pub port: u16,
     pub protocol: String, // TCP/UDP
     pub name: String, // ssh, http
     pub banner: String, // "OpenSSH 8.2p1"
     pub version: String,
+    #[serde(default)]
+    pub cpe: Vec<String>, // from the service probe match, e.g. "cpe:/a:openbsd:openssh:8.9p1"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsInfo>, // versions, suites and certificate chain of a TLS port
     pub cves: Vec<String>,
 }
-
-pub trait ScannerModule {
-    fn name(&self) -> &'static str;
-}
@@ -0,0 +1,254 @@
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// File descriptors left to the database, the API and discovery sockets.
+const FD_RESERVE: usize = 128;
+
+/// Most commonly open TCP ports, ordered by frequency (nmap top ports plus
+/// the IoT/OT services we care about on home and office LANs).
+pub const TOP_PORTS: &[u16] = &[
+    80, 23, 443, 21, 22, 25, 3389, 110, 445, 139, 143, 53, 135, 3306, 8080, 1723, 111, 995, 993, 5900,
+    1025, 587, 8888, 199, 1720, 465, 548, 113, 81, 6001, 10000, 514, 5060, 179, 1026, 2000, 8443, 8000, 32768, 554,
+    26, 1433, 49152, 2001, 515, 8008, 49154, 1027, 5666, 646, 5000, 5631, 631, 49153, 8081, 2049, 88, 79, 5800, 106,
+    2121, 1110, 49155, 6000, 513, 990, 5357, 427, 49156, 543, 544, 5101, 144, 7, 389, 8009, 3128, 444, 9999, 5009,
+    7070, 5190, 3000, 5432, 1900, 3986, 13, 1029, 9, 5051, 6646, 49157, 1028, 873, 1755, 2717, 4899, 9100, 119, 37,
+    // Databases & message brokers
+    1521, 27017, 6379, 9200, 5672, 11211, 5984, 9042, 1883, 8883, 61616,
+    // Cameras, media & smart home
+    8554, 7000, 7100, 8200, 32400, 1400, 8060, 9080, 5353, 62078, 8123, 1880, 502, 102, 47808, 20000,
+    // Management interfaces
+    161, 2375, 2376, 6443, 10250, 9090, 9443, 8006, 5985, 5986, 4443, 7547, 8291, 8728, 50000,
+];
+
+/// A set of TCP ports to probe, parsed from e.g. `top100`, `1-1024`,
+/// `22,80,443,8000-8100`, `all` or `none`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortSpec {
+    ports: Vec<u16>,
+}
+
+impl PortSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        if spec.trim().eq_ignore_ascii_case("none") {
+            return Ok(Self::none());
+        }
+
+        let mut ports = Vec::new();
+
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let lower = item.to_lowercase();
+            if lower == "all" {
+                ports.extend(1..=u16::MAX);
+            } else if let Some(n) = lower.strip_prefix("top") {
+                let n: usize = n.trim_start_matches([':', '-']).parse()
+                    .map_err(|_| format!("Invalid top-N port spec '{}'", item))?;
+                ports.extend(TOP_PORTS.iter().take(n));
+            } else if let Some((from, to)) = item.split_once('-') {
+                let from = parse_port(from)?;
+                let to = parse_port(to)?;
+                if to < from {
+                    return Err(format!("Port range '{}' ends before it starts", item));
+                }
+                ports.extend(from..=to);
+            } else {
+                ports.push(parse_port(item)?);
+            }
+        }
+
+        if ports.is_empty() {
+            return Err(format!("Port spec '{}' contains no ports", spec));
+        }
+        Ok(Self::from_ports(ports))
+    }
+
+    /// No port probing at all (passive profiles).
+    pub fn none() -> Self {
+        Self { ports: Vec::new() }
+    }
+
+    pub fn top(n: usize) -> Self {
+        Self::from_ports(TOP_PORTS.iter().take(n).copied().collect())
+    }
+
+    pub fn range(start: u16, end: u16) -> Self {
+        Self::from_ports((start.min(end)..=end.max(start)).collect())
+    }
+
+    fn from_ports(mut ports: Vec<u16>) -> Self {
+        ports.sort_unstable();
+        ports.dedup();
+        Self { ports }
+    }
+
+    pub fn ports(&self) -> &[u16] {
+        &self.ports
+    }
+
+    pub fn len(&self) -> usize {
+        self.ports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ports.is_empty()
+    }
+}
+
+impl std::fmt::Display for PortSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ports.is_empty() {
+            return write!(f, "none");
+        }
+        // Collapse consecutive ports back into ranges
+        let mut parts = Vec::new();
+        let mut start = self.ports[0];
+        let mut prev = start;
+        for &p in &self.ports[1..] {
+            if p != prev + 1 {
+                parts.push(if start == prev { start.to_string() } else { format!("{}-{}", start, prev) });
+                start = p;
+            }
+            prev = p;
+        }
+        parts.push(if start == prev { start.to_string() } else { format!("{}-{}", start, prev) });
+        write!(f, "{}", parts.join(","))
+    }
+}
+
+impl Default for PortSpec {
+    fn default() -> Self {
+        Self::top(100)
+    }
+}
+
+fn parse_port(s: &str) -> Result<u16, String> {
+    match s.trim().parse::<u16>() {
+        Ok(p) if p > 0 => Ok(p),
+        _ => Err(format!("Invalid port '{}'", s.trim())),
+    }
+}
+
+/// Connect timeout that follows the observed round-trip time of a host,
+/// RFC 6298 style (SRTT + 4 * RTTVAR), clamped to `[min, max]`.
+pub struct AdaptiveTimeout {
+    min: Duration,
+    max: Duration,
+    state: Mutex<Option<(f64, f64)>>, // (srtt_ms, rttvar_ms)
+    initial: Duration,
+}
+
+impl AdaptiveTimeout {
+    pub fn new(initial: Duration, min: Duration, max: Duration) -> Self {
+        Self { min, max, state: Mutex::new(None), initial }
+    }
+
+    pub fn current(&self) -> Duration {
+        let state = self.state.lock().map(|s| *s).unwrap_or(None);
+        match state {
+            Some((srtt, rttvar)) => {
+                let ms = srtt + 4.0 * rttvar;
+                Duration::from_secs_f64(ms / 1000.0).clamp(self.min, self.max)
+            }
+            None => self.initial,
+        }
+    }
+
+    /// Feed a completed handshake (open) or an immediate RST (closed).
+    pub fn observe(&self, rtt: Duration) {
+        let sample = rtt.as_secs_f64() * 1000.0;
+        if let Ok(mut state) = self.state.lock() {
+            *state = Some(match *state {
+                None => (sample, sample / 2.0),
+                Some((srtt, rttvar)) => {
+                    let rttvar = 0.75 * rttvar + 0.25 * (srtt - sample).abs();
+                    let srtt = 0.875 * srtt + 0.125 * sample;
+                    (srtt, rttvar)
+                }
+            });
+        }
+    }
+}
+
+/// How many connects a scan may keep in flight across all hosts: the open file
+/// limit minus a reserve. Read once, raising the soft limit to the hard one
+/// where the OS allows it.
+pub fn socket_budget() -> usize {
+    static BUDGET: OnceLock<usize> = OnceLock::new();
+    *BUDGET.get_or_init(|| open_file_limit().saturating_sub(FD_RESERVE).max(1))
+}
+
+#[cfg(not(windows))]
+fn open_file_limit() -> usize {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return 1024;
+    }
+    if limit.rlim_cur < limit.rlim_max {
+        let raised = libc::rlimit { rlim_cur: limit.rlim_max, rlim_max: limit.rlim_max };
+        // macOS refuses RLIM_INFINITY, the current soft limit stays then
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } == 0 {
+            limit = raised;
+        }
+    }
+    usize::try_from(limit.rlim_cur).unwrap_or(usize::MAX)
+}
+
+// Winsock has no per-process socket limit worth reading
+#[cfg(windows)]
+fn open_file_limit() -> usize {
+    16384
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_spec_parsing() {
+        assert_eq!(PortSpec::parse("22,80,443").unwrap().ports(), &[22, 80, 443]);
+        assert_eq!(PortSpec::parse("8000-8003, 80").unwrap().ports(), &[80, 8000, 8001, 8002, 8003]);
+        assert_eq!(PortSpec::parse("all").unwrap().len(), 65535);
+        assert_eq!(PortSpec::parse("top10").unwrap().len(), 10);
+        assert_eq!(PortSpec::parse("top:20").unwrap(), PortSpec::top(20));
+        assert!(PortSpec::parse("0").is_err());
+        assert!(PortSpec::parse("90-80").is_err());
+        assert!(PortSpec::parse("").is_err());
+        assert!(PortSpec::parse("none").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_port_spec_roundtrip() {
+        let spec = PortSpec::parse("22,80-82,443,8000-8100").unwrap();
+        assert_eq!(spec.to_string(), "22,80-82,443,8000-8100");
+        assert_eq!(PortSpec::parse(&spec.to_string()).unwrap(), spec);
+        assert_eq!(PortSpec::none().to_string(), "none");
+    }
+
+    #[test]
+    fn test_top_ports_unique() {
+        let spec = PortSpec::top(TOP_PORTS.len());
+        assert_eq!(spec.len(), TOP_PORTS.len());
+    }
+
+    #[test]
+    fn test_adaptive_timeout() {
+        let t = AdaptiveTimeout::new(Duration::from_millis(500), Duration::from_millis(50), Duration::from_secs(2));
+        assert_eq!(t.current(), Duration::from_millis(500));
+
+        for _ in 0..10 {
+            t.observe(Duration::from_millis(4));
+        }
+        assert_eq!(t.current(), Duration::from_millis(50)); // clamped to min on a fast LAN
+
+        for _ in 0..10 {
+            t.observe(Duration::from_millis(400));
+        }
+        assert!(t.current() > Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_socket_budget() {
+        let budget = socket_budget();
+        assert!(budget >= 1);
+        assert!(budget < open_file_limit());
+    }
+}
@@ -0,0 +1,173 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::scanner::core::ScanOptions;
+use crate::scanner::ports::PortSpec;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryMethod {
+    Mdns,
+    Icmp,
+    Tcp,
+    Netbios,
+    Llmnr,
+    Udp,
+    Ssdp,
+    Arp,      // the OS neighbour cache, read only
+    ArpSweep, // who-has for every on-link address
+    Ipv6,
+}
+
+impl DiscoveryMethod {
+    pub const ALL: [DiscoveryMethod; 10] = [
+        DiscoveryMethod::Mdns, DiscoveryMethod::Icmp, DiscoveryMethod::Tcp, DiscoveryMethod::Netbios,
+        DiscoveryMethod::Llmnr, DiscoveryMethod::Udp, DiscoveryMethod::Ssdp, DiscoveryMethod::Arp,
+        DiscoveryMethod::ArpSweep, DiscoveryMethod::Ipv6,
+    ];
+}
+
+/// A named scan preset: which discovery modules run and for how long,
+/// which ports get probed, how fast, and whether intrusive fingerprinting
+/// (SMB, SNMP, HTTP) is allowed.
+#[derive(Clone, Debug)]
+pub struct ScanProfile {
+    pub name: String,
+    pub description: String,
+    pub methods: Vec<DiscoveryMethod>,
+    pub mdns_duration: Duration,
+    pub llmnr_duration: Duration,
+    pub ssdp_duration: Duration,
+    pub arp_settle: Duration,
+    pub max_probes_per_sec: u32, // sweep rate for ICMP/TCP/NetBIOS/UDP, 0 = unlimited
+    pub intrusive: bool,
+    pub options: ScanOptions,
+}
+
+impl ScanProfile {
+    pub const DEFAULT: &'static str = "standard";
+
+    pub fn uses(&self, method: DiscoveryMethod) -> bool {
+        self.methods.contains(&method)
+    }
+
+    /// Delay between two probes of a sweep module.
+    pub fn probe_interval(&self) -> Duration {
+        if self.max_probes_per_sec == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(1.0 / self.max_probes_per_sec as f64)
+        }
+    }
+
+    /// The presets seeded into the database on first start.
+    pub fn builtin() -> Vec<ScanProfile> {
+        let standard = ScanProfile {
+            name: Self::DEFAULT.into(),
+            description: "All discovery techniques, top 100 ports, full fingerprinting".into(),
+            methods: DiscoveryMethod::ALL.to_vec(),
+            mdns_duration: Duration::from_secs(4),
+            llmnr_duration: Duration::from_secs(5),
+            ssdp_duration: Duration::from_secs(4),
+            arp_settle: Duration::from_millis(500),
+            max_probes_per_sec: 200,
+            intrusive: true,
+            options: ScanOptions::default(),
+        };
+
+        vec![
+            ScanProfile {
+                name: "passive-only".into(),
+                description: "Listen only (mDNS, LLMNR, SSDP, ARP cache), no sweeps or port probes".into(),
+                methods: vec![DiscoveryMethod::Mdns, DiscoveryMethod::Llmnr, DiscoveryMethod::Ssdp, DiscoveryMethod::Arp],
+                mdns_duration: Duration::from_secs(10),
+                llmnr_duration: Duration::from_secs(10),
+                ssdp_duration: Duration::from_secs(10),
+                max_probes_per_sec: 0,
+                intrusive: false,
+                options: ScanOptions { ports: PortSpec::none(), ..ScanOptions::default() },
+                ..standard.clone()
+            },
+            ScanProfile {
+                name: "quick-inventory".into(),
+                description: "Fast sweep to list devices, top 20 ports, no intrusive probes".into(),
+                methods: vec![DiscoveryMethod::Icmp, DiscoveryMethod::Tcp, DiscoveryMethod::Mdns, DiscoveryMethod::Arp,
+                              DiscoveryMethod::ArpSweep, DiscoveryMethod::Ipv6],
+                mdns_duration: Duration::from_secs(2),
+                arp_settle: Duration::from_millis(200),
+                max_probes_per_sec: 0,
+                intrusive: false,
+                options: ScanOptions {
+                    ports: PortSpec::top(20),
+                    connect_timeout: Duration::from_millis(250),
+                    host_concurrency: 16,
+                    ..ScanOptions::default()
+                },
+                ..standard.clone()
+            },
+            ScanProfile {
+                name: "deep-audit".into(),
+                description: "Every technique with long listen windows, all 65535 ports, full fingerprinting".into(),
+                mdns_duration: Duration::from_secs(8),
+                llmnr_duration: Duration::from_secs(10),
+                ssdp_duration: Duration::from_secs(8),
+                arp_settle: Duration::from_secs(1),
+                max_probes_per_sec: 100,
+                options: ScanOptions {
+                    ports: PortSpec::range(1, u16::MAX),
+                    port_concurrency: 512,
+                    host_concurrency: 4,
+                    connect_timeout: Duration::from_millis(800),
+                    ..ScanOptions::default()
+                },
+                ..standard.clone()
+            },
+            standard,
+        ]
+    }
+}
+
+impl Default for ScanProfile {
+    fn default() -> Self {
+        Self::builtin().into_iter().find(|p| p.name == Self::DEFAULT).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_profiles() {
+        let profiles = ScanProfile::builtin();
+        assert!(profiles.iter().any(|p| p.name == ScanProfile::DEFAULT));
+
+        let passive = profiles.iter().find(|p| p.name == "passive-only").unwrap();
+        assert!(!passive.intrusive);
+        assert!(passive.options.ports.is_empty());
+        assert!(!passive.uses(DiscoveryMethod::Icmp));
+        assert!(passive.uses(DiscoveryMethod::Arp));
+        assert!(!passive.uses(DiscoveryMethod::ArpSweep));
+    }
+
+    #[test]
+    fn test_fit_socket_budget() {
+        let deep = ScanProfile::builtin().into_iter().find(|p| p.name == "deep-audit").unwrap();
+        let mut options = deep.options.clone();
+        assert_eq!(options.sockets_in_flight(), 2048);
+        assert!(options.fit_socket_budget(896));
+        assert_eq!((options.host_concurrency, options.port_concurrency), (4, 224));
+        assert!(!options.fit_socket_budget(896));
+
+        let mut options = ScanOptions { host_concurrency: 2000, ..ScanOptions::default() };
+        assert!(options.fit_socket_budget(896));
+        assert_eq!((options.host_concurrency, options.port_concurrency), (896, 1));
+    }
+
+    #[test]
+    fn test_probe_interval() {
+        let p = ScanProfile { max_probes_per_sec: 200, ..ScanProfile::default() };
+        assert_eq!(p.probe_interval(), Duration::from_millis(5));
+        let p = ScanProfile { max_probes_per_sec: 0, ..p };
+        assert_eq!(p.probe_interval(), Duration::ZERO);
+    }
+}
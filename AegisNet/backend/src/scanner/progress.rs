@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanPhase {
+    Queued,
+    Discovery,
+    Enrichment,
+    Persisting,
+    Done,
+}
+
+/// Shared progress counters, updated by `ScannerCore` while a scan runs
+/// and read by the job API for status polling.
+pub struct ScanProgress {
+    phase: Mutex<ScanPhase>,
+    pub hosts_discovered: AtomicUsize,
+    pub hosts_enriched: AtomicUsize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProgressSnapshot {
+    pub phase: ScanPhase,
+    pub hosts_discovered: usize,
+    pub hosts_enriched: usize,
+}
+
+impl ScanProgress {
+    pub fn new() -> Self {
+        Self {
+            phase: Mutex::new(ScanPhase::Queued),
+            hosts_discovered: AtomicUsize::new(0),
+            hosts_enriched: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn set_phase(&self, phase: ScanPhase) {
+        if let Ok(mut p) = self.phase.lock() { *p = phase; }
+    }
+
+    pub fn phase(&self) -> ScanPhase {
+        self.phase.lock().map(|p| *p).unwrap_or(ScanPhase::Queued)
+    }
+
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            phase: self.phase(),
+            hosts_discovered: self.hosts_discovered.load(Ordering::Relaxed),
+            hosts_enriched: self.hosts_enriched.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for ScanProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::scanner::addr::IpNet;
+use std::str::FromStr;
+
+// Refuse to expand anything bigger than a /16 into individual probes
+const MAX_HOSTS: u64 = 65_536;
+
+/// A parsed scan scope: CIDR prefixes, dash ranges and single addresses,
+/// comma separated, with `!`-prefixed entries excluded.
+///
+/// IPv6 prefixes and addresses are accepted too. They are far too large to
+/// sweep, so they only scope what IPv6 neighbor discovery may report.
+///
+/// Examples: `192.168.1.0/24`, `10.0.0.5-40`, `10.0.0.0/22, !10.0.1.0/28, !10.0.0.1`,
+/// `192.168.1.0/24, 2001:db8:1::/64`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanTarget {
+    spec: String,
+    include: Vec<(u32, u32)>,
+    exclude: Vec<(u32, u32)>,
+    include_v6: Vec<(u128, u128)>,
+    exclude_v6: Vec<(u128, u128)>,
+}
+
+/// One parsed entry of a target spec.
+enum Item {
+    V4(u32, u32),
+    V6(u128, u128),
+}
+
+impl ScanTarget {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let target = Self::parse_scope(spec)?;
+        let count = target.len();
+        if count > MAX_HOSTS {
+            return Err(format!("Target '{}' expands to {} addresses (max {})", spec, count, MAX_HOSTS));
+        }
+        Ok(target)
+    }
+
+    /// Parses without the size cap, for scopes that are only matched against and never swept.
+    pub fn parse_scope(spec: &str) -> Result<Self, String> {
+        let mut target = Self {
+            spec: spec.trim().to_string(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            include_v6: Vec::new(),
+            exclude_v6: Vec::new(),
+        };
+
+        for item in spec.split([',', ' ', ';']).map(str::trim).filter(|s| !s.is_empty()) {
+            match item.strip_prefix('!') {
+                Some(excluded) => target.push_exclusion(parse_item(excluded.trim())?),
+                None => match parse_item(item)? {
+                    Item::V4(s, e) => target.include.push((s, e)),
+                    Item::V6(s, e) => target.include_v6.push((s, e)),
+                },
+            }
+        }
+
+        if target.include.is_empty() && target.include_v6.is_empty() {
+            return Err(format!("Target '{}' contains no addresses", spec));
+        }
+        Ok(target)
+    }
+
+    /// Adds an exclusion list in the same syntax as the target itself (without `!`).
+    pub fn with_exclusions(mut self, spec: &str) -> Result<Self, String> {
+        for item in spec.split([',', ' ', ';']).map(str::trim).filter(|s| !s.is_empty()) {
+            let item = item.strip_prefix('!').unwrap_or(item);
+            self.push_exclusion(parse_item(item)?);
+        }
+        Ok(self)
+    }
+
+    fn push_exclusion(&mut self, item: Item) {
+        match item {
+            Item::V4(s, e) => self.exclude.push((s, e)),
+            Item::V6(s, e) => self.exclude_v6.push((s, e)),
+        }
+    }
+
+    pub fn contains(&self, ip: &Ipv4Addr) -> bool {
+        let v = u32::from(*ip);
+        self.include.iter().any(|&(s, e)| v >= s && v <= e)
+            && !self.exclude.iter().any(|&(s, e)| v >= s && v <= e)
+    }
+
+    pub fn contains_v6(&self, ip: &Ipv6Addr) -> bool {
+        let v = u128::from(*ip);
+        self.include_v6.iter().any(|&(s, e)| v >= s && v <= e)
+            && !self.exclude_v6.iter().any(|&(s, e)| v >= s && v <= e)
+    }
+
+    pub fn contains_ip(&self, ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(v4) => self.contains(v4),
+            IpAddr::V6(v6) => self.contains_v6(v6),
+        }
+    }
+
+    /// Whether the spec names any IPv6 scope at all.
+    pub fn has_ipv6(&self) -> bool {
+        !self.include_v6.is_empty()
+    }
+
+    /// Every in-scope IPv4 address, sorted and deduplicated.
+    pub fn hosts(&self) -> Vec<Ipv4Addr> {
+        let mut ranges = self.include.clone();
+        ranges.sort();
+
+        let mut out = Vec::new();
+        let mut next = 0u64; // first value not yet emitted, guards overlapping ranges
+        for (start, end) in ranges {
+            let from = (start as u64).max(next);
+            for v in from..=end as u64 {
+                let ip = Ipv4Addr::from(v as u32);
+                if !self.exclude.iter().any(|&(s, e)| v >= s as u64 && v <= e as u64) {
+                    out.push(ip);
+                }
+            }
+            next = next.max(end as u64 + 1);
+        }
+        out
+    }
+
+    /// Upper bound of in-scope IPv4 addresses (exclusions not subtracted).
+    pub fn len(&self) -> u64 {
+        self.include.iter().map(|&(s, e)| (e - s) as u64 + 1).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hosts().is_empty()
+    }
+}
+
+impl FromStr for ScanTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for ScanTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+fn parse_item(item: &str) -> Result<Item, String> {
+    // Link-local addresses are often written with their zone, e.g. fe80::1%eth0
+    let unzoned = match item.split_once('%') {
+        Some((addr, zone)) => format!("{}{}", addr, zone.find('/').map_or("", |i| &zone[i..])),
+        None => item.to_string(),
+    };
+
+    // CIDR: 10.0.0.0/22, 2001:db8::/64, or a single address
+    if item.contains('/') || item.contains(':') {
+        let net: IpNet = unzoned.parse()?;
+        return Ok(match (net.network(), net.last()) {
+            // Skip network and broadcast addresses, except on point-to-point / host routes
+            (IpAddr::V4(first), IpAddr::V4(last)) if net.prefix() <= 30 => Item::V4(u32::from(first) + 1, u32::from(last) - 1),
+            (IpAddr::V4(first), IpAddr::V4(last)) => Item::V4(first.into(), last.into()),
+            (IpAddr::V6(first), IpAddr::V6(last)) => Item::V6(first.into(), last.into()),
+            _ => unreachable!("network and last address share the family"),
+        });
+    }
+
+    // Range: 10.0.0.5-40 or 10.0.0.5-10.0.1.20
+    if let Some((from, to)) = item.split_once('-') {
+        let start = parse_ip(from)?;
+        let end = if to.contains('.') {
+            parse_ip(to)?
+        } else {
+            let last: u8 = to.trim().parse().map_err(|_| format!("Invalid range end in '{}'", item))?;
+            (start & 0xFFFF_FF00) | last as u32
+        };
+        if end < start {
+            return Err(format!("Range '{}' ends before it starts", item));
+        }
+        return Ok(Item::V4(start, end));
+    }
+
+    let ip = parse_ip(item)?;
+    Ok(Item::V4(ip, ip))
+}
+
+fn parse_ip(s: &str) -> Result<u32, String> {
+    Ipv4Addr::from_str(s.trim())
+        .map(u32::from)
+        .map_err(|_| format!("Invalid IPv4 address '{}'", s.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> Ipv4Addr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_24() {
+        let t = ScanTarget::parse("192.168.1.0/24").unwrap();
+        let hosts = t.hosts();
+        assert_eq!(hosts.len(), 254);
+        assert_eq!(hosts[0], ip("192.168.1.1"));
+        assert_eq!(hosts[253], ip("192.168.1.254"));
+    }
+
+    #[test]
+    fn test_cidr_22_and_28() {
+        assert_eq!(ScanTarget::parse("10.10.0.0/22").unwrap().hosts().len(), 1022);
+
+        let dmz = ScanTarget::parse("172.16.5.37/28").unwrap().hosts();
+        assert_eq!(dmz.len(), 14);
+        assert_eq!(dmz[0], ip("172.16.5.33"));
+    }
+
+    #[test]
+    fn test_dash_ranges() {
+        let t = ScanTarget::parse("10.0.0.5-40").unwrap();
+        assert_eq!(t.hosts().len(), 36);
+        assert!(t.contains(&ip("10.0.0.40")));
+        assert!(!t.contains(&ip("10.0.0.41")));
+
+        let t = ScanTarget::parse("10.0.0.250-10.0.1.5").unwrap();
+        assert_eq!(t.hosts().len(), 12);
+    }
+
+    #[test]
+    fn test_lists_and_exclusions() {
+        let t = ScanTarget::parse("10.0.0.0/29, 10.0.0.4, 10.0.0.20, !10.0.0.1, !10.0.0.5-6").unwrap();
+        let hosts = t.hosts();
+        assert_eq!(hosts, vec![ip("10.0.0.2"), ip("10.0.0.3"), ip("10.0.0.4"), ip("10.0.0.20")]);
+
+        let t = ScanTarget::parse("192.168.1.0/24").unwrap().with_exclusions("192.168.1.1,192.168.1.100-199").unwrap();
+        assert_eq!(t.hosts().len(), 153);
+    }
+
+    #[test]
+    fn test_invalid_targets() {
+        assert!(ScanTarget::parse("").is_err());
+        assert!(ScanTarget::parse("10.0.0.0/33").is_err());
+        assert!(ScanTarget::parse("10.0.0.40-5").is_err());
+        assert!(ScanTarget::parse("not-an-ip").is_err());
+        assert!(ScanTarget::parse("10.0.0.0/8").is_err()); // too large
+        assert!(ScanTarget::parse_scope("10.0.0.0/8").unwrap().contains(&ip("10.200.0.1")));
+        assert!(ScanTarget::parse("2001:db8::/129").is_err());
+        assert!(ScanTarget::parse("2001:db8::zz").is_err());
+    }
+
+    #[test]
+    fn test_ipv6_scope() {
+        let t = ScanTarget::parse("192.168.1.0/24, 2001:db8:1::/64, fe80::/10, !2001:db8:1::dead").unwrap();
+        assert_eq!(t.hosts().len(), 254); // prefixes are never swept
+        assert!(t.has_ipv6());
+        assert!(t.contains_ip(&"2001:db8:1::42".parse().unwrap()));
+        assert!(t.contains_ip(&"fe80::1c2d:3eff:fe4f:5a6b".parse().unwrap()));
+        assert!(!t.contains_ip(&"2001:db8:1::dead".parse().unwrap()));
+        assert!(!t.contains_ip(&"2001:db8:2::42".parse().unwrap()));
+        assert!(t.contains_ip(&"192.168.1.7".parse().unwrap()));
+
+        let only_v6 = ScanTarget::parse("fe80::1%eth0").unwrap();
+        assert!(only_v6.hosts().is_empty());
+        assert!(only_v6.contains_v6(&"fe80::1".parse().unwrap()));
+        assert!(!ScanTarget::parse("10.0.0.0/24").unwrap().has_ipv6());
+    }
+}
@@ -117,8 +117,8 @@ impl TrafficAnalyzer {
                                  };
 
                                  store.process_packet(
-                                     src_ip.to_string(), 
-                                     dst_ip.to_string(), 
+                                     src_ip.into(), 
+                                     dst_ip.into(), 
                                      size as u64, 
                                      protocol, 
                                      payload
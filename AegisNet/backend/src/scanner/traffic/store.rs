@@ -1,13 +1,15 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::net::IpAddr;
+use std::sync::{Arc, OnceLock};
 use dashmap::DashMap;
 use serde::Serialize;
 use std::time::SystemTime;
+use crate::scanner::addr::IpNet;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct TrafficFlow {
-    pub src_ip: String,
-    pub dst_ip: String,
+    pub src_ip: IpAddr,
+    pub dst_ip: IpAddr,
     pub src_port: u16,
     pub dst_port: u16,
     pub protocol: String,
@@ -26,22 +28,22 @@ pub struct TrafficFlow {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct DeviceTraffic {
-    pub ip: String,
+    pub ip: IpAddr,
     pub total_bytes: u64,
     pub total_packets: u64,
     pub protocols: HashMap<String, u64>,
     pub top_services: HashMap<String, u64>,
-    pub top_destinations: HashMap<String, u64>,
+    pub top_destinations: HashMap<IpAddr, u64>,
 }
 
 #[derive(Clone)]
 pub struct TrafficStore {
     pub flows: Arc<DashMap<String, TrafficFlow>>,
-    pub device_stats: Arc<DashMap<String, DeviceTraffic>>,
+    pub device_stats: Arc<DashMap<IpAddr, DeviceTraffic>>,
     // DNS Cache: Maps IP -> Domain from DNS responses we've seen
-    dns_cache: Arc<DashMap<String, String>>,
+    dns_cache: Arc<DashMap<IpAddr, String>>,
     // Reverse DNS Cache: Populated via background lookups
-    rdns_cache: Arc<DashMap<String, String>>,
+    rdns_cache: Arc<DashMap<IpAddr, String>>,
 }
 
 impl TrafficStore {
@@ -54,7 +56,7 @@ impl TrafficStore {
         }
     }
 
-    pub fn process_packet(&self, src_ip: String, dst_ip: String, len: u64, proto: u8, payload: &[u8]) {
+    pub fn process_packet(&self, src_ip: IpAddr, dst_ip: IpAddr, len: u64, proto: u8, payload: &[u8]) {
         let (protocol_str, src_port, dst_port, service, mut sni, dns_query, http_host, tcp_payload) = 
             self.parse_transport_layer(proto, payload);
 
@@ -64,7 +66,7 @@ impl TrafficStore {
         if let Some(ref domain) = dns_query {
             // The dst_ip in a DNS query response often contains resolved IPs in DNS answers
             // But here we're capturing the query domain and associating with src (the resolver target)
-            self.dns_cache.insert(dst_ip.clone(), domain.clone());
+            self.dns_cache.insert(dst_ip, domain.clone());
         }
         
         // 2. Try to extract domain via multiple methods
@@ -94,12 +96,12 @@ impl TrafficStore {
         }
         // Priority 6: Trigger async reverse DNS lookup (won't block)
         else {
-            self.trigger_rdns_lookup(&dst_ip);
+            self.trigger_rdns_lookup(dst_ip);
         }
 
-        let application = self.identify_application(&dst_ip, dst_port, resolved_domain.as_deref());
+        let application = self.identify_application(dst_port, resolved_domain.as_deref());
         let category = self.categorize_traffic(&service, dst_port, application.as_deref());
-        let insight = self.generate_insight(&dst_ip, &service, &category, application.as_deref(), resolved_domain.as_deref());
+        let insight = self.generate_insight(dst_ip, &service, &category, application.as_deref(), resolved_domain.as_deref());
 
         // Update Flow
         let key = format!("{}:{}|{}:{}|{}", src_ip, src_port, dst_ip, dst_port, protocol_str);
@@ -114,8 +116,8 @@ impl TrafficStore {
             if f.resolved_domain.is_none() && resolved_domain.is_some() { f.resolved_domain = resolved_domain.clone(); }
             if f.application.is_none() && application.is_some() { f.application = application.clone(); }
         }).or_insert(TrafficFlow {
-            src_ip: src_ip.clone(),
-            dst_ip: dst_ip.clone(),
+            src_ip,
+            dst_ip,
             src_port,
             dst_port,
             protocol: protocol_str.to_string(),
@@ -133,12 +135,12 @@ impl TrafficStore {
         });
 
         // Update Device Stats
-        self.device_stats.entry(src_ip.clone()).and_modify(|s| {
+        self.device_stats.entry(src_ip).and_modify(|s| {
             s.total_bytes += len;
             s.total_packets += 1;
             *s.protocols.entry(protocol_str.to_string()).or_insert(0) += len;
             *s.top_services.entry(service.clone()).or_insert(0) += len;
-            *s.top_destinations.entry(dst_ip.clone()).or_insert(0) += len;
+            *s.top_destinations.entry(dst_ip).or_insert(0) += len;
         }).or_insert_with(|| {
             let mut dev = DeviceTraffic {
                 ip: src_ip,
@@ -156,142 +158,20 @@ impl TrafficStore {
     }
 
     // === KNOWN IP RANGES DATABASE ===
-    fn identify_by_ip_range(&self, ip: &str) -> Option<String> {
-        let parts: Vec<u8> = ip.split('.').filter_map(|s| s.parse().ok()).collect();
-        if parts.len() != 4 { return None; }
-        
-        let (a, b, c, _d) = (parts[0], parts[1], parts[2], parts[3]);
-        
-        // Google (8.8.x.x, 8.34-35.x.x, 34.x.x.x, 35.x.x.x, 64.233.x.x, 66.102.x.x, 66.249.x.x, 72.14.x.x, 74.125.x.x, 108.177.x.x, 142.250-251.x.x, 172.217.x.x, 173.194.x.x, 209.85.x.x, 216.58.x.x, 216.239.x.x)
-        if a == 8 && (b == 8 || b == 34 || b == 35) { return Some("google.com".into()); }
-        if a == 34 || a == 35 { return Some("google.com".into()); }
-        if a == 64 && b == 233 { return Some("google.com".into()); }
-        if a == 66 && (b == 102 || b == 249) { return Some("google.com".into()); }
-        if a == 72 && b == 14 { return Some("google.com".into()); }
-        if a == 74 && b == 125 { return Some("google.com".into()); }
-        if a == 108 && b == 177 { return Some("google.com".into()); }
-        if a == 142 && (b >= 250 && b <= 251) { return Some("google.com".into()); }
-        if a == 172 && b == 217 { return Some("google.com".into()); }
-        if a == 173 && b == 194 { return Some("google.com".into()); }
-        if a == 209 && b == 85 { return Some("google.com".into()); }
-        if a == 216 && (b == 58 || b == 239) { return Some("google.com".into()); }
-        
-        // YouTube (same as Google but we can detect via port patterns)
-        // Netflix (23.246.x.x, 37.77.x.x, 45.57.x.x, 64.120.x.x, 66.197.x.x, 108.175.x.x, 185.2.220.x, 185.9.188.x, 192.173.x.x, 198.38.x.x, 198.45.x.x, 207.45.x.x, 208.75.x.x)
-        if a == 23 && b == 246 { return Some("netflix.com".into()); }
-        if a == 37 && b == 77 { return Some("netflix.com".into()); }
-        if a == 45 && b == 57 { return Some("netflix.com".into()); }
-        if a == 64 && b == 120 { return Some("netflix.com".into()); }
-        if a == 66 && b == 197 { return Some("netflix.com".into()); }
-        if a == 108 && b == 175 { return Some("netflix.com".into()); }
-        if a == 185 && (b == 2 || b == 9) { return Some("netflix.com".into()); }
-        if a == 192 && b == 173 { return Some("netflix.com".into()); }
-        if a == 198 && (b == 38 || b == 45) { return Some("netflix.com".into()); }
-        if a == 207 && b == 45 { return Some("netflix.com".into()); }
-        if a == 208 && b == 75 { return Some("netflix.com".into()); }
-        
-        // Facebook/Meta (31.13.x.x, 66.220.x.x, 69.63.x.x, 69.171.x.x, 74.119.x.x, 102.132.x.x, 129.134.x.x, 157.240.x.x, 173.252.x.x, 179.60.x.x, 185.60.x.x, 204.15.x.x)
-        if a == 31 && b == 13 { return Some("facebook.com".into()); }
-        if a == 66 && b == 220 { return Some("facebook.com".into()); }
-        if a == 69 && (b == 63 || b == 171) { return Some("facebook.com".into()); }
-        if a == 74 && b == 119 { return Some("facebook.com".into()); }
-        if a == 102 && b == 132 { return Some("facebook.com".into()); }
-        if a == 129 && b == 134 { return Some("facebook.com".into()); }
-        if a == 157 && b == 240 { return Some("facebook.com".into()); }
-        if a == 173 && b == 252 { return Some("facebook.com".into()); }
-        if a == 179 && b == 60 { return Some("facebook.com".into()); }
-        if a == 185 && b == 60 { return Some("facebook.com".into()); }
-        if a == 204 && b == 15 { return Some("facebook.com".into()); }
-        
-        // Microsoft/Azure (13.x.x.x, 20.x.x.x, 40.x.x.x, 51.x.x.x, 52.x.x.x, 65.52-55.x.x, 104.40-47.x.x, 131.253.x.x, 134.170.x.x, 137.116-117.x.x, 157.55-56.x.x, 168.61-63.x.x, 191.232-239.x.x, 204.79.x.x)
-        if a == 13 || a == 20 || a == 40 || a == 51 || a == 52 { return Some("microsoft.com".into()); }
-        if a == 65 && (b >= 52 && b <= 55) { return Some("microsoft.com".into()); }
-        if a == 104 && (b >= 40 && b <= 47) { return Some("microsoft.com".into()); }
-        if a == 131 && b == 253 { return Some("microsoft.com".into()); }
-        if a == 134 && b == 170 { return Some("microsoft.com".into()); }
-        if a == 137 && (b == 116 || b == 117) { return Some("microsoft.com".into()); }
-        if a == 157 && (b == 55 || b == 56) { return Some("microsoft.com".into()); }
-        if a == 168 && (b >= 61 && b <= 63) { return Some("microsoft.com".into()); }
-        if a == 191 && (b >= 232 && b <= 239) { return Some("microsoft.com".into()); }
-        if a == 204 && b == 79 { return Some("microsoft.com".into()); }
-        
-        // Amazon/AWS (3.x.x.x, 18.x.x.x, 34.x.x.x, 35.x.x.x, 44.x.x.x, 50.x.x.x, 52.x.x.x, 54.x.x.x, 63.x.x.x, 72.x.x.x, 75.x.x.x, 99.x.x.x, 107.x.x.x, 174.x.x.x, 176.x.x.x, 184.x.x.x, 204.x.x.x, 205.x.x.x)
-        if a == 3 || a == 18 || a == 44 || a == 50 || a == 54 { return Some("amazon.com".into()); }
-        if a == 99 || a == 107 || a == 174 || a == 176 { return Some("amazon.com".into()); }
-        
-        // Apple (17.x.x.x)
-        if a == 17 { return Some("apple.com".into()); }
-        
-        // Cloudflare (104.16-31.x.x, 172.64-71.x.x, 173.245.x.x, 188.114.x.x, 190.93.x.x, 197.234.x.x, 198.41.x.x)
-        if a == 104 && (b >= 16 && b <= 31) { return Some("cloudflare.com".into()); }
-        if a == 172 && (b >= 64 && b <= 71) { return Some("cloudflare.com".into()); }
-        if a == 173 && b == 245 { return Some("cloudflare.com".into()); }
-        if a == 188 && b == 114 { return Some("cloudflare.com".into()); }
-        if a == 190 && b == 93 { return Some("cloudflare.com".into()); }
-        if a == 197 && b == 234 { return Some("cloudflare.com".into()); }
-        if a == 198 && b == 41 { return Some("cloudflare.com".into()); }
-        if a == 1 && b == 1 { return Some("cloudflare-dns.com".into()); } // 1.1.1.1
-        
-        // Discord (162.159.x.x - often behind Cloudflare)
-        if a == 162 && b == 159 { return Some("discord.com".into()); }
-        
-        // Twitch (23.160.x.x, 52.223.x.x, 99.181.x.x, 185.42.x.x, 192.16.x.x)
-        if a == 23 && b == 160 { return Some("twitch.tv".into()); }
-        if a == 185 && b == 42 { return Some("twitch.tv".into()); }
-        if a == 99 && b == 181 { return Some("twitch.tv".into()); }
-        
-        // Steam/Valve (103.10.x.x, 146.66.x.x, 155.133.x.x, 162.254.x.x, 185.25.x.x, 192.69.x.x, 205.196.x.x, 208.64.x.x)
-        if a == 103 && b == 10 { return Some("steampowered.com".into()); }
-        if a == 146 && b == 66 { return Some("steampowered.com".into()); }
-        if a == 155 && b == 133 { return Some("steampowered.com".into()); }
-        if a == 162 && b == 254 { return Some("steampowered.com".into()); }
-        if a == 185 && b == 25 { return Some("steampowered.com".into()); }
-        if a == 192 && b == 69 { return Some("steampowered.com".into()); }
-        if a == 205 && b == 196 { return Some("steampowered.com".into()); }
-        if a == 208 && b == 64 { return Some("steampowered.com".into()); }
-        
-        // Spotify (35.186.x.x, 78.31.x.x, 193.182.x.x, 194.132.x.x)
-        if a == 35 && b == 186 { return Some("spotify.com".into()); }
-        if a == 78 && b == 31 { return Some("spotify.com".into()); }
-        if a == 193 && b == 182 { return Some("spotify.com".into()); }
-        if a == 194 && b == 132 { return Some("spotify.com".into()); }
-        
-        // TikTok/ByteDance (161.117.x.x, 152.199.x.x)
-        if a == 161 && b == 117 { return Some("tiktok.com".into()); }
-        if a == 152 && b == 199 { return Some("tiktok.com".into()); }
-        
-        // Twitter/X (104.244.x.x, 192.133.x.x)
-        if a == 104 && b == 244 { return Some("twitter.com".into()); }
-        if a == 192 && b == 133 { return Some("twitter.com".into()); }
-        
-        // Pornhub/MindGeek (66.254.x.x, 185.88.x.x, 216.18.x.x)
-        if a == 66 && b == 254 { return Some("pornhub.com".into()); }
-        if a == 185 && b == 88 { return Some("pornhub.com".into()); }
-        if a == 216 && b == 18 { return Some("pornhub.com".into()); }
-        
-        // Akamai CDN (23.x.x.x partial, 92.122-123.x.x, 95.100-101.x.x, 184.24-31.x.x)
-        if a == 92 && (b == 122 || b == 123) { return Some("akamai.net".into()); }
-        if a == 95 && (b >= 100 && b <= 101) { return Some("akamai.net".into()); }
-        if a == 184 && (b >= 24 && b <= 31) { return Some("akamai.net".into()); }
-        
-        // Fastly CDN (151.101.x.x, 199.232.x.x)
-        if a == 151 && b == 101 { return Some("fastly.net".into()); }
-        if a == 199 && b == 232 { return Some("fastly.net".into()); }
-        
-        None
+    fn identify_by_ip_range(&self, ip: &IpAddr) -> Option<String> {
+        known_ranges().iter().find(|(net, _)| net.contains(ip)).map(|(_, name)| name.to_string())
     }
 
-    fn trigger_rdns_lookup(&self, ip: &str) {
+    fn trigger_rdns_lookup(&self, ip: IpAddr) {
         // Only attempt if not already cached
-        if self.rdns_cache.contains_key(ip) { return; }
+        if self.rdns_cache.contains_key(&ip) { return; }
         
-        let ip_clone = ip.to_string();
         let cache = self.rdns_cache.clone();
         
         // Fire-and-forget async lookup
         std::thread::spawn(move || {
-            if let Ok(names) = dns_lookup::lookup_addr(&ip_clone.parse().unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::new(0,0,0,0)))) {
-                cache.insert(ip_clone, names);
+            if let Ok(names) = dns_lookup::lookup_addr(&ip) {
+                cache.insert(ip, names);
             }
         });
     }
@@ -487,7 +367,7 @@ impl TrafficStore {
         }
     }
 
-    fn identify_application(&self, dst_ip: &str, _dst_port: u16, domain: Option<&str>) -> Option<String> {
+    fn identify_application(&self, _dst_port: u16, domain: Option<&str>) -> Option<String> {
         let domain = domain?;
         let d = domain.to_lowercase();
         
@@ -549,8 +429,9 @@ impl TrafficStore {
         }
     }
 
-    fn generate_insight(&self, dst: &str, service: &str, category: &str, app: Option<&str>, domain: Option<&str>) -> String {
-        let target = app.or(domain).unwrap_or(dst);
+    fn generate_insight(&self, dst: IpAddr, service: &str, category: &str, app: Option<&str>, domain: Option<&str>) -> String {
+        let dst = dst.to_string();
+        let target = app.or(domain).unwrap_or(&dst);
         
         match category {
             "Media" => format!("🎬 Streaming: {}", target),
@@ -570,6 +451,159 @@ impl TrafficStore {
     }
 }
 
+/// Provider address blocks, checked in order: the first match wins.
+const KNOWN_RANGES: &[(&str, &str)] = &[
+    // Google
+    ("8.8.0.0/16", "google.com"), ("8.34.0.0/16", "google.com"), ("8.35.0.0/16", "google.com"),
+    ("34.0.0.0/8", "google.com"), ("35.0.0.0/8", "google.com"),
+    ("64.233.0.0/16", "google.com"),
+    ("66.102.0.0/16", "google.com"), ("66.249.0.0/16", "google.com"),
+    ("72.14.0.0/16", "google.com"),
+    ("74.125.0.0/16", "google.com"),
+    ("108.177.0.0/16", "google.com"),
+    ("142.250.0.0/15", "google.com"),
+    ("172.217.0.0/16", "google.com"),
+    ("173.194.0.0/16", "google.com"),
+    ("209.85.0.0/16", "google.com"),
+    ("216.58.0.0/16", "google.com"), ("216.239.0.0/16", "google.com"),
+
+    // Netflix
+    ("23.246.0.0/16", "netflix.com"),
+    ("37.77.0.0/16", "netflix.com"),
+    ("45.57.0.0/16", "netflix.com"),
+    ("64.120.0.0/16", "netflix.com"),
+    ("66.197.0.0/16", "netflix.com"),
+    ("108.175.0.0/16", "netflix.com"),
+    ("185.2.0.0/16", "netflix.com"), ("185.9.0.0/16", "netflix.com"),
+    ("192.173.0.0/16", "netflix.com"),
+    ("198.38.0.0/16", "netflix.com"), ("198.45.0.0/16", "netflix.com"),
+    ("207.45.0.0/16", "netflix.com"),
+    ("208.75.0.0/16", "netflix.com"),
+
+    // Facebook/Meta
+    ("31.13.0.0/16", "facebook.com"),
+    ("66.220.0.0/16", "facebook.com"),
+    ("69.63.0.0/16", "facebook.com"), ("69.171.0.0/16", "facebook.com"),
+    ("74.119.0.0/16", "facebook.com"),
+    ("102.132.0.0/16", "facebook.com"),
+    ("129.134.0.0/16", "facebook.com"),
+    ("157.240.0.0/16", "facebook.com"),
+    ("173.252.0.0/16", "facebook.com"),
+    ("179.60.0.0/16", "facebook.com"),
+    ("185.60.0.0/16", "facebook.com"),
+    ("204.15.0.0/16", "facebook.com"),
+
+    // Microsoft/Azure
+    ("13.0.0.0/8", "microsoft.com"), ("20.0.0.0/8", "microsoft.com"), ("40.0.0.0/8", "microsoft.com"), ("51.0.0.0/8", "microsoft.com"), ("52.0.0.0/8", "microsoft.com"),
+    ("65.52.0.0/14", "microsoft.com"),
+    ("104.40.0.0/13", "microsoft.com"),
+    ("131.253.0.0/16", "microsoft.com"),
+    ("134.170.0.0/16", "microsoft.com"),
+    ("137.116.0.0/16", "microsoft.com"), ("137.117.0.0/16", "microsoft.com"),
+    ("157.55.0.0/16", "microsoft.com"), ("157.56.0.0/16", "microsoft.com"),
+    ("168.61.0.0/16", "microsoft.com"), ("168.62.0.0/15", "microsoft.com"),
+    ("191.232.0.0/13", "microsoft.com"),
+    ("204.79.0.0/16", "microsoft.com"),
+
+    // Amazon/AWS
+    ("3.0.0.0/8", "amazon.com"), ("18.0.0.0/8", "amazon.com"), ("44.0.0.0/8", "amazon.com"), ("50.0.0.0/8", "amazon.com"), ("54.0.0.0/8", "amazon.com"),
+    ("99.0.0.0/8", "amazon.com"), ("107.0.0.0/8", "amazon.com"), ("174.0.0.0/8", "amazon.com"), ("176.0.0.0/8", "amazon.com"),
+
+    // Apple
+    ("17.0.0.0/8", "apple.com"),
+
+    // Cloudflare
+    ("104.16.0.0/12", "cloudflare.com"),
+    ("172.64.0.0/13", "cloudflare.com"),
+    ("173.245.0.0/16", "cloudflare.com"),
+    ("188.114.0.0/16", "cloudflare.com"),
+    ("190.93.0.0/16", "cloudflare.com"),
+    ("197.234.0.0/16", "cloudflare.com"),
+    ("198.41.0.0/16", "cloudflare.com"),
+    ("1.1.0.0/16", "cloudflare-dns.com"),
+
+    // Discord
+    ("162.159.0.0/16", "discord.com"),
+
+    // Twitch
+    ("23.160.0.0/16", "twitch.tv"),
+    ("185.42.0.0/16", "twitch.tv"),
+    ("99.181.0.0/16", "twitch.tv"),
+
+    // Steam/Valve
+    ("103.10.0.0/16", "steampowered.com"),
+    ("146.66.0.0/16", "steampowered.com"),
+    ("155.133.0.0/16", "steampowered.com"),
+    ("162.254.0.0/16", "steampowered.com"),
+    ("185.25.0.0/16", "steampowered.com"),
+    ("192.69.0.0/16", "steampowered.com"),
+    ("205.196.0.0/16", "steampowered.com"),
+    ("208.64.0.0/16", "steampowered.com"),
+
+    // Spotify
+    ("35.186.0.0/16", "spotify.com"),
+    ("78.31.0.0/16", "spotify.com"),
+    ("193.182.0.0/16", "spotify.com"),
+    ("194.132.0.0/16", "spotify.com"),
+
+    // TikTok/ByteDance
+    ("161.117.0.0/16", "tiktok.com"),
+    ("152.199.0.0/16", "tiktok.com"),
+
+    // Twitter/X
+    ("104.244.0.0/16", "twitter.com"),
+    ("192.133.0.0/16", "twitter.com"),
+
+    // Pornhub/MindGeek
+    ("66.254.0.0/16", "pornhub.com"),
+    ("185.88.0.0/16", "pornhub.com"),
+    ("216.18.0.0/16", "pornhub.com"),
+
+    // Akamai CDN
+    ("92.122.0.0/16", "akamai.net"), ("92.123.0.0/16", "akamai.net"),
+    ("95.100.0.0/15", "akamai.net"),
+    ("184.24.0.0/13", "akamai.net"),
+
+    // Fastly CDN
+    ("151.101.0.0/16", "fastly.net"),
+    ("199.232.0.0/16", "fastly.net"),
+];
+
+static RANGES: OnceLock<Vec<(IpNet, &'static str)>> = OnceLock::new();
+
+fn known_ranges() -> &'static [(IpNet, &'static str)] {
+    RANGES.get_or_init(|| {
+        KNOWN_RANGES.iter().filter_map(|(net, name)| Some((net.parse().ok()?, *name))).collect()
+    })
+}
+
 fn now_unix() -> u64 {
     SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_ranges() {
+        let store = TrafficStore::new();
+        let lookup = |ip: &str| store.identify_by_ip_range(&ip.parse().unwrap());
+        assert_eq!(lookup("142.251.36.14").as_deref(), Some("google.com"));
+        assert_eq!(lookup("168.63.129.16").as_deref(), Some("microsoft.com"));
+        assert_eq!(lookup("104.31.0.1").as_deref(), Some("cloudflare.com"));
+        assert_eq!(lookup("104.32.0.1"), None);
+        assert_eq!(lookup("2001:4860:4860::8888"), None);
+    }
+
+    #[test]
+    fn test_device_stats_json() {
+        let store = TrafficStore::new();
+        let src: IpAddr = "192.168.1.20".parse().unwrap();
+        store.process_packet(src, "1.1.1.1".parse().unwrap(), 60, 1, &[]);
+
+        let stats = serde_json::to_value(store.device_stats.get(&src).unwrap().value()).unwrap();
+        assert_eq!(stats["ip"], "192.168.1.20");
+        assert_eq!(stats["top_destinations"]["1.1.1.1"], 60);
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+use chrono::Utc;
+use sea_orm::*;
+use serde::Serialize;
+use serde_json::json;
+use crate::entities::{change_event, log, scan_snapshot};
+use crate::scanner::Host;
+use crate::scanner::addr::MacAddr;
+use crate::scanner::target::ScanTarget;
+use crate::services::detection::DetectionEngine;
+use crate::services::normalization::NormalizedLog;
+
+// Only the latest snapshot is needed for diffing, a few more are kept for inspection
+const SNAPSHOTS_PER_TARGET: u64 = 10;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    HostAppeared,
+    HostDisappeared,
+    MacChanged,
+    PortOpened,
+    PortClosed,
+    BannerChanged,
+    CveAdded,
+    CveResolved,
+    NamePoisoning,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::HostAppeared => "host_appeared",
+            ChangeKind::HostDisappeared => "host_disappeared",
+            ChangeKind::MacChanged => "mac_changed",
+            ChangeKind::PortOpened => "port_opened",
+            ChangeKind::PortClosed => "port_closed",
+            ChangeKind::BannerChanged => "banner_changed",
+            ChangeKind::CveAdded => "cve_added",
+            ChangeKind::CveResolved => "cve_resolved",
+            ChangeKind::NamePoisoning => "name_poisoning",
+        }
+    }
+
+    pub fn severity(&self) -> &'static str {
+        match self {
+            // A known IP answering with another MAC is how ARP spoofing looks
+            ChangeKind::MacChanged | ChangeKind::CveAdded | ChangeKind::NamePoisoning => "HIGH",
+            ChangeKind::HostAppeared | ChangeKind::PortOpened => "MEDIUM",
+            ChangeKind::BannerChanged | ChangeKind::HostDisappeared => "LOW",
+            ChangeKind::PortClosed | ChangeKind::CveResolved => "INFO",
+        }
+    }
+}
+
+/// One difference between two consecutive snapshots of the same target.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub ip: IpAddr,
+    pub mac: MacAddr,
+    pub port: Option<u16>,
+    pub previous: Option<String>,
+    pub current: Option<String>,
+}
+
+impl Change {
+    fn new(kind: ChangeKind, host: &Host) -> Self {
+        Self { kind, ip: host.ip, mac: host.mac, port: None, previous: None, current: None }
+    }
+
+    fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    fn values(mut self, previous: Option<String>, current: Option<String>) -> Self {
+        self.previous = previous;
+        self.current = current;
+        self
+    }
+
+    pub fn message(&self) -> String {
+        let port = self.port.map(|p| format!(":{}", p)).unwrap_or_default();
+        let prev = self.previous.as_deref().unwrap_or("-");
+        let curr = self.current.as_deref().unwrap_or("-");
+        match self.kind {
+            ChangeKind::HostAppeared => format!("New host {} ({}) appeared", self.ip, self.mac),
+            ChangeKind::HostDisappeared => format!("Host {} ({}) disappeared", self.ip, self.mac),
+            ChangeKind::MacChanged => format!("MAC changed for {}: {} -> {}", self.ip, prev, curr),
+            ChangeKind::PortOpened => format!("Port opened on {}{}", self.ip, port),
+            ChangeKind::PortClosed => format!("Port closed on {}{}", self.ip, port),
+            ChangeKind::BannerChanged => format!("Banner changed on {}{}: '{}' -> '{}'", self.ip, port, prev, curr),
+            ChangeKind::CveAdded => format!("New vulnerability {} on {}{}", curr, self.ip, port),
+            ChangeKind::CveResolved => format!("Vulnerability {} no longer seen on {}{}", prev, self.ip, port),
+            ChangeKind::NamePoisoning => format!("Host {} ({}) answered name queries for {}", self.ip, self.mac, curr),
+        }
+    }
+}
+
+/// Observations that are news on every scan, not only when they change.
+pub fn current_threats(current: &[Host]) -> Vec<Change> {
+    current.iter()
+        .filter(|h| !h.spoofed_names.is_empty())
+        .map(|h| Change::new(ChangeKind::NamePoisoning, h).values(None, Some(h.spoofed_names.join(", "))))
+        .collect()
+}
+
+/// Compares two scans of the same target host by host (keyed by IP).
+pub fn diff_hosts(previous: &[Host], current: &[Host]) -> Vec<Change> {
+    let prev: BTreeMap<IpAddr, &Host> = previous.iter().map(|h| (h.ip, h)).collect();
+    let curr: BTreeMap<IpAddr, &Host> = current.iter().map(|h| (h.ip, h)).collect();
+    let mut changes = Vec::new();
+
+    for (ip, new) in &curr {
+        match prev.get(ip) {
+            None => changes.push(Change::new(ChangeKind::HostAppeared, new)),
+            Some(old) => diff_host(old, new, &mut changes),
+        }
+    }
+    for (ip, old) in &prev {
+        if !curr.contains_key(ip) {
+            changes.push(Change::new(ChangeKind::HostDisappeared, old));
+        }
+    }
+
+    changes
+}
+
+fn diff_host(old: &Host, new: &Host, changes: &mut Vec<Change>) {
+    // An unknown MAC only means ARP didn't answer this time
+    if old.mac != new.mac && !old.mac.is_unspecified() && !new.mac.is_unspecified() {
+        changes.push(Change::new(ChangeKind::MacChanged, new).values(Some(old.mac.to_string()), Some(new.mac.to_string())));
+    }
+
+    let old_ports: BTreeSet<u16> = old.open_ports.iter().copied().collect();
+    let new_ports: BTreeSet<u16> = new.open_ports.iter().copied().collect();
+    for port in new_ports.difference(&old_ports) {
+        changes.push(Change::new(ChangeKind::PortOpened, new).port(*port));
+    }
+    for port in old_ports.difference(&new_ports) {
+        changes.push(Change::new(ChangeKind::PortClosed, new).port(*port));
+    }
+
+    let old_services: BTreeMap<(u16, &str), _> = old.services.iter().map(|s| ((s.port, s.protocol.as_str()), s)).collect();
+    for svc in &new.services {
+        let Some(before) = old_services.get(&(svc.port, svc.protocol.as_str())) else { continue };
+
+        if before.banner != svc.banner {
+            changes.push(Change::new(ChangeKind::BannerChanged, new).port(svc.port)
+                .values(Some(before.banner.clone()), Some(svc.banner.clone())));
+        }
+
+        let old_cves: BTreeSet<&str> = before.cves.iter().map(|c| cve_id(c)).collect();
+        let new_cves: BTreeSet<&str> = svc.cves.iter().map(|c| cve_id(c)).collect();
+        for cve in new_cves.difference(&old_cves) {
+            changes.push(Change::new(ChangeKind::CveAdded, new).port(svc.port).values(None, Some(cve.to_string())));
+        }
+        for cve in old_cves.difference(&new_cves) {
+            changes.push(Change::new(ChangeKind::CveResolved, new).port(svc.port).values(Some(cve.to_string()), None));
+        }
+    }
+
+    // CVEs on a service we had never seen before are news too
+    for svc in new.services.iter().filter(|s| !old_services.contains_key(&(s.port, s.protocol.as_str()))) {
+        for cve in &svc.cves {
+            changes.push(Change::new(ChangeKind::CveAdded, new).port(svc.port).values(None, Some(cve_id(cve).to_string())));
+        }
+    }
+}
+
+// Service.cves entries are encoded as "ID|URL"
+fn cve_id(entry: &str) -> &str {
+    entry.split('|').next().unwrap_or(entry)
+}
+
+/// Stores the scan as the newest snapshot of its target, diffs it against the
+/// previous snapshot taken with the same profile, and records the resulting
+/// change events in `change_events` and as log entries for alerting.
+pub async fn record_scan(
+    db: &DatabaseConnection,
+    target: &ScanTarget,
+    profile: &str,
+    job_id: &str,
+    hosts: &[Host],
+) -> Result<Vec<change_event::Model>, DbErr> {
+    let now = Utc::now().naive_utc();
+    let target_key = target.to_string();
+
+    let previous = scan_snapshot::Entity::find()
+        .filter(scan_snapshot::Column::Target.eq(target_key.clone()))
+        .filter(scan_snapshot::Column::Profile.eq(profile))
+        .order_by_desc(scan_snapshot::Column::Id)
+        .one(db)
+        .await?;
+
+    let snapshot = scan_snapshot::ActiveModel {
+        target: Set(target_key.clone()),
+        profile: Set(profile.to_string()),
+        job_id: Set(job_id.to_string()),
+        host_count: Set(hosts.len() as i32),
+        hosts: Set(serde_json::to_string(hosts).unwrap_or_else(|_| "[]".into())),
+        created_at: Set(now),
+        ..Default::default()
+    }.insert(db).await?;
+
+    prune_snapshots(db, &target_key, profile).await?;
+
+    // The first scan of a target is the baseline, only ongoing threats are news
+    let mut changes = current_threats(hosts);
+    if let Some(previous) = previous {
+        let mut previous_hosts: Vec<Host> = serde_json::from_str(&previous.hosts).unwrap_or_default();
+        // Exclusions may have changed since, those hosts didn't vanish
+        previous_hosts.retain(|h| target.contains_ip(&h.ip));
+        changes.extend(diff_hosts(&previous_hosts, hosts));
+    }
+    if changes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let detector = DetectionEngine::new();
+    let mut events = Vec::with_capacity(changes.len());
+
+    for change in &changes {
+        let message = change.message();
+        let event = change_event::ActiveModel {
+            snapshot_id: Set(snapshot.id),
+            target: Set(target_key.clone()),
+            kind: Set(change.kind.as_str().to_string()),
+            severity: Set(change.kind.severity().to_string()),
+            ip: Set(change.ip.to_string()),
+            mac: Set(change.mac.to_string()),
+            port: Set(change.port.map(i32::from)),
+            previous: Set(change.previous.clone()),
+            current: Set(change.current.clone()),
+            message: Set(message.clone()),
+            detected_at: Set(now),
+            ..Default::default()
+        }.insert(db).await?;
+
+        // Feed the SIEM side like any other log source
+        let level = match change.kind.severity() {
+            "HIGH" | "MEDIUM" => "WARN",
+            _ => "INFO",
+        };
+        let metadata = json!({ "change": change, "target": target_key, "snapshot_id": snapshot.id });
+        let normalized = NormalizedLog {
+            source: "scanner".into(),
+            level: level.into(),
+            message: message.clone(),
+            event_time: Utc::now(),
+            metadata: Some(metadata.clone()),
+        };
+        if let Some(alert_msg) = detector.analyze(&normalized) {
+            tracing::warn!("{}", alert_msg);
+        }
+
+        log::ActiveModel {
+            source: Set(normalized.source),
+            level: Set(normalized.level),
+            message: Set(message),
+            raw_content: Set(metadata.to_string()),
+            event_time: Set(now),
+            received_at: Set(now),
+            metadata: Set(Some(metadata.to_string())),
+            ..Default::default()
+        }.insert(db).await?;
+
+        events.push(event);
+    }
+
+    tracing::info!("Scan of {} produced {} change events", target_key, events.len());
+    Ok(events)
+}
+
+async fn prune_snapshots(db: &DatabaseConnection, target: &str, profile: &str) -> Result<(), DbErr> {
+    let keep: Vec<i32> = scan_snapshot::Entity::find()
+        .select_only()
+        .column(scan_snapshot::Column::Id)
+        .filter(scan_snapshot::Column::Target.eq(target))
+        .filter(scan_snapshot::Column::Profile.eq(profile))
+        .order_by_desc(scan_snapshot::Column::Id)
+        .limit(SNAPSHOTS_PER_TARGET)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    scan_snapshot::Entity::delete_many()
+        .filter(scan_snapshot::Column::Target.eq(target))
+        .filter(scan_snapshot::Column::Profile.eq(profile))
+        .filter(scan_snapshot::Column::Id.is_not_in(keep))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Service;
+
+    fn host(ip: &str, mac: &str, ports: &[u16]) -> Host {
+        Host {
+            ip: ip.parse().unwrap(),
+            ipv6: vec![],
+            mac: mac.parse().unwrap(),
+            hostname: ip.into(),
+            vendor: "Unknown".into(),
+            manufacturer: None,
+            model: None,
+            friendly_name: None,
+            os_family: "Linux".into(),
+            os_confidence: 0,
+            device_type: "Server/Web".into(),
+            workgroup: None,
+            logged_in_user: None,
+            spoofed_names: Vec::new(),
+            discovered_by: Vec::new(),
+            identity: Default::default(),
+            snmp: None,
+            open_ports: ports.to_vec(),
+            services: Vec::new(),
+            risk_score: 0,
+        }
+    }
+
+    fn service(port: u16, banner: &str, cves: &[&str]) -> Service {
+        Service {
+            port,
+            protocol: "TCP".into(),
+            name: "tcp".into(),
+            banner: banner.into(),
+            version: "".into(),
+            cpe: vec![],
+            tls: None,
+            cves: cves.iter().map(|c| format!("{}|https://nvd.nist.gov/vuln/detail/{}", c, c)).collect(),
+        }
+    }
+
+    fn kinds(changes: &[Change]) -> Vec<ChangeKind> {
+        changes.iter().map(|c| c.kind).collect()
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let scan = vec![host("10.0.0.1", "AA:BB:CC:00:00:01", &[22, 80])];
+        assert!(diff_hosts(&scan, &scan).is_empty());
+    }
+
+    #[test]
+    fn test_hosts_and_ports() {
+        let before = vec![
+            host("10.0.0.1", "AA:BB:CC:00:00:01", &[22, 80]),
+            host("10.0.0.2", "AA:BB:CC:00:00:02", &[]),
+        ];
+        let after = vec![
+            host("10.0.0.1", "AA:BB:CC:00:00:01", &[80, 443]),
+            host("10.0.0.3", "AA:BB:CC:00:00:03", &[]),
+        ];
+
+        let changes = diff_hosts(&before, &after);
+        assert_eq!(kinds(&changes), vec![
+            ChangeKind::PortOpened, ChangeKind::PortClosed, ChangeKind::HostAppeared, ChangeKind::HostDisappeared,
+        ]);
+        assert_eq!(changes[0].port, Some(443));
+        assert_eq!(changes[1].port, Some(22));
+        assert_eq!(changes[3].ip.to_string(), "10.0.0.2");
+    }
+
+    #[test]
+    fn test_mac_change() {
+        let before = vec![host("10.0.0.1", "AA:BB:CC:00:00:01", &[])];
+        let after = vec![host("10.0.0.1", "DE:AD:BE:EF:00:01", &[])];
+        let changes = diff_hosts(&before, &after);
+        assert_eq!(kinds(&changes), vec![ChangeKind::MacChanged]);
+        assert_eq!(changes[0].previous.as_deref(), Some("AA:BB:CC:00:00:01"));
+
+        // Missing ARP reply is not a MAC change
+        let after = vec![host("10.0.0.1", "00:00:00:00:00:00", &[])];
+        assert!(diff_hosts(&before, &after).is_empty());
+    }
+
+    #[test]
+    fn test_banners_and_cves() {
+        let mut before = host("10.0.0.1", "AA:BB:CC:00:00:01", &[22]);
+        before.services = vec![service(22, "SSH-2.0-OpenSSH_8.2", &["CVE-2020-15778"])];
+        let mut after = before.clone();
+        after.services = vec![service(22, "SSH-2.0-OpenSSH_7.4", &["CVE-2018-15473"])];
+
+        let changes = diff_hosts(&[before], &[after]);
+        assert_eq!(kinds(&changes), vec![ChangeKind::BannerChanged, ChangeKind::CveAdded, ChangeKind::CveResolved]);
+        assert_eq!(changes[1].current.as_deref(), Some("CVE-2018-15473"));
+        assert_eq!(changes[2].previous.as_deref(), Some("CVE-2020-15778"));
+        assert!(changes[1].message().contains("CVE-2018-15473"));
+    }
+
+    #[test]
+    fn test_name_poisoning() {
+        let mut responder = host("10.0.0.66", "AA:BB:CC:00:00:66", &[]);
+        assert!(current_threats(std::slice::from_ref(&responder)).is_empty());
+
+        responder.spoofed_names = vec!["AEGIS1234567890".into()];
+        let changes = current_threats(&[responder]);
+        assert_eq!(kinds(&changes), vec![ChangeKind::NamePoisoning]);
+
+        let log = NormalizedLog {
+            source: "scanner".into(),
+            level: "WARN".into(),
+            message: changes[0].message(),
+            event_time: Utc::now(),
+            metadata: None,
+        };
+        let alert = DetectionEngine::new().analyze(&log).unwrap();
+        assert!(alert.contains("LLMNR/NBNS Poisoning"), "{}", alert);
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm::*;
+use crate::entities::snmp_credential;
+use crate::scanner::fingerprint::snmp::usm::{AuthProtocol, Credential, PrivProtocol, UsmUser};
+use crate::scanner::target::ScanTarget;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Every stored v3 user, ready for a scan. Rows that no longer parse are skipped.
+pub async fn load(db: &DatabaseConnection) -> Result<Vec<Credential>, DbErr> {
+    let models = snmp_credential::Entity::find()
+        .order_by_asc(snmp_credential::Column::Id)
+        .all(db)
+        .await?;
+    Ok(models.iter().filter_map(|m| {
+        let credential = from_model(m);
+        if credential.is_none() {
+            tracing::warn!("Ignoring unreadable SNMPv3 credential {}", m.id);
+        }
+        credential
+    }).collect())
+}
+
+fn from_model(m: &snmp_credential::Model) -> Option<Credential> {
+    let privacy = match (&m.priv_protocol, &m.priv_key) {
+        (Some(protocol), Some(key)) => Some((PrivProtocol::parse(protocol)?, from_hex(key)?)),
+        _ => None,
+    };
+    Some(Credential {
+        scope: ScanTarget::parse_scope(&m.target).ok()?,
+        user: UsmUser {
+            name: m.username.clone(),
+            auth: AuthProtocol::parse(&m.auth_protocol)?,
+            auth_key: from_hex(&m.auth_key)?,
+            privacy,
+        },
+    })
+}
+
+/// Stores a user by its password keys (Ku), in plain hex.
+///
+/// Ku is not localized to an engine, so it authenticates as this user on every
+/// agent that knows them: the database must be protected like the passwords.
+pub async fn save(db: &DatabaseConnection, target: &str, user: &UsmUser) -> Result<snmp_credential::Model, DbErr> {
+    snmp_credential::ActiveModel {
+        target: Set(target.to_string()),
+        username: Set(user.name.clone()),
+        auth_protocol: Set(user.auth.as_str().into()),
+        auth_key: Set(to_hex(&user.auth_key)),
+        priv_protocol: Set(user.privacy.as_ref().map(|(p, _)| p.as_str().into())),
+        priv_key: Set(user.privacy.as_ref().map(|(_, key)| to_hex(key))),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
@@ -25,6 +25,26 @@ impl DetectionEngine {
                 pattern: Regex::new(r"(?i)sudo:.*COMMAND").unwrap(),
                 severity: "MEDIUM".to_string(),
             },
+            Rule {
+                name: "Possible ARP Spoofing".to_string(),
+                pattern: Regex::new(r"^MAC changed for ").unwrap(),
+                severity: "HIGH".to_string(),
+            },
+            Rule {
+                name: "New Vulnerability".to_string(),
+                pattern: Regex::new(r"^New vulnerability CVE-").unwrap(),
+                severity: "HIGH".to_string(),
+            },
+            Rule {
+                name: "LLMNR/NBNS Poisoning".to_string(),
+                pattern: Regex::new(r"^Host \S+ \(\S+\) answered name queries for ").unwrap(),
+                severity: "HIGH".to_string(),
+            },
+            Rule {
+                name: "Rogue Device".to_string(),
+                pattern: Regex::new(r"^New host \S+ \(").unwrap(),
+                severity: "MEDIUM".to_string(),
+            },
         ];
         
         Self { rules }
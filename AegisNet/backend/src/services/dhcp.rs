@@ -0,0 +1,152 @@
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::*;
+use tokio::sync::mpsc;
+use crate::entities::{dhcp_client, host};
+use crate::scanner::discovery::dhcp::{DhcpListener, DhcpRequest};
+use crate::scanner::fingerprint::dhcp::{DhcpFingerprint, DhcpGuess};
+use crate::scanner::fingerprint::oui;
+use crate::scanner::identity::{Attribute, Identity, Source};
+use crate::services::inventory;
+
+const QUEUE_SIZE: usize = 256;
+
+/// Background listener that fingerprints clients from their DHCP requests
+/// and feeds what it learns into the asset inventory.
+pub struct DhcpMonitor {
+    db: DatabaseConnection,
+}
+
+impl DhcpMonitor {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Off unless `DHCP_MONITOR` is set to 1/true: it needs raw sockets and sees every client.
+    pub fn enabled() -> bool {
+        std::env::var("DHCP_MONITOR").is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+    }
+
+    pub fn start(self) {
+        let (tx, mut rx) = mpsc::channel(QUEUE_SIZE);
+
+        tokio::spawn(async move {
+            // Raw sockets need root (or CAP_NET_RAW); the rest of the backend works without them
+            if let Err(e) = DhcpListener::listen(tx).await {
+                tracing::warn!("DHCP fingerprinting disabled: {}", e);
+            }
+        });
+
+        tokio::spawn(async move {
+            while let Some(request) = rx.recv().await {
+                if let Err(e) = self.record(&request).await {
+                    tracing::error!("Failed to record DHCP request from {}: {}", request.client_mac, e);
+                }
+            }
+        });
+        tracing::info!("DHCP monitor started");
+    }
+
+    async fn record(&self, request: &DhcpRequest) -> Result<(), DbErr> {
+        let now = Utc::now().naive_utc();
+        let guess = DhcpFingerprint::identify(request);
+        tracing::debug!("DHCP {} from {} [{}] -> {:?}", request.message_name(), request.client_mac, request.param_key(), guess);
+
+        upsert_client(&self.db, request, guess.as_ref(), now).await?;
+        update_host(&self.db, request, guess.as_ref(), now).await
+    }
+}
+
+async fn upsert_client(db: &DatabaseConnection, request: &DhcpRequest, guess: Option<&DhcpGuess>, now: NaiveDateTime) -> Result<(), DbErr> {
+    let existing = dhcp_client::Entity::find()
+        .filter(dhcp_client::Column::Mac.eq(request.client_mac.to_string()))
+        .one(db)
+        .await?;
+
+    let mut model = match existing {
+        Some(existing) => {
+            let mut model: dhcp_client::ActiveModel = existing.into();
+            // A renewal carries less than the initial request, keep what we had
+            if request.ip.is_some() { model.ip = Set(request.ip.map(|ip| ip.to_string())); }
+            if request.hostname.is_some() { model.hostname = Set(request.hostname.clone()); }
+            model
+        }
+        None => dhcp_client::ActiveModel {
+            mac: Set(request.client_mac.to_string()),
+            ip: Set(request.ip.map(|ip| ip.to_string())),
+            hostname: Set(request.hostname.clone()),
+            first_seen: Set(now),
+            ..Default::default()
+        },
+    };
+    model.vendor_class = Set(request.vendor_class.clone());
+    model.param_list = Set(request.param_key());
+    model.message_type = Set(request.message_name().to_string());
+    model.os_family = Set(guess.map_or("Unknown", |g| g.family.as_str()).to_string());
+    model.device_type = Set(guess.map_or("Unknown", |g| g.device_type.as_str()).to_string());
+    model.label = Set(guess.map_or("", |g| g.label.as_str()).to_string());
+    model.confidence = Set(guess.map_or(0, |g| g.confidence as i32));
+    model.last_seen = Set(now);
+    model.save(db).await?;
+    Ok(())
+}
+
+/// Fills in what active scans could not learn. Hosts are matched by MAC; a client
+/// that was never scanned is added once it has an address. The requested host name
+/// is one more hostname candidate, weighed against what scans reported.
+async fn update_host(db: &DatabaseConnection, request: &DhcpRequest, guess: Option<&DhcpGuess>, now: NaiveDateTime) -> Result<(), DbErr> {
+    let existing = host::Entity::find()
+        .filter(host::Column::Mac.eq(request.client_mac.to_string()))
+        .one(db)
+        .await?;
+    let mut offered = Identity::default();
+    offered.offer(Attribute::Hostname, Source::Dhcp, 60, request.hostname.as_deref());
+
+    match existing {
+        Some(existing) => {
+            let better_os = guess.is_some_and(|g| g.confidence as i32 > existing.os_confidence);
+            let mut model: host::ActiveModel = existing.clone().into();
+
+            if !offered.candidates().is_empty() {
+                let identity = inventory::record_identity(db, existing.id, offered.candidates(), now).await?;
+                inventory::apply_identity(&mut model, &identity);
+            }
+            if let (true, Some(g)) = (better_os, guess) {
+                model.os_family = Set(g.family.clone());
+                model.os_confidence = Set(g.confidence as i32);
+                model.device_type = Set(g.device_type.clone());
+            }
+            if existing.dhcp_first_seen.is_none() {
+                model.dhcp_first_seen = Set(Some(now));
+            }
+            if model.is_changed() {
+                model.update(db).await?;
+            }
+        }
+        None => {
+            let Some(ip) = request.ip else { return Ok(()) };
+            let vendor = oui::OuiDb::lookup(&request.client_mac);
+            let model = host::ActiveModel {
+                ip: Set(ip.to_string()),
+                ipv6: Set("[]".into()),
+                interfaces: Set("[]".into()),
+                neighbors: Set("[]".into()),
+                mac: Set(request.client_mac.to_string()),
+                hostname: Set(request.hostname.clone().unwrap_or_else(|| vendor.clone())),
+                vendor: Set(vendor),
+                os_family: Set(guess.map_or("Unknown", |g| g.family.as_str()).to_string()),
+                os_confidence: Set(guess.map_or(0, |g| g.confidence as i32)),
+                device_type: Set(guess.map_or("Unknown", |g| g.device_type.as_str()).to_string()),
+                open_ports: Set("[]".into()),
+                risk_score: Set(0),
+                first_seen: Set(now),
+                last_seen: Set(now),
+                dhcp_first_seen: Set(Some(now)),
+                ..Default::default()
+            };
+            let res = host::Entity::insert(model).exec(db).await?;
+            inventory::record_identity(db, res.last_insert_id, offered.candidates(), now).await?;
+            tracing::info!("New host {} ({}) joined via DHCP", ip, request.client_mac);
+        }
+    }
+    Ok(())
+}
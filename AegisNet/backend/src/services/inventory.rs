@@ -0,0 +1,318 @@
+use sea_orm::*;
+use chrono::{NaiveDateTime, Utc};
+use crate::entities::{host, host_attribute, service, finding};
+use crate::scanner::addr::MacAddr;
+use crate::scanner::identity::{Attribute, Candidate, Identity, Source};
+use crate::scanner::{Host, Service};
+
+/// Upserts the result of a `ScannerCore::scan_network` run into the asset inventory.
+/// Hosts are matched by MAC when we have one, otherwise by IP.
+pub async fn persist_scan(db: &DatabaseConnection, hosts: &[Host]) -> Result<Vec<i32>, DbErr> {
+    let now = Utc::now().naive_utc();
+    let mut ids = Vec::with_capacity(hosts.len());
+
+    for h in hosts {
+        let host_id = upsert_host(db, h, now).await?;
+        for svc in &h.services {
+            upsert_service(db, host_id, svc, now).await?;
+            upsert_findings(db, host_id, svc, now).await?;
+        }
+        ids.push(host_id);
+    }
+
+    tracing::info!("Inventory updated: {} hosts persisted", ids.len());
+    Ok(ids)
+}
+
+async fn find_existing(db: &DatabaseConnection, h: &Host) -> Result<Option<host::Model>, DbErr> {
+    if !h.mac.is_unspecified() {
+        if let Some(existing) = host::Entity::find()
+            .filter(host::Column::Mac.eq(h.mac.to_string()))
+            .one(db)
+            .await?
+        {
+            return Ok(Some(existing));
+        }
+    }
+
+    // Fallback: same IP. If this scan knows the MAC, only claim a record that has none yet,
+    // otherwise a re-assigned DHCP lease would merge two different devices.
+    let mut query = host::Entity::find().filter(host::Column::Ip.eq(h.ip.to_string()));
+    if !h.mac.is_unspecified() {
+        query = query.filter(host::Column::Mac.eq(MacAddr::UNSPECIFIED.to_string()));
+    }
+    query.order_by_desc(host::Column::LastSeen).one(db).await
+}
+
+async fn upsert_host(db: &DatabaseConnection, h: &Host, now: NaiveDateTime) -> Result<i32, DbErr> {
+    let open_ports = serde_json::to_string(&h.open_ports).unwrap_or_else(|_| "[]".into());
+    let ipv6 = serde_json::to_string(&h.ipv6).unwrap_or_else(|_| "[]".into());
+    let interfaces = serde_json::to_string(&h.snmp.as_ref().map(|s| &s.interfaces).unwrap_or(&Vec::new())).unwrap_or_else(|_| "[]".into());
+    let neighbors = serde_json::to_string(&h.snmp.as_ref().map(|s| &s.neighbors).unwrap_or(&Vec::new())).unwrap_or_else(|_| "[]".into());
+
+    match find_existing(db, h).await? {
+        Some(existing) => {
+            let id = existing.id;
+            let mut model: host::ActiveModel = existing.into();
+            model.ip = Set(h.ip.to_string());
+            if !h.mac.is_unspecified() { model.mac = Set(h.mac.to_string()); }
+            model.vendor = Set(h.vendor.clone());
+            // Name, manufacturer and model are resolved from everything seen so far
+            let identity = record_identity(db, id, h.identity.candidates(), now).await?;
+            apply_identity(&mut model, &identity);
+            if h.workgroup.is_some() { model.workgroup = Set(h.workgroup.clone()); }
+            if h.logged_in_user.is_some() { model.logged_in_user = Set(h.logged_in_user.clone()); }
+            if !h.ipv6.is_empty() { model.ipv6 = Set(ipv6); }
+            // Interfaces and neighbors only change when the agent answered this time
+            if let Some(snmp) = &h.snmp {
+                model.sys_object_id = Set(snmp.sys_object_id.clone());
+                model.interfaces = Set(interfaces);
+                model.neighbors = Set(neighbors);
+            }
+            if h.os_confidence > 0 {
+                model.os_family = Set(h.os_family.clone());
+                model.os_confidence = Set(h.os_confidence as i32);
+                model.device_type = Set(h.device_type.clone());
+            }
+            model.open_ports = Set(open_ports);
+            model.risk_score = Set(h.risk_score as i32);
+            model.last_seen = Set(now);
+            model.update(db).await?;
+            Ok(id)
+        }
+        None => {
+            let model = host::ActiveModel {
+                ip: Set(h.ip.to_string()),
+                ipv6: Set(ipv6),
+                mac: Set(h.mac.to_string()),
+                hostname: Set(h.hostname.clone()),
+                vendor: Set(h.vendor.clone()),
+                manufacturer: Set(h.manufacturer.clone()),
+                model: Set(h.model.clone()),
+                friendly_name: Set(h.friendly_name.clone()),
+                os_family: Set(h.os_family.clone()),
+                os_confidence: Set(h.os_confidence as i32),
+                device_type: Set(h.device_type.clone()),
+                workgroup: Set(h.workgroup.clone()),
+                logged_in_user: Set(h.logged_in_user.clone()),
+                sys_object_id: Set(h.snmp.as_ref().and_then(|s| s.sys_object_id.clone())),
+                interfaces: Set(interfaces),
+                neighbors: Set(neighbors),
+                open_ports: Set(open_ports),
+                risk_score: Set(h.risk_score as i32),
+                first_seen: Set(now),
+                last_seen: Set(now),
+                ..Default::default()
+            };
+            let res = host::Entity::insert(model).exec(db).await?;
+            record_identity(db, res.last_insert_id, h.identity.candidates(), now).await?;
+            Ok(res.last_insert_id)
+        }
+    }
+}
+
+/// Stores the identity candidates of one sighting of a host, counting repeats,
+/// and returns every candidate known for it.
+pub async fn record_identity(db: &DatabaseConnection, host_id: i32, candidates: &[Candidate], now: NaiveDateTime) -> Result<Identity, DbErr> {
+    for c in candidates {
+        let existing = host_attribute::Entity::find()
+            .filter(host_attribute::Column::HostId.eq(host_id))
+            .filter(host_attribute::Column::Attribute.eq(c.attribute.as_str()))
+            .filter(host_attribute::Column::Source.eq(c.source.as_str()))
+            .filter(host_attribute::Column::Value.eq(c.value.clone()))
+            .one(db)
+            .await?;
+
+        match existing {
+            Some(existing) => {
+                let seen = existing.seen_count + 1;
+                let mut model: host_attribute::ActiveModel = existing.into();
+                model.confidence = Set(c.confidence as i32);
+                model.seen_count = Set(seen);
+                model.last_seen = Set(now);
+                model.update(db).await?;
+            }
+            None => {
+                let model = host_attribute::ActiveModel {
+                    host_id: Set(host_id),
+                    attribute: Set(c.attribute.as_str().to_string()),
+                    value: Set(c.value.clone()),
+                    source: Set(c.source.as_str().to_string()),
+                    confidence: Set(c.confidence as i32),
+                    seen_count: Set(1),
+                    first_seen: Set(now),
+                    last_seen: Set(now),
+                    ..Default::default()
+                };
+                host_attribute::Entity::insert(model).exec(db).await?;
+            }
+        }
+    }
+    load_identity(db, host_id).await
+}
+
+pub async fn load_identity(db: &DatabaseConnection, host_id: i32) -> Result<Identity, DbErr> {
+    let rows = host_attribute::Entity::find()
+        .filter(host_attribute::Column::HostId.eq(host_id))
+        .all(db)
+        .await?;
+    let candidates = rows.into_iter()
+        .filter_map(|r| Some(Candidate {
+            attribute: Attribute::parse(&r.attribute)?,
+            source: Source::parse(&r.source)?,
+            value: r.value,
+            confidence: r.confidence.clamp(0, 100) as u8,
+            seen: r.seen_count.max(1) as u32,
+            last_seen: r.last_seen,
+        }))
+        .collect::<Vec<_>>();
+    Ok(candidates.into())
+}
+
+/// Sets the identity columns to the best candidates. Attributes nobody reported keep
+/// their value, so does a hostname learned before candidates were recorded.
+pub fn apply_identity(model: &mut host::ActiveModel, identity: &Identity) {
+    let best = |attribute| identity.best(attribute).map(|c| c.value.clone());
+    if let Some(hostname) = best(Attribute::Hostname) { model.hostname = Set(hostname); }
+    if let Some(manufacturer) = best(Attribute::Manufacturer) { model.manufacturer = Set(Some(manufacturer)); }
+    if let Some(m) = best(Attribute::Model) { model.model = Set(Some(m)); }
+    if let Some(name) = best(Attribute::FriendlyName) { model.friendly_name = Set(Some(name)); }
+}
+
+async fn upsert_service(db: &DatabaseConnection, host_id: i32, svc: &Service, now: NaiveDateTime) -> Result<(), DbErr> {
+    let existing = service::Entity::find()
+        .filter(service::Column::HostId.eq(host_id))
+        .filter(service::Column::Port.eq(svc.port as i32))
+        .filter(service::Column::Protocol.eq(svc.protocol.clone()))
+        .one(db)
+        .await?;
+
+    match existing {
+        Some(existing) => {
+            let mut model: service::ActiveModel = existing.into();
+            model.name = Set(svc.name.clone());
+            model.banner = Set(svc.banner.clone());
+            model.version = Set(svc.version.clone());
+            model.cpe = Set(serde_json::to_string(&svc.cpe).unwrap_or_else(|_| "[]".into()));
+            model.tls = Set(svc.tls.as_ref().and_then(|t| serde_json::to_string(t).ok()));
+            model.last_seen = Set(now);
+            model.update(db).await?;
+        }
+        None => {
+            let model = service::ActiveModel {
+                host_id: Set(host_id),
+                port: Set(svc.port as i32),
+                protocol: Set(svc.protocol.clone()),
+                name: Set(svc.name.clone()),
+                banner: Set(svc.banner.clone()),
+                version: Set(svc.version.clone()),
+                cpe: Set(serde_json::to_string(&svc.cpe).unwrap_or_else(|_| "[]".into())),
+                tls: Set(svc.tls.as_ref().and_then(|t| serde_json::to_string(t).ok())),
+                first_seen: Set(now),
+                last_seen: Set(now),
+                ..Default::default()
+            };
+            service::Entity::insert(model).exec(db).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn upsert_findings(db: &DatabaseConnection, host_id: i32, svc: &Service, now: NaiveDateTime) -> Result<(), DbErr> {
+    for cve in &svc.cves {
+        // Service.cves entries are encoded as "ID|URL"
+        let (cve_id, url) = cve.split_once('|').unwrap_or((cve.as_str(), ""));
+
+        let existing = finding::Entity::find()
+            .filter(finding::Column::HostId.eq(host_id))
+            .filter(finding::Column::Port.eq(svc.port as i32))
+            .filter(finding::Column::CveId.eq(cve_id))
+            .one(db)
+            .await?;
+
+        match existing {
+            Some(existing) => {
+                let mut model: finding::ActiveModel = existing.into();
+                model.url = Set(url.to_string());
+                model.last_seen = Set(now);
+                model.update(db).await?;
+            }
+            None => {
+                let model = finding::ActiveModel {
+                    host_id: Set(host_id),
+                    port: Set(svc.port as i32),
+                    cve_id: Set(cve_id.to_string()),
+                    url: Set(url.to_string()),
+                    first_seen: Set(now),
+                    last_seen: Set(now),
+                    ..Default::default()
+                };
+                finding::Entity::insert(model).exec(db).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(ip: &str, mac: &str) -> Host {
+        Host {
+            ip: ip.parse().unwrap(),
+            ipv6: vec![],
+            mac: mac.parse().unwrap(),
+            hostname: ip.into(),
+            vendor: "Unknown".into(),
+            manufacturer: None,
+            model: None,
+            friendly_name: None,
+            os_family: "Linux".into(),
+            os_confidence: 0,
+            device_type: "Network Device".into(),
+            workgroup: None,
+            logged_in_user: None,
+            spoofed_names: Vec::new(),
+            discovered_by: Vec::new(),
+            identity: Default::default(),
+            snmp: None,
+            open_ports: vec![22],
+            services: Vec::new(),
+            risk_score: 0,
+        }
+    }
+
+    async fn all_hosts(db: &DatabaseConnection) -> Vec<host::Model> {
+        host::Entity::find().order_by_asc(host::Column::Id).all(db).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upsert_follows_mac_across_ip_change() {
+        let db = crate::db::memory().await;
+        let first = persist_scan(&db, &[host("10.0.0.5", "aa:bb:cc:00:00:01")]).await.unwrap();
+
+        // New DHCP lease, same device
+        let second = persist_scan(&db, &[host("10.0.0.9", "aa:bb:cc:00:00:01")]).await.unwrap();
+        assert_eq!(first, second);
+        let hosts = all_hosts(&db).await;
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].ip, "10.0.0.9");
+        assert_eq!(hosts[0].mac, "AA:BB:CC:00:00:01");
+    }
+
+    #[tokio::test]
+    async fn test_upsert_by_ip() {
+        let db = crate::db::memory().await;
+        let routed = persist_scan(&db, &[host("192.168.5.1", "00:00:00:00:00:00")]).await.unwrap();
+
+        // A MAC learned later claims the MAC-less record at that IP
+        let learned = persist_scan(&db, &[host("192.168.5.1", "aa:bb:cc:00:00:02")]).await.unwrap();
+        assert_eq!(routed, learned);
+
+        // but a different device on a re-assigned lease gets its own
+        let other = persist_scan(&db, &[host("192.168.5.1", "aa:bb:cc:00:00:03")]).await.unwrap();
+        assert_ne!(other, learned);
+        assert_eq!(all_hosts(&db).await.len(), 2);
+    }
+}
@@ -0,0 +1,380 @@
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures::FutureExt;
+use sea_orm::DatabaseConnection;
+use serde::{Serialize, Serializer};
+use tokio::sync::watch;
+use tokio::task::AbortHandle;
+use uuid::Uuid;
+use crate::scanner::Host;
+use crate::scanner::core::{ScanOutcome, ScannerCore};
+use crate::scanner::discovery::registry::ModuleReport;
+use crate::scanner::ports;
+use crate::scanner::profile::ScanProfile;
+use crate::scanner::progress::{ScanProgress, ScanPhase, ProgressSnapshot};
+use crate::scanner::target::ScanTarget;
+use crate::services::{changes, credentials, inventory};
+
+// Finished jobs are kept around for polling, but not forever
+const MAX_FINISHED_JOBS: usize = 50;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed(String), // the scan task panicked
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Failed(_) => "failed",
+        }
+    }
+}
+
+// Clients compare against plain strings; a failure's reason goes in `JobView::error`
+impl Serialize for JobStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+struct JobState {
+    status: JobStatus,
+    finished_at: Option<DateTime<Utc>>,
+    hosts: Option<Vec<Host>>,
+    modules: Vec<ModuleReport>,
+    abort: Option<AbortHandle>,
+}
+
+pub struct ScanJob {
+    pub id: Uuid,
+    pub target: ScanTarget,
+    pub profile: String,
+    pub created_at: DateTime<Utc>,
+    pub progress: Arc<ScanProgress>,
+    state: Mutex<JobState>,
+    done: watch::Sender<JobStatus>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct JobView {
+    pub job_id: Uuid,
+    pub target: String,
+    pub profile: String,
+    pub status: JobStatus,
+    #[serde(flatten)]
+    pub progress: ProgressSnapshot,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<ModuleReport>, // per discovery module, once the scan is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hosts: Option<Vec<Host>>,
+}
+
+impl ScanJob {
+    pub fn status(&self) -> JobStatus {
+        self.state.lock().map(|s| s.status.clone()).unwrap_or(JobStatus::Cancelled)
+    }
+
+    pub fn view(&self, include_hosts: bool) -> JobView {
+        let state = self.state.lock().unwrap();
+        JobView {
+            job_id: self.id,
+            target: self.target.to_string(),
+            profile: self.profile.clone(),
+            status: state.status.clone(),
+            progress: self.progress.snapshot(),
+            created_at: self.created_at.to_rfc3339(),
+            finished_at: state.finished_at.map(|t| t.to_rfc3339()),
+            error: match &state.status {
+                JobStatus::Failed(e) => Some(e.clone()),
+                _ => None,
+            },
+            modules: state.modules.clone(),
+            hosts: if include_hosts { state.hosts.clone() } else { None },
+        }
+    }
+
+    pub fn hosts_found(&self) -> usize {
+        self.state.lock().ok()
+            .and_then(|s| s.hosts.as_ref().map(Vec::len))
+            .unwrap_or(0)
+    }
+
+    /// Resolves once the job has completed, failed or been cancelled.
+    pub async fn wait(&self) -> JobStatus {
+        let mut rx = self.done.subscribe();
+        let status = match rx.wait_for(|s| *s != JobStatus::Running).await {
+            Ok(s) => s.clone(),
+            Err(_) => JobStatus::Cancelled,
+        };
+        status
+    }
+
+    /// Scans, persists the results and finishes the job.
+    async fn run(&self, db: DatabaseConnection, mut profile: ScanProfile) {
+        tracing::info!("Scan job {} started on {} (profile: {})", self.id, self.target, self.profile);
+        match credentials::load(&db).await {
+            Ok(users) => profile.options.snmp_credentials = users,
+            Err(e) => tracing::warn!("Failed to load SNMP credentials: {}", e),
+        }
+        // Past the open file limit connects fail and open ports would read as closed
+        let options = &mut profile.options;
+        if options.fit_socket_budget(ports::socket_budget()) {
+            tracing::warn!("Scan job {}: profile exceeds the open file limit, scanning {} hosts x {} ports at a time",
+                self.id, options.host_concurrency, options.port_concurrency);
+        }
+        let outcome = ScannerCore::scan_network(&self.target, &profile, &self.progress).await;
+        let hosts = &outcome.hosts;
+
+        // Persist into the asset inventory
+        self.progress.set_phase(ScanPhase::Persisting);
+        if let Err(e) = inventory::persist_scan(&db, hosts).await {
+            tracing::error!("Failed to persist scan results: {}", e);
+        }
+        if let Err(e) = changes::record_scan(&db, &self.target, &self.profile, &self.id.to_string(), hosts).await {
+            tracing::error!("Failed to record scan changes: {}", e);
+        }
+
+        self.progress.set_phase(ScanPhase::Done);
+        tracing::info!("Scan job {} finished: {} hosts", self.id, hosts.len());
+        self.finish(outcome);
+    }
+
+    fn finish(&self, outcome: ScanOutcome) {
+        let mut state = self.state.lock().unwrap();
+        // A cancel may have raced the final persist; don't resurrect the job
+        if state.status != JobStatus::Running { return; }
+        state.status = JobStatus::Completed;
+        state.finished_at = Some(Utc::now());
+        state.hosts = Some(outcome.hosts);
+        state.modules = outcome.modules;
+        state.abort = None;
+        self.done.send_replace(JobStatus::Completed);
+    }
+
+    fn fail(&self, error: String) {
+        let mut state = self.state.lock().unwrap();
+        if state.status != JobStatus::Running { return; }
+        state.status = JobStatus::Failed(error.clone());
+        state.finished_at = Some(Utc::now());
+        state.abort = None;
+        self.done.send_replace(JobStatus::Failed(error));
+    }
+
+    fn cancel(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.status != JobStatus::Running { return false; }
+        if let Some(handle) = state.abort.take() {
+            handle.abort();
+        }
+        state.status = JobStatus::Cancelled;
+        state.finished_at = Some(Utc::now());
+        self.done.send_replace(JobStatus::Cancelled);
+        true
+    }
+}
+
+/// Background scan jobs, keyed by job ID. Held in `AppState`.
+pub struct ScanJobRegistry {
+    jobs: DashMap<Uuid, Arc<ScanJob>>,
+}
+
+impl ScanJobRegistry {
+    pub fn new() -> Self {
+        Self { jobs: DashMap::new() }
+    }
+
+    /// Spawns a scan in the background and returns immediately.
+    pub fn start(&self, db: DatabaseConnection, target: ScanTarget, profile: ScanProfile) -> Arc<ScanJob> {
+        let job = self.register(target, &profile.name);
+        let runner = job.clone();
+        self.spawn(&job, async move { runner.run(db, profile).await });
+        job
+    }
+
+    fn register(&self, target: ScanTarget, profile: &str) -> Arc<ScanJob> {
+        self.prune();
+
+        let job = Arc::new(ScanJob {
+            id: Uuid::new_v4(),
+            target,
+            profile: profile.to_string(),
+            created_at: Utc::now(),
+            progress: Arc::new(ScanProgress::new()),
+            state: Mutex::new(JobState {
+                status: JobStatus::Running,
+                finished_at: None,
+                hosts: None,
+                modules: Vec::new(),
+                abort: None,
+            }),
+            done: watch::channel(JobStatus::Running).0,
+        });
+        self.jobs.insert(job.id, job.clone());
+        job
+    }
+
+    /// Runs the job's task, which is expected to `finish` it.
+    fn spawn(&self, job: &Arc<ScanJob>, task: impl Future<Output = ()> + Send + 'static) {
+        let runner = job.clone();
+        let handle = tokio::spawn(async move {
+            // A panic anywhere in the scan must still end the job, or pollers wait forever
+            if let Err(panic) = AssertUnwindSafe(task).catch_unwind().await {
+                let error = panic_message(panic.as_ref());
+                tracing::error!("Scan job {} failed: {}", runner.id, error);
+                runner.fail(error);
+            }
+        });
+
+        if let Ok(mut state) = job.state.lock() {
+            state.abort = Some(handle.abort_handle());
+        }
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<Arc<ScanJob>> {
+        self.jobs.get(id).map(|j| j.value().clone())
+    }
+
+    /// Returns `None` if the job is unknown, `Some(false)` if it already finished.
+    pub fn cancel(&self, id: &Uuid) -> Option<bool> {
+        let job = self.get(id)?;
+        let cancelled = job.cancel();
+        if cancelled {
+            tracing::info!("Scan job {} cancelled", id);
+        }
+        Some(cancelled)
+    }
+
+    fn prune(&self) {
+        let mut finished: Vec<(Uuid, DateTime<Utc>)> = self.jobs.iter()
+            .filter(|j| j.status() != JobStatus::Running)
+            .map(|j| (j.id, j.created_at))
+            .collect();
+        if finished.len() <= MAX_FINISHED_JOBS { return; }
+
+        finished.sort_by_key(|(_, created)| *created);
+        let excess = finished.len() - MAX_FINISHED_JOBS;
+        for (id, _) in finished.into_iter().take(excess) {
+            self.jobs.remove(&id);
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Scan task panicked".into())
+}
+
+impl Default for ScanJobRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn outcome() -> ScanOutcome {
+        ScanOutcome { hosts: Vec::new(), modules: Vec::new() }
+    }
+
+    fn register(registry: &ScanJobRegistry) -> Arc<ScanJob> {
+        registry.register(ScanTarget::parse("10.0.0.0/30").unwrap(), ScanProfile::DEFAULT)
+    }
+
+    async fn wait(job: &ScanJob) -> JobStatus {
+        tokio::time::timeout(Duration::from_secs(5), job.wait()).await.expect("job never finished")
+    }
+
+    #[tokio::test]
+    async fn test_job_completes() {
+        let registry = ScanJobRegistry::new();
+        let job = register(&registry);
+        assert_eq!(job.status(), JobStatus::Running);
+        assert!(job.view(false).finished_at.is_none());
+
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let runner = job.clone();
+        registry.spawn(&job, async move {
+            let _ = released.await;
+            runner.finish(outcome());
+        });
+        assert_eq!(registry.get(&job.id).unwrap().status(), JobStatus::Running);
+
+        release.send(()).unwrap();
+        assert_eq!(wait(&job).await, JobStatus::Completed);
+        let view = job.view(true);
+        assert!(view.finished_at.is_some() && view.error.is_none());
+        assert_eq!(view.hosts.map(|h| h.len()), Some(0));
+
+        // Finished jobs stay finished
+        assert_eq!(registry.cancel(&job.id), Some(false));
+        job.fail("late".into());
+        assert_eq!(job.status(), JobStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_job_panic_fails() {
+        let registry = ScanJobRegistry::new();
+        let job = register(&registry);
+        registry.spawn(&job, async { panic!("raw socket exploded") });
+
+        assert_eq!(wait(&job).await, JobStatus::Failed("raw socket exploded".into()));
+        let view = job.view(false);
+        assert_eq!(view.status.as_str(), "failed");
+        assert_eq!(view.error.as_deref(), Some("raw socket exploded"));
+        assert!(view.finished_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_job_cancel() {
+        let registry = ScanJobRegistry::new();
+        let job = register(&registry);
+        let (dropped, task_dropped) = tokio::sync::oneshot::channel::<()>();
+        registry.spawn(&job, async move {
+            let _dropped = dropped; // closes the channel once the task is aborted
+            std::future::pending::<()>().await;
+        });
+
+        assert_eq!(registry.cancel(&job.id), Some(true));
+        assert_eq!(wait(&job).await, JobStatus::Cancelled);
+        assert!(tokio::time::timeout(Duration::from_secs(5), task_dropped).await.is_ok(), "scan task kept running");
+
+        // A scan finishing after the cancel doesn't resurrect the job
+        job.finish(outcome());
+        assert_eq!(job.status(), JobStatus::Cancelled);
+        assert_eq!(registry.cancel(&job.id), Some(false));
+        assert_eq!(registry.cancel(&Uuid::new_v4()), None);
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_running_jobs() {
+        let registry = ScanJobRegistry::new();
+        let running = register(&registry);
+        for _ in 0..MAX_FINISHED_JOBS + 5 {
+            register(&registry).finish(outcome());
+        }
+        register(&registry);
+
+        let finished = registry.jobs.iter().filter(|j| j.status() != JobStatus::Running).count();
+        assert_eq!(finished, MAX_FINISHED_JOBS);
+        assert!(registry.get(&running.id).is_some());
+    }
+}
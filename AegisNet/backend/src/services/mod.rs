@@ -3,3 +3,10 @@ pub mod scanner;
 pub mod detection;
 pub mod discovery;
 pub mod cve;
+pub mod inventory;
+pub mod changes;
+pub mod jobs;
+pub mod profiles;
+pub mod scheduler;
+pub mod dhcp;
+pub mod credentials;
@@ -0,0 +1,110 @@
+use std::time::Duration;
+use sea_orm::*;
+use crate::entities::scan_profile;
+use crate::scanner::core::ScanOptions;
+use crate::scanner::ports::PortSpec;
+use crate::scanner::profile::ScanProfile;
+
+/// Inserts the built-in presets that are missing and refreshes the stored ones
+/// (they are read-only, so new discovery methods reach existing databases).
+/// Custom profiles are left alone.
+pub async fn seed_builtin(db: &DatabaseConnection) -> Result<(), DbErr> {
+    for profile in ScanProfile::builtin() {
+        let existing = scan_profile::Entity::find()
+            .filter(scan_profile::Column::Name.eq(profile.name.clone()))
+            .one(db)
+            .await?;
+        let mut model = to_active_model(&profile);
+        model.builtin = Set(true);
+        match existing {
+            Some(e) if e.builtin => {
+                model.id = Unchanged(e.id);
+                model.update(db).await?;
+            }
+            Some(_) => {}
+            None => { scan_profile::Entity::insert(model).exec(db).await?; }
+        }
+    }
+    Ok(())
+}
+
+pub async fn load(db: &DatabaseConnection, name: &str) -> Result<Option<ScanProfile>, DbErr> {
+    let model = scan_profile::Entity::find()
+        .filter(scan_profile::Column::Name.eq(name))
+        .one(db)
+        .await?;
+    Ok(model.map(|m| from_model(&m)))
+}
+
+/// Creates or replaces a custom profile. Built-in presets are read-only.
+pub async fn save(db: &DatabaseConnection, profile: &ScanProfile) -> Result<Option<scan_profile::Model>, DbErr> {
+    let existing = scan_profile::Entity::find()
+        .filter(scan_profile::Column::Name.eq(profile.name.clone()))
+        .one(db)
+        .await?;
+
+    if existing.as_ref().is_some_and(|e| e.builtin) {
+        return Ok(None);
+    }
+
+    let mut model = to_active_model(profile);
+    match existing {
+        Some(e) => {
+            model.id = Unchanged(e.id);
+            model.builtin = Set(false);
+            Ok(Some(model.update(db).await?))
+        }
+        None => {
+            model.builtin = Set(false);
+            Ok(Some(model.insert(db).await?))
+        }
+    }
+}
+
+pub fn from_model(m: &scan_profile::Model) -> ScanProfile {
+    let defaults = ScanOptions::default();
+    ScanProfile {
+        name: m.name.clone(),
+        description: m.description.clone(),
+        methods: serde_json::from_str(&m.methods).unwrap_or_default(),
+        mdns_duration: Duration::from_secs(m.mdns_secs.max(0) as u64),
+        llmnr_duration: Duration::from_secs(m.llmnr_secs.max(0) as u64),
+        ssdp_duration: Duration::from_secs(m.ssdp_secs.max(0) as u64),
+        arp_settle: Duration::from_millis(m.arp_settle_ms.max(0) as u64),
+        max_probes_per_sec: m.max_probes_per_sec.max(0) as u32,
+        intrusive: m.intrusive,
+        options: ScanOptions {
+            ports: PortSpec::parse(&m.ports).unwrap_or(defaults.ports),
+            port_concurrency: m.port_concurrency.max(1) as usize,
+            host_concurrency: m.host_concurrency.max(1) as usize,
+            connect_timeout: Duration::from_millis(m.connect_timeout_ms.max(1) as u64),
+            snmp_communities: serde_json::from_str(&m.snmp_communities).unwrap_or(defaults.snmp_communities),
+            snmp_credentials: Vec::new(),
+        },
+    }
+}
+
+fn to_active_model(p: &ScanProfile) -> scan_profile::ActiveModel {
+    scan_profile::ActiveModel {
+        name: Set(p.name.clone()),
+        description: Set(p.description.clone()),
+        methods: Set(serde_json::to_string(&p.methods).unwrap_or_else(|_| "[]".into())),
+        mdns_secs: Set(column(p.mdns_duration.as_secs())),
+        llmnr_secs: Set(column(p.llmnr_duration.as_secs())),
+        ssdp_secs: Set(column(p.ssdp_duration.as_secs())),
+        arp_settle_ms: Set(column(p.arp_settle.as_millis())),
+        ports: Set(p.options.ports.to_string()),
+        port_concurrency: Set(column(p.options.port_concurrency)),
+        host_concurrency: Set(column(p.options.host_concurrency)),
+        connect_timeout_ms: Set(column(p.options.connect_timeout.as_millis())),
+        max_probes_per_sec: Set(column(p.max_probes_per_sec)),
+        intrusive: Set(p.intrusive),
+        snmp_communities: Set(serde_json::to_string(&p.options.snmp_communities).unwrap_or_else(|_| "[]".into())),
+        ..Default::default()
+    }
+}
+
+/// Saturates instead of wrapping into a negative value that reads back as the minimum.
+fn column(value: impl TryInto<i32>) -> i32 {
+    value.try_into().unwrap_or(i32::MAX)
+}
@@ -1,70 +1,167 @@
+use std::io;
 use std::net::{IpAddr, SocketAddr};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use futures::stream::{self, StreamExt};
 use tokio::net::TcpStream;
-use tokio::sync::mpsc;
 use serde::{Deserialize, Serialize};
+use crate::scanner::ports::{AdaptiveTimeout, PortSpec};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanResult {
-    pub ip: String,
+    pub ip: IpAddr,
     pub open_ports: Vec<u16>,
     pub status: String,
 }
 
 pub struct Scanner {
     pub target: IpAddr,
-    pub start_port: u16,
-    pub end_port: u16,
+    pub ports: PortSpec,
+    pub concurrency: usize,
+    timeout: Arc<AdaptiveTimeout>,
 }
 
 impl Scanner {
-    pub fn new(target: IpAddr, start_port: u16, end_port: u16) -> Self {
+    pub fn new(target: IpAddr, ports: PortSpec) -> Self {
         Self {
             target,
-            start_port,
-            end_port,
+            ports,
+            concurrency: 256,
+            timeout: Arc::new(AdaptiveTimeout::new(
+                Duration::from_millis(500),
+                Duration::from_millis(80),
+                Duration::from_millis(1500),
+            )),
         }
     }
 
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_timeout(mut self, initial: Duration) -> Self {
+        self.timeout = Arc::new(AdaptiveTimeout::new(
+            initial,
+            Duration::from_millis(80).min(initial),
+            (initial * 3).max(Duration::from_millis(1500)),
+        ));
+        self
+    }
+
     pub async fn run(&self) -> ScanResult {
-        let (tx, mut rx) = mpsc::channel(100);
         let mut open_ports = Vec::new();
 
-        // Spawn tasks for each port
-        for port in self.start_port..=self.end_port {
-            let target = self.target;
-            let tx = tx.clone();
-            
-            tokio::spawn(async move {
-                if scan_port(target, port).await {
-                    let _ = tx.send(port).await;
+        // At most `concurrency` connects in flight, results collected as they finish
+        let mut probes = stream::iter(self.ports.ports().iter().copied())
+            .map(|port| {
+                let timeout = &self.timeout;
+                async move { (port, scan_port(self.target, port, timeout).await) }
+            })
+            .buffer_unordered(self.concurrency);
+
+        let mut status = "Completed".to_string();
+        let mut local_errors = 0usize;
+        while let Some((port, probe)) = probes.next().await {
+            match probe {
+                Probe::Open => open_ports.push(port),
+                Probe::Closed => {}
+                Probe::Exhausted(e) => {
+                    // Every further connect would fail the same way and read as closed
+                    tracing::error!("Port scan of {} aborted at port {}: {}", self.target, port, e);
+                    status = format!("Aborted: {}", e);
+                    break;
+                }
+                Probe::Failed(e) => {
+                    if local_errors == 0 {
+                        tracing::warn!("Port scan of {}: connect to port {} failed: {}", self.target, port, e);
+                    }
+                    local_errors += 1;
                 }
-            });
+            }
         }
-        
-        // Drop original tx so rx knows when to stop
-        drop(tx);
-
-        while let Some(port) = rx.recv().await {
-            open_ports.push(port);
+        if local_errors > 1 {
+            tracing::warn!("Port scan of {}: {} connects failed with local errors", self.target, local_errors);
         }
         open_ports.sort();
 
         ScanResult {
-            ip: self.target.to_string(),
+            ip: self.target,
             open_ports,
-            status: "Completed".to_string(),
+            status,
         }
     }
 }
 
-async fn scan_port(ip: IpAddr, port: u16) -> bool {
+/// Outcome of a single connect attempt.
+enum Probe {
+    Open,
+    /// Refused or timed out
+    Closed,
+    /// The connect never reached the target (unreachable network, permissions, ...)
+    Failed(io::Error),
+    /// Out of file descriptors, the scan cannot go on
+    Exhausted(io::Error),
+}
+
+async fn scan_port(ip: IpAddr, port: u16, timeout: &AdaptiveTimeout) -> Probe {
     let addr = SocketAddr::new(ip, port);
-    // Short timeout for speed
-    let timeout = Duration::from_millis(500); 
-    
-    match tokio::time::timeout(timeout, TcpStream::connect(&addr)).await {
-        Ok(Ok(_)) => true,
-        _ => false,
+    let started = Instant::now();
+
+    match tokio::time::timeout(timeout.current(), TcpStream::connect(&addr)).await {
+        Ok(Ok(_)) => {
+            timeout.observe(started.elapsed());
+            Probe::Open
+        }
+        // A RST is as good an RTT sample as a SYN-ACK
+        Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => {
+            timeout.observe(started.elapsed());
+            Probe::Closed
+        }
+        Ok(Err(e)) if is_fd_exhaustion(&e) => Probe::Exhausted(e),
+        Ok(Err(e)) => Probe::Failed(e),
+        Err(_) => Probe::Closed,
+    }
+}
+
+fn is_fd_exhaustion(e: &io::Error) -> bool {
+    #[cfg(not(windows))]
+    { matches!(e.raw_os_error(), Some(libc::EMFILE) | Some(libc::ENFILE)) }
+    #[cfg(windows)]
+    { e.raw_os_error() == Some(windows_sys::Win32::Networking::WinSock::WSAEMFILE) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_run_many_open_ports() {
+        // More open ports than any buffer in the scan path, few connects in flight
+        let mut listeners = Vec::new();
+        for _ in 0..150 {
+            listeners.push(TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap());
+        }
+        let mut expected: Vec<u16> = listeners.iter().map(|l| l.local_addr().unwrap().port()).collect();
+        expected.sort_unstable();
+
+        let spec = PortSpec::parse(&expected.iter().map(u16::to_string).collect::<Vec<_>>().join(",")).unwrap();
+        let scanner = Scanner::new(IpAddr::V4(Ipv4Addr::LOCALHOST), spec).with_concurrency(4);
+        let result = tokio::time::timeout(Duration::from_secs(30), scanner.run()).await
+            .expect("scan of a host with many open ports hung");
+
+        assert_eq!(result.open_ports, expected);
+        assert_eq!(result.status, "Completed");
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_fd_exhaustion_detected() {
+        assert!(is_fd_exhaustion(&io::Error::from_raw_os_error(libc::EMFILE)));
+        assert!(is_fd_exhaustion(&io::Error::from_raw_os_error(libc::ENFILE)));
+        assert!(!is_fd_exhaustion(&io::Error::from_raw_os_error(libc::ENETUNREACH)));
+        assert!(!is_fd_exhaustion(&io::Error::from(io::ErrorKind::ConnectionRefused)));
     }
 }
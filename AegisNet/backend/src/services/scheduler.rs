@@ -0,0 +1,317 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{NaiveDateTime, Utc};
+use cron::Schedule;
+use sea_orm::*;
+use crate::entities::{schedule, schedule_run};
+use crate::scanner::target::ScanTarget;
+use crate::services::discovery;
+use crate::services::jobs::{ScanJobRegistry, JobStatus};
+use crate::services::profiles;
+
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Parses a crontab expression. Accepts the classic 5-field form
+/// (`min hour dom month dow`, Sunday = 0 or 7), the 6-field form with
+/// seconds (same weekday numbering), and the `@hourly` / `@daily` / `@weekly` / `@monthly` macros.
+pub fn parse_cron(expr: &str) -> Result<Schedule, String> {
+    let expr = expr.trim();
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+
+    // The cron crate counts weekdays 1-7 from Sunday, crontab 0-7 with Sunday at both ends
+    let normalized = match fields.len() {
+        1 if expr.starts_with('@') => expr.to_lowercase(),
+        5 => format!("0 {} {} {} {} {}", fields[0], fields[1], fields[2], fields[3], shift_weekdays(fields[4])?),
+        6 => format!("{} {} {} {} {} {}", fields[0], fields[1], fields[2], fields[3], fields[4], shift_weekdays(fields[5])?),
+        _ => return Err(format!("Invalid cron expression '{}': expected 5 or 6 fields", expr)),
+    };
+
+    Schedule::from_str(&normalized).map_err(|e| format!("Invalid cron expression '{}': {}", expr, e))
+}
+
+/// Numeric days are expanded to a list, so a range ending on Sunday (7)
+/// doesn't turn into an inverted one like "6-1".
+fn shift_weekdays(field: &str) -> Result<String, String> {
+    let day = |part: &str| match part.parse::<u8>() {
+        Ok(n @ 0..=7) => Ok(Some(n)),
+        Ok(_) => Err(format!("Invalid day of week '{}'", part)),
+        Err(_) => Ok(None), // names and wildcards
+    };
+
+    let mut days: Vec<String> = Vec::new();
+    for item in field.split(',') {
+        let (base, step) = match item.split_once('/') {
+            Some((b, s)) => (b, Some(s.parse::<usize>().ok().filter(|s| *s > 0).ok_or_else(|| format!("Invalid step '{}'", s))?)),
+            None => (item, None),
+        };
+        let range = match (base, base.split_once('-')) {
+            ("*", _) if step.is_some() => Some((0, 6)),
+            (_, Some((from, to))) => day(from)?.zip(day(to)?),
+            (_, None) => day(base)?.map(|d| (d, d)),
+        };
+        match range {
+            Some((from, to)) if from <= to => {
+                for d in (from..=to).step_by(step.unwrap_or(1)) {
+                    let shifted = (d % 7 + 1).to_string();
+                    if !days.contains(&shifted) { days.push(shifted); }
+                }
+            }
+            Some(_) => return Err(format!("Invalid day of week range '{}'", base)),
+            None => days.push(item.to_string()),
+        }
+    }
+    Ok(days.join(","))
+}
+
+/// Next fire time after now, in naive UTC like the rest of the schema.
+pub fn next_run(cron: &str) -> Option<NaiveDateTime> {
+    parse_cron(cron).ok()?.upcoming(Utc).next().map(|t| t.naive_utc())
+}
+
+/// "auto" resolves to the local subnet at run time, like a manual scan.
+pub fn resolve_target(spec: &str, exclude: Option<&str>) -> Result<ScanTarget, String> {
+    let spec = if spec == "auto" {
+        discovery::NetworkDiscovery::detect_local_subnet()
+    } else {
+        spec.to_string()
+    };
+    ScanTarget::parse(&spec)?.with_exclusions(exclude.unwrap_or(""))
+}
+
+/// Background loop that launches due schedules through the job registry.
+pub struct Scheduler {
+    db: DatabaseConnection,
+    jobs: Arc<ScanJobRegistry>,
+}
+
+impl Scheduler {
+    pub fn new(db: DatabaseConnection, jobs: Arc<ScanJobRegistry>) -> Self {
+        Self { db, jobs }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            if let Err(e) = self.recover().await {
+                tracing::error!("Scheduler failed to recover interrupted runs: {}", e);
+            }
+
+            let mut interval = tokio::time::interval(TICK_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.tick().await {
+                    tracing::error!("Scheduler tick failed: {}", e);
+                }
+            }
+        });
+        tracing::info!("Scan scheduler started");
+    }
+
+    /// Runs that were in flight when the backend stopped will never report back.
+    async fn recover(&self) -> Result<(), DbErr> {
+        schedule_run::Entity::update_many()
+            .col_expr(schedule_run::Column::Status, sea_query::Expr::value("failed"))
+            .col_expr(schedule_run::Column::Error, sea_query::Expr::value("Interrupted by backend restart"))
+            .col_expr(schedule_run::Column::FinishedAt, sea_query::Expr::value(Utc::now().naive_utc()))
+            .filter(schedule_run::Column::Status.eq("running"))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn tick(&self) -> Result<(), DbErr> {
+        let now = Utc::now().naive_utc();
+        let schedules = schedule::Entity::find()
+            .filter(schedule::Column::Enabled.eq(true))
+            .all(&self.db)
+            .await?;
+
+        // One schedule's DB error must not hold back the others due this tick
+        for sched in schedules {
+            let (id, name) = (sched.id, sched.name.clone());
+            let result = match sched.next_run_at {
+                Some(next) if next <= now => self.launch(sched).await,
+                Some(_) => Ok(()),
+                // Never planned (e.g. just re-enabled), plan it rather than firing right away
+                None => {
+                    let mut model: schedule::ActiveModel = sched.clone().into();
+                    model.next_run_at = Set(next_run(&sched.cron));
+                    model.update(&self.db).await.map(|_| ())
+                }
+            };
+            if let Err(e) = result {
+                tracing::error!("Schedule '{}' ({}) failed this tick: {}", name, id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn launch(&self, sched: schedule::Model) -> Result<(), DbErr> {
+        let now = Utc::now().naive_utc();
+
+        let mut model: schedule::ActiveModel = sched.clone().into();
+        model.last_run_at = Set(Some(now));
+        model.next_run_at = Set(next_run(&sched.cron));
+        model.update(&self.db).await?;
+
+        // A long scan can outlast its interval, don't stack runs on the same target
+        let in_progress = schedule_run::Entity::find()
+            .filter(schedule_run::Column::ScheduleId.eq(sched.id))
+            .filter(schedule_run::Column::Status.eq("running"))
+            .count(&self.db)
+            .await?;
+        if in_progress > 0 {
+            tracing::info!("Schedule '{}' skipped, its previous run is still in progress", sched.name);
+            return self.record_unstarted(&sched, "skipped", "Previous run still in progress".into(), now).await;
+        }
+
+        let target = resolve_target(&sched.target, sched.exclude.as_deref());
+        let profile = profiles::load(&self.db, &sched.profile).await?
+            .ok_or_else(|| format!("Unknown scan profile '{}'", sched.profile));
+
+        let (target, profile) = match (target, profile) {
+            (Ok(t), Ok(p)) => (t, p),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::warn!("Schedule '{}' could not start: {}", sched.name, e);
+                return self.record_unstarted(&sched, "failed", e, now).await;
+            }
+        };
+
+        tracing::info!("Schedule '{}' firing on {} (profile: {})", sched.name, target, profile.name);
+        let job = self.jobs.start(self.db.clone(), target, profile);
+
+        let run = schedule_run::ActiveModel {
+            schedule_id: Set(sched.id),
+            job_id: Set(job.id.to_string()),
+            status: Set("running".into()),
+            hosts_found: Set(0),
+            error: Set(None),
+            started_at: Set(now),
+            finished_at: Set(None),
+            ..Default::default()
+        }.insert(&self.db).await?;
+
+        // Record the outcome once the job is done
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            let status = job.wait().await;
+            let mut model: schedule_run::ActiveModel = run.into();
+            model.status = Set(status.as_str().into());
+            if let JobStatus::Failed(error) = status {
+                model.error = Set(Some(error));
+            }
+            model.hosts_found = Set(job.hosts_found() as i32);
+            model.finished_at = Set(Some(Utc::now().naive_utc()));
+            if let Err(e) = model.update(&db).await {
+                tracing::error!("Failed to record schedule run: {}", e);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// A run that never got a scan job: it could not start or was skipped.
+    async fn record_unstarted(&self, sched: &schedule::Model, status: &str, error: String, at: NaiveDateTime) -> Result<(), DbErr> {
+        schedule_run::ActiveModel {
+            schedule_id: Set(sched.id),
+            job_id: Set(String::new()),
+            status: Set(status.into()),
+            hosts_found: Set(0),
+            error: Set(Some(error)),
+            started_at: Set(at),
+            finished_at: Set(Some(at)),
+            ..Default::default()
+        }.insert(&self.db).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Timelike, Weekday};
+
+    #[test]
+    fn test_parse_cron() {
+        assert!(parse_cron("0 2 * * *").is_ok());
+        assert!(parse_cron("*/15 * * * *").is_ok());
+        assert!(parse_cron("0 0 2 * * *").is_ok());
+        assert!(parse_cron("@hourly").is_ok());
+        assert!(parse_cron("@Daily").is_ok());
+        assert!(parse_cron("0 2 * *").is_err());
+        assert!(parse_cron("61 * * * *").is_err());
+        assert!(parse_cron("0 2 * * 8").is_err());
+    }
+
+    #[test]
+    fn test_crontab_weekdays() {
+        // Sunday is 0 (or 7) in crontab
+        for expr in ["30 3 * * 0", "30 3 * * 7", "30 3 * * SUN"] {
+            let next = parse_cron(expr).unwrap().upcoming(Utc).next().unwrap();
+            assert_eq!(next.weekday(), Weekday::Sun, "{}", expr);
+            assert_eq!((next.hour(), next.minute()), (3, 30));
+        }
+
+        let days = |expr: &str| {
+            let mut days: Vec<Weekday> = parse_cron(expr).unwrap().upcoming(Utc).take(7).map(|t| t.weekday()).collect();
+            days.sort_by_key(|d| d.num_days_from_monday());
+            days.dedup();
+            days
+        };
+        assert_eq!(days("0 9 * * 1-5"), [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]);
+        // Ends on Sunday as 7, must not wrap
+        assert_eq!(days("0 9 * * 5-7"), [Weekday::Fri, Weekday::Sat, Weekday::Sun]);
+        assert_eq!(days("0 9 * * 0,6"), [Weekday::Sat, Weekday::Sun]);
+        assert_eq!(days("0 9 * * */3"), [Weekday::Wed, Weekday::Sat, Weekday::Sun]);
+        assert_eq!(days("0 9 * * MON-FRI"), days("0 9 * * 1-5"));
+        assert!(parse_cron("0 9 * * 6-2").is_err());
+
+        // Seconds in front change nothing about the weekdays
+        assert_eq!(days("0 0 9 * * 1"), [Weekday::Mon]);
+        assert_eq!(days("0 0 9 * * 0"), days("0 9 * * 0"));
+        assert_eq!(days("30 0 9 * * 5-7"), days("0 9 * * 5-7"));
+    }
+
+    #[tokio::test]
+    async fn test_skips_overlapping_run() {
+        let db = crate::db::memory().await;
+        let now = Utc::now().naive_utc();
+        let sched = schedule::ActiveModel {
+            name: Set("hourly audit".into()),
+            target: Set("10.0.0.0/24".into()),
+            exclude: Set(None),
+            profile: Set("deep-audit".into()),
+            cron: Set("@hourly".into()),
+            enabled: Set(true),
+            created_at: Set(now),
+            last_run_at: Set(None),
+            next_run_at: Set(Some(now)),
+            ..Default::default()
+        }.insert(&db).await.unwrap();
+        schedule_run::ActiveModel {
+            schedule_id: Set(sched.id),
+            job_id: Set("previous".into()),
+            status: Set("running".into()),
+            hosts_found: Set(0),
+            error: Set(None),
+            started_at: Set(now),
+            finished_at: Set(None),
+            ..Default::default()
+        }.insert(&db).await.unwrap();
+
+        let jobs = Arc::new(ScanJobRegistry::new());
+        Scheduler::new(db.clone(), jobs).launch(sched.clone()).await.unwrap();
+
+        let runs = schedule_run::Entity::find()
+            .filter(schedule_run::Column::ScheduleId.eq(sched.id))
+            .order_by_asc(schedule_run::Column::Id)
+            .all(&db).await.unwrap();
+        let statuses: Vec<&str> = runs.iter().map(|r| r.status.as_str()).collect();
+        assert_eq!(statuses, ["running", "skipped"]);
+        assert!(runs[1].finished_at.is_some());
+
+        // The next tick is still planned
+        let sched = schedule::Entity::find_by_id(sched.id).one(&db).await.unwrap().unwrap();
+        assert!(sched.next_run_at.unwrap() > now);
+    }
+}
@@ -27,9 +27,13 @@ interface Host {
 }
 
 interface ScanResponse {
+    job_id: string;
     target: string;
     status: string;
-    hosts: Host[];
+    phase?: string;
+    hosts_discovered?: number;
+    hosts_enriched?: number;
+    hosts?: Host[];
 }
 
 export default function Scanner() {
@@ -54,9 +58,17 @@ export default function Scanner() {
                     end_port: 1000
                 })
             });
-            const data = await res.json();
+            const job = await res.json();
+            if (useAuto && job.target) setTarget(job.target);
+
+            // Scans run as background jobs - poll until finished
+            let data: ScanResponse = job;
+            while (data.status === 'running') {
+                await new Promise(resolve => setTimeout(resolve, 2000));
+                const poll = await fetch(`http://localhost:8000/api/v1/scan/${job.job_id}`);
+                data = await poll.json();
+            }
             setResult(data);
-            if (useAuto && data.target) setTarget(data.target);
         } catch (err) {
             console.error(err);
         } finally {
@@ -161,11 +173,11 @@ export default function Scanner() {
                         <motion.div initial={{ opacity: 0 }} animate={{ opacity: 1 }} className="space-y-6">
                             <div className="flex justify-between items-center text-white mb-4">
                                 <h3 className="text-xl font-bold flex gap-2"><Monitor /> Network Topology</h3>
-                                <span className="bg-aegis-500/20 text-aegis-400 px-3 py-1 rounded-full text-sm font-mono">{result.hosts.length} Assets Identified</span>
+                                <span className="bg-aegis-500/20 text-aegis-400 px-3 py-1 rounded-full text-sm font-mono">{(result.hosts ?? []).length} Assets Identified</span>
                             </div>
 
                             <div className="grid grid-cols-1 gap-4">
-                                {result.hosts.map(host => {
+                                {(result.hosts ?? []).map(host => {
                                     const displayName = host.friendly_name || host.hostname || host.ip;
                                     const showIp = (host.friendly_name || host.hostname) && host.ip !== displayName;
 